@@ -1,27 +1,69 @@
 use std::ffi::CString;
+use std::path::Path;
 
 use swage_core::memory::{ConsecBlocks, Memory};
 use swage_core::util::Size::{self, MB};
 
 use swage_core::allocator::ConsecAllocator;
+use thiserror::Error;
+
+/// Device exposed by the coco_dec_mem kernel module
+const MOD_PATH: &str = "/dev/coco_dec_mem";
 
 /// A CoCo kernel memory allocator
 /// Requires the coco_dec_mem kernel module
 pub struct CoCo {}
 
+/// Errors that can happen during CoCo allocation
+#[derive(Debug, Error)]
+pub enum CoCoError {
+    /// The coco_dec_mem kernel module is not loaded
+    #[error("{MOD_PATH} not found, is the coco_dec_mem kernel module loaded?")]
+    ModuleNotLoaded,
+    /// Opening the device failed
+    #[error("Cannot open {MOD_PATH}: {0}")]
+    OpenFailed(std::io::Error),
+    /// Mapping a block from the device failed
+    #[error("mmap of {MOD_PATH} failed: {0}")]
+    MmapFailed(std::io::Error),
+}
+
+impl CoCo {
+    /// Returns true if the coco_dec_mem device exists and is readable.
+    pub fn available() -> bool {
+        Path::new(MOD_PATH).exists() && std::fs::File::open(MOD_PATH).is_ok()
+    }
+
+    /// Creates a CoCo allocator.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoCoError::ModuleNotLoaded`] if the coco_dec_mem device is absent, which
+    /// allows callers to fall back to another allocator.
+    pub fn new() -> Result<Self, CoCoError> {
+        if !Path::new(MOD_PATH).exists() {
+            return Err(CoCoError::ModuleNotLoaded);
+        }
+        Ok(CoCo {})
+    }
+}
+
 impl ConsecAllocator for CoCo {
-    type Error = std::io::Error;
+    type Error = CoCoError;
     fn block_size(&self) -> Size {
         MB(4)
     }
 
     fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
         unsafe {
-            const MOD_PATH: &str = "/dev/coco_dec_mem";
-            let c_mod_path = CString::new(MOD_PATH)?;
+            let c_mod_path = CString::new(MOD_PATH).expect("CString");
             let fd = libc::open(c_mod_path.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC);
             if fd == -1 {
-                return Err(std::io::Error::last_os_error());
+                let e = std::io::Error::last_os_error();
+                return Err(match e.kind() {
+                    std::io::ErrorKind::NotFound => CoCoError::ModuleNotLoaded,
+                    _ => CoCoError::OpenFailed(e),
+                });
             }
             let block_size = self.block_size();
             let block_count = (size.bytes() as f32 / block_size.bytes() as f32).ceil() as i32;
@@ -36,16 +78,28 @@ impl ConsecAllocator for CoCo {
                         0,
                     );
                     if v == libc::MAP_FAILED {
-                        return Err(std::io::Error::last_os_error());
+                        return Err(CoCoError::MmapFailed(std::io::Error::last_os_error()));
                     }
                     let block = Memory::new(v as *mut u8, MB(4).bytes());
-                    libc::memset(block.ptr as *mut libc::c_void, 0, block.len);
                     //consec_checker.check(&block)?;
                     Ok(block)
                 })
-                .collect::<Result<Vec<_>, _>>()?;
+                .collect::<Result<Vec<_>, _>>();
             libc::close(fd);
-            Ok(ConsecBlocks::new(blocks))
+            Ok(ConsecBlocks::zeroed(blocks?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_without_module() {
+        if CoCo::available() {
+            return;
         }
+        assert!(matches!(CoCo::new(), Err(CoCoError::ModuleNotLoaded)));
     }
 }
@@ -14,4 +14,4 @@
 
 mod coco;
 
-pub use coco::CoCo;
+pub use coco::{CoCo, CoCoError};
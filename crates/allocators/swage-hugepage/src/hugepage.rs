@@ -1,8 +1,8 @@
 use lazy_static::lazy_static;
 use libc::{MAP_POPULATE, MAP_SHARED, O_CREAT, O_RDWR};
-use std::ffi::{CString, c_void};
+use std::ffi::CString;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 use swage_core::allocator::ConsecAllocator;
 use swage_core::memory::{ConsecBlocks, Memory, PfnOffset};
 use swage_core::util::Size::{self, MB};
@@ -20,16 +20,14 @@ use swage_core::util::Size::{self, MB};
 // constant.
 const MEMINFO_PATH: &str = "/proc/meminfo";
 const TOKEN: &str = "Hugepagesize:";
+// /proc/meminfo and /proc/sys/vm/nr_hugepages only cover the default hugepage size, which
+// is usually 2 MB. The 1 GB pool is exposed separately in sysfs.
+const HUGEPAGES_1GB_DIR: &str = "/sys/kernel/mm/hugepages/hugepages-1048576kB";
+const HUGEPAGE_FILE: &str = "/dev/hugepages/hammer_huge";
+const HUGEPAGE_ADDR: usize = 0x2000000000;
 
 lazy_static! {
-    static ref HUGEPAGE_SIZE: isize = {
-        let buf = File::open(MEMINFO_PATH).map_or("".to_owned(), |mut f| {
-            let mut s = String::new();
-            let _ = f.read_to_string(&mut s);
-            s
-        });
-        parse_hugepage_size(&buf)
-    };
+    static ref HUGEPAGE_SIZE: isize = parse_hugepage_size(&read_meminfo());
 }
 
 fn parse_hugepage_size(s: &str) -> isize {
@@ -55,6 +53,24 @@ fn parse_hugepage_size(s: &str) -> isize {
     -1
 }
 
+fn read_meminfo() -> String {
+    File::open(MEMINFO_PATH).map_or("".to_owned(), |mut f| {
+        let mut s = String::new();
+        let _ = f.read_to_string(&mut s);
+        s
+    })
+}
+
+fn parse_hugepage_count(s: &str) -> usize {
+    s.trim().parse::<usize>().unwrap_or(0)
+}
+
+/// Reads the counter `name` of the 1GB hugepage pool, or 0 if it cannot be read.
+fn read_1gb_count(name: &str) -> usize {
+    std::fs::read_to_string(format!("{}/{}", HUGEPAGES_1GB_DIR, name))
+        .map_or(0, |s| parse_hugepage_count(&s))
+}
+
 /// Hugepage-based memory allocator using 1GB pages.
 ///
 /// Allocates memory using Linux hugepages mounted at `/dev/hugepages`.
@@ -63,6 +79,8 @@ fn parse_hugepage_size(s: &str) -> isize {
 /// # Implementation
 ///
 /// Implements [`swage_core::allocator::ConsecAllocator`] with 1GB block size.
+/// Allocations larger than 1GB are served from multiple hugepages, each backed by a
+/// distinct file `/dev/hugepages/hammer_huge_<n>`.
 ///
 /// # Platform Requirements
 ///
@@ -70,8 +88,11 @@ fn parse_hugepage_size(s: &str) -> isize {
 /// - Hugepagefs must be mounted at `/dev/hugepages`
 /// - Currently only supports x86_64 architecture
 #[cfg(target_arch = "x86_64")]
-#[derive(Debug, Default, Copy, Clone)]
-pub struct HugepageAllocator {}
+#[derive(Debug, Default, Clone)]
+pub struct HugepageAllocator {
+    /// Hugepage files created by this allocator
+    files: Vec<String>,
+}
 
 /// Supported hugepage sizes.
 ///
@@ -82,51 +103,153 @@ pub enum HugepageSize {
     OneGb,
 }
 
+#[cfg(target_arch = "x86_64")]
+impl HugepageAllocator {
+    /// Returns the number of free 1GB hugepages in the pool.
+    ///
+    /// Reads `free_hugepages` of the 1GB pool in `/sys/kernel/mm/hugepages`.
+    /// Returns 0 if the value cannot be read.
+    pub fn available_1gb_pages() -> usize {
+        read_1gb_count("free_hugepages")
+    }
+
+    /// Returns the total number of 1GB hugepages in the pool.
+    ///
+    /// Reads `nr_hugepages` of the 1GB pool in `/sys/kernel/mm/hugepages`.
+    /// Returns 0 if the value cannot be read.
+    pub fn total_1gb_pages() -> usize {
+        read_1gb_count("nr_hugepages")
+    }
+
+    /// Grows the 1GB hugepage pool by `count` pages.
+    ///
+    /// Writes the new pool size to `nr_hugepages` of the 1GB pool in
+    /// `/sys/kernel/mm/hugepages`. The kernel may reserve fewer pages than requested
+    /// if not enough contiguous memory is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool size cannot be written, e.g., when not running as
+    /// root or when the kernel does not support 1GB hugepages.
+    pub fn reserve_pages(count: usize) -> Result<(), std::io::Error> {
+        let total = Self::total_1gb_pages() + count;
+        let mut f = File::create(format!("{}/nr_hugepages", HUGEPAGES_1GB_DIR))?;
+        write!(f, "{}", total)
+    }
+
+    /// Ensures at least `count` hugepages are free, reserving more if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reserving pages fails or the pool still has fewer
+    /// than `count` free pages afterwards.
+    pub fn ensure_available(count: usize) -> Result<(), std::io::Error> {
+        let available = Self::available_1gb_pages();
+        if available >= count {
+            return Ok(());
+        }
+        log::info!(
+            "Only {} of {} required hugepages available, reserving more",
+            available,
+            count
+        );
+        Self::reserve_pages(count - available)?;
+        let available = Self::available_1gb_pages();
+        if available < count {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::OutOfMemory,
+                format!(
+                    "Only {} of {} required hugepages available",
+                    available, count
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the hugepage files created by this allocator.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Unlinks all hugepage files created by this allocator.
+    ///
+    /// Files are already unlinked after mapping them, so this only removes files left over by
+    /// failed allocations. Mapped blocks stay valid until they are deallocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of `unlink`. Files that no longer exist are skipped.
+    pub fn cleanup(&mut self) -> Result<(), std::io::Error> {
+        let mut result = Ok(());
+        for file in self.files.drain(..) {
+            if let Err(e) = std::fs::remove_file(&file)
+                && e.kind() != std::io::ErrorKind::NotFound
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
 impl ConsecAllocator for HugepageAllocator {
     type Error = std::io::Error;
     fn block_size(&self) -> Size {
         Size::B(*HUGEPAGE_SIZE as usize)
     }
     fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
-        assert!(
-            size.bytes() < self.block_size().bytes(),
-            "Only support allocations up to 0x{:x} bytes",
-            self.block_size().bytes()
-        );
-        assert_eq!(self.block_size().bytes(), MB(1024).bytes());
-        let block = Memory::hugepage(HugepageSize::OneGb)?;
-        unsafe { libc::memset(block.ptr as *mut c_void, 0x00, self.block_size().bytes()) };
-        Ok(ConsecBlocks::new(vec![block]))
+        assert_eq!(self.block_size(), MB(1024));
+        let num_blocks = size.bytes().div_ceil(self.block_size().bytes()).max(1);
+        Self::ensure_available(num_blocks)?;
+        let mut blocks = Vec::with_capacity(num_blocks);
+        // Files are unlinked right after mapping them, so names and address hints only need
+        // to be distinct within one allocation and are reused by later allocations.
+        for index in 0..num_blocks {
+            let file = format!("{}_{}", HUGEPAGE_FILE, index);
+            if !self.files.contains(&file) {
+                self.files.push(file.clone());
+            }
+            let addr = HUGEPAGE_ADDR + index * self.block_size().bytes();
+            match Memory::hugepage(HugepageSize::OneGb, &file, addr) {
+                Ok(block) => blocks.push(block),
+                Err(e) => {
+                    blocks.into_iter().for_each(Memory::dealloc);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(ConsecBlocks::zeroed(blocks))
     }
 }
 
 trait Hugepage {
-    fn hugepage(size: HugepageSize) -> Result<Self, std::io::Error>
+    fn hugepage(size: HugepageSize, file: &str, addr: usize) -> Result<Self, std::io::Error>
     where
         Self: Sized;
 }
 
 impl Hugepage for Memory {
-    fn hugepage(size: HugepageSize) -> Result<Self, std::io::Error> {
-        const ADDR: usize = 0x2000000000;
+    /// Maps a hugepage backed by `file`, preferably at `addr`.
+    ///
+    /// The file is unlinked right after mapping it, so the hugepage is returned to the pool
+    /// when the mapping is removed (e.g., by [`Memory::dealloc`]) and later runs never map a
+    /// stale file.
+    fn hugepage(size: HugepageSize, file: &str, addr: usize) -> Result<Self, std::io::Error> {
         let hp_size = match size {
             HugepageSize::OneGb => MB(1024).bytes(),
         };
-        let fd = unsafe {
-            libc::open(
-                CString::new("/dev/hugepages/hammer_huge")
-                    .expect("CString")
-                    .as_ptr(),
-                O_RDWR | O_CREAT,
-                666,
-            )
-        };
+        let path = CString::new(file).expect("CString");
+        // remove leftovers of previous runs
+        unsafe { libc::unlink(path.as_ptr()) };
+        let fd = unsafe { libc::open(path.as_ptr(), O_RDWR | O_CREAT, 666) };
         if fd == -1 {
             return Err(std::io::Error::last_os_error());
         }
         let p = unsafe {
             libc::mmap(
-                ADDR as *mut libc::c_void,
+                addr as *mut libc::c_void,
                 hp_size,
                 libc::PROT_READ | libc::PROT_WRITE,
                 MAP_SHARED | MAP_POPULATE,
@@ -134,9 +257,13 @@ impl Hugepage for Memory {
                 0,
             )
         };
-        unsafe { libc::close(fd) };
+        let mmap_error = std::io::Error::last_os_error();
+        unsafe {
+            libc::close(fd);
+            libc::unlink(path.as_ptr());
+        }
         if p == libc::MAP_FAILED {
-            return Err(std::io::Error::last_os_error());
+            return Err(mmap_error);
         }
         Ok(Memory::new_with_parts(
             p as *mut u8,
@@ -162,11 +289,39 @@ mod tests {
         // wrong.
         assert_eq!(parse_hugepage_size("Hugepagesize:1kB"), -1);
         assert_eq!(parse_hugepage_size("Hugepagesize: 2kB"), -1);
+
+        // 1GB.
+        assert_eq!(
+            parse_hugepage_size("Hugepagesize:    1048576 kB"),
+            1024 * 1024 * 1024
+        );
+        assert_eq!(parse_hugepage_size(MEMINFO_FIXTURE), 1024 * 1024 * 1024);
+    }
+
+    const MEMINFO_FIXTURE: &str = "MemTotal:       65536000 kB
+MemFree:        32768000 kB
+HugePages_Total:       4
+HugePages_Free:        3
+HugePages_Rsvd:        0
+HugePages_Surp:        0
+Hugepagesize:    1048576 kB
+Hugetlb:         4194304 kB
+";
+
+    #[test]
+    fn test_parse_hugepage_count() {
+        // sysfs counters end with a newline.
+        assert_eq!(parse_hugepage_count("3\n"), 3);
+        assert_eq!(parse_hugepage_count("0"), 0);
+
+        // missing or malformed.
+        assert_eq!(parse_hugepage_count(""), 0);
+        assert_eq!(parse_hugepage_count("x\n"), 0);
     }
 
     #[test]
     fn test_allocator() {
-        let mut hugepage_alloc = HugepageAllocator {};
+        let mut hugepage_alloc = HugepageAllocator::default();
 
         // u16.
         unsafe {
@@ -200,4 +355,44 @@ mod tests {
             mem.dealloc();
         }
     }
+
+    #[test]
+    #[ignore = "requires 1GB hugepages"]
+    fn test_hugepage_file_removed() {
+        let mut hugepage_alloc = HugepageAllocator::default();
+        let mem = hugepage_alloc
+            .alloc_consec_blocks(Size::B(4096))
+            .expect("allocation failed");
+        mem.dealloc();
+        for file in hugepage_alloc.files() {
+            assert!(!std::path::Path::new(file).exists());
+        }
+    }
+
+    #[test]
+    #[ignore = "requires 2 free 1GB hugepages"]
+    fn test_allocate_2gb() {
+        let mut hugepage_alloc = HugepageAllocator::default();
+        let mem = hugepage_alloc
+            .alloc_consec_blocks(MB(2048))
+            .expect("allocation failed");
+        assert_eq!(mem.blocks.len(), 2);
+        assert_eq!(hugepage_alloc.files().len(), 2);
+        assert_ne!(hugepage_alloc.files()[0], hugepage_alloc.files()[1]);
+        mem.dealloc();
+        hugepage_alloc.cleanup().expect("cleanup");
+    }
+
+    #[test]
+    #[ignore = "requires 1GB hugepages"]
+    fn test_reuse_file_names() {
+        let mut hugepage_alloc = HugepageAllocator::default();
+        for _ in 0..3 {
+            let mem = hugepage_alloc
+                .alloc_consec_blocks(Size::B(4096))
+                .expect("allocation failed");
+            mem.dealloc();
+        }
+        assert_eq!(hugepage_alloc.files().len(), 1);
+    }
 }
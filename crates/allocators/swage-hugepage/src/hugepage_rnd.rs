@@ -3,7 +3,7 @@ use log::info;
 use rand::prelude::SliceRandom;
 use swage_core::allocator::ConsecAllocator;
 use swage_core::memory::{BytePointer, ConsecBlocks, Memory};
-use swage_core::util::{Size, Size::MB, make_vec};
+use swage_core::util::{Size, Size::MB};
 
 /// Allocator using randomized hugepage chunks.
 ///
@@ -24,14 +24,37 @@ impl HugepageRandomized {
     /// # Arguments
     ///
     /// * `num_hugepages` - Number of 1GB hugepages to allocate
-    pub fn new_with_count(num_hugepages: NumHugePages) -> Self {
-        let hugepages = make_vec(num_hugepages.0, |_| {
-            HugepageAllocator::default()
-                .alloc_consec_blocks(MB(1024))
-                .expect("hugepage alloc")
-        });
-        HugepageRandomized { hugepages }
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the hugepages cannot be allocated. The hugepages allocated
+    /// so far are deallocated in that case.
+    pub fn new_with_count(num_hugepages: NumHugePages) -> Result<Self, std::io::Error> {
+        let hugepages = alloc_all(num_hugepages.0, || {
+            HugepageAllocator::default().alloc_consec_blocks(MB(1024))
+        })?;
+        Ok(HugepageRandomized { hugepages })
+    }
+}
+
+/// Calls `alloc` `n` times and collects the allocated blocks.
+///
+/// If an allocation fails, the blocks allocated before are deallocated and the error is returned.
+fn alloc_all<E>(
+    n: usize,
+    mut alloc: impl FnMut() -> Result<ConsecBlocks, E>,
+) -> Result<Vec<ConsecBlocks>, E> {
+    let mut blocks = Vec::with_capacity(n);
+    for _ in 0..n {
+        match alloc() {
+            Ok(block) => blocks.push(block),
+            Err(e) => {
+                blocks.into_iter().for_each(ConsecBlocks::dealloc);
+                return Err(e);
+            }
+        }
     }
+    Ok(blocks)
 }
 
 impl ConsecAllocator for HugepageRandomized {
@@ -71,3 +94,38 @@ impl From<usize> for NumHugePages {
         NumHugePages(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swage_core::util::PAGE_SIZE;
+
+    /// Returns whether `ptr` is mapped, using `msync`, which fails with ENOMEM otherwise
+    fn is_mapped(ptr: *mut u8) -> bool {
+        unsafe { libc::msync(ptr as *mut libc::c_void, PAGE_SIZE, libc::MS_ASYNC) == 0 }
+    }
+
+    #[test]
+    fn test_alloc_all_partial_failure() {
+        let mut ptrs = vec![];
+        let result = alloc_all(3, || {
+            if ptrs.len() == 2 {
+                return Err("out of hugepages");
+            }
+            let block = Memory::mmap(PAGE_SIZE).expect("mmap failed");
+            ptrs.push(block.ptr);
+            Ok(ConsecBlocks::new(vec![block]))
+        });
+        assert!(matches!(result, Err("out of hugepages")));
+        assert_eq!(ptrs.len(), 2);
+        assert!(ptrs.iter().all(|&ptr| !is_mapped(ptr)));
+
+        let blocks = alloc_all(2, || {
+            Memory::mmap(PAGE_SIZE).map(|block| ConsecBlocks::new(vec![block]))
+        })
+        .expect("allocation failed");
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().all(|block| is_mapped(block.ptr())));
+        blocks.into_iter().for_each(ConsecBlocks::dealloc);
+    }
+}
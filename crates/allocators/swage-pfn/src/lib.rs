@@ -15,4 +15,5 @@
 mod pfn;
 
 pub use pfn::Pfn;
+pub use pfn::PfnAllocatorConfig;
 pub use pfn::SharedMem;
@@ -1,23 +1,35 @@
-use log::debug;
+use log::{debug, warn};
 use lpfs::ProcErr;
 use lpfs::proc::buddyinfo::buddyinfo;
-use std::ffi::c_void;
-use swage_core::util::Size;
+use std::cell::Cell;
+use std::ffi::{CString, c_void};
+use std::time::Duration;
+use swage_core::retry;
+use swage_core::util::{BackoffStrategy, Size};
 use thiserror::Error;
 
 use swage_core::allocator::ConsecAllocator;
 use swage_core::memory::{
-    ConsecBlocks, DRAMAddr, GetConsecPfns, LinuxPageMapError, MemConfiguration, Memory, PfnResolver,
+    ConsecBlocks, DRAMAddr, GetConsecPfns, LinuxPageMapError, MemConfiguration, Memory,
+    MemoryError, PfnResolver,
 };
 use swage_core::util::{PAGE_SIZE, Size::MB};
-use swage_core::util::{mmap, mmap_shm, munmap};
+use swage_core::util::{compact_mem, mmap, mmap_retry_at, mmap_shm, munmap};
 
 /// Shared memory configuration for PFN allocator.
 ///
-/// Optionally specifies a shared memory name to use with `shm_open`.
+/// Optionally specifies a shared memory name to use with `shm_open`. The [`Pfn`] allocator
+/// appends an increasing suffix to this name for each mapping, see [`SharedMem::with_suffix`].
 #[derive(Clone)]
 pub struct SharedMem(Option<String>);
 
+impl SharedMem {
+    /// Returns the shared memory name `base` with `suffix` appended.
+    pub fn with_suffix(base: &str, suffix: u64) -> Self {
+        SharedMem(Some(format!("{}.{}", base, suffix)))
+    }
+}
+
 /// PFN-based memory allocator.
 ///
 /// Allocates memory and checks `/proc/self/pagemap` to find consecutive
@@ -32,18 +44,131 @@ pub struct SharedMem(Option<String>);
 pub struct Pfn {
     mem_config: MemConfiguration,
     shared_mem: SharedMem,
+    config: PfnAllocatorConfig,
+    /// Suffix of the next shared memory name
+    shm_suffix: u64,
+    /// Shared memory names used so far, unlinked by [`Pfn::cleanup`]
+    shm_names: Vec<String>,
+}
+
+/// Configuration of the [`Pfn`] allocator.
+#[derive(Clone, Debug)]
+pub struct PfnAllocatorConfig {
+    /// How often to compact memory if the buddy allocator has no free order-9 or order-10
+    /// pages, before giving up
+    pub compact_retries: u32,
+    /// Delay after each compaction, giving the kernel time to merge pages
+    pub compact_delay_ms: u64,
+    /// Number of further 1 GB slots after `0x2000000000` to try if a slot is already
+    /// occupied (by previously allocated blocks or other mappings). Defaults to all slots
+    /// below `0x4000000000`.
+    pub mmap_retries: u32,
+}
+
+impl Default for PfnAllocatorConfig {
+    fn default() -> Self {
+        PfnAllocatorConfig {
+            compact_retries: 3,
+            compact_delay_ms: 100,
+            mmap_retries: MMAP_RETRIES,
+        }
+    }
 }
 
 /// Pfn allocator. This finds consecutive PFNs by allocating memory (optionally using shared memory mapping with shm_open, if `shared_mem` is provided) and checking the page map.
 /// Useful for testing purposes.
 impl Pfn {
     /// Constructor for the Pfn allocator
-    pub fn new(mem_config: MemConfiguration, shared_mem: SharedMem) -> Self {
+    pub fn new(
+        mem_config: MemConfiguration,
+        shared_mem: SharedMem,
+        config: PfnAllocatorConfig,
+    ) -> Self {
         Self {
             mem_config,
             shared_mem,
+            config,
+            shm_suffix: 0,
+            shm_names: vec![],
         }
     }
+
+    /// Unlinks all shared memory objects created by this allocator.
+    ///
+    /// Mapped blocks stay valid until they are deallocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of `shm_unlink`. Objects that no longer exist are skipped.
+    pub fn cleanup(&mut self) -> Result<(), std::io::Error> {
+        let mut result = Ok(());
+        for name in self.shm_names.drain(..) {
+            if let Err(e) = shm_unlink(&name)
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Unlinks the shared memory object `name` and forgets about it.
+    ///
+    /// Used for buffers that are unmapped without handing out any block, so retries do not
+    /// accumulate shared memory objects.
+    fn discard_shm_name(&mut self, name: &str) {
+        self.shm_names.retain(|n| n != name);
+        if let Err(e) = shm_unlink(name) {
+            warn!("Failed to unlink shared memory {}: {}", name, e);
+        }
+    }
+
+    /// Returns a fresh shared memory name, or `None` if no shared memory is configured.
+    fn next_shm_name(&mut self) -> Option<String> {
+        let base = self.shared_mem.0.as_ref()?;
+        let SharedMem(name) = SharedMem::with_suffix(base, self.shm_suffix);
+        let name = name.expect("name is set");
+        self.shm_suffix += 1;
+        self.shm_names.push(name.clone());
+        Some(name)
+    }
+
+    /// Returns the free page counts of the Normal zone, compacting memory until order-9 or
+    /// order-10 pages are available.
+    fn ensure_high_order_pages(&self) -> Result<[u64; 11], Error> {
+        let retries = self.config.compact_retries;
+        // the delay before each retry gives the kernel time to merge the compacted pages
+        let backoff = BackoffStrategy::Fixed(Duration::from_millis(self.config.compact_delay_ms));
+        let compactions = Cell::new(0);
+        retry!(
+            || {
+                let pages = get_normal_page_nums().map_err(ProcErrWrap::from)?;
+                if has_high_order_pages(&pages) {
+                    return Ok(pages);
+                }
+                // no compaction after the last attempt
+                if compactions.get() < retries {
+                    compactions.set(compactions.get() + 1);
+                    warn!(
+                        "No free order-9 or order-10 pages, compacting memory ({}/{})",
+                        compactions.get(),
+                        retries
+                    );
+                    if let Err(e) = compact_mem() {
+                        warn!("Memory compaction failed: {:?}", e);
+                    }
+                }
+                Err(Error::NoHighOrderPages(retries))
+            },
+            backoff,
+            retries
+        )
+    }
+}
+
+/// Returns true if `pages` contains free order-9 or order-10 pages.
+fn has_high_order_pages(pages: &[u64; 11]) -> bool {
+    pages[9] > 0 || pages[10] > 0
 }
 
 /// Wrapper for ProcErr, which does not implement Error.
@@ -74,9 +199,21 @@ pub enum Error {
     ConsecPfns(#[from] swage_core::memory::ConsecPfnsError),
     #[error(transparent)]
     LinuxPageMapError(#[from] LinuxPageMapError),
+    #[error(transparent)]
+    MemoryError(#[from] MemoryError),
+    #[error("No free order-9 or order-10 pages after {0} compaction attempts")]
+    NoHighOrderPages(u32),
 }
 
-const BASE_ADDR: *mut c_void = 0x2000000000 as *mut c_void;
+const BASE: usize = 0x2000000000;
+const BASE_ADDR: *mut c_void = BASE as *mut c_void;
+
+/// Size of the buffer searched for consecutive PFNs.
+const BUFSIZE: usize = MB(1024).bytes();
+
+/// Default number of further buffers to try after `BASE_ADDR`, covering every
+/// `BUFSIZE` slot in `[BASE_ADDR, 2 * BASE_ADDR)`.
+const MMAP_RETRIES: u32 = (BASE / BUFSIZE - 1) as u32;
 
 impl ConsecAllocator for Pfn {
     type Error = Error;
@@ -88,21 +225,27 @@ impl ConsecAllocator for Pfn {
         assert!(size.bytes().is_multiple_of(self.block_size().bytes()));
         let block_count = size.bytes() / self.block_size().bytes();
         // allocate low-order pages
-        let blocks = get_normal_page_nums().map_err(ProcErrWrap::from)?;
+        let blocks = self.ensure_high_order_pages()?;
         let blocks: [i64; 11] = blocks.map(|x| x as i64);
         let low_order_bytes = low_order_bytes(&blocks, 9);
         let buf: *mut c_void = mmap(std::ptr::null_mut(), low_order_bytes);
-        const BUFSIZE: usize = MB(1024).bytes();
         let mut blocks = vec![];
         'outer: while blocks.len() < block_count {
-            let x: *mut u8 = match &self.shared_mem.0 {
-                Some(shared_mem) => mmap_shm(BASE_ADDR, BUFSIZE, shared_mem.into()),
-                None => mmap(BASE_ADDR, BUFSIZE),
+            // The buffer starts at a multiple of BUFSIZE, which is a multiple of the block size,
+            // so a block-aligned offset in the buffer is a block-aligned virtual address.
+            let shm_name = self.next_shm_name();
+            let x: *mut u8 = match &shm_name {
+                Some(shm_name) => mmap_shm(BASE_ADDR, BUFSIZE, shm_name.clone()),
+                None => mmap_retry_at(BASE_ADDR, BUFSIZE, self.config.mmap_retries).ok_or_else(
+                    || {
+                        std::io::Error::new(
+                            std::io::ErrorKind::AddrNotAvailable,
+                            "failed to mmap at block-aligned address",
+                        )
+                    },
+                )?,
             };
-            if x.is_null() {
-                return Err(std::io::Error::last_os_error().into());
-            }
-            debug!("phys(x) = {:p}", x.pfn()?);
+            debug!("phys(x) = {:?}", x.pfn()?);
             let pfns = (x, BUFSIZE).consec_pfns()?;
             (x, BUFSIZE).log_pfns(log::Level::Trace);
             let consecs = pfns.iter().enumerate().filter(|(_, range)| {
@@ -120,7 +263,7 @@ impl ConsecAllocator for Pfn {
                     .take(idx)
                     .map(|range| (range.end - range.start).as_usize())
                     .sum();
-                let bank = DRAMAddr::from_virt(pfns[idx].start.into(), &self.mem_config).bank;
+                let bank = DRAMAddr::from_phys(pfns[idx].start, &self.mem_config).bank;
                 //assert_eq!(bank, 0, "Base bank of 0x{:x} is not zero. The PFN allocation strategy only supports allocation of up to 4 MB (22 bit address alignment), but apparently, some bank bits are above bit 22 (or you found a bug).", pfns[idx].start);
                 if bank != 0 {
                     debug!("Bank {} != 0, retrying...", bank);
@@ -135,6 +278,9 @@ impl ConsecAllocator for Pfn {
             if blocks.len() < block_count {
                 debug!("Not enough consecutive PFNs found, unmapping...");
                 unsafe { munmap(x, BUFSIZE) };
+                if let Some(shm_name) = &shm_name {
+                    self.discard_shm_name(shm_name);
+                }
                 continue 'outer;
             }
             for unmap_range in unmap_ranges {
@@ -176,3 +322,98 @@ impl From<Option<String>> for SharedMem {
         SharedMem(value)
     }
 }
+
+/// Unlinks the shared memory object `name`. Objects that no longer exist are skipped.
+fn shm_unlink(name: &str) -> Result<(), std::io::Error> {
+    let name = CString::new(name).expect("CString");
+    if unsafe { libc::shm_unlink(name.as_ptr()) } == -1 {
+        let e = std::io::Error::last_os_error();
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shm_name_rotation() -> Result<(), std::io::Error> {
+        let base = format!("/swage-pfn-test-{}", std::process::id());
+        let mut pfn = Pfn::new(
+            MemConfiguration::default(),
+            Some(base.clone()).into(),
+            PfnAllocatorConfig::default(),
+        );
+        let first = pfn.next_shm_name().expect("shared memory configured");
+        let second = pfn.next_shm_name().expect("shared memory configured");
+        assert_eq!(first, format!("{}.0", base));
+        assert_ne!(first, second);
+        // map, unmap and map again: each mapping gets its own object
+        let a: *mut u8 = mmap_shm(std::ptr::null_mut(), PAGE_SIZE, first.clone());
+        unsafe { a.write_volatile(0x42) };
+        unsafe { munmap(a, PAGE_SIZE) };
+        let b: *mut u8 = mmap_shm(std::ptr::null_mut(), PAGE_SIZE, second.clone());
+        assert_eq!(unsafe { b.read_volatile() }, 0xAA);
+        unsafe { munmap(b, PAGE_SIZE) };
+        assert!(std::path::Path::new(&format!("/dev/shm{}", first)).exists());
+        pfn.cleanup()?;
+        assert!(!std::path::Path::new(&format!("/dev/shm{}", first)).exists());
+        assert!(!std::path::Path::new(&format!("/dev/shm{}", second)).exists());
+        assert!(pfn.shm_names.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discard_shm_name() {
+        let base = format!("/swage-pfn-discard-{}", std::process::id());
+        let mut pfn = Pfn::new(
+            MemConfiguration::default(),
+            Some(base).into(),
+            PfnAllocatorConfig::default(),
+        );
+        let name = pfn.next_shm_name().expect("shared memory configured");
+        let a: *mut u8 = mmap_shm(std::ptr::null_mut(), PAGE_SIZE, name.clone());
+        unsafe { munmap(a, PAGE_SIZE) };
+        pfn.discard_shm_name(&name);
+        assert!(!std::path::Path::new(&format!("/dev/shm{}", name)).exists());
+        assert!(pfn.shm_names.is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_shm_realloc() -> Result<(), Error> {
+        let base = format!("/swage-pfn-realloc-{}", std::process::id());
+        let mut pfn = Pfn::new(
+            MemConfiguration::default(),
+            Some(base).into(),
+            PfnAllocatorConfig::default(),
+        );
+        pfn.alloc_consec_blocks(MB(4))?.dealloc();
+        pfn.alloc_consec_blocks(MB(4))?.dealloc();
+        pfn.cleanup()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_high_order_pages() {
+        let mut pages = [100; 11];
+        pages[9] = 0;
+        pages[10] = 0;
+        assert!(!has_high_order_pages(&pages));
+        pages[9] = 1;
+        assert!(has_high_order_pages(&pages));
+        pages[9] = 0;
+        pages[10] = 1;
+        assert!(has_high_order_pages(&pages));
+    }
+
+    #[test]
+    fn test_default_mmap_retries() {
+        let retries = PfnAllocatorConfig::default().mmap_retries as usize;
+        // the last slot ends at 2 * BASE_ADDR
+        assert_eq!(BASE + (retries + 1) * BUFSIZE, 2 * BASE);
+    }
+}
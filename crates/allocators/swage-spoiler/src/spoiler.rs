@@ -8,7 +8,8 @@ use log::{debug, error, info, log_enabled, warn};
 use swage_core::allocator::ConsecAllocator;
 use swage_core::memory::{
     BytePointer, ConsecBlocks, DRAMAddr, FormatPfns, GetConsecPfns, LinuxPageMapError,
-    MemConfiguration, Memory, PfnResolver, TimerError, construct_memory_tuple_timer,
+    MemConfiguration, Memory, MemoryError, PfnResolver, RowConflictDetector, TimerError,
+    construct_memory_tuple_timer,
 };
 use swage_core::util::Size;
 use swage_core::util::{NamedProgress, PAGE_SIZE, Size::MB};
@@ -68,6 +69,8 @@ pub enum Error {
     LinuxPageMapError(#[from] LinuxPageMapError),
     #[error("Failed to construct memory tuple timer: {0}")]
     Timer(#[from] TimerError),
+    #[error(transparent)]
+    MemoryError(#[from] MemoryError),
 }
 
 impl ConsecAllocator for Spoiler {
@@ -112,10 +115,8 @@ impl ConsecAllocator for Spoiler {
                 "Banks: {:?}",
                 blocks
                     .iter()
-                    .map(|b| DRAMAddr::from_virt(
-                        b.pfn().unwrap_or_default().into(),
-                        &self.mem_config
-                    ))
+                    .filter_map(|b| b.pfn().ok().flatten())
+                    .map(|pfn| DRAMAddr::from_phys(pfn, &self.mem_config))
                     .collect_vec()
             );
             for block in round_blocks {
@@ -138,22 +139,28 @@ impl ConsecAllocator for Spoiler {
                 }
                 // PFN based check to confirm timings (for debugging, skipped if PageMap ist not available)
                 let pfn = block.pfn();
-                let last_pfn = blocks.last().map(|b| b.pfn()).transpose();
-                if let (Ok(pfn), Ok(Some(last_pfn))) = (&pfn, &last_pfn) {
-                    let bank = DRAMAddr::from_virt((*pfn).into(), &self.mem_config).bank;
+                let last_pfn = blocks
+                    .last()
+                    .map(|b| b.pfn())
+                    .transpose()
+                    .map(Option::flatten);
+                if let (Ok(Some(pfn)), Ok(Some(last_pfn))) = (&pfn, &last_pfn) {
+                    let bank = DRAMAddr::from_phys(*pfn, &self.mem_config).bank;
                     if bank != 0 {
                         debug!("Not bank 0: {}", bank);
                         //block.dealloc();
                         //continue;
                     }
-                    let last_bank = DRAMAddr::from_virt((*last_pfn).into(), &self.mem_config).bank;
+                    let last_bank = DRAMAddr::from_phys(*last_pfn, &self.mem_config).bank;
                     assert_eq!(bank, last_bank);
                 } else {
                     warn!("Skipped PFN check: {:?} {:?}", pfn, last_pfn);
                 }
                 info!(
                     "Adding block (phys) {:?}:\n{}",
-                    DRAMAddr::from_virt(block.pfn()?.into(), &self.mem_config),
+                    block
+                        .pfn()?
+                        .map(|pfn| DRAMAddr::from_phys(pfn, &self.mem_config)),
                     block.consec_pfns()?.format_pfns()
                 );
                 if let Some(p) = &p {
@@ -168,7 +175,7 @@ impl ConsecAllocator for Spoiler {
         if let Some(p) = &p {
             p.finish();
         }
-        Ok(ConsecBlocks { blocks })
+        Ok(ConsecBlocks::new(blocks))
     }
 }
 
@@ -198,6 +205,8 @@ enum SpoilerRoundError {
     NoCandidatesFound,
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error("Failed to construct memory tuple timer: {0}")]
+    Timer(#[from] TimerError),
 }
 
 impl Spoiler {
@@ -228,10 +237,9 @@ impl Spoiler {
             debug!("Aligned PFNs: {:?}", consecs);
         }
         assert_eq!(aligned as usize & (ALIGNMENT - 1), 0);
-        assert_eq!(
-            aligned.pfn().unwrap_or_default().as_usize() & (ALIGNMENT - 1),
-            0
-        );
+        if let Ok(Some(pfn)) = aligned.pfn() {
+            assert_eq!(pfn.as_usize() & (ALIGNMENT - 1), 0);
+        }
         Ok(Memory::new(aligned as *mut u8, ALIGNMENT))
     }
 
@@ -244,14 +252,17 @@ impl Spoiler {
         const DUMMY_BUF_SIZE: usize = MB(2048).bytes();
         const SEARCH_BUFFER_SIZE: usize = MB(2048).bytes();
         const CONT_SIZE: usize = MB(8).bytes();
+        const BANK_MAP_ROUNDS: usize = 100;
+        // only needed if PFNs cannot be resolved, so it is constructed on first use
+        let mut detector = None;
         let dummy_buf: *mut u8 = mmap(null_mut(), DUMMY_BUF_SIZE); // dummy buffer to collect small page blocks
         let aligned = Self::allocate_2m_aligned()?;
-        debug!("Base PFN: {:p}", aligned.pfn().unwrap_or_default());
+        debug!("Base PFN: {:?}", aligned.pfn().ok().flatten());
         let search_buffer = mmap(null_mut(), SEARCH_BUFFER_SIZE);
         unsafe { munmap(dummy_buf, DUMMY_BUF_SIZE) };
         let spoiler_candidates =
             spoiler_candidates(search_buffer, SEARCH_BUFFER_SIZE, aligned.ptr(), CONT_SIZE);
-        debug!("Base PFN: {:p}", aligned.pfn().unwrap_or_default());
+        debug!("Base PFN: {:?}", aligned.pfn().ok().flatten());
         aligned.dealloc();
         if spoiler_candidates.is_empty() {
             trash_buffers.push(Memory::new(search_buffer, SEARCH_BUFFER_SIZE));
@@ -290,7 +301,32 @@ impl Spoiler {
                     //continue;
                 }
             } else {
+                // fall back to timing: a consecutive block spans rows in all banks
                 debug!("Found candidate, but failed to resolve PFNs (are we root?)");
+                if detector.is_none() {
+                    match construct_memory_tuple_timer() {
+                        Ok(timer) => {
+                            detector =
+                                Some(RowConflictDetector::new(timer, self.conflict_threshold.0))
+                        }
+                        Err(e) => {
+                            trash_buffers.push(Memory::new(search_buffer, SEARCH_BUFFER_SIZE));
+                            return Err(e.into());
+                        }
+                    }
+                }
+                let banks = detector
+                    .as_ref()
+                    .expect("detector is constructed above")
+                    .build_bank_map(&ConsecBlocks::new(vec![block.clone()]), BANK_MAP_ROUNDS);
+                if banks.0.len() != self.mem_config.get_bank_count() {
+                    warn!(
+                        "Found {} banks in candidate, expected {}",
+                        banks.0.len(),
+                        self.mem_config.get_bank_count()
+                    );
+                    continue;
+                }
             }
             intervals.add(candidate);
             debug!("Current ranges: {}", intervals);
@@ -14,16 +14,19 @@
 #![warn(missing_docs)]
 
 use std::ptr::null_mut;
+use std::time::Duration;
 
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use itertools::max;
 use log::{debug, log_enabled, warn};
 use swage_core::allocator::ConsecAllocator;
 use swage_core::memory::{
-    ConsecBlocks, GetConsecPfns, PfnResolver, TimerError, construct_memory_tuple_timer,
+    ConsecBlocks, GetConsecPfns, MemConfiguration, MemoryError, MemoryTupleTimer, PfnResolver,
+    TimerError, construct_memory_tuple_timer,
 };
+use swage_core::retry;
 use swage_core::util::Size::MB;
-use swage_core::util::{NamedProgress, Size};
+use swage_core::util::{BackoffStrategy, NamedProgress, Size};
 use swage_core::{memory::Memory, util::PAGE_SIZE};
 use thiserror::Error;
 
@@ -31,23 +34,143 @@ use thiserror::Error;
 pub struct THP {
     conflict_threshold: u64,
     progress: Option<MultiProgress>,
+    mem_config: Option<MemConfiguration>,
+    collapse_retries: u32,
+    collapse_delay_ms: u64,
+}
+
+/// Path of the sysfs file configuring THP
+const THP_ENABLED_PATH: &str = "/sys/kernel/mm/transparent_hugepage/enabled";
+
+/// THP mode as configured in `/sys/kernel/mm/transparent_hugepage/enabled`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThpMode {
+    /// All anonymous mappings are backed by huge pages
+    Always,
+    /// Only regions advised with `madvise` are backed by huge pages
+    Madvise,
+    /// THP is disabled
+    Never,
+}
+
+impl ThpMode {
+    /// Parses the contents of the sysfs file, which marks the active mode with brackets,
+    /// e.g., `always [madvise] never`.
+    fn parse(contents: &str) -> Option<ThpMode> {
+        let active = contents
+            .split_whitespace()
+            .find_map(|mode| mode.strip_prefix('[')?.strip_suffix(']'))?;
+        match active {
+            "always" => Some(ThpMode::Always),
+            "madvise" => Some(ThpMode::Madvise),
+            "never" => Some(ThpMode::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can happen when checking the THP configuration
+#[derive(Debug, Error)]
+pub enum ThpCheckError {
+    /// The sysfs file cannot be read, e.g., because the kernel lacks THP support
+    #[error("Cannot read {THP_ENABLED_PATH}: {0}")]
+    Unavailable(std::io::Error),
+    /// The sysfs file has an unexpected format
+    #[error("Unexpected THP mode in {THP_ENABLED_PATH}: {0:?}")]
+    UnknownMode(String),
+    /// THP is disabled
+    #[error("THP is disabled (mode {mode:?}). {suggested_fix}")]
+    Disabled {
+        /// The configured mode
+        mode: ThpMode,
+        /// How to enable THP
+        suggested_fix: String,
+    },
 }
 
 impl THP {
+    /// Returns the configured THP mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ThpCheckError::Disabled`] if the mode is `never`, or another error if the mode
+    /// cannot be read.
+    pub fn verify_thp_enabled() -> Result<ThpMode, ThpCheckError> {
+        let contents =
+            std::fs::read_to_string(THP_ENABLED_PATH).map_err(ThpCheckError::Unavailable)?;
+        Self::check_mode(&contents)
+    }
+
+    fn check_mode(contents: &str) -> Result<ThpMode, ThpCheckError> {
+        match ThpMode::parse(contents) {
+            Some(ThpMode::Never) => Err(ThpCheckError::Disabled {
+                mode: ThpMode::Never,
+                suggested_fix: format!(
+                    "Enable THP with `echo madvise | sudo tee {}`.",
+                    THP_ENABLED_PATH
+                ),
+            }),
+            Some(mode) => Ok(mode),
+            None => Err(ThpCheckError::UnknownMode(contents.trim().to_string())),
+        }
+    }
+
+    /// Constructor for THP allocator that fails if THP is disabled.
+    ///
+    /// # Errors
+    ///
+    /// See [`THP::verify_thp_enabled`].
+    pub fn new_checked(
+        conflict_threshold: u64,
+        progress: Option<MultiProgress>,
+    ) -> Result<Self, ThpCheckError> {
+        let mode = Self::verify_thp_enabled()?;
+        debug!("THP mode: {:?}", mode);
+        Ok(Self::new(conflict_threshold, progress))
+    }
+
     /// Constructor for THP allocator
+    ///
+    /// Failed `MADV_COLLAPSE` calls are retried [`DEFAULT_COLLAPSE_RETRIES`] times, starting
+    /// with a delay of [`DEFAULT_COLLAPSE_DELAY_MS`]. See [`THP::with_collapse_retries`].
     pub fn new(conflict_threshold: u64, progress: Option<MultiProgress>) -> Self {
         THP {
             conflict_threshold,
             progress,
+            mem_config: None,
+            collapse_retries: DEFAULT_COLLAPSE_RETRIES,
+            collapse_delay_ms: DEFAULT_COLLAPSE_DELAY_MS,
         }
     }
+
+    /// Sets how often a failed `MADV_COLLAPSE` is retried.
+    ///
+    /// The delay before the first retry is `initial_delay_ms` and doubles with every retry.
+    pub fn with_collapse_retries(mut self, max_retries: u32, initial_delay_ms: u64) -> Self {
+        self.collapse_retries = max_retries;
+        self.collapse_delay_ms = initial_delay_ms;
+        self
+    }
+
+    /// Checks banks by physical address instead of access timing.
+    ///
+    /// This requires resolving PFNs, i.e., running as root.
+    pub fn with_mem_config(mut self, mem_config: MemConfiguration) -> Self {
+        self.mem_config = Some(mem_config);
+        self
+    }
 }
 
 const ALIGN_SIZE: Size = MB(2);
 
+/// Default number of retries of a failed `MADV_COLLAPSE`
+pub const DEFAULT_COLLAPSE_RETRIES: u32 = 3;
+/// Default delay before the first retry of a failed `MADV_COLLAPSE`
+pub const DEFAULT_COLLAPSE_DELAY_MS: u64 = 10;
+
 impl THP {
     /// allocate a 2 MB physically aligned memory block.
-    fn allocate_2m_aligned(size: Size) -> Result<Memory, std::io::Error> {
+    fn allocate_2m_aligned(&self, size: Size) -> Result<Memory, Error> {
         let aligned = unsafe {
             libc::mmap(
                 null_mut(),
@@ -59,11 +182,27 @@ impl THP {
             )
         };
         if aligned == libc::MAP_FAILED {
-            return Err(std::io::Error::last_os_error());
+            return Err(std::io::Error::last_os_error().into());
         }
+        // prefault the mapping, MADV_COLLAPSE needs populated pages to collapse
         unsafe { libc::memset(aligned, 0, size.bytes()) };
-        if unsafe { libc::madvise(aligned, size.bytes(), libc::MADV_COLLAPSE) } != 0 {
-            return Err(std::io::Error::last_os_error());
+        // the delay doubles after every retry
+        let backoff = BackoffStrategy::Exponential {
+            base: Duration::from_millis(self.collapse_delay_ms),
+            max: Duration::MAX,
+            factor: 2.0,
+        };
+        let collapsed = retry!(
+            || match unsafe { libc::madvise(aligned, size.bytes(), libc::MADV_COLLAPSE) } {
+                0 => Ok(()),
+                _ => Err(std::io::Error::last_os_error()),
+            },
+            backoff,
+            self.collapse_retries
+        );
+        if let Err(e) = collapsed {
+            unsafe { libc::munmap(aligned, size.bytes()) };
+            return Err(Error::CollapseRetryExhausted(e));
         }
         unsafe { libc::mlock(aligned, PAGE_SIZE) };
         if log_enabled!(log::Level::Debug)
@@ -72,12 +211,50 @@ impl THP {
             debug!("Aligned PFNs: {:?}", consecs);
         }
         assert_eq!(aligned as usize & (ALIGN_SIZE.bytes() - 1), 0);
-        assert_eq!(
-            aligned.pfn().unwrap_or_default().as_usize() & (ALIGN_SIZE.bytes() - 1),
-            0
-        );
+        if let Ok(Some(pfn)) = aligned.pfn() {
+            assert_eq!(pfn.as_usize() & (ALIGN_SIZE.bytes() - 1), 0);
+        }
         Ok(Memory::new(aligned as *mut u8, size.bytes()))
     }
+
+    /// Checks whether `block` and `last_block` start in the same bank.
+    ///
+    /// Uses [`ConsecBlocks::all_same_bank`] if a memory configuration is set, and falls back to
+    /// timing the blocks against the conflict threshold otherwise.
+    fn same_bank(
+        &self,
+        timer: Option<&dyn MemoryTupleTimer>,
+        block: &Memory,
+        last_block: &Memory,
+    ) -> Result<bool, Error> {
+        match (&self.mem_config, timer) {
+            (Some(mem_config), _) => {
+                let pair = ConsecBlocks::new(vec![last_block.clone(), block.clone()]);
+                let same_bank = pair.all_same_bank(mem_config).map_err(MemoryError::from)?;
+                if !same_bank {
+                    warn!(
+                        "Bank check failed for blocks {:?} and {:?}",
+                        block, last_block
+                    );
+                }
+                Ok(same_bank)
+            }
+            (None, Some(timer)) => {
+                let timing = unsafe {
+                    timer.time_subsequent_access_from_ram(block.ptr, last_block.ptr, 10000)
+                };
+                let same_bank = timing >= self.conflict_threshold;
+                if !same_bank {
+                    warn!(
+                        "Bank check failed: {} < {} for blocks {:?} and {:?}",
+                        timing, self.conflict_threshold, block, last_block
+                    );
+                }
+                Ok(same_bank)
+            }
+            (None, None) => unreachable!("timer is constructed without a memory configuration"),
+        }
+    }
 }
 
 /// Errors that can happen during THP allocation
@@ -88,8 +265,12 @@ pub enum Error {
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     TimerError(#[from] TimerError),
+    #[error(transparent)]
+    MemoryError(#[from] MemoryError),
     #[error("Size must be a multiple of {0}")]
     SizeError(Size),
+    #[error("MADV_COLLAPSE failed after all retries: {0}")]
+    CollapseRetryExhausted(std::io::Error),
 }
 
 impl ConsecAllocator for THP {
@@ -109,7 +290,10 @@ impl ConsecAllocator for THP {
         let mut blocks: Vec<Memory> = vec![];
         let required_blocks =
             max([size.bytes() / self.block_size().bytes(), 1]).expect("empty iter");
-        let timer = construct_memory_tuple_timer()?;
+        let timer = match self.mem_config {
+            Some(_) => None,
+            None => Some(construct_memory_tuple_timer()?),
+        };
         let p = self.progress.as_ref().map(|p| {
             p.add(
                 ProgressBar::new(required_blocks as u64)
@@ -118,24 +302,16 @@ impl ConsecAllocator for THP {
         });
         let mut garbage = vec![];
         while blocks.len() < required_blocks {
-            let block = Self::allocate_2m_aligned(size)?;
+            let block = self.allocate_2m_aligned(size)?;
 
             // check for same bank
-            if let Some(last_block) = blocks.last() {
-                let timing = unsafe {
-                    timer.time_subsequent_access_from_ram(block.ptr, last_block.ptr, 10000)
-                };
-                let same_bank = timing >= self.conflict_threshold;
-                if !same_bank {
-                    warn!(
-                        "Bank check failed: {} < {} for blocks {:?} and {:?}",
-                        timing, self.conflict_threshold, block, last_block
-                    );
-                    block.log_pfns(log::Level::Warn);
-                    last_block.log_pfns(log::Level::Warn);
-                    garbage.push(block);
-                    continue;
-                }
+            if let Some(last_block) = blocks.last()
+                && !self.same_bank(timer.as_deref(), &block, last_block)?
+            {
+                block.log_pfns(log::Level::Warn);
+                last_block.log_pfns(log::Level::Warn);
+                garbage.push(block);
+                continue;
             }
             if let Some(p) = &p {
                 p.inc(1);
@@ -145,6 +321,37 @@ impl ConsecAllocator for THP {
         for block in garbage {
             block.dealloc();
         }
-        Ok(ConsecBlocks::new(blocks))
+        Ok(ConsecBlocks::zeroed(blocks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_mode() {
+        assert_eq!(
+            THP::check_mode("always [madvise] never\n").unwrap(),
+            ThpMode::Madvise
+        );
+        assert_eq!(
+            THP::check_mode("[always] madvise never").unwrap(),
+            ThpMode::Always
+        );
+        match THP::check_mode("always madvise [never]") {
+            Err(ThpCheckError::Disabled {
+                mode,
+                suggested_fix,
+            }) => {
+                assert_eq!(mode, ThpMode::Never);
+                assert!(suggested_fix.contains(THP_ENABLED_PATH));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            THP::check_mode("always madvise never"),
+            Err(ThpCheckError::UnknownMode(_))
+        ));
     }
 }
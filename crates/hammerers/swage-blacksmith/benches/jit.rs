@@ -0,0 +1,100 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use swage_blacksmith::{
+    Blacksmith, BlacksmithCache, BlacksmithConfig, FromBlacksmithConfig, HammeringPattern,
+};
+use swage_core::memory::{ConsecBlocks, MemConfiguration, Memory};
+use swage_core::util::Size::MB;
+
+/// Number of aggressor rows accessed by the benchmarked pattern
+const AGGRESSORS: usize = 32;
+
+fn mem_config() -> MemConfiguration {
+    let bits =
+        |range: std::ops::Range<u64>| range.map(|b| b.to_string()).collect::<Vec<_>>().join(",");
+    let json = format!(
+        r#"{{"threshold":300,"bank_bits":[{}],"col_bits":[{}],"row_bits":[{}]}}"#,
+        bits(13..17),
+        bits(0..13),
+        bits(17..30)
+    );
+    let config: BlacksmithConfig = serde_json::from_str(&json).expect("invalid config");
+    MemConfiguration::from_blacksmith(&config).expect("invalid config")
+}
+
+/// Returns a pattern accessing `AGGRESSORS` rows of bank 0 within a 4 MB block.
+fn pattern() -> HammeringPattern {
+    // the configuration maps the lowest row bit to address bit 29, so reverse the 13 row bits
+    let row = |i: usize| (i as u32).reverse_bits() >> 19;
+    let aggressors = (0..AGGRESSORS)
+        .map(|i| format!(r#"[{},{{"bank":0,"row":{},"col":0}}]"#, i, row(i)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let access_ids = (0..AGGRESSORS)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let json = format!(
+        r#"{{"hammering_patterns":[{{
+            "id":"p",
+            "total_activations":5000000,
+            "num_refresh_intervals":16,
+            "access_ids":[{}],
+            "address_mappings":[{{
+                "id":"m",
+                "aggressor_to_addr":[{}],
+                "bit_flips":[],
+                "code_jitter":{{
+                    "fencing_strategy":"LATEST_POSSIBLE",
+                    "flushing_strategy":"EARLIEST_POSSIBLE",
+                    "num_aggs_for_sync":2,
+                    "pattern_sync_each_ref":false,
+                    "total_activations":5000000
+                }}
+            }}]
+        }}]}}"#,
+        access_ids, aggressors
+    );
+    HammeringPattern::load_from_str(&json)
+        .expect("invalid pattern")
+        .remove(0)
+}
+
+fn bench_jit(c: &mut Criterion) {
+    let mem_config = mem_config();
+    let pattern = pattern();
+    let mapping = &pattern.address_mappings[0];
+    let memory = ConsecBlocks::new(vec![Memory::mmap(MB(4).bytes()).expect("mmap failed")]);
+    let mut group = c.benchmark_group("Blacksmith");
+    group.sample_size(10);
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            black_box(
+                Blacksmith::new(mem_config, &pattern, mapping, 22.into(), &memory, 1.into())
+                    .expect("jit failed"),
+            )
+        })
+    });
+    group.bench_function("from_cached", |b| {
+        let cache = BlacksmithCache::default();
+        b.iter(|| {
+            black_box(
+                Blacksmith::from_cached(
+                    &cache,
+                    mem_config,
+                    &pattern,
+                    mapping,
+                    22.into(),
+                    &memory,
+                    1.into(),
+                )
+                .expect("jit failed"),
+            )
+        })
+    });
+    group.finish();
+    memory.dealloc();
+}
+
+criterion_group!(benches, bench_jit);
+criterion_main!(benches);
@@ -16,7 +16,7 @@ fn main() -> Result<(), swage_blacksmith::Error> {
     let args = CliArgs::parse();
     let config = BlacksmithConfig::from_jsonfile(&args.config)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
     let addr = 0x2000000000 as *mut u8;
     let row_offsets = mem_config.bank_function_period() as usize;
     info!("Row offsets: {}", row_offsets);
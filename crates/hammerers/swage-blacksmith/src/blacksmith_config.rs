@@ -1,13 +1,19 @@
+use crate::{FromBlacksmithConfig, MatrixError};
+use log::{info, warn};
 use serde::Deserialize;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use swage_core::memory::{
+    DRAMGeometry, DRAMStandard, MTX_SIZE, MemConfiguration, TimerError,
+    construct_memory_tuple_timer,
+};
 use thiserror::Error;
 
 /// Defines which physical address bits are used for DRAM mapping.
 ///
 /// Can specify a single bit or multiple bits for row/column/bank functions.
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 #[allow(missing_docs)]
 pub enum BitDef {
@@ -47,6 +53,12 @@ pub enum Error {
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+    #[error(transparent)]
+    TimerError(#[from] TimerError),
+    #[error(transparent)]
+    MatrixError(#[from] MatrixError),
 }
 
 /// Result type for BlacksmithConfig constructor.
@@ -64,6 +76,9 @@ pub struct BlacksmithConfig {
     //total_banks: u64,
     //max_rows: u64,
     /// Timing threshold for bank conflict detection (in CPU cycles)
+    ///
+    /// If 0 or absent, [`BlacksmithConfig::from_jsonfile`] calibrates the threshold.
+    #[serde(default)]
     pub threshold: u64,
     //hammer_rounds: usize,
     //drama_rounds: usize,
@@ -79,24 +94,63 @@ pub struct BlacksmithConfig {
 impl BlacksmithConfig {
     /// Loads configuration from a JSON file.
     ///
+    /// If the file does not specify a `threshold`, it is measured with
+    /// [`swage_core::memory::MemoryTupleTimer::calibrate`].
+    ///
     /// # Arguments
     ///
     /// * `filepath` - Path to the JSON configuration file
     ///
     /// # Errors
     ///
-    /// Returns error if file cannot be read or parsed
+    /// Returns error if file cannot be read or parsed, or if calibrating the threshold fails.
+    /// Calibrating requires a valid addressing matrix, see [`MatrixError`].
     pub fn from_jsonfile(filepath: &str) -> Result<BlacksmithConfig> {
         let mut file = File::open(Path::new(filepath))?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
-        let config: BlacksmithConfig = serde_json::from_str(&contents)?;
+        let mut config: BlacksmithConfig = serde_json::from_str(&contents)?;
+        if config.threshold == 0 {
+            let mem_config = MemConfiguration::from_blacksmith(&config)?;
+            config.threshold = construct_memory_tuple_timer()?.calibrate(&mem_config)?;
+            info!("Calibrated conflict threshold: {}", config.threshold);
+        }
         Ok(config)
     }
+
+    /// Validates the bit definitions and derives the DRAM geometry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidConfig`] if the bit definitions do not cover
+    /// [`MTX_SIZE`] bits or result in an empty geometry, and [`Error::MatrixError`] if
+    /// they do not form an invertible addressing matrix.
+    pub fn validate(&self) -> Result<DRAMGeometry> {
+        let num_bits = self.bank_bits.len() + self.row_bits.len() + self.col_bits.len();
+        if num_bits != MTX_SIZE {
+            return Err(Error::InvalidConfig(format!(
+                "expected {} bank, row and column bits, got {}",
+                MTX_SIZE, num_bits
+            )));
+        }
+        if self.bank_bits.is_empty() || self.row_bits.is_empty() || self.col_bits.is_empty() {
+            return Err(Error::InvalidConfig(
+                "bank, row and column bits must not be empty".into(),
+            ));
+        }
+        let geometry =
+            DRAMGeometry::from_mem_configuration(&MemConfiguration::from_blacksmith(self)?);
+        if geometry.matches_standard() == Some(DRAMStandard::Unknown) {
+            warn!("Unknown DRAM geometry: {}", geometry.display_summary());
+        }
+        Ok(geometry)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     #[test]
     fn test_bank_function_period() {
         use crate::FromBitDefs;
@@ -105,7 +159,74 @@ mod tests {
         let config = BlacksmithConfig::from_jsonfile("config/bs-config.json")
             .expect("failed to read config file");
         let mem_config =
-            MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+            MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)
+                .expect("invalid config");
         assert_eq!(mem_config.bank_function_period(), 512);
     }
+
+    #[test]
+    fn test_validate() {
+        use crate::blacksmith_config::{BlacksmithConfig, Error};
+        let bits = |range: std::ops::Range<u64>| range.map(|b| format!("{}", b)).join(",");
+        let json = format!(
+            r#"{{"threshold":300,"bank_bits":[{}],"col_bits":[{}],"row_bits":[{}]}}"#,
+            bits(13..17),
+            bits(0..13),
+            bits(17..30)
+        );
+        let config: BlacksmithConfig = serde_json::from_str(&json).expect("invalid json");
+        let geometry = config.validate().expect("invalid config");
+        assert_eq!(geometry.banks, 16);
+        assert_eq!(geometry.rows, 1 << 13);
+        assert_eq!(geometry.columns, 1 << 13);
+        {
+            use crate::FromBlacksmithConfig;
+            use swage_core::memory::MemConfiguration;
+            assert!(
+                MemConfiguration::from_blacksmith(&config)
+                    .expect("invalid config")
+                    .is_valid()
+            );
+        }
+
+        let json = format!(
+            r#"{{"threshold":300,"bank_bits":[{}],"col_bits":[{}],"row_bits":[]}}"#,
+            bits(13..17),
+            bits(0..13)
+        );
+        let config: BlacksmithConfig = serde_json::from_str(&json).expect("invalid json");
+        assert!(matches!(config.validate(), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_from_bitdefs_errors() {
+        use crate::{BitDef::Single, FromBitDefs, MatrixError};
+        use swage_core::memory::MemConfiguration;
+        let bits = |range: std::ops::Range<u64>| range.map(Single).collect::<Vec<_>>();
+        assert!(matches!(
+            MemConfiguration::from_bitdefs(bits(13..17), bits(17..29), bits(0..13)),
+            Err(MatrixError::DimensionMismatch {
+                expected: 30,
+                actual: 29
+            })
+        ));
+        // bit 16 is used twice, bit 29 is never used
+        let err = MemConfiguration::from_bitdefs(bits(13..17), bits(16..29), bits(0..13))
+            .expect_err("singular matrix");
+        assert!(matches!(err, MatrixError::NotInvertible { .. }));
+        assert!(err.to_string().contains("row bits: [Single(16)"));
+        assert!(
+            MemConfiguration::from_bitdefs(bits(13..17), bits(17..30), bits(0..13))
+                .expect("invalid config")
+                .is_valid()
+        );
+    }
+
+    #[test]
+    fn test_threshold_default() {
+        use crate::blacksmith_config::BlacksmithConfig;
+        let json = r#"{"bank_bits":[13],"col_bits":[0],"row_bits":[17]}"#;
+        let config: BlacksmithConfig = serde_json::from_str(json).expect("invalid json");
+        assert_eq!(config.threshold, 0);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::jitter::{CodeJitter, Jitter, Program};
+use crate::jitter::{CodeJitter, JitValidationError, Jitter, MAX_JIT_SIZE, Program};
 use itertools::Itertools;
 use log::{debug, error, info, trace, warn};
 use rand::Rng;
@@ -8,16 +8,25 @@ use std::arch::asm;
 use std::arch::x86_64::{__rdtscp, _mm_mfence};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::Range;
+use std::sync::Arc;
+#[cfg(feature = "jit-cache")]
+use std::sync::Mutex;
 use std::time::Instant;
-use std::{collections::HashMap, fs::File, io::BufReader};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+};
+use swage_core::MemCheck;
 use swage_core::hammerer::Hammering;
 use swage_core::memory::{
-    AggressorPtr, BytePointer, ConsecBlocks, DRAMAddr, LinuxPageMap, MemConfiguration,
-    VirtToPhysResolver,
+    AggressorPtr, ConsecBlocks, DRAMAddr, DataPattern, LinuxPageMap, MemConfiguration,
+    MemoryRegion, VirtToPhysResolver,
 };
 use swage_core::util;
-use swage_core::util::{CL_SIZE, GroupBy, Size::MB};
-use swage_core::victim::HammerVictimError;
+use swage_core::util::{CL_SIZE, GroupBySorted, Size::MB};
+use swage_core::victim::{HammerVictimError, VictimOrchestrator};
 use thiserror::Error;
 #[cfg(feature = "iperf")]
 use {
@@ -69,6 +78,14 @@ pub struct PatternAddressMapper {
 }
 
 impl PatternAddressMapper {
+    /// Returns the DRAM address of each aggressor, ordered by aggressor ID.
+    pub fn aggressor_to_addr(&self) -> BTreeMap<u64, DRAMAddr> {
+        self.aggressor_to_addr
+            .iter()
+            .map(|(agg, addr)| (agg.0, addr.clone()))
+            .collect()
+    }
+
     /// Translates aggressor identifiers to virtual addresses.
     ///
     /// # Arguments
@@ -104,12 +121,12 @@ impl PatternAddressMapper {
     ///
     /// # Returns
     ///
-    /// Map from block prefix to aggressors in that block
+    /// Map from block prefix to aggressors in that block, ordered by block prefix
     pub fn aggressor_sets(
         &self,
         mem_config: MemConfiguration,
         block_shift: usize,
-    ) -> HashMap<usize, Vec<Aggressor>> {
+    ) -> BTreeMap<usize, Vec<Aggressor>> {
         // find mapping classes
         let addrs: &HashMap<Aggressor, DRAMAddr> = &self.aggressor_to_addr;
 
@@ -117,7 +134,7 @@ impl PatternAddressMapper {
 
         // group aggressors by prefix
         addrs_vec
-            .group_by(|(_, addr)| {
+            .group_by_sorted(|(_, addr)| {
                 #[allow(clippy::zero_ptr)]
                 let virt = addr.to_virt(0 as *const u8, mem_config) as usize;
                 virt >> block_shift
@@ -148,13 +165,14 @@ impl PatternAddressMapper {
         aggressors: &[Aggressor],
         mem_config: MemConfiguration,
         block_shift: usize,
-        memory: &ConsecBlocks,
+        memory: &dyn MemoryRegion,
     ) -> Vec<AggressorPtr> {
         info!("Relocating aggressors with shift {}", block_shift);
         let block_size = 1 << block_shift;
         let addrs = &self.aggressor_to_addr;
         let sets = self.aggressor_sets(mem_config, block_shift);
 
+        // sets are ordered by block prefix, so each set is relocated to the same block on every run
         let mut base_lookup: HashMap<Aggressor, usize> = HashMap::new();
         for (idx, (base, group)) in sets.iter().enumerate() {
             debug!("Index/Base/Group: {}, {}, {:?}", idx, base, group);
@@ -187,7 +205,7 @@ impl PatternAddressMapper {
                 let p = pagemap.get_phys(relocated as u64);
                 match p {
                     Ok(p) => {
-                        let phys = DRAMAddr::from_virt(p.into(), &mem_config);
+                        let phys = DRAMAddr::from_phys(p, &mem_config);
                         debug!(
                             "Relocate {:?} to {:?} (0x{:x}), phys {:?} ({:p}), base: 0x{:x}, base_idx {}",
                             addr,
@@ -228,6 +246,23 @@ pub struct FuzzSummary {
     pub hammering_patterns: Vec<HammeringPattern>,
 }
 
+impl FuzzSummary {
+    /// Keeps only patterns with at least `min` bit flips over all mappings.
+    pub fn filter_by_min_flips(&mut self, min: usize) {
+        self.hammering_patterns
+            .retain(|pattern| pattern.count_bitflips() >= min);
+    }
+
+    /// Finds a pattern by its identifier.
+    ///
+    /// # Returns
+    ///
+    /// The matching pattern, or None if not found
+    pub fn filter_by_pattern_id(&self, id: &str) -> Option<&HammeringPattern> {
+        self.hammering_patterns.iter().find(|p| p.id == id)
+    }
+}
+
 /// A Blacksmith hammering pattern discovered through fuzzing.
 ///
 /// Contains aggressor access sequences and address mappings that
@@ -265,6 +300,23 @@ pub enum PatternLoadError {
     NotFound(String),
 }
 
+/// Errors reported by [`HammeringPattern::verify_pattern_validity`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PatternValidationError {
+    /// The aggressors map to more than one DRAM bank
+    #[error("Aggressors map to different banks: {banks_found:?}")]
+    MixedBanks {
+        /// The distinct banks the aggressors map to, in ascending order
+        banks_found: Vec<usize>,
+    },
+    /// The pattern has no address mappings
+    #[error("Pattern has no address mappings")]
+    NoAddressMappings,
+    /// The aggressor with this id has no address in the mapping
+    #[error("Aggressor {0} has no address in the mapping")]
+    InvalidAddress(usize),
+}
+
 impl HammeringPattern {
     /// Loads all patterns from a Blacksmith JSON file.
     ///
@@ -277,11 +329,30 @@ impl HammeringPattern {
     /// Returns error if file cannot be read or parsed
     pub fn load_patterns(json_filename: &str) -> Result<Vec<HammeringPattern>, PatternLoadError> {
         let f = File::open(json_filename)?;
-        let reader = BufReader::new(f);
+        HammeringPattern::load_from_reader(BufReader::new(f))
+    }
+
+    /// Loads all patterns from Blacksmith fuzzing results read from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the results cannot be read or parsed
+    pub fn load_from_reader<R: std::io::Read>(
+        reader: R,
+    ) -> Result<Vec<HammeringPattern>, PatternLoadError> {
         let patterns: FuzzSummary = serde_json::from_reader(reader)?;
         Ok(patterns.hammering_patterns)
     }
 
+    /// Loads all patterns from Blacksmith fuzzing results given as a JSON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the results cannot be parsed
+    pub fn load_from_str(json: &str) -> Result<Vec<HammeringPattern>, PatternLoadError> {
+        HammeringPattern::load_from_reader(json.as_bytes())
+    }
+
     /// Load pattern with ID `pattern_id` from `json_filename`
     pub fn load_pattern_from_json(
         json_filename: &str,
@@ -302,10 +373,32 @@ impl HammeringPattern {
     ///
     /// The most effective mapping, or None if no mappings exist
     pub fn determine_most_effective_mapping(&self) -> Option<PatternAddressMapper> {
+        self.rank_mappings_by(PatternAddressMapper::count_bitflips)
+            .first()
+            .map(|m| (*m).clone())
+    }
+
+    /// Returns all address mappings ordered by descending `scorer` value.
+    ///
+    /// Mappings with equal scores keep their original order.
+    pub fn rank_mappings_by<F>(&self, scorer: F) -> Vec<&PatternAddressMapper>
+    where
+        F: Fn(&PatternAddressMapper) -> usize,
+    {
+        let mut mappings = self.address_mappings.iter().collect_vec();
+        mappings.sort_by_key(|m| std::cmp::Reverse(scorer(m)));
+        mappings
+    }
+
+    /// Returns all address mappings matching `predicate`.
+    pub fn filter_mappings<F>(&self, predicate: F) -> Vec<&PatternAddressMapper>
+    where
+        F: Fn(&PatternAddressMapper) -> bool,
+    {
         self.address_mappings
             .iter()
-            .max_by_key(|m| m.count_bitflips())
-            .cloned()
+            .filter(|m| predicate(m))
+            .collect()
     }
 
     /// Finds an address mapping by its identifier.
@@ -323,6 +416,98 @@ impl HammeringPattern {
             .find(|m| m.id == mapping_id)
             .cloned()
     }
+
+    /// Returns the total number of bit flips over all address mappings.
+    pub fn count_bitflips(&self) -> usize {
+        self.address_mappings
+            .iter()
+            .map(|m| m.count_bitflips())
+            .sum()
+    }
+
+    /// Returns the total number of row activations in this pattern.
+    pub fn total_activations(&self) -> u32 {
+        self.total_activations
+    }
+
+    /// Checks that all aggressors of the first address mapping map to the same DRAM bank.
+    ///
+    /// Aggressors in different banks do not cause row buffer conflicts with each other, so
+    /// such a pattern is not effective.
+    ///
+    /// # Arguments
+    ///
+    /// * `mem_config` - DRAM configuration
+    /// * `base` - Base address for virtual address calculation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatternValidationError::MixedBanks`] if the aggressors map to different banks.
+    pub fn verify_pattern_validity(
+        &self,
+        mem_config: &MemConfiguration,
+        base: *const u8,
+    ) -> Result<(), PatternValidationError> {
+        let mapping = self
+            .address_mappings
+            .first()
+            .ok_or(PatternValidationError::NoAddressMappings)?;
+        self.verify_mapping_validity(mapping, mem_config, base)
+    }
+
+    /// Checks that all aggressors of `mapping` map to the same DRAM bank.
+    ///
+    /// See [`Self::verify_pattern_validity`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatternValidationError::MixedBanks`] if the aggressors map to different banks.
+    pub fn verify_mapping_validity(
+        &self,
+        mapping: &PatternAddressMapper,
+        mem_config: &MemConfiguration,
+        base: *const u8,
+    ) -> Result<(), PatternValidationError> {
+        let banks = self.aggressor_banks(mapping, mem_config, base)?;
+        if banks.len() > 1 {
+            return Err(PatternValidationError::MixedBanks { banks_found: banks });
+        }
+        Ok(())
+    }
+
+    /// Returns the bank all aggressors of the first address mapping map to.
+    ///
+    /// Returns None if the pattern is invalid, see [`Self::verify_pattern_validity`].
+    pub fn expected_bank(&self, config: &MemConfiguration, base: *const u8) -> Option<usize> {
+        let mapping = self.address_mappings.first()?;
+        match self.aggressor_banks(mapping, config, base).ok()?.as_slice() {
+            [bank] => Some(*bank),
+            _ => None,
+        }
+    }
+
+    /// Returns the distinct, sorted banks of the aggressors in `mapping`.
+    fn aggressor_banks(
+        &self,
+        mapping: &PatternAddressMapper,
+        mem_config: &MemConfiguration,
+        base: *const u8,
+    ) -> Result<Vec<usize>, PatternValidationError> {
+        let aggressors = self.access_ids.iter().copied().unique().collect_vec();
+        if let Some(missing) = aggressors
+            .iter()
+            .find(|agg| !mapping.aggressor_to_addr.contains_key(agg))
+        {
+            return Err(PatternValidationError::InvalidAddress(missing.0 as usize));
+        }
+        Ok(mapping
+            .get_hammering_addresses(&aggressors, base, *mem_config)
+            .into_iter()
+            .map(|addr| DRAMAddr::from_virt(addr, mem_config).bank)
+            .unique()
+            .sorted()
+            .collect())
+    }
 }
 
 /// Number of hammering attempts to perform.
@@ -333,12 +518,76 @@ pub struct Attempts(u32);
 #[derive(Copy, Clone)]
 pub struct BlockShift(usize);
 
+/// Errors that can occur when constructing or configuring a [`Blacksmith`] hammerer.
+#[derive(Debug, Error)]
+pub enum BlacksmithError {
+    /// JIT compilation of the hammering pattern failed or produced an invalid program
+    #[error("JIT failed: {0}")]
+    JitFailed(Box<dyn std::error::Error>),
+    /// [`BlacksmithHammerConfig::wait_until_start_hammering_range`] is empty
+    #[error("wait_until_start_hammering_range {0:?} is empty")]
+    EmptyWaitRange(Range<u32>),
+}
+
+/// Timing parameters of [`Blacksmith`] hammering.
+///
+/// The defaults match DDR4 with a refresh interval (tREFI) of 7.8us. Use
+/// [`Blacksmith::with_config`] to adapt them to other DRAM modules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlacksmithHammerConfig {
+    /// Length of a refresh interval in microseconds.
+    ///
+    /// This only converts [`wait_until_start_hammering_range`](Self::wait_until_start_hammering_range)
+    /// into a duration. The hammering itself is timed by the number of activations per
+    /// refresh interval of the pattern and is not affected.
+    pub ref_interval_us: f32,
+    /// Number of refresh intervals to do random accesses for before each hammering attempt.
+    ///
+    /// The number is drawn uniformly from this range, which must not be empty.
+    pub wait_until_start_hammering_range: Range<u32>,
+}
+
+impl Default for BlacksmithHammerConfig {
+    fn default() -> Self {
+        BlacksmithHammerConfig {
+            ref_interval_us: 7.8,
+            // range 10..128 is hard-coded in FuzzingParameterSet
+            wait_until_start_hammering_range: 10..128,
+        }
+    }
+}
+
+impl BlacksmithHammerConfig {
+    /// Checks that the parameters can be used for hammering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BlacksmithError::EmptyWaitRange`] if
+    /// [`wait_until_start_hammering_range`](Self::wait_until_start_hammering_range) is empty.
+    pub fn validate(&self) -> Result<(), BlacksmithError> {
+        if self.wait_until_start_hammering_range.is_empty() {
+            return Err(BlacksmithError::EmptyWaitRange(
+                self.wait_until_start_hammering_range.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draws the time to do random accesses for before hammering, in microseconds.
+    fn wait_until_start_hammering_us(&self, rng: &mut impl Rng) -> f32 {
+        let refs = rng.random_range(self.wait_until_start_hammering_range.clone());
+        refs as f32 * self.ref_interval_us
+    }
+}
+
 /// Blacksmith Rowhammer attack implementation.
 ///
 /// Executes JIT-compiled hammering patterns discovered through fuzzing.
 pub struct Blacksmith {
     /// JIT-compiled hammering program
-    program: Program,
+    program: Arc<Program>,
+    /// Hammering timing parameters
+    hammer_config: BlacksmithHammerConfig,
     /// Number of hammering attempts
     attempts: Attempts,
     /// Cache flush addresses
@@ -358,30 +607,156 @@ impl Blacksmith {
     /// * `block_shift` - Memory block alignment
     /// * `memory` - Target memory blocks
     /// * `attempts` - Number of hammering attempts
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BlacksmithError::JitFailed`] if the pattern cannot be JIT-compiled or the
+    /// compiled program exceeds the maximum size.
     pub fn new(
         mem_config: MemConfiguration,
         pattern: &HammeringPattern,
         mapping: &PatternAddressMapper,
         block_shift: BlockShift,
-        memory: &ConsecBlocks, // TODO change to dyn BytePointer after updating hammer_log_cb
+        memory: &dyn MemoryRegion,
         attempts: Attempts,
-    ) -> Self {
+    ) -> Result<Self, BlacksmithError> {
+        let hammering_addrs =
+            Self::hammering_addrs(mem_config, pattern, mapping, block_shift, memory);
+        let program = Self::jit(mem_config, pattern, mapping, &hammering_addrs, memory)?;
+        Ok(Self::with_program(Arc::new(program), attempts))
+    }
+
+    /// Creates a new Blacksmith hammerer, reusing the program compiled for the same pattern
+    /// and mapping from `cache`.
+    ///
+    /// The cached program is only reused if it hammers the same addresses, i.e., if
+    /// `memory` did not change. Otherwise, the pattern is compiled again and replaces the
+    /// cached program. See [`Blacksmith::new`] for the arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BlacksmithError::JitFailed`] if the pattern cannot be JIT-compiled or the
+    /// compiled program exceeds the maximum size.
+    #[cfg(feature = "jit-cache")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_cached(
+        cache: &BlacksmithCache,
+        mem_config: MemConfiguration,
+        pattern: &HammeringPattern,
+        mapping: &PatternAddressMapper,
+        block_shift: BlockShift,
+        memory: &dyn MemoryRegion,
+        attempts: Attempts,
+    ) -> Result<Self, BlacksmithError> {
+        let hammering_addrs =
+            Self::hammering_addrs(mem_config, pattern, mapping, block_shift, memory);
+        let addrs = hammering_addrs.iter().map(|&a| a as usize).collect_vec();
+        let key = (pattern.id.clone(), mapping.id.clone());
+        let mut programs = cache.0.lock().expect("poisoned cache");
+        let program = match programs.get(&key) {
+            Some(cached) if cached.addrs == addrs => {
+                debug!("Reusing cached program for {:?}", key);
+                cached.program.clone()
+            }
+            _ => {
+                let program = Arc::new(Self::jit(
+                    mem_config,
+                    pattern,
+                    mapping,
+                    &hammering_addrs,
+                    memory,
+                )?);
+                programs.insert(
+                    key,
+                    CachedProgram {
+                        addrs,
+                        program: program.clone(),
+                    },
+                );
+                program
+            }
+        };
+        drop(programs);
+        Ok(Self::with_program(program, attempts))
+    }
+
+    fn with_program(program: Arc<Program>, attempts: Attempts) -> Self {
         let flush_buf: *mut u8 = util::mmap(std::ptr::null_mut(), MB(1024).bytes());
         let flush_lines = (0..MB(1024).bytes())
             .step_by(CL_SIZE)
             .map(|offset| unsafe { flush_buf.byte_add(offset) as usize })
             .collect_vec();
+        Self {
+            program,
+            hammer_config: BlacksmithHammerConfig::default(),
+            attempts,
+            flush_lines,
+        }
+    }
 
+    /// Returns the JIT-compiled hammering program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Sets the hammering timing parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BlacksmithError::EmptyWaitRange`] if `config` is invalid, see
+    /// [`BlacksmithHammerConfig::validate`].
+    pub fn with_config(mut self, config: BlacksmithHammerConfig) -> Result<Self, BlacksmithError> {
+        config.validate()?;
+        self.hammer_config = config;
+        Ok(self)
+    }
+
+    /// Relocates the aggressors of `pattern` into `memory`.
+    fn hammering_addrs(
+        mem_config: MemConfiguration,
+        pattern: &HammeringPattern,
+        mapping: &PatternAddressMapper,
+        block_shift: BlockShift,
+        memory: &dyn MemoryRegion,
+    ) -> Vec<AggressorPtr> {
         info!("Using pattern {}", pattern.id);
         info!("Using mapping {}", mapping.id);
+        debug_assert!(
+            pattern
+                .verify_mapping_validity(mapping, &mem_config, memory.ptr())
+                .is_ok(),
+            "invalid mapping {} of pattern {}",
+            mapping.id,
+            pattern.id
+        );
 
+        let hammering_addrs = mapping.get_hammering_addresses_relocate(
+            &pattern.access_ids,
+            mem_config,
+            block_shift.0,
+            memory,
+        );
+        let num_accessed_addrs = hammering_addrs
+            .iter()
+            .map(|x| (*x as usize) & !0xFFF)
+            .unique()
+            .count();
+
+        info!("Pattern contains {} accessed addresses", num_accessed_addrs);
+        hammering_addrs
+    }
+
+    /// JIT-compiles `pattern` hammering `hammering_addrs`.
+    fn jit(
+        mem_config: MemConfiguration,
+        pattern: &HammeringPattern,
+        mapping: &PatternAddressMapper,
+        hammering_addrs: &[AggressorPtr],
+        memory: &dyn MemoryRegion,
+    ) -> Result<Program, BlacksmithError> {
         let hammer_log_cb = |action: &str, addr: *const u8| {
-            let block_idx = memory.blocks.iter().find_position(|base| {
-                (addr as u64) >= base.ptr() as u64
-                    && (addr as u64) <= (base.addr(base.len() - 1) as u64)
-            });
-            let found = block_idx.is_some();
-            if !found {
+            let offset = memory.offset_of(addr);
+            if offset.is_none() {
                 error!("OUT OF BOUNDS ACCESS: {} {:?}", action, addr);
             }
             let paddr = LinuxPageMap::new()
@@ -389,14 +764,14 @@ impl Blacksmith {
                 .get_phys(addr as u64);
             match paddr {
                 Ok(paddr) => {
-                    let dram = DRAMAddr::from_virt(paddr.into(), &mem_config);
+                    let dram = DRAMAddr::from_phys(paddr, &mem_config);
                     trace!(
-                        "{:>06} {:02},{:04},{:p},{}",
+                        "{:>06} {:02},{:04},{:p},0x{:x}",
                         action,
                         dram.bank,
                         dram.row,
                         paddr,
-                        block_idx.map(|(idx, _)| idx).unwrap_or(usize::MAX)
+                        offset.unwrap_or(usize::MAX)
                     )
                 }
                 Err(e) => warn!("Failed to get physical address: {}", e),
@@ -405,36 +780,138 @@ impl Blacksmith {
 
         let acts_per_tref = pattern.total_activations / pattern.num_refresh_intervals;
 
-        let hammering_addrs = mapping.get_hammering_addresses_relocate(
-            &pattern.access_ids,
-            mem_config,
-            block_shift.0,
-            memory,
-        );
-        let num_accessed_addrs = hammering_addrs
-            .iter()
-            .map(|x| (*x as usize) & !0xFFF)
-            .unique()
-            .count();
-
-        info!("Pattern contains {} accessed addresses", num_accessed_addrs);
-
+        let estimated_size = CodeJitter::estimated_size(acts_per_tref as u64, hammering_addrs);
+        if estimated_size >= MAX_JIT_SIZE {
+            return Err(BlacksmithError::JitFailed(Box::new(
+                JitValidationError::TooLarge {
+                    size: estimated_size,
+                    max: MAX_JIT_SIZE,
+                },
+            )));
+        }
         let program = mapping
             .code_jitter
-            .jit(acts_per_tref as u64, &hammering_addrs, &hammer_log_cb)
-            .expect("JIT failed");
+            .jit(acts_per_tref as u64, hammering_addrs, &hammer_log_cb)
+            .map_err(|e| BlacksmithError::JitFailed(Box::new(e)))?;
+        program
+            .validate()
+            .map_err(|e| BlacksmithError::JitFailed(Box::new(e)))?;
+        debug!(
+            "JIT program: {} instructions, {} bytes",
+            program.instruction_count(),
+            program.byte_size()
+        );
         if cfg!(feature = "jitter_dump") {
             program
                 .write("hammer_jit.o")
                 .expect("failed to write function to disk");
         }
+        Ok(program)
+    }
+}
 
-        Self {
-            program,
-            attempts,
-            flush_lines,
+/// Cache of JIT-compiled programs shared between [`Blacksmith`] instances.
+///
+/// Programs are keyed by pattern and mapping id. Cloning the cache shares the
+/// underlying programs.
+#[cfg(feature = "jit-cache")]
+#[derive(Clone, Default)]
+pub struct BlacksmithCache(Arc<Mutex<HashMap<(String, String), CachedProgram>>>);
+
+#[cfg(feature = "jit-cache")]
+struct CachedProgram {
+    /// Addresses the program hammers
+    addrs: Vec<usize>,
+    program: Arc<Program>,
+}
+
+#[cfg(feature = "jit-cache")]
+impl BlacksmithCache {
+    /// Returns the number of cached programs.
+    pub fn len(&self) -> usize {
+        self.0.lock().expect("poisoned cache").len()
+    }
+
+    /// Returns true if no program is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Blacksmith {
+    /// Selects the address mapping of `pattern` that causes the most bit flips in `memory`.
+    ///
+    /// In contrast to [`HammeringPattern::determine_most_effective_mapping`], which relies on
+    /// the bit flips observed during fuzzing, this hammers each mapping `profiling_rounds`
+    /// times and counts the bit flips on the current hardware.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` has no address mappings that can be JIT-compiled.
+    pub fn profile_pattern(
+        mem_config: MemConfiguration,
+        pattern: &HammeringPattern,
+        block_shift: BlockShift,
+        memory: &ConsecBlocks,
+        attempts: Attempts,
+        profiling_rounds: u32,
+    ) -> (Blacksmith, PatternAddressMapper) {
+        let candidates =
+            pattern.address_mappings.iter().filter_map(|mapping| {
+                match Blacksmith::new(mem_config, pattern, mapping, block_shift, memory, attempts) {
+                    Ok(blacksmith) => Some((mapping.clone(), blacksmith)),
+                    Err(e) => {
+                        warn!("Skipping mapping {}: {}", mapping.id, e);
+                        None
+                    }
+                }
+            });
+        let (mapping, blacksmith) = select_most_flips(candidates, memory, profiling_rounds)
+            .expect("Pattern has no address mappings");
+        (blacksmith, mapping)
+    }
+}
+
+/// Returns the candidate whose hammerer causes the most bit flips in `memory`.
+///
+/// Candidates are constructed lazily, so at most two hammerers are alive at any time.
+fn select_most_flips<T, H: Hammering>(
+    candidates: impl IntoIterator<Item = (T, H)>,
+    memory: &ConsecBlocks,
+    profiling_rounds: u32,
+) -> Option<(T, H)> {
+    let mut best: Option<(usize, T, H)> = None;
+    for (candidate, hammerer) in candidates {
+        let flips = count_flips(&hammerer, memory, profiling_rounds);
+        if best.as_ref().is_none_or(|(max, _, _)| flips > *max) {
+            best = Some((flips, candidate, hammerer));
+        }
+    }
+    best.map(|(flips, candidate, hammerer)| {
+        info!("Selected candidate with {} bit flips", flips);
+        (candidate, hammerer)
+    })
+}
+
+/// Hammers `memory` `rounds` times and returns the total number of bit flips.
+fn count_flips<H: Hammering>(hammerer: &H, memory: &ConsecBlocks, rounds: u32) -> usize {
+    let pattern = DataPattern::Random(Box::new(util::Rng::from_seed(rand::random())));
+    let mut victim = MemCheck::new(memory.clone(), pattern, vec![].into());
+    let mut flips = 0;
+    for round in 0..rounds {
+        victim.init();
+        if let Err(e) = hammerer.hammer() {
+            warn!("Profiling round {} failed: {:?}", round, e);
+            continue;
+        }
+        match victim.check() {
+            Ok(result) => flips += result.bit_flips().len(),
+            Err(HammerVictimError::NoFlips) => {}
+            Err(e) => warn!("Profiling round {} failed: {:?}", round, e),
         }
     }
+    debug!("Counted {} bit flips in {} rounds", flips, rounds);
+    flips
 }
 
 impl Drop for Blacksmith {
@@ -463,7 +940,6 @@ impl Hammering for Blacksmith {
     fn hammer(&self) -> Result<(), Self::Error> {
         info!("Hammering with {} attempts", self.attempts.0);
         let mut rng = rand::rng();
-        const REF_INTERVAL_LEN_US: f32 = 7.8; // check if can be derived from pattern?
         #[cfg(feature = "iperf")]
         {
             let mut pc_miss: PerfCounter =
@@ -485,9 +961,8 @@ impl Hammering for Blacksmith {
                 pc_miss.reset().expect("Could not reset counter");
                 pc_ref.reset().expect("Could not reset counter");
             }
-            let wait_until_start_hammering_refs = rng.random_range(10..128); // range 10..128 is hard-coded in FuzzingParameterSet
             let wait_until_start_hammering_us =
-                wait_until_start_hammering_refs as f32 * REF_INTERVAL_LEN_US;
+                self.hammer_config.wait_until_start_hammering_us(&mut rng);
             let random_rows = vec![];
             trace!(
                 "do random memory accesses for {} us before running jitted code",
@@ -555,3 +1030,347 @@ impl From<usize> for BlockShift {
         BlockShift(u)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swage_core::memory::{BytePointer, Memory};
+    use swage_core::util::PAGE_SIZE;
+    use swage_dummy::Dummy;
+
+    fn mapping_json(id: &str, flips: usize) -> String {
+        let flip = r#"{"dram_addr":{"bank":1,"row":2,"col":3},"bitmask":1,"data":0}"#;
+        format!(
+            r#"{{
+                "id":"{}",
+                "aggressor_to_addr":[[0,{{"bank":1,"row":1,"col":0}}]],
+                "bit_flips":[[{}]],
+                "code_jitter":{{
+                    "fencing_strategy":"LATEST_POSSIBLE",
+                    "flushing_strategy":"EARLIEST_POSSIBLE",
+                    "num_aggs_for_sync":2,
+                    "pattern_sync_each_ref":false,
+                    "total_activations":5000000
+                }}
+            }}"#,
+            id,
+            vec![flip; flips].join(",")
+        )
+    }
+
+    fn pattern_json(id: &str, mappings: &[(&str, usize)]) -> String {
+        format!(
+            r#"{{
+                "id":"{}",
+                "total_activations":5000000,
+                "num_refresh_intervals":16,
+                "access_ids":[0],
+                "address_mappings":[{}]
+            }}"#,
+            id,
+            mappings
+                .iter()
+                .map(|(id, flips)| mapping_json(id, *flips))
+                .join(",")
+        )
+    }
+
+    fn fuzz_summary() -> FuzzSummary {
+        let json = format!(
+            r#"{{"hammering_patterns":[{},{},{}]}}"#,
+            pattern_json("p0", &[]),
+            pattern_json("p1", &[("m0", 1), ("m1", 2)]),
+            pattern_json("p2", &[("m2", 5)]),
+        );
+        serde_json::from_str(&json).expect("invalid fuzz summary")
+    }
+
+    #[test]
+    fn test_load_from_str() -> anyhow::Result<()> {
+        let json = format!(
+            r#"{{"hammering_patterns":[{},{}]}}"#,
+            pattern_json("p0", &[]),
+            pattern_json("p1", &[("m0", 1)]),
+        );
+        let patterns = HammeringPattern::load_from_str(&json)?;
+        assert_eq!(
+            patterns.iter().map(|p| p.id.as_str()).collect_vec(),
+            ["p0", "p1"]
+        );
+        let patterns = HammeringPattern::load_from_reader(std::io::Cursor::new(json))?;
+        assert_eq!(patterns[1].count_bitflips(), 1);
+        assert!(matches!(
+            HammeringPattern::load_from_str(r#"{"hammering_patterns":"#),
+            Err(PatternLoadError::Json(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_by_min_flips() {
+        let mut summary = fuzz_summary();
+        summary.filter_by_min_flips(0);
+        assert_eq!(summary.hammering_patterns.len(), 3);
+        summary.filter_by_min_flips(3);
+        let ids = summary
+            .hammering_patterns
+            .iter()
+            .map(|p| p.id.as_str())
+            .collect_vec();
+        assert_eq!(ids, ["p1", "p2"]);
+        summary.filter_by_min_flips(6);
+        assert!(summary.hammering_patterns.is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_pattern_id() {
+        let summary = fuzz_summary();
+        let pattern = summary
+            .filter_by_pattern_id("p1")
+            .expect("pattern not found");
+        assert_eq!(pattern.count_bitflips(), 3);
+        assert_eq!(
+            pattern.determine_most_effective_mapping().map(|m| m.id),
+            Some("m1".to_string())
+        );
+        assert!(summary.filter_by_pattern_id("p3").is_none());
+    }
+
+    #[test]
+    fn test_aggressor_to_addr() {
+        let summary = fuzz_summary();
+        let mapping = &summary.hammering_patterns[1].address_mappings[0];
+        assert_eq!(
+            mapping.aggressor_to_addr(),
+            BTreeMap::from([(
+                0,
+                DRAMAddr {
+                    bank: 1,
+                    row: 1,
+                    col: 0
+                }
+            )])
+        );
+    }
+
+    #[test]
+    fn test_select_most_flips() -> anyhow::Result<()> {
+        let memory = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]);
+        // flips outside of the checked memory are not counted
+        let outside = Memory::mmap(PAGE_SIZE)?;
+        let candidates = vec![
+            ("outside", Dummy::new(outside.addr(0).into())),
+            ("inside", Dummy::new(memory.addr(42).into())),
+            ("also inside", Dummy::new(memory.addr(43).into())),
+        ];
+        let (best, _) = select_most_flips(candidates, &memory, 3).expect("no candidate");
+        assert_eq!(best, "inside");
+        assert_eq!(
+            count_flips(&Dummy::new(memory.addr(0).into()), &memory, 3),
+            3
+        );
+        assert!(select_most_flips(Vec::<(&str, Dummy)>::new(), &memory, 1).is_none());
+        memory.dealloc();
+        outside.dealloc();
+        Ok(())
+    }
+
+    fn linear_mem_config() -> MemConfiguration {
+        use crate::FromBlacksmithConfig;
+        let bits = |range: std::ops::Range<u64>| range.map(|b| b.to_string()).join(",");
+        let json = format!(
+            r#"{{"threshold":300,"bank_bits":[{}],"col_bits":[{}],"row_bits":[{}]}}"#,
+            bits(13..17),
+            bits(0..13),
+            bits(17..30)
+        );
+        let config: crate::BlacksmithConfig = serde_json::from_str(&json).expect("invalid json");
+        MemConfiguration::from_blacksmith(&config).expect("invalid config")
+    }
+
+    /// Returns a pattern accessing aggressor `i` in row `i` of `banks[i]`.
+    fn pattern_with_banks(banks: &[usize]) -> HammeringPattern {
+        let mut pattern: HammeringPattern =
+            serde_json::from_str(&pattern_json("p", &[("m", 0)])).expect("invalid pattern");
+        pattern.access_ids = (0..banks.len() as u64).map(Aggressor).collect();
+        pattern.address_mappings[0].aggressor_to_addr = banks
+            .iter()
+            .enumerate()
+            .map(|(i, &bank)| {
+                (
+                    Aggressor(i as u64),
+                    DRAMAddr {
+                        bank,
+                        row: i,
+                        col: 0,
+                    },
+                )
+            })
+            .collect();
+        pattern
+    }
+
+    #[test]
+    fn test_verify_pattern_validity() {
+        let mem_config = linear_mem_config();
+        let base = 0x40000000 as *const u8;
+        let pattern = pattern_with_banks(&[3, 3, 3, 3]);
+        assert_eq!(pattern.verify_pattern_validity(&mem_config, base), Ok(()));
+        assert_eq!(pattern.expected_bank(&mem_config, base), Some(3));
+
+        let pattern = pattern_with_banks(&[5, 1, 5, 2]);
+        assert_eq!(
+            pattern.verify_pattern_validity(&mem_config, base),
+            Err(PatternValidationError::MixedBanks {
+                banks_found: vec![1, 2, 5]
+            })
+        );
+        assert_eq!(pattern.expected_bank(&mem_config, base), None);
+    }
+
+    #[test]
+    fn test_verify_mapping_validity() {
+        let mem_config = linear_mem_config();
+        let base = 0x40000000 as *const u8;
+        let mut pattern = pattern_with_banks(&[3, 3, 3, 3]);
+        let mixed = pattern_with_banks(&[5, 1, 5, 2]).address_mappings.remove(0);
+        pattern.address_mappings.push(mixed);
+        assert_eq!(pattern.verify_pattern_validity(&mem_config, base), Ok(()));
+        assert_eq!(
+            pattern.verify_mapping_validity(&pattern.address_mappings[1], &mem_config, base),
+            Err(PatternValidationError::MixedBanks {
+                banks_found: vec![1, 2, 5]
+            })
+        );
+    }
+
+    #[cfg(feature = "jit-cache")]
+    #[test]
+    fn test_from_cached() -> anyhow::Result<()> {
+        let mem_config = linear_mem_config();
+        let mut pattern = pattern_with_banks(&[0, 0, 0, 0]);
+        // the linear configuration maps the lowest row bit to address bit 29, so use the
+        // highest row bits to keep the aggressors within a 4 MB block
+        for addr in pattern.address_mappings[0].aggressor_to_addr.values_mut() {
+            addr.row = (addr.row as u32).reverse_bits() as usize >> 19;
+        }
+        let mapping = &pattern.address_mappings[0];
+        let memory = ConsecBlocks::new(vec![Memory::mmap(MB(4).bytes())?]);
+        let cache = BlacksmithCache::default();
+        let first = Blacksmith::from_cached(
+            &cache,
+            mem_config,
+            &pattern,
+            mapping,
+            22.into(),
+            &memory,
+            1.into(),
+        )
+        .expect("jit failed");
+        assert_eq!(cache.len(), 1);
+        let program = first.program.clone();
+        drop(first);
+        let second = Blacksmith::from_cached(
+            &cache,
+            mem_config,
+            &pattern,
+            mapping,
+            22.into(),
+            &memory,
+            1.into(),
+        )
+        .expect("jit failed");
+        assert!(Arc::ptr_eq(&program, &second.program));
+        drop(second);
+
+        // relocated memory is compiled again
+        let other = ConsecBlocks::new(vec![Memory::mmap(MB(4).bytes())?]);
+        let third = Blacksmith::from_cached(
+            &cache,
+            mem_config,
+            &pattern,
+            mapping,
+            22.into(),
+            &other,
+            1.into(),
+        )
+        .expect("jit failed");
+        assert!(!Arc::ptr_eq(&program, &third.program));
+        assert_eq!(cache.len(), 1);
+        drop(third);
+        memory.dealloc();
+        other.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_rank_mappings_by() {
+        let summary = fuzz_summary();
+        let pattern = summary
+            .filter_by_pattern_id("p1")
+            .expect("pattern not found");
+        let ids = |mappings: Vec<&PatternAddressMapper>| {
+            mappings.iter().map(|m| m.id.clone()).collect_vec()
+        };
+        assert_eq!(
+            ids(pattern.rank_mappings_by(PatternAddressMapper::count_bitflips)),
+            ["m1", "m0"]
+        );
+        assert_eq!(
+            ids(pattern.rank_mappings_by(|m| usize::MAX - m.count_bitflips())),
+            ["m0", "m1"]
+        );
+        assert_eq!(ids(pattern.rank_mappings_by(|_| 0)), ["m0", "m1"]);
+        assert_eq!(
+            ids(pattern.filter_mappings(|m| m.count_bitflips() < 2)),
+            ["m0"]
+        );
+        assert!(pattern.filter_mappings(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_wait_until_start_hammering_us() {
+        let config = BlacksmithHammerConfig {
+            ref_interval_us: 3.9,
+            wait_until_start_hammering_range: 4..5,
+        };
+        let mut rng = rand::rng();
+        assert_eq!(config.wait_until_start_hammering_us(&mut rng), 4.0 * 3.9);
+        let config = BlacksmithHammerConfig::default();
+        for _ in 0..100 {
+            let wait = config.wait_until_start_hammering_us(&mut rng);
+            assert!((10.0 * 7.8..128.0 * 7.8).contains(&wait));
+        }
+    }
+
+    #[test]
+    fn test_hammer_config_validate() {
+        assert!(BlacksmithHammerConfig::default().validate().is_ok());
+        let config = BlacksmithHammerConfig {
+            wait_until_start_hammering_range: 5..5,
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(BlacksmithError::EmptyWaitRange(range)) if range == (5..5)
+        ));
+    }
+
+    #[test]
+    fn test_verify_pattern_validity_invalid() {
+        let mem_config = linear_mem_config();
+        let base = 0x40000000 as *const u8;
+        let mut pattern = pattern_with_banks(&[0, 0]);
+        pattern.access_ids.push(Aggressor(7));
+        assert_eq!(
+            pattern.verify_pattern_validity(&mem_config, base),
+            Err(PatternValidationError::InvalidAddress(7))
+        );
+        pattern.address_mappings.clear();
+        assert_eq!(
+            pattern.verify_pattern_validity(&mem_config, base),
+            Err(PatternValidationError::NoAddressMappings)
+        );
+        assert_eq!(pattern.expected_bank(&mem_config, base), None);
+    }
+}
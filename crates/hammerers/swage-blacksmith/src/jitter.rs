@@ -59,6 +59,25 @@ pub(crate) enum JitError {
 
 pub type Result<T> = std::result::Result<T, JitError>;
 
+/// Maximum size of a JIT-compiled program in bytes.
+pub const MAX_JIT_SIZE: usize = 1 << 20;
+
+/// Errors that can occur when validating a JIT-compiled [`Program`].
+#[derive(Debug, Error)]
+pub enum JitValidationError {
+    /// The program exceeds the maximum size
+    #[error("JIT program too large: {size} bytes (max {max})")]
+    TooLarge {
+        /// Size of the program in bytes
+        size: usize,
+        /// Maximum allowed size in bytes
+        max: usize,
+    },
+    /// The program contains an instruction that cannot be decoded
+    #[error("Invalid instruction at offset {0:#x}")]
+    InvalidInstruction(usize),
+}
+
 pub(crate) trait Jitter {
     fn jit(
         &self,
@@ -77,8 +96,9 @@ pub(crate) trait Jitter {
     "total_activations":5000000
 },
 */
+/// Settings for JIT-compiling a hammering pattern, as found in a Blacksmith fuzz summary.
 #[derive(Deserialize, Debug, Clone)]
-pub(crate) struct CodeJitter {
+pub struct CodeJitter {
     fencing_strategy: FencingStrategy,
     flushing_strategy: FlushingStrategy,
     num_aggs_for_sync: usize,
@@ -89,7 +109,7 @@ pub(crate) struct CodeJitter {
 /// A program that can be executed.
 ///
 /// The program is represented as a byte buffer containing machine code.
-pub(crate) struct Program {
+pub struct Program {
     code: Mmap,
     start: u64,
 }
@@ -129,6 +149,41 @@ impl Program {
         unsafe { jit_function() }
     }
 
+    /// Returns the number of instructions in the program.
+    pub fn instruction_count(&self) -> usize {
+        Decoder::new(64, &self.code, DecoderOptions::NONE)
+            .into_iter()
+            .count()
+    }
+
+    /// Returns the size of the program in bytes.
+    pub fn byte_size(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Checks that the program is smaller than [`MAX_JIT_SIZE`] and decodes to valid instructions.
+    pub fn validate(&self) -> std::result::Result<(), JitValidationError> {
+        self.validate_with_limit(MAX_JIT_SIZE)
+    }
+
+    /// Checks that the program is smaller than `max` bytes and decodes to valid instructions.
+    pub fn validate_with_limit(&self, max: usize) -> std::result::Result<(), JitValidationError> {
+        let size = self.byte_size();
+        if size >= max {
+            return Err(JitValidationError::TooLarge { size, max });
+        }
+        let mut decoder = Decoder::new(64, &self.code, DecoderOptions::NONE);
+        let mut instruction = Instruction::default();
+        while decoder.can_decode() {
+            let offset = decoder.position();
+            decoder.decode_out(&mut instruction);
+            if instruction.is_invalid() {
+                return Err(JitValidationError::InvalidInstruction(offset));
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn write(&self, filename: &str) -> Result<()> {
         let mut file = File::create(filename)?;
         file.write_all(self.code.as_ref())?;
@@ -136,6 +191,24 @@ impl Program {
     }
 }
 
+/// Upper bound of the code size of a single hammering access, including flushes and fences
+const ESTIMATED_ACCESS_SIZE: usize = 46;
+/// Upper bound of the code size of a synchronization with a refresh, excluding its accesses
+const ESTIMATED_SYNC_SIZE: usize = 64;
+/// Upper bound of the code size of the program prologue and epilogue
+const ESTIMATED_OVERHEAD: usize = 128;
+
+impl CodeJitter {
+    /// Estimates the size in bytes of the program jitted for `addrs`.
+    ///
+    /// This allows rejecting patterns before compiling them. The estimate assumes a
+    /// synchronization after every `acts_per_tref` accesses.
+    pub fn estimated_size(acts_per_tref: u64, addrs: &[AggressorPtr]) -> usize {
+        let syncs = addrs.len() / (acts_per_tref.max(1) as usize) + 1;
+        ESTIMATED_OVERHEAD + addrs.len() * ESTIMATED_ACCESS_SIZE + syncs * ESTIMATED_SYNC_SIZE
+    }
+}
+
 impl Jitter for CodeJitter {
     fn jit(
         &self,
@@ -379,3 +452,69 @@ pub fn disas(bytes: &[u8], bitness: u32, ip: u64) {
 }
 
 const HEXBYTES_COLUMN_BYTE_LENGTH: usize = 10;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jitter(num_aggs_for_sync: usize) -> CodeJitter {
+        CodeJitter {
+            fencing_strategy: FencingStrategy::LatestPossible,
+            flushing_strategy: FlushingStrategy::EarliestPossible,
+            num_aggs_for_sync,
+            pattern_sync_each_ref: true,
+            total_activations: 5000000,
+        }
+    }
+
+    fn aggressors(count: usize) -> Vec<AggressorPtr> {
+        (0..count)
+            .map(|i| (0x2000000000 + (i % 7) * 0x2000) as AggressorPtr)
+            .collect()
+    }
+
+    fn check_pattern(count: usize, num_aggs_for_sync: usize) -> Program {
+        let addrs = aggressors(count);
+        let program = jitter(num_aggs_for_sync)
+            .jit(4, &addrs, &|_, _| {})
+            .expect("JIT failed");
+        program.validate().expect("invalid program");
+        assert!(program.instruction_count() > count);
+        assert!(
+            program.byte_size() <= CodeJitter::estimated_size(4, &addrs),
+            "{} > {}",
+            program.byte_size(),
+            CodeJitter::estimated_size(4, &addrs)
+        );
+        program
+    }
+
+    #[test]
+    fn test_jit_1_aggressor() {
+        check_pattern(1, 1);
+    }
+
+    #[test]
+    fn test_jit_10_aggressors() {
+        check_pattern(10, 2);
+    }
+
+    #[test]
+    fn test_jit_100_aggressors() {
+        let program = check_pattern(100, 2);
+        let size = program.byte_size();
+        assert!(matches!(
+            program.validate_with_limit(size),
+            Err(JitValidationError::TooLarge { max, .. }) if max == size
+        ));
+    }
+
+    #[test]
+    fn test_estimated_size() {
+        let small = CodeJitter::estimated_size(10, &aggressors(10));
+        let large = CodeJitter::estimated_size(10, &aggressors(100));
+        assert!(small < large);
+        assert!(CodeJitter::estimated_size(0, &aggressors(100)) > large);
+        assert!(CodeJitter::estimated_size(1, &aggressors(100_000)) > MAX_JIT_SIZE);
+    }
+}
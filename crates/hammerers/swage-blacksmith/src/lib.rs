@@ -20,6 +20,7 @@
 //!
 //! - `jitter_dump` - Enable jitter measurement dumping for analysis
 //! - `iperf` - Enable iPerf performance measurements
+//! - `jit-cache` - Enable [`BlacksmithCache`] to reuse JIT-compiled programs across hammerers
 
 #![warn(missing_docs)]
 
@@ -29,24 +30,64 @@ mod jitter;
 
 pub use blacksmith_config::*;
 pub use hammerer::*;
+pub use jitter::{CodeJitter, JitValidationError, MAX_JIT_SIZE, Program};
 
 use nalgebra::SMatrix;
 use swage_core::memory::{MTX_SIZE, MemConfiguration};
+use thiserror::Error;
+
+/// Errors that can occur when building a [`MemConfiguration`] from bit definitions.
+#[derive(Debug, Error)]
+pub enum MatrixError {
+    /// The bank, row and column bits do not add up to [`MTX_SIZE`] bits
+    #[error("expected {expected} bank, row and column bits, got {actual}")]
+    DimensionMismatch {
+        /// Required number of bits
+        expected: usize,
+        /// Number of bits defined
+        actual: usize,
+    },
+    /// The bit definitions do not form an invertible addressing matrix
+    #[error(
+        "bit definitions do not form an invertible matrix (bank bits: {bank_bits:?}, row bits: {row_bits:?}, col bits: {col_bits:?})"
+    )]
+    NotInvertible {
+        /// Bank bit definitions
+        bank_bits: Vec<BitDef>,
+        /// Row bit definitions
+        row_bits: Vec<BitDef>,
+        /// Column bit definitions
+        col_bits: Vec<BitDef>,
+    },
+}
 
 /// Trait to build from a BlacksmithConfig
-pub trait FromBlacksmithConfig {
+pub trait FromBlacksmithConfig: Sized {
     /// Build from a BlacksmithConfig
-    fn from_blacksmith(config: &BlacksmithConfig) -> Self;
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bit definitions of `config` do not form a valid addressing matrix
+    fn from_blacksmith(config: &BlacksmithConfig) -> std::result::Result<Self, MatrixError>;
 }
 
 /// Trait to build from vectors of `BitDefs`
-pub trait FromBitDefs {
+pub trait FromBitDefs: Sized {
     /// Build from vectors of `BitDefs`
-    fn from_bitdefs(bank_bits: Vec<BitDef>, row_bits: Vec<BitDef>, col_bits: Vec<BitDef>) -> Self;
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MatrixError::DimensionMismatch`] if the bits do not add up to [`MTX_SIZE`],
+    /// and [`MatrixError::NotInvertible`] if the addressing matrix cannot be inverted.
+    fn from_bitdefs(
+        bank_bits: Vec<BitDef>,
+        row_bits: Vec<BitDef>,
+        col_bits: Vec<BitDef>,
+    ) -> std::result::Result<Self, MatrixError>;
 }
 
 impl FromBlacksmithConfig for MemConfiguration {
-    fn from_blacksmith(config: &BlacksmithConfig) -> Self {
+    fn from_blacksmith(config: &BlacksmithConfig) -> std::result::Result<Self, MatrixError> {
         MemConfiguration::from_bitdefs(
             config.bank_bits.clone(),
             config.row_bits.clone(),
@@ -56,11 +97,21 @@ impl FromBlacksmithConfig for MemConfiguration {
 }
 
 impl FromBitDefs for MemConfiguration {
-    fn from_bitdefs(bank_bits: Vec<BitDef>, row_bits: Vec<BitDef>, col_bits: Vec<BitDef>) -> Self {
+    fn from_bitdefs(
+        bank_bits: Vec<BitDef>,
+        row_bits: Vec<BitDef>,
+        col_bits: Vec<BitDef>,
+    ) -> std::result::Result<Self, MatrixError> {
         let mut out = MemConfiguration::default();
         let mut i = 0;
 
-        assert_eq!(MTX_SIZE, bank_bits.len() + col_bits.len() + row_bits.len());
+        let num_bits = bank_bits.len() + col_bits.len() + row_bits.len();
+        if num_bits != MTX_SIZE {
+            return Err(MatrixError::DimensionMismatch {
+                expected: MTX_SIZE,
+                actual: num_bits,
+            });
+        }
 
         out.bk_shift = MTX_SIZE - bank_bits.len();
         out.bk_mask = (1 << bank_bits.len()) - 1;
@@ -101,26 +152,28 @@ impl FromBitDefs for MemConfiguration {
             }
         }
         // invert dram matrix, assign addr matrix
+        let not_invertible = || MatrixError::NotInvertible {
+            bank_bits: bank_bits.clone(),
+            row_bits: row_bits.clone(),
+            col_bits: col_bits.clone(),
+        };
         let matrix_inv = matrix
             .cast::<f64>()
             .try_inverse()
-            .expect("The matrix defined in the config file is not invertible.")
+            .ok_or_else(not_invertible)?
             .try_cast::<i8>()
-            .expect("inverse cast to i8 failed")
+            .ok_or_else(not_invertible)?
             .map(|e| e.abs());
 
         for row in 0..MTX_SIZE {
             for col in 0..MTX_SIZE {
                 if matrix_inv[(row, col)] != 0 && matrix_inv[(row, col)] != 1 {
-                    panic!(
-                        "expected element to be 0 or 1, got {}",
-                        matrix_inv[(row, col)]
-                    );
+                    return Err(not_invertible());
                 }
                 addr_mtx[row] |= (matrix_inv[(row, col)] as usize) << (MTX_SIZE - col - 1);
             }
         }
         out.addr_mtx = addr_mtx;
-        out
+        Ok(out)
     }
 }
@@ -0,0 +1,17 @@
+//! Simple double-sided Rowhammer hammerer.
+//!
+//! This crate provides a plain, readable hammerer that repeatedly flushes and reads a set
+//! of aggressor rows. In contrast to Blacksmith, it does not JIT-compile patterns and does
+//! not synchronize with refresh.
+//!
+//! Implements the [`swage_core::hammerer::Hammering`] trait.
+//!
+//! # Platform Requirements
+//!
+//! - x86_64 CPU supporting the selected [`FlushStrategy`]
+
+#![warn(missing_docs)]
+
+mod simple;
+
+pub use simple::{FlushStrategy, SimpleHammer, SimpleHammerBuilder, SimpleHammerError};
@@ -0,0 +1,216 @@
+use std::arch::asm;
+use std::arch::x86_64::{__cpuid_count, _mm_clflush, _mm_mfence};
+use std::convert::Infallible;
+
+use log::{debug, info};
+use swage_core::hammerer::Hammering;
+use swage_core::memory::AggressorPtr;
+use thiserror::Error;
+
+/// Default number of hammering rounds
+const DEFAULT_ROUNDS: u64 = 1_000_000;
+
+/// Instruction used to evict the aggressors from the cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlushStrategy {
+    /// `clflush`, supported by all x86_64 CPUs (part of SSE2)
+    #[default]
+    Clflush,
+    /// `clflushopt`, weakly ordered and faster than `clflush`
+    Clflushopt,
+    /// `clwb`, writes back the cache line, possibly without evicting it
+    Clwb,
+}
+
+impl FlushStrategy {
+    /// Returns true if the CPU supports this strategy.
+    pub fn is_supported(&self) -> bool {
+        // CPUID leaf 7 reports CLFLUSHOPT in EBX bit 23 and CLWB in EBX bit 24
+        let extended_features = || __cpuid_count(7, 0).ebx;
+        match self {
+            FlushStrategy::Clflush => true,
+            FlushStrategy::Clflushopt => extended_features() & (1 << 23) != 0,
+            FlushStrategy::Clwb => extended_features() & (1 << 24) != 0,
+        }
+    }
+
+    /// Flushes the cache line containing `addr`.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support this strategy.
+    #[inline(always)]
+    unsafe fn flush(&self, addr: AggressorPtr) {
+        match self {
+            FlushStrategy::Clflush => unsafe { _mm_clflush(addr) },
+            FlushStrategy::Clflushopt => unsafe { asm!("clflushopt [{}]", in(reg) addr) },
+            FlushStrategy::Clwb => unsafe { asm!("clwb [{}]", in(reg) addr) },
+        }
+    }
+}
+
+/// Errors that can occur when building a [`SimpleHammer`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SimpleHammerError {
+    /// No aggressors were given
+    #[error("No aggressors given")]
+    NoAggressors,
+    /// The CPU does not support the flush strategy
+    #[error("Flush strategy {0:?} is not supported by this CPU")]
+    UnsupportedFlushStrategy(FlushStrategy),
+}
+
+/// Double-sided hammerer accessing aggressor rows in a flush-and-read loop.
+///
+/// Each round flushes all aggressors, reads all aggressors, and separates both steps with
+/// `mfence`. For double-sided hammering, pass the two rows adjacent to the victim row.
+pub struct SimpleHammer {
+    /// Aggressor addresses, accessed in order
+    aggressors: Vec<AggressorPtr>,
+    /// Number of hammering rounds
+    rounds: u64,
+    /// Instruction used to evict the aggressors
+    flush_strategy: FlushStrategy,
+}
+
+impl SimpleHammer {
+    /// Returns a builder for a [`SimpleHammer`].
+    pub fn builder() -> SimpleHammerBuilder {
+        SimpleHammerBuilder::default()
+    }
+}
+
+impl Hammering for SimpleHammer {
+    type Error = Infallible;
+    fn hammer(&self) -> Result<(), Self::Error> {
+        info!(
+            "Hammering {} aggressors for {} rounds using {:?}",
+            self.aggressors.len(),
+            self.rounds,
+            self.flush_strategy
+        );
+        for _ in 0..self.rounds {
+            for &aggressor in &self.aggressors {
+                // the strategy is checked in `SimpleHammerBuilder::build`
+                unsafe { self.flush_strategy.flush(aggressor) };
+            }
+            unsafe { _mm_mfence() };
+            for &aggressor in &self.aggressors {
+                unsafe { std::ptr::read_volatile(aggressor) };
+            }
+            unsafe { _mm_mfence() };
+        }
+        debug!("Hammering done");
+        Ok(())
+    }
+}
+
+/// Builder for [`SimpleHammer`].
+///
+/// By default, the hammerer runs 1,000,000 rounds using [`FlushStrategy::Clflush`].
+pub struct SimpleHammerBuilder {
+    aggressors: Vec<AggressorPtr>,
+    rounds: u64,
+    flush_strategy: FlushStrategy,
+}
+
+impl Default for SimpleHammerBuilder {
+    fn default() -> Self {
+        SimpleHammerBuilder {
+            aggressors: vec![],
+            rounds: DEFAULT_ROUNDS,
+            flush_strategy: FlushStrategy::default(),
+        }
+    }
+}
+
+impl SimpleHammerBuilder {
+    /// Adds an aggressor. The aggressor must be valid for reads while hammering.
+    pub fn aggressor(mut self, aggressor: AggressorPtr) -> Self {
+        self.aggressors.push(aggressor);
+        self
+    }
+
+    /// Adds multiple aggressors. The aggressors must be valid for reads while hammering.
+    pub fn aggressors(mut self, aggressors: impl IntoIterator<Item = AggressorPtr>) -> Self {
+        self.aggressors.extend(aggressors);
+        self
+    }
+
+    /// Sets the number of hammering rounds.
+    pub fn rounds(mut self, rounds: u64) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Sets the instruction used to evict the aggressors.
+    pub fn flush_strategy(mut self, flush_strategy: FlushStrategy) -> Self {
+        self.flush_strategy = flush_strategy;
+        self
+    }
+
+    /// Builds the hammerer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no aggressors were given or the CPU does not support the flush
+    /// strategy.
+    pub fn build(self) -> Result<SimpleHammer, SimpleHammerError> {
+        if self.aggressors.is_empty() {
+            return Err(SimpleHammerError::NoAggressors);
+        }
+        if !self.flush_strategy.is_supported() {
+            return Err(SimpleHammerError::UnsupportedFlushStrategy(
+                self.flush_strategy,
+            ));
+        }
+        Ok(SimpleHammer {
+            aggressors: self.aggressors,
+            rounds: self.rounds,
+            flush_strategy: self.flush_strategy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        assert_eq!(
+            SimpleHammer::builder().build().err(),
+            Some(SimpleHammerError::NoAggressors)
+        );
+        let value = 0u8;
+        let hammer = SimpleHammer::builder()
+            .aggressor(&value)
+            .rounds(3)
+            .build()
+            .expect("build failed");
+        assert_eq!(hammer.aggressors, vec![&value as AggressorPtr]);
+        assert_eq!(hammer.rounds, 3);
+        assert_eq!(hammer.flush_strategy, FlushStrategy::Clflush);
+    }
+
+    #[test]
+    fn test_hammer() {
+        let rows = [0u8; 2];
+        for strategy in [
+            FlushStrategy::Clflush,
+            FlushStrategy::Clflushopt,
+            FlushStrategy::Clwb,
+        ] {
+            let hammer = SimpleHammer::builder()
+                .aggressors(rows.iter().map(|row| row as AggressorPtr))
+                .rounds(100)
+                .flush_strategy(strategy)
+                .build();
+            match hammer {
+                Ok(hammer) => assert!(hammer.hammer().is_ok()),
+                Err(e) => assert_eq!(e, SimpleHammerError::UnsupportedFlushStrategy(strategy)),
+            }
+        }
+        assert_eq!(rows, [0, 0]);
+    }
+}
@@ -0,0 +1,25 @@
+//! TRR-bypass Rowhammer hammerer.
+//!
+//! In-DRAM Target Row Refresh (TRR) samples frequently activated rows and refreshes their
+//! neighbors. This crate provides hammering patterns that evade this mitigation:
+//! many-sided hammering, RowPress, and spraying decoy rows into the TRR sampler.
+//!
+//! Implements the [`swage_core::hammerer::Hammering`] trait.
+//!
+//! # References
+//!
+//! - Frigo et al., "TRRespass: Exploiting the Many Sides of Target Row Refresh",
+//!   IEEE S&P 2020.
+//! - Luo et al., "RowPress: Amplifying Read Disturbance in High-Density DRAM Chips",
+//!   ISCA 2023.
+//!
+//! # Features
+//!
+//! - `wait-instruction` - Hold rows open for [`TrrPattern::RowPress`] with `umonitor`/`umwait`
+//!   if supported by the CPU, or `nanosleep` otherwise, instead of busy-waiting
+
+#![warn(missing_docs)]
+
+mod trr_bypass;
+
+pub use trr_bypass::{TrrBypassError, TrrBypassHammer, TrrPattern};
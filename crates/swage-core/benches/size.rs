@@ -0,0 +1,29 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use swage_core::util::Size;
+
+const ITERATIONS: usize = 10_000_000;
+
+fn bench_size_bytes(c: &mut Criterion) {
+    c.bench_function("Size::bytes", |b| {
+        b.iter(|| {
+            let mut sum = 0usize;
+            for i in 0..ITERATIONS {
+                sum = sum.wrapping_add(Size::MB(black_box(i & 0xFFF)).bytes());
+            }
+            sum
+        })
+    });
+    c.bench_function("Size::bytes_unchecked", |b| {
+        b.iter(|| {
+            let mut sum = 0usize;
+            for i in 0..ITERATIONS {
+                sum = sum.wrapping_add(unsafe { Size::MB(black_box(i & 0xFFF)).bytes_unchecked() });
+            }
+            sum
+        })
+    });
+}
+
+criterion_group!(benches, bench_size_bytes);
+criterion_main!(benches);
@@ -3,10 +3,15 @@
 //! This module defines the [`ConsecAllocator`] trait and the main [`alloc_memory`] function
 //! for allocating physically consecutive memory blocks required for effective Rowhammer attacks.
 
-use crate::memory::{ConsecBlocks, GetConsecPfns};
-use crate::util::Size;
+use crate::memory::{BytePointer, ConsecBlocks, DefragStrategy, GetConsecPfns, defragment};
 use crate::util::compact_mem;
+use crate::util::{PAGE_SIZE, Size};
+use itertools::Itertools;
 use log::warn;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
 
 /// Trait for memory allocation strategies that provide consecutive physical memory blocks.
 ///
@@ -60,6 +65,20 @@ pub trait ConsecAllocator {
     fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error>;
 }
 
+/// Forwards to the boxed allocator, e.g., to choose the allocator at runtime as
+/// `Box<dyn ConsecAllocator<Error = E>>`.
+impl<A: ConsecAllocator + ?Sized> ConsecAllocator for Box<A> {
+    type Error = A::Error;
+
+    fn block_size(&self) -> Size {
+        (**self).block_size()
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        (**self).alloc_consec_blocks(size)
+    }
+}
+
 /// Allocate memory using an allocation strategy.
 ///
 /// This is the main entry point for users who simply want to allocate some consecutive memory.
@@ -98,12 +117,745 @@ pub fn alloc_memory<E: std::error::Error>(
     );
     assert!(size.bytes() > 0, "Size must be greater than 0");
 
-    let compacted = compact_mem();
-    match compacted {
-        Ok(_) => {}
-        Err(e) => warn!("Memory compaction failed: {:?}", e),
-    }
+    reduce_fragmentation(compact_mem, defragment);
     let memory = allocator.alloc_consec_blocks(size)?;
     memory.log_pfns(log::Level::Info);
     Ok(memory)
 }
+
+/// Runs `compact`, falling back to `defrag` with [`DefragStrategy::Both`] if it fails.
+///
+/// Unlike `compact`, [`DefragStrategy::Both`] also drops the page cache, which frees up
+/// pages that compaction alone cannot move. Failures are only logged.
+fn reduce_fragmentation(
+    compact: impl FnOnce() -> Result<(), std::io::Error>,
+    defrag: impl FnOnce(DefragStrategy) -> Result<(), std::io::Error>,
+) {
+    if let Err(e) = compact() {
+        warn!("Memory compaction failed: {:?}", e);
+        if let Err(e) = defrag(DefragStrategy::Both) {
+            warn!("Memory defragmentation failed: {:?}", e);
+        }
+    }
+}
+
+/// Pool of allocators shared between threads.
+///
+/// Allocations are distributed round-robin across the pool members. Each member is protected
+/// by a [`Mutex`], so concurrent allocations use different allocators whenever possible.
+pub struct AllocatorPool<A: ConsecAllocator + Send> {
+    allocators: Vec<Mutex<A>>,
+    next: AtomicUsize,
+}
+
+impl<A: ConsecAllocator + Send> AllocatorPool<A> {
+    /// Creates a new pool from the given allocators.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `allocators` is empty.
+    pub fn new(allocators: Vec<A>) -> Self {
+        assert!(!allocators.is_empty(), "AllocatorPool must not be empty");
+        AllocatorPool {
+            allocators: allocators.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a new pool of `n` allocators constructed by `factory`.
+    pub fn with_capacity(n: usize, factory: impl Fn() -> A) -> Self {
+        Self::new((0..n).map(|_| factory()).collect())
+    }
+
+    /// Returns the index of the allocator used for the next allocation.
+    pub fn current_index(&self) -> usize {
+        self.next.load(Ordering::Relaxed) % self.allocators.len()
+    }
+
+    /// Locks the first allocator that is not currently in use, or returns None if all are busy.
+    pub fn try_lock_any(&self) -> Option<MutexGuard<'_, A>> {
+        self.allocators
+            .iter()
+            .find_map(|allocator| allocator.try_lock().ok())
+    }
+
+    fn lock_next(&self) -> MutexGuard<'_, A> {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.allocators.len();
+        self.allocators[idx]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn block_size(&self) -> Size {
+        self.allocators[0]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .block_size()
+    }
+}
+
+impl<A: ConsecAllocator + Send> ConsecAllocator for AllocatorPool<A> {
+    type Error = A::Error;
+
+    fn block_size(&self) -> Size {
+        AllocatorPool::block_size(self)
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        self.lock_next().alloc_consec_blocks(size)
+    }
+}
+
+/// Shared handle to an [`AllocatorPool`], e.g., to use one pool from several [`Swage`](crate::Swage) instances.
+impl<A: ConsecAllocator + Send> ConsecAllocator for Arc<AllocatorPool<A>> {
+    type Error = A::Error;
+
+    fn block_size(&self) -> Size {
+        AllocatorPool::block_size(self)
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        self.lock_next().alloc_consec_blocks(size)
+    }
+}
+
+/// Allocator that falls back to `B` if allocating with `A` fails.
+///
+/// Useful to support different systems with a single binary, e.g., by trying a privileged
+/// allocator first and an unprivileged one afterwards.
+pub struct FallbackAllocator<A: ConsecAllocator, B: ConsecAllocator> {
+    primary: A,
+    fallback: B,
+}
+
+impl<A: ConsecAllocator, B: ConsecAllocator> FallbackAllocator<A, B> {
+    /// Creates a new allocator trying `primary` first and `fallback` second.
+    pub fn new(primary: A, fallback: B) -> Self {
+        FallbackAllocator { primary, fallback }
+    }
+}
+
+/// Both allocators of a [`FallbackAllocator`] failed.
+#[derive(Debug, Error)]
+#[error("Primary allocator failed: {primary}; fallback allocator failed: {fallback}")]
+pub struct FallbackError<EA: std::error::Error, EB: std::error::Error> {
+    /// Error of the primary allocator
+    pub primary: EA,
+    /// Error of the fallback allocator
+    pub fallback: EB,
+}
+
+impl<A: ConsecAllocator, B: ConsecAllocator> ConsecAllocator for FallbackAllocator<A, B> {
+    type Error = FallbackError<A::Error, B::Error>;
+
+    /// Returns the smaller block size of both allocators.
+    fn block_size(&self) -> Size {
+        self.primary.block_size().min(self.fallback.block_size())
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        let primary = match self.primary.alloc_consec_blocks(size) {
+            Ok(blocks) => return Ok(blocks),
+            Err(e) => e,
+        };
+        warn!("Primary allocator failed, falling back: {}", primary);
+        self.fallback
+            .alloc_consec_blocks(size)
+            .map_err(|fallback| FallbackError { primary, fallback })
+    }
+}
+
+/// Type-erased allocation error, see [`ChainAllocator`].
+#[derive(Debug)]
+pub struct BoxedError(pub Box<dyn std::error::Error>);
+
+impl fmt::Display for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for BoxedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Adapter erasing the error type of an allocator.
+struct Boxed<A: ConsecAllocator>(A);
+
+impl<A: ConsecAllocator> ConsecAllocator for Boxed<A>
+where
+    A::Error: 'static,
+{
+    type Error = BoxedError;
+
+    fn block_size(&self) -> Size {
+        self.0.block_size()
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        self.0
+            .alloc_consec_blocks(size)
+            .map_err(|e| BoxedError(Box::new(e)))
+    }
+}
+
+/// Allocator that tries any number of allocators in order until one succeeds.
+///
+/// Unlike [`FallbackAllocator`], the allocators are type-erased and can be chosen at runtime.
+#[derive(Default)]
+pub struct ChainAllocator(pub Vec<Box<dyn ConsecAllocator<Error = BoxedError>>>);
+
+impl ChainAllocator {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `allocator` to the chain.
+    pub fn with(mut self, allocator: impl ConsecAllocator<Error: 'static> + 'static) -> Self {
+        self.0.push(Box::new(Boxed(allocator)));
+        self
+    }
+}
+
+/// All allocators of a [`ChainAllocator`] failed.
+#[derive(Debug, Error)]
+#[error("All allocators failed: [{}]", .0.iter().join("; "))]
+pub struct ChainError(pub Vec<BoxedError>);
+
+impl ConsecAllocator for ChainAllocator {
+    type Error = ChainError;
+
+    /// Returns the smallest block size in the chain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chain is empty.
+    fn block_size(&self) -> Size {
+        self.0
+            .iter()
+            .map(|allocator| allocator.block_size())
+            .min()
+            .expect("ChainAllocator must not be empty")
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        let mut errors = vec![];
+        for allocator in &mut self.0 {
+            match allocator.alloc_consec_blocks(size) {
+                Ok(blocks) => return Ok(blocks),
+                Err(e) => {
+                    warn!("Allocator failed, trying next: {}", e);
+                    errors.push(e);
+                }
+            }
+        }
+        Err(ChainError(errors))
+    }
+}
+
+/// Allocator serving blocks from a pool pre-allocated with an inner allocator.
+///
+/// Allocating with some allocators is slow, e.g., due to memory compaction or SPOILER
+/// scanning. This allocator splits allocations of the inner allocator into blocks of
+/// [`ConsecAllocator::block_size`] and hands them out on request. Blocks passed to
+/// [`PoolAllocator::release`] are reused instead of unmapped.
+///
+/// Blocks are only physically consecutive within themselves; consecutive blocks returned
+/// by one allocation may come from different inner allocations.
+pub struct PoolAllocator<A: ConsecAllocator> {
+    inner: A,
+    pool_size: usize,
+    pool: Vec<ConsecBlocks>,
+}
+
+impl<A: ConsecAllocator> PoolAllocator<A> {
+    /// Creates a new pool and pre-allocates `pool_size` blocks with `inner`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `inner` if the pre-allocation fails.
+    pub fn new(inner: A, pool_size: usize) -> Result<Self, A::Error> {
+        let mut pool = PoolAllocator {
+            inner,
+            pool_size,
+            pool: vec![],
+        };
+        pool.refill(pool_size)?;
+        Ok(pool)
+    }
+
+    /// Returns the number of blocks currently in the pool.
+    pub fn available(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Returns `blocks` to the pool.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `blocks` is not a multiple of the block size.
+    pub fn release(&mut self, blocks: ConsecBlocks) {
+        let block_size = self.inner.block_size().bytes();
+        assert!(
+            blocks.len().is_multiple_of(block_size),
+            "Released size {} is not a multiple of block size {}",
+            blocks.len(),
+            block_size
+        );
+        self.pool.extend(blocks.chunks(block_size));
+    }
+
+    /// Deallocates all pooled blocks and returns the inner allocator.
+    pub fn dealloc(self) -> A {
+        for blocks in self.pool {
+            blocks.dealloc();
+        }
+        self.inner
+    }
+
+    /// Allocates `count` blocks with the inner allocator and adds them to the pool.
+    fn refill(&mut self, count: usize) -> Result<(), A::Error> {
+        if count == 0 {
+            return Ok(());
+        }
+        let block_size = self.inner.block_size().bytes();
+        let blocks = self
+            .inner
+            .alloc_consec_blocks(Size::B(count * block_size))?;
+        self.pool.extend(blocks.chunks(block_size));
+        Ok(())
+    }
+}
+
+impl<A: ConsecAllocator> ConsecAllocator for PoolAllocator<A> {
+    type Error = A::Error;
+
+    fn block_size(&self) -> Size {
+        self.inner.block_size()
+    }
+
+    /// Takes blocks from the pool, refilling it with at least the pool size if it is exhausted.
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        let block_size = self.block_size().bytes();
+        assert!(
+            size.bytes().is_multiple_of(block_size),
+            "Size {} must be a multiple of block size {}",
+            size,
+            self.block_size()
+        );
+        let count = size.bytes() / block_size;
+        if self.pool.len() < count {
+            self.refill((count - self.pool.len()).max(self.pool_size))?;
+        }
+        Ok(ConsecBlocks::new(
+            self.pool
+                .drain(..count)
+                .flat_map(|blocks| blocks.blocks)
+                .collect(),
+        ))
+    }
+}
+
+/// Path listing the NUMA nodes of the system
+const NUMA_NODE_PATH: &str = "/sys/devices/system/node";
+
+/// Allocator binding the allocations of an inner allocator to a NUMA node.
+///
+/// DRAM bank functions and timing side channels differ between NUMA nodes. While the inner
+/// allocator runs, the memory policy of the calling thread is set to `MPOL_BIND`, so that all
+/// pages faulted in are taken from the requested node. Binding the blocks with `mbind` after
+/// allocation would migrate the pages and break their physical contiguity. Afterwards, the
+/// node of every page is verified with `move_pages`.
+///
+/// The inner allocator must fault in its blocks on the calling thread.
+pub struct NumaAllocator<A> {
+    inner: A,
+    numa_node: u32,
+}
+
+/// Errors of the [`NumaAllocator`].
+#[derive(Debug, Error)]
+pub enum NumaError<E: std::error::Error> {
+    /// The inner allocator failed
+    #[error(transparent)]
+    Inner(E),
+    /// The requested node does not exist
+    #[error("NUMA node {node} does not exist, available nodes: {available:?}")]
+    InvalidNode {
+        /// The requested node
+        node: u32,
+        /// Nodes found in `/sys/devices/system/node`
+        available: Vec<u32>,
+    },
+    /// A page of the allocation is not on the requested node
+    #[error("Page 0x{addr:x} is on NUMA node {actual}, expected {expected}")]
+    WrongNode {
+        /// Virtual address of the page
+        addr: usize,
+        /// The requested node
+        expected: u32,
+        /// The node reported by `move_pages`, or a negative error code
+        actual: i32,
+    },
+    /// `set_mempolicy` or `move_pages` failed
+    #[error("NUMA syscall failed: {0}")]
+    Syscall(std::io::Error),
+}
+
+impl NumaAllocator<()> {
+    /// Returns the NUMA nodes of the system, in ascending order.
+    ///
+    /// Returns an empty list if `/sys/devices/system/node` cannot be read.
+    pub fn detected_nodes() -> Vec<u32> {
+        let Ok(entries) = std::fs::read_dir(NUMA_NODE_PATH) else {
+            return vec![];
+        };
+        entries
+            .filter_map(|entry| {
+                entry
+                    .ok()?
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("node")?
+                    .parse()
+                    .ok()
+            })
+            .sorted()
+            .collect()
+    }
+}
+
+impl<A: ConsecAllocator> NumaAllocator<A> {
+    /// Creates a new allocator binding the allocations of `inner` to `numa_node`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NumaError::InvalidNode`] if `numa_node` is not listed in
+    /// `/sys/devices/system/node`.
+    pub fn new(inner: A, numa_node: u32) -> Result<Self, NumaError<A::Error>> {
+        let available = NumaAllocator::detected_nodes();
+        // the node mask passed to set_mempolicy is a single u64
+        if !available.contains(&numa_node) || numa_node >= u64::BITS {
+            return Err(NumaError::InvalidNode {
+                node: numa_node,
+                available,
+            });
+        }
+        Ok(NumaAllocator { inner, numa_node })
+    }
+
+    /// Returns the node allocations are bound to.
+    pub fn numa_node(&self) -> u32 {
+        self.numa_node
+    }
+
+    /// Checks that every page of `blocks` is on the requested node.
+    fn verify(&self, blocks: &ConsecBlocks) -> Result<(), NumaError<A::Error>> {
+        let mut pages = blocks
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                (0..block.len)
+                    .step_by(PAGE_SIZE)
+                    .map(|offset| block.addr(offset) as *mut libc::c_void)
+            })
+            .collect_vec();
+        let mut status = vec![0i32; pages.len()];
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_move_pages,
+                0,
+                pages.len(),
+                pages.as_mut_ptr(),
+                std::ptr::null::<i32>(),
+                status.as_mut_ptr(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(NumaError::Syscall(std::io::Error::last_os_error()));
+        }
+        match pages
+            .iter()
+            .zip(status)
+            .find(|&(_, node)| node != self.numa_node as i32)
+        {
+            Some((&addr, actual)) => Err(NumaError::WrongNode {
+                addr: addr as usize,
+                expected: self.numa_node,
+                actual,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sets the memory policy of the calling thread, binding to the nodes in `node_mask`.
+fn set_mempolicy(mode: libc::c_int, node_mask: Option<u64>) -> Result<(), std::io::Error> {
+    let mask = node_mask
+        .as_ref()
+        .map_or(std::ptr::null(), |mask| mask as *const u64);
+    let max_node = node_mask.map_or(0, |_| u64::BITS as u64 + 1);
+    if unsafe { libc::syscall(libc::SYS_set_mempolicy, mode, mask, max_node) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl<A: ConsecAllocator> ConsecAllocator for NumaAllocator<A> {
+    type Error = NumaError<A::Error>;
+
+    fn block_size(&self) -> Size {
+        self.inner.block_size()
+    }
+
+    fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+        set_mempolicy(libc::MPOL_BIND, Some(1 << self.numa_node)).map_err(NumaError::Syscall)?;
+        let blocks = self.inner.alloc_consec_blocks(size);
+        if let Err(e) = set_mempolicy(libc::MPOL_DEFAULT, None) {
+            warn!("Failed to reset memory policy: {}", e);
+        }
+        let blocks = blocks.map_err(NumaError::Inner)?;
+        if let Err(e) = self.verify(&blocks) {
+            blocks.dealloc();
+            return Err(e);
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use crate::util::PAGE_SIZE;
+    use std::convert::Infallible;
+    use std::sync::atomic::AtomicU64;
+
+    struct CountingAllocator(Arc<AtomicU64>);
+
+    impl ConsecAllocator for CountingAllocator {
+        type Error = Infallible;
+        fn block_size(&self) -> Size {
+            Size::B(PAGE_SIZE)
+        }
+        fn alloc_consec_blocks(&mut self, _size: Size) -> Result<ConsecBlocks, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(ConsecBlocks::new(vec![Memory::new(
+                std::ptr::null_mut(),
+                0,
+            )]))
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error("failing allocator {0}")]
+    struct FailingError(usize);
+
+    struct FailingAllocator(usize, Size);
+
+    impl ConsecAllocator for FailingAllocator {
+        type Error = FailingError;
+        fn block_size(&self) -> Size {
+            self.1
+        }
+        fn alloc_consec_blocks(&mut self, _size: Size) -> Result<ConsecBlocks, Self::Error> {
+            Err(FailingError(self.0))
+        }
+    }
+
+    #[test]
+    fn test_reduce_fragmentation() {
+        let strategies = std::cell::RefCell::new(vec![]);
+        let defrag = |strategy| {
+            strategies.borrow_mut().push(strategy);
+            Ok(())
+        };
+        reduce_fragmentation(|| Ok(()), defrag);
+        assert!(strategies.borrow().is_empty());
+        reduce_fragmentation(|| Err(std::io::Error::other("compaction failed")), defrag);
+        assert_eq!(*strategies.borrow(), vec![DefragStrategy::Both]);
+        // a failing fallback is only logged
+        reduce_fragmentation(
+            || Err(std::io::Error::other("compaction failed")),
+            |_| Err(std::io::Error::other("defragmentation failed")),
+        );
+    }
+
+    #[test]
+    fn test_fallback_allocator() {
+        let counter = Arc::new(AtomicU64::new(0));
+        let mut allocator = FallbackAllocator::new(
+            FailingAllocator(0, Size::MB(4)),
+            CountingAllocator(counter.clone()),
+        );
+        assert_eq!(allocator.block_size(), Size::B(PAGE_SIZE));
+        allocator.alloc_consec_blocks(Size::B(PAGE_SIZE)).unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+        let mut allocator = FallbackAllocator::new(
+            CountingAllocator(counter.clone()),
+            FailingAllocator(0, Size::MB(4)),
+        );
+        allocator.alloc_consec_blocks(Size::B(PAGE_SIZE)).unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 2);
+
+        let mut allocator = FallbackAllocator::new(
+            FailingAllocator(0, Size::MB(4)),
+            FailingAllocator(1, Size::MB(2)),
+        );
+        assert_eq!(allocator.block_size(), Size::MB(2));
+        let err = allocator.alloc_consec_blocks(Size::MB(4)).unwrap_err();
+        assert_eq!((err.primary.0, err.fallback.0), (0, 1));
+    }
+
+    #[test]
+    fn test_chain_allocator() {
+        let counter = Arc::new(AtomicU64::new(0));
+        let mut allocator = ChainAllocator::new()
+            .with(FailingAllocator(0, Size::MB(4)))
+            .with(FailingAllocator(1, Size::MB(2)))
+            .with(CountingAllocator(counter.clone()));
+        assert_eq!(allocator.block_size(), Size::B(PAGE_SIZE));
+        allocator.alloc_consec_blocks(Size::B(PAGE_SIZE)).unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+
+        let mut allocator = ChainAllocator::new()
+            .with(FailingAllocator(0, Size::MB(4)))
+            .with(FailingAllocator(1, Size::MB(2)));
+        let err = allocator.alloc_consec_blocks(Size::MB(4)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "All allocators failed: [failing allocator 0; failing allocator 1]"
+        );
+        assert!(
+            ChainAllocator::new()
+                .alloc_consec_blocks(Size::MB(4))
+                .unwrap_err()
+                .0
+                .is_empty()
+        );
+    }
+
+    struct MmapAllocator(usize);
+
+    impl ConsecAllocator for MmapAllocator {
+        type Error = crate::memory::MemoryError;
+        fn block_size(&self) -> Size {
+            Size::B(2 * PAGE_SIZE)
+        }
+        fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+            self.0 += 1;
+            Ok(ConsecBlocks::new(vec![Memory::mmap(size.bytes())?]))
+        }
+    }
+
+    #[test]
+    fn test_pool_allocator() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::new(MmapAllocator(0), 4)?;
+        assert_eq!((pool.inner.0, pool.available()), (1, 4));
+        let first = pool.alloc_consec_blocks(Size::B(4 * PAGE_SIZE))?;
+        assert_eq!(first.len(), 4 * PAGE_SIZE);
+        assert_eq!((pool.inner.0, pool.available()), (1, 2));
+        // the pool is exhausted and refilled with 4 blocks
+        let second = pool.alloc_consec_blocks(Size::B(6 * PAGE_SIZE))?;
+        assert_eq!(second.len(), 6 * PAGE_SIZE);
+        assert_eq!((pool.inner.0, pool.available()), (2, 3));
+        unsafe { std::ptr::write_volatile(second.addr(6 * PAGE_SIZE - 1), 0x42) };
+        let reused = second.addr(0);
+        pool.release(second);
+        assert_eq!(pool.available(), 6);
+        pool.alloc_consec_blocks(Size::B(6 * PAGE_SIZE))
+            .map(|blocks| pool.release(blocks))?;
+        // released blocks are reused without allocating
+        assert_eq!(pool.inner.0, 2);
+        assert!(pool.pool.iter().any(|blocks| blocks.addr(0) == reused));
+        pool.release(first);
+        assert_eq!(pool.dealloc().0, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_boxed_allocator() -> anyhow::Result<()> {
+        let allocator: Box<dyn ConsecAllocator<Error = crate::memory::MemoryError>> =
+            Box::new(MmapAllocator(0));
+        let mut pool = PoolAllocator::new(allocator, 2)?;
+        assert_eq!(pool.block_size(), Size::B(2 * PAGE_SIZE));
+        let blocks = pool.alloc_consec_blocks(Size::B(4 * PAGE_SIZE))?;
+        assert_eq!(blocks.len(), 4 * PAGE_SIZE);
+        pool.release(blocks);
+        pool.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_numa_allocator() -> anyhow::Result<()> {
+        let nodes = NumaAllocator::detected_nodes();
+        assert!(!nodes.is_empty(), "no NUMA nodes detected");
+        for &node in &nodes {
+            let mut allocator = NumaAllocator::new(MmapAllocator(0), node)?;
+            assert_eq!(allocator.numa_node(), node);
+            let blocks = allocator.alloc_consec_blocks(Size::B(4 * PAGE_SIZE))?;
+            assert_eq!(blocks.len(), 4 * PAGE_SIZE);
+            blocks.dealloc();
+        }
+        let invalid = nodes.last().unwrap() + 1;
+        assert!(matches!(
+            NumaAllocator::new(MmapAllocator(0), invalid),
+            Err(NumaError::InvalidNode { node, .. }) if node == invalid
+        ));
+        Ok(())
+    }
+
+    fn pool(n: usize) -> (AllocatorPool<CountingAllocator>, Vec<Arc<AtomicU64>>) {
+        let counters = (0..n)
+            .map(|_| Arc::new(AtomicU64::new(0)))
+            .collect::<Vec<_>>();
+        let allocators = counters
+            .iter()
+            .map(|c| CountingAllocator(c.clone()))
+            .collect();
+        (AllocatorPool::new(allocators), counters)
+    }
+
+    #[test]
+    fn test_pool_round_robin() {
+        let (mut pool, counters) = pool(3);
+        for i in 0..9 {
+            assert_eq!(pool.current_index(), i % 3);
+            pool.alloc_consec_blocks(Size::B(PAGE_SIZE)).unwrap();
+        }
+        assert!(counters.iter().all(|c| c.load(Ordering::Relaxed) == 3));
+    }
+
+    #[test]
+    fn test_pool_concurrent_fairness() {
+        let (pool, counters) = pool(4);
+        let pool = Arc::new(pool);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let mut pool = pool.clone();
+                s.spawn(move || {
+                    for _ in 0..25 {
+                        pool.alloc_consec_blocks(Size::B(PAGE_SIZE)).unwrap();
+                    }
+                });
+            }
+        });
+        assert!(counters.iter().all(|c| c.load(Ordering::Relaxed) == 25));
+    }
+
+    #[test]
+    fn test_pool_try_lock_any() {
+        let pool = AllocatorPool::with_capacity(2, || CountingAllocator(Default::default()));
+        let first = pool.try_lock_any().expect("first allocator free");
+        let second = pool.try_lock_any().expect("second allocator free");
+        assert!(pool.try_lock_any().is_none());
+        drop(first);
+        assert!(pool.try_lock_any().is_some());
+        drop(second);
+    }
+}
@@ -18,6 +18,10 @@
 /// Implementors must provide:
 /// * [`hammer()`](Hammering::hammer) - Performs the hammering operation
 ///
+/// # Provided Methods
+///
+/// * [`hammer_n()`](Hammering::hammer_n) - Repeats the hammering operation
+///
 /// # Examples
 ///
 /// See individual hammerer implementations such as `swage-blacksmith`, `swage-dev-mem`,
@@ -45,4 +49,60 @@ pub trait Hammering {
     /// * Required hardware interfaces are unavailable
     /// * The hammering operation is interrupted
     fn hammer(&self) -> Result<(), Self::Error>;
+
+    /// Performs the hammering operation `n` times.
+    ///
+    /// `n = 0` is a no-op, and `n = 1` is identical to [`hammer()`](Hammering::hammer).
+    ///
+    /// # Errors
+    ///
+    /// Stops at and returns the first error of [`hammer()`](Hammering::hammer).
+    fn hammer_n(&self, n: u64) -> Result<(), Self::Error> {
+        for _ in 0..n {
+            self.hammer()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Counts calls and fails on call `fail_at`.
+    struct CountingHammerer {
+        calls: Cell<u64>,
+        fail_at: u64,
+    }
+
+    impl Hammering for CountingHammerer {
+        type Error = std::io::Error;
+        fn hammer(&self) -> Result<(), Self::Error> {
+            self.calls.set(self.calls.get() + 1);
+            if self.calls.get() == self.fail_at {
+                return Err(std::io::Error::other("failed"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hammer_n() {
+        let hammerer = CountingHammerer {
+            calls: Cell::new(0),
+            fail_at: u64::MAX,
+        };
+        hammerer.hammer_n(0).expect("hammer failed");
+        assert_eq!(hammerer.calls.get(), 0);
+        hammerer.hammer_n(5).expect("hammer failed");
+        assert_eq!(hammerer.calls.get(), 5);
+
+        let hammerer = CountingHammerer {
+            calls: Cell::new(0),
+            fail_at: 3,
+        };
+        assert!(hammerer.hammer_n(10).is_err());
+        assert_eq!(hammerer.calls.get(), 3);
+    }
 }
@@ -42,6 +42,8 @@ pub mod hammerer;
 mod mem_check;
 pub mod memory;
 pub mod page_inject;
+#[cfg(feature = "parallel")]
+mod parallel_swage;
 mod swage;
 pub mod util;
 pub mod victim;
@@ -49,4 +51,8 @@ pub mod victim;
 pub use crate::mem_check::HammerVictimTargetCheck;
 pub use crate::mem_check::{ExcludeFromInit, MemCheck};
 
-pub use swage::{DataPatternKind, ExperimentData, RoundProfile, Swage, SwageConfig};
+#[cfg(feature = "parallel")]
+pub use parallel_swage::ParallelSwage;
+pub use swage::{
+    ConfigError, DataPatternKind, ExperimentData, RoundProfile, Swage, SwageConfig, SwageStatistics,
+};
@@ -1,6 +1,10 @@
-use crate::memory::{BitFlip, Checkable, ConsecBlocks, DataPattern, Initializable};
+use crate::memory::{
+    BitFlip, Checkable, ConsecBlocks, DataPattern, Initializable, TlbFlushStrategy,
+    check_data_pattern,
+};
+use crate::util::PAGE_MASK;
 use crate::victim::VictimOrchestrator;
-use log::debug;
+use log::{debug, warn};
 use serde::Serialize;
 use std::arch::x86_64::_mm_clflush;
 
@@ -25,6 +29,15 @@ pub struct MemCheck {
     pub pattern: DataPattern,
     #[serde(skip_serializing)]
     excluding: ExcludeFromInit,
+    /// TLB flush performed before reading back the memory
+    pub tlb_flush: TlbFlushStrategy,
+    /// Tolerance of the memory health check performed on start, if enabled
+    pub health_check: Option<f64>,
+    #[serde(skip_serializing)]
+    check_pages: Vec<*const u8>,
+    /// Whether each page is read back right after writing it on init
+    #[cfg(feature = "verify-writes")]
+    pub verify_write: bool,
 }
 
 impl MemCheck {
@@ -40,34 +53,120 @@ impl MemCheck {
             memory,
             pattern,
             excluding,
+            tlb_flush: TlbFlushStrategy::None,
+            health_check: None,
+            check_pages: vec![],
+            #[cfg(feature = "verify-writes")]
+            verify_write: false,
         }
     }
+
+    /// Sets the TLB flush to perform before checking the memory for bit flips.
+    pub fn with_tlb_flush(mut self, tlb_flush: TlbFlushStrategy) -> Self {
+        self.tlb_flush = tlb_flush;
+        self
+    }
+
+    /// Enables a memory health check on start.
+    ///
+    /// On start, the memory is expected to hold `pattern` already, e.g., as written by
+    /// profiling in [`crate::Swage`]. Starting fails if more than a fraction of `tolerance`
+    /// bytes outside of the excluded pages does not match the pattern.
+    pub fn with_health_check(mut self, tolerance: f64) -> Self {
+        self.health_check = Some(tolerance);
+        self
+    }
+
+    /// Restricts checking for bit flips to the given pages.
+    ///
+    /// Pages excluded from initialization are not checked either. If `pages` is empty, the
+    /// whole memory is checked.
+    pub fn with_check_pages(mut self, pages: Vec<*const u8>) -> Self {
+        self.check_pages = pages;
+        self
+    }
+
+    /// Enables reading back each page right after writing it in [`VictimOrchestrator::init`].
+    ///
+    /// Pages that do not read back as written are logged. This helps debugging silent
+    /// memory failures, e.g., when profiling finds no bit flips on known-vulnerable hardware.
+    #[cfg(feature = "verify-writes")]
+    pub fn with_verify_write(mut self, enable: bool) -> Self {
+        self.verify_write = enable;
+        self
+    }
 }
 
 impl VictimOrchestrator for MemCheck {
     fn start(&mut self) -> Result<(), HammerVictimError> {
+        if let Some(tolerance) = self.health_check {
+            let result = check_data_pattern(
+                &self.memory,
+                self.pattern.clone(),
+                &self.excluding.0,
+                tolerance,
+            );
+            debug!("Memory health check: {:?}", result);
+            if !result.passed {
+                warn!("Memory health check failed: {:?}", result);
+                return Err(HammerVictimError::HealthCheckFailed(result));
+            }
+        }
         Ok(())
     }
 
     fn init(&mut self) {
         debug!("initialize victim");
+        #[cfg(feature = "verify-writes")]
+        if self.verify_write {
+            let mismatches = self
+                .memory
+                .initialize_excluding_verified(self.pattern.clone(), &self.excluding.0);
+            if !mismatches.is_empty() {
+                warn!(
+                    "{} pages did not read back as written after init",
+                    mismatches.len()
+                );
+            }
+            return;
+        }
         self.memory
             .initialize_excluding(self.pattern.clone(), &self.excluding.0);
     }
 
     fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
         debug!("check victim");
-        let flips = self
-            .memory
-            .check_excluding(self.pattern.clone(), &self.excluding.0);
+        self.tlb_flush.flush();
+        let flips = if self.check_pages.is_empty() {
+            self.memory
+                .check_excluding(self.pattern.clone(), &self.excluding.0)
+        } else {
+            let pages = self
+                .check_pages
+                .iter()
+                .copied()
+                .filter(|&page| {
+                    !self.excluding.0.iter().any(|&excluded| {
+                        excluded as usize & !PAGE_MASK == page as usize & !PAGE_MASK
+                    })
+                })
+                .collect::<Vec<_>>();
+            self.memory.check_sparse(self.pattern.clone(), &pages)
+        };
         if !flips.is_empty() {
-            Ok(VictimResult::BitFlips(flips.clone()))
+            Ok(VictimResult::BitFlips(flips))
         } else {
             Err(HammerVictimError::NoFlips)
         }
     }
 
     fn stop(&mut self) {}
+
+    /// Does nothing, as [`VictimOrchestrator::init`] overwrites the whole memory. The health
+    /// check only runs on start.
+    fn reset(&mut self) -> Result<(), HammerVictimError> {
+        Ok(())
+    }
 }
 
 /// Target-specific bit flip checker.
@@ -97,6 +196,22 @@ impl HammerVictimTargetCheck {
             targets,
         }
     }
+
+    /// Compares each target byte to its expected value.
+    fn check_targets(&self) -> Vec<BitFlip> {
+        let mut flips = vec![];
+        for target in &self.targets {
+            let value = unsafe {
+                _mm_clflush(target.addr as *const u8);
+                std::ptr::read_volatile(target.addr as *const u8)
+            };
+            if value != target.data {
+                let bitmask = target.data ^ value;
+                flips.push(BitFlip::new(target.addr as *const u8, bitmask, target.data))
+            }
+        }
+        flips
+    }
 }
 
 impl VictimOrchestrator for HammerVictimTargetCheck {
@@ -111,17 +226,7 @@ impl VictimOrchestrator for HammerVictimTargetCheck {
 
     fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
         debug!("check victim");
-        let mut flips = vec![];
-        for target in &self.targets {
-            let value = unsafe {
-                _mm_clflush(target.addr as *const u8);
-                std::ptr::read_volatile(target.addr as *const u8)
-            };
-            if value != target.data {
-                let bitmask = target.data ^ value;
-                flips.push(BitFlip::new(target.addr as *const u8, bitmask, target.data))
-            }
-        }
+        let flips = self.check_targets();
         if !flips.is_empty() {
             Ok(VictimResult::BitFlips(flips))
         } else {
@@ -137,3 +242,51 @@ impl From<Vec<*const u8>> for ExcludeFromInit {
         ExcludeFromInit(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{BytePointer, Memory};
+    use crate::util::ROW_SIZE;
+
+    #[test]
+    fn test_health_check() -> anyhow::Result<()> {
+        let memory = ConsecBlocks::new(vec![Memory::mmap(ROW_SIZE)?]);
+        memory.initialize(DataPattern::One);
+        let mut victim =
+            MemCheck::new(memory.clone(), DataPattern::One, vec![].into()).with_health_check(0.0);
+        victim
+            .start()
+            .expect("health check failed on initialized memory");
+        unsafe { *memory.addr(8) = 0x00 };
+        assert!(matches!(
+            victim.start(),
+            Err(HammerVictimError::HealthCheckFailed(result)) if result.matching_bytes == ROW_SIZE - 1
+        ));
+        memory.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_check_reports_targets_only() -> anyhow::Result<()> {
+        let memory = ConsecBlocks::new(vec![Memory::mmap(2 * ROW_SIZE)?]);
+        let target = BitFlip::new(memory.addr(8), 0x01, 0x00);
+        let mut victim =
+            HammerVictimTargetCheck::new(memory.clone(), DataPattern::Zero, vec![target]);
+        victim.init();
+        assert!(matches!(victim.check(), Err(HammerVictimError::NoFlips)));
+        // a flip in the target row, but not at the target
+        unsafe { *memory.addr(100) = 0x04 };
+        assert!(matches!(victim.check(), Err(HammerVictimError::NoFlips)));
+        unsafe { *memory.addr(8) = 0x01 };
+        match victim.check() {
+            Ok(VictimResult::BitFlips(flips)) => {
+                assert_eq!(flips.len(), 1);
+                assert_eq!(flips[0].addr, memory.addr(8) as usize);
+            }
+            _ => panic!("expected bit flips"),
+        }
+        memory.dealloc();
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::memory::{BytePointer, Memory};
+use crate::util::{CL_SIZE, Size};
+use core::arch::x86_64;
+use core::ptr;
+use log::debug;
+
+/// Cache level serving a memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CacheLevel {
+    /// Level 1 data cache
+    L1,
+    /// Level 2 cache
+    L2,
+    /// Last level cache
+    L3,
+    /// Main memory
+    DRAM,
+}
+
+/// Latency thresholds (in cycles) separating the cache levels.
+///
+/// An access is attributed to the fastest level whose threshold it does not exceed,
+/// and to DRAM if it exceeds all thresholds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheHierarchyConfig {
+    /// Maximum latency of an L1 hit
+    pub l1_threshold: u64,
+    /// Maximum latency of an L2 hit
+    pub l2_threshold: u64,
+    /// Maximum latency of an L3 hit
+    pub l3_threshold: u64,
+}
+
+impl Default for CacheHierarchyConfig {
+    fn default() -> Self {
+        Self {
+            l1_threshold: 50,
+            l2_threshold: 70,
+            l3_threshold: 150,
+        }
+    }
+}
+
+/// Number of samples per measurement in [`CacheHierarchyConfig::detect`]
+const DETECT_ROUNDS: u32 = 1000;
+
+/// Allocation size fitting into the L1 data cache (typically 32 to 48 KB)
+const L1_DETECT_SIZE: usize = 16 * 1024;
+
+impl CacheHierarchyConfig {
+    /// Measures the latency thresholds on the current machine.
+    ///
+    /// Times accesses to a small, cached allocation and to a large allocation flushed from
+    /// the cache, and places the thresholds between the L1 and DRAM latency.
+    pub fn detect() -> CacheHierarchyConfig {
+        let small = Memory::mmap(L1_DETECT_SIZE).expect("mmap");
+        warmup_cache(&small);
+        let l1 = measure_access_latency(small.ptr(), DETECT_ROUNDS);
+        small.dealloc();
+
+        let large = Memory::mmap(Size::MB(64).bytes()).expect("mmap");
+        warmup_cache(&large);
+        let stride = (large.len() / DETECT_ROUNDS as usize) & !(CL_SIZE - 1);
+        let mut samples = (0..DETECT_ROUNDS as usize)
+            .map(|i| measure_uncached_latency(large.addr(i * stride)))
+            .collect::<Vec<_>>();
+        large.dealloc();
+        let dram = median(&mut samples).max(l1 + 1);
+        debug!("Measured L1 latency {}, DRAM latency {}", l1, dram);
+
+        let span = dram - l1;
+        CacheHierarchyConfig {
+            l1_threshold: l1 + span / 8,
+            l2_threshold: l1 + span / 4,
+            l3_threshold: l1 + span / 2,
+        }
+    }
+
+    /// Returns the cache level likely serving an access with the given latency.
+    pub fn classify_latency(&self, cycles: u64) -> CacheLevel {
+        if cycles <= self.l1_threshold {
+            CacheLevel::L1
+        } else if cycles <= self.l2_threshold {
+            CacheLevel::L2
+        } else if cycles <= self.l3_threshold {
+            CacheLevel::L3
+        } else {
+            CacheLevel::DRAM
+        }
+    }
+}
+
+/// Returns the cache level likely serving an access with the given latency, using the
+/// default thresholds of [`CacheHierarchyConfig`].
+pub fn classify_latency(cycles: u64) -> CacheLevel {
+    CacheHierarchyConfig::default().classify_latency(cycles)
+}
+
+/// Returns the median latency in cycles of `rounds` subsequent accesses to `addr`.
+///
+/// The cache is not flushed between accesses, so all but the first access are typically
+/// served from the L1 cache. `addr` must be valid for reads.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn measure_access_latency(addr: *const u8, rounds: u32) -> u64 {
+    let mut samples = (0..rounds.max(1))
+        .map(|_| unsafe { time_access(addr) })
+        .collect::<Vec<_>>();
+    median(&mut samples)
+}
+
+/// Reads every cache line of `region` sequentially, loading it into the cache.
+pub fn warmup_cache(region: &impl BytePointer) {
+    for offset in (0..region.len()).step_by(CL_SIZE) {
+        unsafe { ptr::read_volatile(region.addr(offset)) };
+    }
+}
+
+/// Flushes every cache line of `region` from the cache hierarchy.
+///
+/// Uses `clflushopt` if supported by the CPU and falls back to `clflush` otherwise.
+pub fn evict_from_cache(region: &impl BytePointer) {
+    let has_clflushopt = x86_64::__cpuid_count(7, 0).ebx & (1 << 23) != 0;
+    for offset in (0..region.len()).step_by(CL_SIZE) {
+        let addr = region.addr(offset);
+        if has_clflushopt {
+            // SAFETY: clflushopt is supported and does not modify memory contents
+            unsafe {
+                std::arch::asm!("clflushopt [{}]", in(reg) addr, options(nostack, preserves_flags))
+            };
+        } else {
+            unsafe { x86_64::_mm_clflush(addr) };
+        }
+    }
+    unsafe { x86_64::_mm_mfence() };
+}
+
+/// Times a single access to `addr` using `rdtscp`.
+///
+/// # Safety
+///
+/// `addr` must be valid for reads.
+unsafe fn time_access(addr: *const u8) -> u64 {
+    let mut aux = 0;
+    unsafe {
+        x86_64::_mm_mfence();
+        let before = x86_64::__rdtscp(&mut aux);
+        ptr::read_volatile(addr);
+        let after = x86_64::__rdtscp(&mut aux);
+        x86_64::_mm_mfence();
+        after - before
+    }
+}
+
+/// Times a single access to `addr` after flushing it from the cache.
+fn measure_uncached_latency(addr: *const u8) -> u64 {
+    unsafe {
+        x86_64::_mm_clflush(addr);
+        x86_64::_mm_mfence();
+        time_access(addr)
+    }
+}
+
+fn median(samples: &mut [u64]) -> u64 {
+    samples.sort_unstable();
+    samples[samples.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_latency() {
+        let config = CacheHierarchyConfig {
+            l1_threshold: 10,
+            l2_threshold: 20,
+            l3_threshold: 30,
+        };
+        assert_eq!(config.classify_latency(5), CacheLevel::L1);
+        assert_eq!(config.classify_latency(10), CacheLevel::L1);
+        assert_eq!(config.classify_latency(15), CacheLevel::L2);
+        assert_eq!(config.classify_latency(30), CacheLevel::L3);
+        assert_eq!(config.classify_latency(31), CacheLevel::DRAM);
+    }
+
+    #[test]
+    fn test_l1_faster_than_dram() -> anyhow::Result<()> {
+        let mem = Memory::mmap(Size::MB(4).bytes())?;
+        warmup_cache(&mem);
+        let l1 = measure_access_latency(mem.ptr(), 1000);
+        evict_from_cache(&mem);
+        let mut samples = (0..100)
+            .map(|i| measure_uncached_latency(mem.addr(i * 4096)))
+            .collect::<Vec<_>>();
+        let dram = median(&mut samples);
+        assert!(l1 < dram, "L1 latency {} >= DRAM latency {}", l1, dram);
+        mem.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect() {
+        let config = CacheHierarchyConfig::detect();
+        assert!(config.l1_threshold <= config.l2_threshold);
+        assert!(config.l2_threshold <= config.l3_threshold);
+    }
+}
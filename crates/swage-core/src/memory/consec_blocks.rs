@@ -1,8 +1,15 @@
-use std::{collections::VecDeque, ops::Range};
+use crate::memory::memblock::consec_ranges;
+use crate::memory::{
+    BytePointer, ConsecPfns, ConsecPfnsError, DRAMAddr, GetConsecPfns, LinuxPageMap,
+    LinuxPageMapError, MemConfiguration, MemoryRegion,
+};
+use crate::util::PAGE_SIZE;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
 
-use crate::memory::{BytePointer, GetConsecPfns};
-
-use crate::memory::{Memory, PhysAddr, VictimMemory};
+use crate::memory::{Memory, VictimMemory};
 
 /// Collection of consecutive physical memory blocks.
 ///
@@ -12,7 +19,11 @@ use crate::memory::{Memory, PhysAddr, VictimMemory};
 #[derive(Clone, Debug)]
 pub struct ConsecBlocks {
     /// Vector of memory blocks managed by this collection
+    ///
+    /// Call [`ConsecBlocks::invalidate_phys_cache`] after modifying the blocks.
     pub blocks: Vec<Memory>,
+    /// Physical ranges cached by [`ConsecBlocks::physical_ranges`]
+    phys_cache: OnceLock<ConsecPfns>,
 }
 
 impl ConsecBlocks {
@@ -22,7 +33,84 @@ impl ConsecBlocks {
     ///
     /// * `blocks` - Vector of memory blocks to manage
     pub fn new(blocks: Vec<Memory>) -> Self {
-        ConsecBlocks { blocks }
+        ConsecBlocks {
+            blocks,
+            phys_cache: OnceLock::new(),
+        }
+    }
+
+    /// Creates a new collection and zero-fills all blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Vector of memory blocks to manage. All blocks must be valid for writes.
+    pub fn zeroed(blocks: Vec<Memory>) -> Self {
+        Self::fill(blocks, 0x00)
+    }
+
+    /// Creates a new collection and fills all blocks with `byte`.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - Vector of memory blocks to manage. All blocks must be valid for writes.
+    /// * `byte` - Value written to every byte of the blocks
+    pub fn fill(blocks: Vec<Memory>, byte: u8) -> Self {
+        for block in &blocks {
+            unsafe { std::ptr::write_bytes(block.ptr, byte, block.len) };
+        }
+        Self::new(blocks)
+    }
+
+    /// Returns the physical address ranges backing this collection.
+    ///
+    /// Unlike [`GetConsecPfns::consec_pfns`], the ranges are resolved only on the first
+    /// successful call and cached afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical addresses cannot be resolved.
+    pub fn physical_ranges(&self) -> Result<ConsecPfns, ConsecPfnsError> {
+        if let Some(ranges) = self.phys_cache.get() {
+            return Ok(ranges.clone());
+        }
+        let ranges = self.consec_pfns()?;
+        // a concurrent call may have cached the ranges in the meantime, keep the first
+        Ok(self.phys_cache.get_or_init(|| ranges).clone())
+    }
+
+    /// Clears the ranges cached by [`ConsecBlocks::physical_ranges`].
+    pub fn invalidate_phys_cache(&mut self) {
+        self.phys_cache.take();
+    }
+
+    /// Groups the blocks by the DRAM bank of their first byte.
+    ///
+    /// Returns a map from bank to the indices of the blocks in that bank, in ascending order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical addresses of the blocks cannot be resolved.
+    pub fn bank_distribution(
+        &self,
+        mem_config: &MemConfiguration,
+    ) -> Result<HashMap<usize, Vec<usize>>, LinuxPageMapError> {
+        let virts = self.blocks.iter().map(|b| b.ptr() as u64).collect_vec();
+        let phys = LinuxPageMap::new()?.batch_get_phys(&virts)?;
+        let mut banks: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (idx, phys) in phys.into_iter().enumerate() {
+            let bank = DRAMAddr::from_phys(phys, mem_config).bank;
+            banks.entry(bank).or_default().push(idx);
+        }
+        Ok(banks)
+    }
+
+    /// Returns true if all blocks start in the same DRAM bank.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical addresses of the blocks cannot be resolved.
+    pub fn all_same_bank(&self, mem_config: &MemConfiguration) -> Result<bool, LinuxPageMapError> {
+        Ok(self.bank_distribution(mem_config)?.len() <= 1)
     }
 
     /// Deallocates all memory blocks in this collection.
@@ -33,8 +121,167 @@ impl ConsecBlocks {
             block.dealloc();
         }
     }
+
+    /// Splits this collection at the block boundary nearest to `byte_offset`.
+    ///
+    /// The blocks are shared with `self`, see [`ConsecBlocks::take_blocks`].
+    pub fn split_at_offset(&self, byte_offset: usize) -> (ConsecBlocks, ConsecBlocks) {
+        let mut boundary = 0;
+        let mut split = 0;
+        for (i, block) in self.blocks.iter().enumerate() {
+            let next = boundary + block.len;
+            if next.abs_diff(byte_offset) < boundary.abs_diff(byte_offset) {
+                split = i + 1;
+            }
+            boundary = next;
+        }
+        (self.take_blocks(split), self.skip_blocks(split))
+    }
+
+    /// Splits this collection into views of `[0, byte_offset)` and `[byte_offset, len)`.
+    ///
+    /// A block containing `byte_offset` is split into two blocks, all other blocks are kept
+    /// as they are. As for [`ConsecBlocks::take_blocks`], the returned collections must not
+    /// be deallocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_offset` is larger than the length of this collection.
+    pub fn split_at(&self, byte_offset: usize) -> (ConsecBlocks, ConsecBlocks) {
+        assert!(
+            byte_offset <= self.len(),
+            "Offset {} > {}",
+            byte_offset,
+            self.len()
+        );
+        (
+            self.view(0, byte_offset),
+            self.view(byte_offset, self.len()),
+        )
+    }
+
+    /// Iterates over consecutive views of `chunk_size` bytes.
+    ///
+    /// The last view is shorter if the length is not a multiple of `chunk_size`. As for
+    /// [`ConsecBlocks::take_blocks`], the returned collections must not be deallocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = ConsecBlocks> + '_ {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        (0..self.len())
+            .step_by(chunk_size)
+            .map(move |start| self.view(start, (start + chunk_size).min(self.len())))
+    }
+
+    /// Returns a view of the bytes in `start..end`.
+    fn view(&self, start: usize, end: usize) -> ConsecBlocks {
+        let mut blocks = vec![];
+        let mut block_start = 0;
+        for block in &self.blocks {
+            let block_end = block_start + block.len;
+            if start <= block_start && block_end <= end {
+                blocks.push(block.clone());
+            } else if start < block_end && block_start < end {
+                let from = start.max(block_start) - block_start;
+                let to = end.min(block_end) - block_start;
+                blocks.push(Memory::new(block.addr(from), to - from));
+            }
+            block_start = block_end;
+        }
+        ConsecBlocks::new(blocks)
+    }
+
+    /// Returns the first `count` blocks as a new collection.
+    ///
+    /// The blocks are not copied, i.e., the new collection refers to the same memory
+    /// and `self` still owns it. Do not call [`ConsecBlocks::dealloc`] on the returned
+    /// collection; deallocate `self` instead.
+    pub fn take_blocks(&self, count: usize) -> ConsecBlocks {
+        ConsecBlocks::new(self.blocks.iter().take(count).cloned().collect())
+    }
+
+    /// Returns all but the first `count` blocks as a new collection.
+    ///
+    /// As for [`ConsecBlocks::take_blocks`], the returned collection must not be deallocated.
+    pub fn skip_blocks(&self, count: usize) -> ConsecBlocks {
+        ConsecBlocks::new(self.blocks.iter().skip(count).cloned().collect())
+    }
+
+    /// Iterates over all overlapping windows of `window_size` consecutive blocks.
+    ///
+    /// As for [`ConsecBlocks::take_blocks`], the returned collections must not be deallocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window_size` is 0.
+    pub fn windows(&self, window_size: usize) -> impl Iterator<Item = ConsecBlocks> + '_ {
+        self.blocks
+            .windows(window_size)
+            .map(|window| ConsecBlocks::new(window.to_vec()))
+    }
+
+    /// Wraps this collection in an [`ArcConsecBlocks`] for sharing across threads.
+    #[allow(clippy::arc_with_non_send_sync)] // ArcConsecBlocks implements Send and Sync
+    pub fn into_arc(self) -> ArcConsecBlocks {
+        ArcConsecBlocks(Arc::new(self))
+    }
 }
 
+/// Reference-counted [`ConsecBlocks`] that can be shared across threads.
+///
+/// Cloning only increments the reference count; the underlying memory is never copied.
+#[derive(Clone, Debug)]
+pub struct ArcConsecBlocks(Arc<ConsecBlocks>);
+
+// SAFETY: ConsecBlocks only holds pointers to mmapped memory, which stays valid independent
+// of the thread accessing it. Synchronizing concurrent writes to the memory is up to the user,
+// just as for separate ConsecBlocks clones. The cache of physical ranges is a OnceLock, which
+// is safe to initialize concurrently.
+unsafe impl Send for ArcConsecBlocks {}
+unsafe impl Sync for ArcConsecBlocks {}
+
+impl ArcConsecBlocks {
+    /// Returns the inner [`ConsecBlocks`] if this is the last reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` if other references still exist.
+    pub fn try_unwrap(self) -> Result<ConsecBlocks, ArcConsecBlocks> {
+        Arc::try_unwrap(self.0).map_err(ArcConsecBlocks)
+    }
+}
+
+impl Deref for ArcConsecBlocks {
+    type Target = ConsecBlocks;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl BytePointer for ArcConsecBlocks {
+    fn addr(&self, offset: usize) -> *mut u8 {
+        self.0.addr(offset)
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        self.0.ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl GetConsecPfns for ArcConsecBlocks {
+    fn consec_pfns(&self) -> Result<ConsecPfns, crate::memory::memblock::Error> {
+        self.0.consec_pfns()
+    }
+}
+
+impl VictimMemory for ArcConsecBlocks {}
+
 impl VictimMemory for ConsecBlocks {}
 
 impl BytePointer for ConsecBlocks {
@@ -59,21 +306,260 @@ impl BytePointer for ConsecBlocks {
     }
 }
 
-impl GetConsecPfns for ConsecBlocks {
-    fn consec_pfns(&self) -> Result<Vec<Range<PhysAddr>>, crate::memory::memblock::Error> {
-        let mut pfns = vec![];
+impl MemoryRegion for ConsecBlocks {
+    fn offset_of(&self, addr: *const u8) -> Option<usize> {
+        let mut base = 0;
         for block in &self.blocks {
-            let mut block_pfns = VecDeque::from(block.consec_pfns()?);
-            let is_cons = pfns
-                .last()
-                .is_some_and(|last: &Range<PhysAddr>| last.start == block_pfns[0].end);
-            if is_cons {
-                let prev = pfns.pop();
-                let next = block_pfns.pop_front();
-                pfns.push(prev.unwrap().start..next.unwrap().end);
+            if let Some(offset) = block.offset_of(addr) {
+                return Some(base + offset);
             }
-            pfns.extend(block_pfns);
+            base += block.len;
         }
-        Ok(pfns)
+        None
+    }
+}
+
+impl GetConsecPfns for ConsecBlocks {
+    fn consec_pfns(&self) -> Result<ConsecPfns, crate::memory::memblock::Error> {
+        let pages = self
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                (0..block.len)
+                    .step_by(PAGE_SIZE)
+                    .map(|offset| block.addr(offset) as u64)
+            })
+            .collect_vec();
+        let pfns = LinuxPageMap::new()?.batch_get_phys(&pages)?;
+        consec_ranges(pfns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{PfnResolver, PhysAddr};
+
+    #[test]
+    fn test_arc_consec_blocks_clone() -> anyhow::Result<()> {
+        let blocks = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]).into_arc();
+        let clone = blocks.clone();
+        assert_eq!(blocks.ptr(), clone.ptr());
+        assert_eq!(blocks.len(), clone.len());
+        unsafe { std::ptr::write_volatile(blocks.addr(42), 0x5A) };
+        assert_eq!(unsafe { std::ptr::read_volatile(clone.addr(42)) }, 0x5A);
+        let clone = clone.try_unwrap().expect_err("blocks still referenced");
+        drop(blocks);
+        clone.try_unwrap().expect("last reference").dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_arc_consec_blocks_send() -> anyhow::Result<()> {
+        let blocks = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]).into_arc();
+        let clone = blocks.clone();
+        std::thread::spawn(move || unsafe { std::ptr::write_volatile(clone.addr(0), 0xA5) })
+            .join()
+            .unwrap();
+        assert_eq!(unsafe { std::ptr::read_volatile(blocks.addr(0)) }, 0xA5);
+        blocks.try_unwrap().expect("last reference").dealloc();
+        Ok(())
+    }
+
+    fn blocks(sizes: &[usize]) -> ConsecBlocks {
+        let mut base = 0x2000_0000usize;
+        ConsecBlocks::new(
+            sizes
+                .iter()
+                .map(|&size| {
+                    let block = Memory::new(base as *mut u8, size * PAGE_SIZE);
+                    base += 2 * size * PAGE_SIZE;
+                    block
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_take_skip_blocks() {
+        let blocks = blocks(&[1, 2, 4]);
+        let head = blocks.take_blocks(2);
+        assert_eq!(head.blocks.len(), 2);
+        assert_eq!(head.len(), 3 * PAGE_SIZE);
+        assert_eq!(head.ptr(), blocks.ptr());
+        let tail = blocks.skip_blocks(2);
+        assert_eq!(tail.len(), 4 * PAGE_SIZE);
+        assert_eq!(tail.ptr(), blocks.blocks[2].ptr());
+        assert_eq!(blocks.take_blocks(5).blocks.len(), 3);
+        assert!(blocks.skip_blocks(5).blocks.is_empty());
+        // the parent still owns all blocks
+        assert_eq!(blocks.blocks.len(), 3);
+    }
+
+    #[test]
+    fn test_split_at_offset() {
+        let blocks = blocks(&[1, 2, 4]);
+        let split = |offset: usize| {
+            let (head, tail) = blocks.split_at_offset(offset);
+            assert_eq!(head.blocks.len() + tail.blocks.len(), 3);
+            head.blocks.len()
+        };
+        assert_eq!(split(0), 0);
+        assert_eq!(split(PAGE_SIZE / 2 - 1), 0);
+        assert_eq!(split(PAGE_SIZE), 1);
+        assert_eq!(split(2 * PAGE_SIZE + 1), 2);
+        assert_eq!(split(4 * PAGE_SIZE + 1), 2);
+        assert_eq!(split(6 * PAGE_SIZE), 3);
+        assert_eq!(split(100 * PAGE_SIZE), 3);
+        let (head, tail) = blocks.split_at_offset(3 * PAGE_SIZE);
+        assert_eq!(head.len(), 3 * PAGE_SIZE);
+        assert_eq!(tail.addr(0), blocks.addr(3 * PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_split_at() {
+        let blocks = blocks(&[1, 2, 4]);
+        let total = blocks.len();
+        for offset in [
+            0,
+            1,
+            PAGE_SIZE,
+            total / 2,
+            3 * PAGE_SIZE + 5,
+            total - 1,
+            total,
+        ] {
+            let (head, tail) = blocks.split_at(offset);
+            assert_eq!(head.len(), offset);
+            assert_eq!(head.len() + tail.len(), total);
+            if offset < total {
+                assert_eq!(tail.addr(0), blocks.addr(offset));
+            }
+            if offset > 0 {
+                assert_eq!(head.addr(offset - 1), blocks.addr(offset - 1));
+            }
+        }
+        // splitting at a block boundary keeps the blocks intact
+        let (head, tail) = blocks.split_at(PAGE_SIZE);
+        assert_eq!(head.blocks.len(), 1);
+        assert_eq!(tail.blocks.len(), 2);
+        // splitting inside a block splits it
+        let (head, tail) = blocks.split_at(2 * PAGE_SIZE);
+        assert_eq!(head.blocks.len(), 2);
+        assert_eq!(tail.blocks.len(), 2);
+        assert_eq!(tail.blocks[0].len, PAGE_SIZE);
+        assert!(blocks.split_at(0).0.blocks.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Offset")]
+    fn test_split_at_out_of_bounds() {
+        let blocks = blocks(&[1]);
+        blocks.split_at(PAGE_SIZE + 1);
+    }
+
+    #[test]
+    fn test_chunks() {
+        let blocks = blocks(&[1, 2, 4]);
+        let chunks = blocks.chunks(3 * PAGE_SIZE).collect_vec();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].blocks.len(), 2);
+        assert_eq!(chunks[1].len(), 3 * PAGE_SIZE);
+        assert_eq!(chunks[1].addr(0), blocks.addr(3 * PAGE_SIZE));
+        assert_eq!(chunks[2].len(), PAGE_SIZE);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.len()).sum::<usize>(),
+            blocks.len()
+        );
+    }
+
+    #[test]
+    fn test_physical_ranges_cached() -> anyhow::Result<()> {
+        let mut blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+        let ranges = blocks.physical_ranges()?;
+        assert_eq!(ranges, blocks.consec_pfns()?);
+        assert_eq!(ranges.total_bytes(), 4 * PAGE_SIZE);
+        // a fake cache entry proves that the second call does not resolve the ranges again
+        let fake = ConsecPfns(vec![PhysAddr::new(0)..PhysAddr::new(PAGE_SIZE)]);
+        blocks.phys_cache = OnceLock::from(fake.clone());
+        assert_eq!(blocks.physical_ranges()?, fake);
+        blocks.invalidate_phys_cache();
+        assert_eq!(blocks.physical_ranges()?, ranges);
+        blocks.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_physical_ranges_shared() -> anyhow::Result<()> {
+        let blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]).into_arc();
+        let expected = blocks.consec_pfns()?;
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let blocks = blocks.clone();
+                let expected = &expected;
+                s.spawn(move || assert_eq!(&blocks.physical_ranges().unwrap(), expected));
+            }
+        });
+        blocks.try_unwrap().expect("last reference").dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_bank_distribution() -> anyhow::Result<()> {
+        use crate::memory::MTX_SIZE;
+        use crate::util::PAGE_SHIFT;
+        // single bank bit at the lowest PFN bit
+        let mut dram_mtx = [0; MTX_SIZE];
+        dram_mtx[MTX_SIZE - 1] = 1 << PAGE_SHIFT;
+        let mem_config = MemConfiguration {
+            bk_shift: 0,
+            bk_mask: 0b1,
+            dram_mtx,
+            ..Default::default()
+        };
+        let blocks = ConsecBlocks::new(
+            (0..8)
+                .map(|_| Memory::mmap(PAGE_SIZE))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+        let banks = blocks.bank_distribution(&mem_config)?;
+        assert_eq!(banks.values().map(Vec::len).sum::<usize>(), 8);
+        for (bank, indices) in &banks {
+            assert!(indices.is_sorted());
+            for &idx in indices {
+                let Some(phys) = blocks.blocks[idx].pfn()? else {
+                    continue;
+                };
+                assert_eq!(DRAMAddr::from_phys(phys, &mem_config).bank, *bank);
+            }
+        }
+        assert_eq!(blocks.all_same_bank(&mem_config)?, banks.len() == 1);
+        assert!(blocks.all_same_bank(&MemConfiguration::default())?);
+        blocks.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill() -> anyhow::Result<()> {
+        let blocks = ConsecBlocks::fill(
+            vec![Memory::mmap(PAGE_SIZE)?, Memory::mmap(PAGE_SIZE)?],
+            0xA5,
+        );
+        assert!((0..blocks.len()).all(|offset| unsafe { *blocks.addr(offset) } == 0xA5));
+        let blocks = ConsecBlocks::zeroed(blocks.blocks);
+        assert!((0..blocks.len()).all(|offset| unsafe { *blocks.addr(offset) } == 0x00));
+        blocks.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows() {
+        let blocks = blocks(&[1, 2, 4]);
+        let windows = blocks.windows(2).collect_vec();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].len(), 3 * PAGE_SIZE);
+        assert_eq!(windows[1].ptr(), blocks.blocks[1].ptr());
+        assert_eq!(windows[1].len(), 6 * PAGE_SIZE);
+        assert_eq!(blocks.windows(4).count(), 0);
     }
 }
@@ -0,0 +1,137 @@
+use crate::util::{PAGE_SIZE, Size::MB};
+use log::debug;
+use std::fs::{File, read_to_string};
+use std::io::Write;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const COMPACT_MEMORY_PATH: &str = "/proc/sys/vm/compact_memory";
+const DROP_CACHES_PATH: &str = "/proc/sys/vm/drop_caches";
+const BUDDYINFO_PATH: &str = "/proc/buddyinfo";
+
+/// Buddy allocator order of 4 MB blocks.
+const ORDER_4MB: usize = 10;
+
+/// Strategy for reducing physical memory fragmentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefragStrategy {
+    /// Trigger kernel memory compaction
+    CompactMemory,
+    /// Drop the page cache, dentries and inodes
+    DropCaches,
+    /// Drop caches, then compact memory
+    Both,
+}
+
+/// Reduces physical memory fragmentation using `strategy`.
+///
+/// Requires root privileges.
+///
+/// # Errors
+///
+/// Returns an error if writing to `/proc/sys/vm` fails.
+pub fn defragment(strategy: DefragStrategy) -> Result<(), std::io::Error> {
+    if matches!(strategy, DefragStrategy::DropCaches | DefragStrategy::Both) {
+        debug!("Dropping caches");
+        write!(File::create(DROP_CACHES_PATH)?, "3")?;
+    }
+    if matches!(
+        strategy,
+        DefragStrategy::CompactMemory | DefragStrategy::Both
+    ) {
+        debug!("Compacting memory");
+        write!(File::create(COMPACT_MEMORY_PATH)?, "1")?;
+    }
+    Ok(())
+}
+
+/// Returns the fragmentation of free physical memory.
+///
+/// The index is the fraction of free memory that is not available as 4 MB blocks, i.e.,
+/// 0.0 if all free memory is in 4 MB blocks and 1.0 if none is.
+///
+/// # Errors
+///
+/// Returns an error if `/proc/buddyinfo` cannot be read.
+pub fn fragmentation_index() -> Result<f64, std::io::Error> {
+    Ok(parse_fragmentation_index(&read_to_string(BUDDYINFO_PATH)?))
+}
+
+fn parse_fragmentation_index(buddyinfo: &str) -> f64 {
+    let mut total_free = 0;
+    let mut free_4mb = 0;
+    for line in buddyinfo.lines() {
+        // Node 0, zone   Normal   1   2   3 ...
+        let counts = line
+            .split_whitespace()
+            .skip(4)
+            .filter_map(|count| count.parse::<usize>().ok());
+        for (order, count) in counts.enumerate() {
+            total_free += count * (PAGE_SIZE << order);
+            if order == ORDER_4MB {
+                free_4mb += count;
+            }
+        }
+    }
+    if total_free == 0 {
+        return 1.0;
+    }
+    1.0 - (free_4mb * MB(4).bytes()) as f64 / total_free as f64
+}
+
+/// Waits until the fragmentation index drops below `threshold`.
+///
+/// # Errors
+///
+/// Returns [`std::io::ErrorKind::TimedOut`] if the fragmentation does not drop below
+/// `threshold` within `timeout`, or an error if `/proc/buddyinfo` cannot be read.
+pub fn wait_for_fragmentation_below(
+    threshold: f64,
+    timeout: Duration,
+) -> Result<(), std::io::Error> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    let start = Instant::now();
+    loop {
+        let index = fragmentation_index()?;
+        if index < threshold {
+            return Ok(());
+        }
+        if start.elapsed() > timeout {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!(
+                    "Fragmentation index {:.2} did not drop below {:.2}",
+                    index, threshold
+                ),
+            ));
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUDDYINFO: &str = "Node 0, zone      DMA      0      0      0      0      0      0      0      0      1      1      2
+Node 0, zone    DMA32      4      3      2      1      0      0      0      0      0      0      0
+Node 0, zone   Normal      0      0      0      0      0      0      0      0      0      0      0
+";
+
+    #[test]
+    fn test_parse_fragmentation_index() {
+        // DMA: 1 MB + 2 MB + 2 * 4 MB, DMA32: 16 KB + 24 KB + 32 KB + 32 KB
+        let total = MB(11).bytes() + 104 * 1024;
+        let expected = 1.0 - MB(8).bytes() as f64 / total as f64;
+        assert!((parse_fragmentation_index(BUDDYINFO) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_fragmentation_index_edge_cases() {
+        assert_eq!(parse_fragmentation_index(""), 1.0);
+        let unfragmented = "Node 0, zone   Normal   0   0   0   0   0   0   0   0   0   0   8";
+        assert_eq!(parse_fragmentation_index(unfragmented), 0.0);
+        let fragmented = "Node 0, zone   Normal  42   0   0   0   0   0   0   0   0   0   0";
+        assert_eq!(parse_fragmentation_index(fragmented), 1.0);
+    }
+}
@@ -1,13 +1,14 @@
 use crate::memory::AggressorPtr;
 use crate::memory::MemConfiguration;
-use serde::Deserialize;
+use crate::memory::PhysAddr;
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
 
 /// DRAM address with bank, row, and column components.
 ///
 /// Represents the physical organization of a memory address in DRAM,
 /// decoded from a virtual/physical address using DRAM configuration.
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct DRAMAddr {
     /// Bank number
     pub bank: usize,
@@ -45,7 +46,20 @@ impl DRAMAddr {
     /// * `addr` - Virtual address pointer
     /// * `mem_config` - DRAM addressing configuration
     pub fn from_virt(addr: AggressorPtr, mem_config: &MemConfiguration) -> DRAMAddr {
-        let p = addr as usize;
+        DRAMAddr::decode(addr as usize, mem_config)
+    }
+
+    /// Decodes a physical address into DRAM components.
+    ///
+    /// # Arguments
+    ///
+    /// * `phys` - Physical address, e.g., from [`crate::memory::LinuxPageMap`]
+    /// * `mem_config` - DRAM addressing configuration
+    pub fn from_phys(phys: PhysAddr, mem_config: &MemConfiguration) -> DRAMAddr {
+        DRAMAddr::decode(phys.as_usize(), mem_config)
+    }
+
+    fn decode(p: usize, mem_config: &MemConfiguration) -> DRAMAddr {
         let mut res = 0;
 
         for &i in mem_config.dram_mtx.iter() {
@@ -103,6 +117,55 @@ impl DRAMAddr {
     }
 }
 
+impl DRAMAddr {
+    /// Returns the address `distance` rows away in the same bank and column.
+    ///
+    /// The row wraps around at the number of rows in `mem_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - Number of rows to move, negative values move towards row 0
+    /// * `mem_config` - DRAM addressing configuration
+    pub fn row_neighbors(&self, distance: isize, mem_config: &MemConfiguration) -> DRAMAddr {
+        DRAMAddr {
+            bank: self.bank,
+            row: self.row.wrapping_add_signed(distance) & mem_config.row_mask,
+            col: self.col,
+        }
+    }
+
+    /// Returns true if both addresses are in the same bank.
+    pub fn is_same_bank(&self, other: &DRAMAddr) -> bool {
+        self.bank == other.bank
+    }
+
+    /// Returns the number of rows between both addresses, or None if they are in different banks.
+    pub fn row_distance(&self, other: &DRAMAddr) -> Option<usize> {
+        self.is_same_bank(other)
+            .then(|| self.row.abs_diff(other.row))
+    }
+
+    /// Returns the virtual addresses of the rows directly below and above this address.
+    ///
+    /// Hammering both rows is double-sided hammering of the row at this address. As for
+    /// [`DRAMAddr::to_virt`], memory must be physically contiguous starting at `base_msb`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_msb` - Base address for MSB bits
+    /// * `mem_config` - DRAM addressing configuration
+    pub fn double_sided_aggressors(
+        &self,
+        base_msb: AggressorPtr,
+        mem_config: &MemConfiguration,
+    ) -> [AggressorPtr; 2] {
+        [-1, 1].map(|distance| {
+            self.row_neighbors(distance, mem_config)
+                .to_virt(base_msb, *mem_config)
+        })
+    }
+}
+
 impl DRAMAddr {
     /// Adds offsets to each DRAM address component.
     ///
@@ -0,0 +1,156 @@
+use crate::memory::MemConfiguration;
+use serde::Serialize;
+
+/// Number of banks per rank on DDR4 modules (4 bank groups with 4 banks each).
+const DDR4_BANKS_PER_RANK: usize = 16;
+
+/// Width of the DRAM data bus in bytes.
+const BUS_WIDTH_BYTES: usize = 8;
+
+/// Human-readable geometry of a DRAM module.
+///
+/// Derived from the bank, row and column masks of a [`MemConfiguration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DRAMGeometry {
+    /// Total number of banks (over all ranks and channels)
+    pub banks: usize,
+    /// Number of rows per bank
+    pub rows: usize,
+    /// Number of columns per row
+    pub columns: usize,
+    /// Number of ranks
+    pub rank: usize,
+    /// Number of channels
+    pub channel: usize,
+}
+
+/// Well-known DRAM module standards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[allow(non_camel_case_types)]
+pub enum DRAMStandard {
+    /// DDR4 module with 8 GB capacity
+    DDR4_8GB,
+    /// DDR4 module with 16 GB capacity
+    DDR4_16GB,
+    /// DDR4 module with 32 GB capacity
+    DDR4_32GB,
+    /// Geometry does not match any known standard
+    Unknown,
+}
+
+impl DRAMGeometry {
+    /// Derives the geometry from a memory configuration.
+    ///
+    /// The number of ranks is estimated from the bank count, assuming 16 banks per rank as
+    /// on DDR4. The channel count cannot be derived from the masks and is set to 1.
+    pub fn from_mem_configuration(config: &MemConfiguration) -> DRAMGeometry {
+        let banks = config.get_bank_count();
+        DRAMGeometry {
+            banks,
+            rows: config.get_row_count(),
+            columns: 1 << config.col_mask.count_ones(),
+            rank: (banks / DDR4_BANKS_PER_RANK).max(1),
+            channel: 1,
+        }
+    }
+
+    /// Returns the total capacity of the module in bytes.
+    pub fn total_capacity_bytes(&self) -> u64 {
+        self.banks as u64 * self.rows as u64 * self.columns as u64 * BUS_WIDTH_BYTES as u64
+    }
+
+    /// Returns the size of a single row in bytes.
+    pub fn row_size_bytes(&self) -> usize {
+        self.columns * BUS_WIDTH_BYTES
+    }
+
+    /// Returns true if the module has two ranks.
+    pub fn is_dual_rank(&self) -> bool {
+        self.rank == 2
+    }
+
+    /// Matches the geometry against well-known module standards.
+    ///
+    /// # Returns
+    ///
+    /// The matching standard, [`DRAMStandard::Unknown`] if the capacity does not match any
+    /// standard, or None if the geometry is empty
+    pub fn matches_standard(&self) -> Option<DRAMStandard> {
+        const GB: u64 = 1 << 30;
+        if self.banks == 0 || self.rows == 0 || self.columns == 0 {
+            return None;
+        }
+        Some(match self.total_capacity_bytes() {
+            c if c == 8 * GB => DRAMStandard::DDR4_8GB,
+            c if c == 16 * GB => DRAMStandard::DDR4_16GB,
+            c if c == 32 * GB => DRAMStandard::DDR4_32GB,
+            _ => DRAMStandard::Unknown,
+        })
+    }
+
+    /// Returns a one-line summary of the geometry.
+    pub fn display_summary(&self) -> String {
+        format!(
+            "{} banks x {} rows x {} columns ({} rank(s), {} channel(s), {} GB)",
+            self.banks,
+            self.rows,
+            self.columns,
+            self.rank,
+            self.channel,
+            self.total_capacity_bytes() >> 30
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mem_config(bank_bits: u32, row_bits: u32, col_bits: u32) -> MemConfiguration {
+        MemConfiguration {
+            bk_mask: (1 << bank_bits) - 1,
+            row_mask: (1 << row_bits) - 1,
+            col_mask: (1 << col_bits) - 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_single_rank() {
+        let geometry = DRAMGeometry::from_mem_configuration(&mem_config(4, 16, 10));
+        assert_eq!(geometry.banks, 16);
+        assert_eq!(geometry.rows, 1 << 16);
+        assert_eq!(geometry.columns, 1 << 10);
+        assert!(!geometry.is_dual_rank());
+        assert_eq!(geometry.row_size_bytes(), 8192);
+        assert_eq!(geometry.total_capacity_bytes(), 8 << 30);
+        assert_eq!(geometry.matches_standard(), Some(DRAMStandard::DDR4_8GB));
+        assert_eq!(
+            geometry.display_summary(),
+            "16 banks x 65536 rows x 1024 columns (1 rank(s), 1 channel(s), 8 GB)"
+        );
+    }
+
+    #[test]
+    fn test_dual_rank() {
+        let geometry = DRAMGeometry::from_mem_configuration(&mem_config(5, 16, 10));
+        assert!(geometry.is_dual_rank());
+        assert_eq!(geometry.matches_standard(), Some(DRAMStandard::DDR4_16GB));
+        let geometry = DRAMGeometry::from_mem_configuration(&mem_config(5, 17, 10));
+        assert_eq!(geometry.matches_standard(), Some(DRAMStandard::DDR4_32GB));
+    }
+
+    #[test]
+    fn test_unknown_standard() {
+        let geometry = DRAMGeometry::from_mem_configuration(&mem_config(3, 12, 10));
+        assert_eq!(geometry.matches_standard(), Some(DRAMStandard::Unknown));
+        let geometry = DRAMGeometry {
+            banks: 0,
+            rows: 0,
+            columns: 0,
+            rank: 1,
+            channel: 1,
+        };
+        assert_eq!(geometry.matches_standard(), None);
+    }
+}
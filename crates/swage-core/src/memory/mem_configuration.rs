@@ -8,7 +8,11 @@ pub const MTX_SIZE: usize = 30;
 ///
 /// Defines how virtual addresses map to physical DRAM organization
 /// (bank, row, column) using transformation matrices.
-#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+///
+/// Two configurations are equal if their matrices, masks and shifts are equal element-wise,
+/// i.e., if they map addresses identically. How they were constructed, e.g., the order of
+/// fields in a config file, does not matter. This makes configurations usable as cache keys.
+#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
 pub struct MemConfiguration {
     /// Bit shift for bank extraction
     pub bk_shift: usize,
@@ -33,7 +37,23 @@ pub struct MemConfiguration {
 impl MemConfiguration {
     /// Returns the periodicity of the bank function in rows.
     ///
-    /// Indicates how many rows must be iterated before the bank function repeats.
+    /// Indicates how many rows must be iterated before the bank function repeats. Address bits
+    /// above `max_bank_bit` do not affect the bank, so addresses that are
+    /// `1 << (max_bank_bit + 1)` bytes apart map to the same bank. With rows of
+    /// `1 << ROW_SHIFT` bytes, this is `1 << (max_bank_bit + 1 - ROW_SHIFT)` rows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swage_core::memory::MemConfiguration;
+    ///
+    /// // highest bank bit is 21, rows are 8 KB
+    /// let config = MemConfiguration {
+    ///     max_bank_bit: 21,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(config.bank_function_period(), 512);
+    /// ```
     pub fn bank_function_period(&self) -> u64 {
         1 << (self.max_bank_bit + 1 - ROW_SHIFT as u64)
     }
@@ -49,4 +69,170 @@ impl MemConfiguration {
     pub fn get_row_count(&self) -> usize {
         1_usize << (self.row_mask.count_ones() as usize)
     }
+
+    /// Returns the number of banks, i.e., the number of values of the bank bits.
+    ///
+    /// Same as [`MemConfiguration::get_bank_count`], as `u64` for analysis code.
+    pub fn bank_count(&self) -> u64 {
+        self.get_bank_count() as u64
+    }
+
+    /// Returns the number of rows in each bank, i.e., the number of values of the row bits.
+    ///
+    /// Same as [`MemConfiguration::get_row_count`], as `u64` for analysis code.
+    pub fn row_count_per_bank(&self) -> u64 {
+        self.get_row_count() as u64
+    }
+}
+
+impl MemConfiguration {
+    /// Returns true if this configuration describes a consistent address mapping.
+    ///
+    /// A configuration is valid if
+    /// - the bank, row and column masks are contiguous and, shifted into place, cover the
+    ///   `MTX_SIZE` address bits without overlapping,
+    /// - `dram_mtx` is invertible over GF(2) and `addr_mtx` is its inverse, and
+    /// - `max_bank_bit` is the highest address bit used by the bank functions.
+    pub fn is_valid(&self) -> bool {
+        self.fields_valid() && self.matrices_valid() && self.max_bank_bit_valid()
+    }
+
+    fn fields_valid(&self) -> bool {
+        let fields = [
+            (self.bk_mask, self.bk_shift),
+            (self.row_mask, self.row_shift),
+            (self.col_mask, self.col_shift),
+        ];
+        let mut covered = 0usize;
+        for (mask, shift) in fields {
+            let contiguous = mask & mask.wrapping_add(1) == 0;
+            if !contiguous || shift + (mask.count_ones() as usize) > MTX_SIZE {
+                return false;
+            }
+            if covered & (mask << shift) != 0 {
+                return false;
+            }
+            covered |= mask << shift;
+        }
+        covered == (1 << MTX_SIZE) - 1
+    }
+
+    fn matrices_valid(&self) -> bool {
+        // addr_mtx * dram_mtx is the identity if it maps each unit vector to itself
+        (0..MTX_SIZE).all(|bit| {
+            let unit = 1 << bit;
+            mtx_apply(&self.addr_mtx, mtx_apply(&self.dram_mtx, unit)) == unit
+        }) && gf2_rank(&self.dram_mtx) == MTX_SIZE
+    }
+
+    fn max_bank_bit_valid(&self) -> bool {
+        // row i of dram_mtx computes bit MTX_SIZE - 1 - i of the linearized DRAM address
+        let bank_bits = self.bk_mask.count_ones() as usize;
+        let first_row = MTX_SIZE - self.bk_shift - bank_bits;
+        self.dram_mtx[first_row..first_row + bank_bits]
+            .iter()
+            .filter(|&&row| row != 0)
+            .map(|row| (usize::BITS - 1 - row.leading_zeros()) as u64)
+            .max()
+            == Some(self.max_bank_bit)
+    }
+}
+
+/// Multiplies `mtx` with the bit vector `v` over GF(2), like [`crate::memory::DRAMAddr`]
+/// does when translating addresses.
+fn mtx_apply(mtx: &[usize; MTX_SIZE], v: usize) -> usize {
+    mtx.iter().fold(0, |res, row| {
+        (res << 1) | ((v & row).count_ones() as usize & 1)
+    })
+}
+
+/// Returns the rank of `mtx` over GF(2) using Gaussian elimination.
+fn gf2_rank(mtx: &[usize; MTX_SIZE]) -> usize {
+    let mut rows = *mtx;
+    let mut rank = 0;
+    for bit in 0..usize::BITS as usize {
+        let Some(pivot) = (rank..MTX_SIZE).find(|&i| rows[i] >> bit & 1 == 1) else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+        for i in 0..MTX_SIZE {
+            if i != rank && rows[i] >> bit & 1 == 1 {
+                rows[i] ^= rows[rank];
+            }
+        }
+        rank += 1;
+    }
+    rank
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// 4 bank bits, 13 column bits and 13 row bits, each taken directly from the address.
+    fn identity_config() -> MemConfiguration {
+        let mut mtx = [0; MTX_SIZE];
+        for (i, row) in mtx.iter_mut().enumerate() {
+            *row = 1 << (MTX_SIZE - 1 - i);
+        }
+        MemConfiguration {
+            bk_shift: 26,
+            bk_mask: 0xF,
+            col_shift: 13,
+            col_mask: 0x1FFF,
+            row_shift: 0,
+            row_mask: 0x1FFF,
+            dram_mtx: mtx,
+            addr_mtx: mtx,
+            max_bank_bit: 29,
+        }
+    }
+
+    #[test]
+    fn test_is_valid() {
+        let config = identity_config();
+        assert!(config.is_valid());
+        assert!(!MemConfiguration::default().is_valid());
+
+        // bank function xor-ing two bits, inverted by the same xor
+        let mut xored = config;
+        xored.dram_mtx[0] |= 1 << 20;
+        assert!(!xored.is_valid());
+        xored.addr_mtx[0] |= 1 << 20;
+        assert!(xored.is_valid());
+
+        let mut singular = config;
+        singular.dram_mtx[1] = singular.dram_mtx[0];
+        assert!(!singular.is_valid());
+
+        let mut overlapping = config;
+        overlapping.col_shift = 12;
+        assert!(!overlapping.is_valid());
+
+        let mut wrong_max_bit = config;
+        wrong_max_bit.max_bank_bit = 28;
+        assert!(!wrong_max_bit.is_valid());
+    }
+
+    #[test]
+    fn test_counts() {
+        let config = identity_config();
+        assert_eq!(config.bank_count(), 16);
+        assert_eq!(config.row_count_per_bank(), 1 << 13);
+        // bank bits 26..30 repeat every 1 << 30 bytes
+        assert_eq!(config.bank_function_period(), 1 << (30 - ROW_SHIFT));
+    }
+
+    #[test]
+    fn test_eq_hash() {
+        let config = identity_config();
+        let mut other = identity_config();
+        assert_eq!(config, other);
+        let set = HashSet::from([config, other]);
+        assert_eq!(set.len(), 1);
+        other.addr_mtx[3] ^= 1;
+        assert_ne!(config, other);
+        assert!(!set.contains(&other));
+    }
 }
@@ -0,0 +1,139 @@
+use crate::memory::{BytePointer, ConsecBlocks, VictimMemory};
+use serde::Serialize;
+use serde::ser::SerializeStruct;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// [`ConsecBlocks`] labeled with metadata for logging and result analysis.
+///
+/// Annotations describe the role of a memory region in an attack, e.g., `"aggressor_bank_0"`
+/// or `"victim_row_42"`. They can be attached to results using
+/// [`crate::ExperimentData::annotate`].
+#[derive(Clone, Debug)]
+pub struct MemRegionAnnotation {
+    /// The annotated memory region
+    pub region: ConsecBlocks,
+    /// Human-readable name of the region
+    pub label: String,
+    /// Additional data describing the region
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl MemRegionAnnotation {
+    /// Creates a new annotation without metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - Memory region to annotate
+    /// * `label` - Human-readable name of the region
+    pub fn new(region: ConsecBlocks, label: impl Into<String>) -> Self {
+        MemRegionAnnotation {
+            region,
+            label: label.into(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Adds the metadata entry `key` to this annotation, replacing any previous value.
+    pub fn with_metadata(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns the label of this region.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the annotated region, dropping the label and metadata.
+    pub fn into_inner(self) -> ConsecBlocks {
+        self.region
+    }
+}
+
+impl Deref for MemRegionAnnotation {
+    type Target = ConsecBlocks;
+    fn deref(&self) -> &Self::Target {
+        &self.region
+    }
+}
+
+impl BytePointer for MemRegionAnnotation {
+    fn addr(&self, offset: usize) -> *mut u8 {
+        self.region.addr(offset)
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        self.region.ptr()
+    }
+
+    fn len(&self) -> usize {
+        self.region.len()
+    }
+}
+
+impl VictimMemory for MemRegionAnnotation {}
+
+#[derive(Serialize)]
+struct BlockSummary {
+    addr: String,
+    len: usize,
+}
+
+/// Serializes the label, the metadata and the virtual address and length of each block.
+impl Serialize for MemRegionAnnotation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let blocks = self
+            .region
+            .blocks
+            .iter()
+            .map(|block| BlockSummary {
+                addr: format!("{:p}", block.ptr()),
+                len: block.len(),
+            })
+            .collect::<Vec<_>>();
+        let mut state = serializer.serialize_struct("MemRegionAnnotation", 3)?;
+        state.serialize_field("label", &self.label)?;
+        state.serialize_field("metadata", &self.metadata)?;
+        state.serialize_field("blocks", &blocks)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{Checkable, DataPattern, Initializable, Memory};
+    use crate::util::PAGE_SIZE;
+
+    #[test]
+    fn test_annotation() -> anyhow::Result<()> {
+        let blocks = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?, Memory::mmap(PAGE_SIZE)?]);
+        let annotation = MemRegionAnnotation::new(blocks.clone(), "victim_row_42")
+            .with_metadata("row", 42)
+            .with_metadata("bank", "0");
+        assert_eq!(annotation.label(), "victim_row_42");
+        assert_eq!(annotation.len(), 2 * PAGE_SIZE);
+        assert_eq!(annotation.addr(PAGE_SIZE), blocks.addr(PAGE_SIZE));
+
+        annotation.initialize(DataPattern::Zero);
+        unsafe { *annotation.addr(PAGE_SIZE + 1) = 0x10 };
+        assert_eq!(annotation.check(DataPattern::Zero).len(), 1);
+
+        let json = serde_json::to_value(&annotation)?;
+        assert_eq!(json["label"], "victim_row_42");
+        assert_eq!(json["metadata"]["row"], 42);
+        assert_eq!(json["blocks"].as_array().map(Vec::len), Some(2));
+        assert_eq!(json["blocks"][1]["len"], PAGE_SIZE);
+        assert_eq!(
+            json["blocks"][0]["addr"],
+            format!("{:p}", blocks.blocks[0].ptr())
+        );
+        annotation.into_inner().dealloc();
+        Ok(())
+    }
+}
@@ -1,9 +1,16 @@
+use std::arch::x86_64::{_mm_clflush, _mm_mfence};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use std::{cell::RefCell, ops::Range, ptr::null_mut};
 
-use super::{BytePointer, PfnOffset, PhysAddr, pfn_offset::CachedPfnOffset};
+use super::{
+    BytePointer, MemoryError, MemoryRegion, PfnOffset, PhysAddr, pfn_offset::CachedPfnOffset,
+};
 use crate::memory::virt_to_phys::LinuxPageMapError;
 use crate::memory::{LinuxPageMap, VirtToPhysResolver};
-use crate::util::PAGE_SIZE;
+use crate::util::{CL_SIZE, PAGE_SIZE, RowOffset};
 use libc::{MAP_ANONYMOUS, MAP_POPULATE, MAP_SHARED};
 use log::{log, trace, warn};
 use pagemap2::VirtualMemoryArea;
@@ -51,12 +58,23 @@ impl Memory {
     /// Allocates memory using mmap.
     ///
     /// Creates a memory-mapped region of the specified size with
-    /// read/write permissions.
+    /// read/write permissions. The region is zeroed by the kernel; use
+    /// [`crate::memory::ConsecBlocks::fill`] to initialize it with other values.
     ///
     /// # Errors
     ///
-    /// Returns an I/O error if mmap fails.
-    pub fn mmap(size: usize) -> std::result::Result<Self, std::io::Error> {
+    /// Returns [`MemoryError::ZeroSizeLayout`] or [`MemoryError::SizeTooLarge`] for invalid
+    /// sizes, and [`MemoryError::MmapFailed`] if mmap fails.
+    pub fn mmap(size: usize) -> std::result::Result<Self, MemoryError> {
+        if size == 0 {
+            return Err(MemoryError::ZeroSizeLayout);
+        }
+        if size > isize::MAX as usize {
+            return Err(MemoryError::SizeTooLarge {
+                requested: size,
+                max: isize::MAX as usize,
+            });
+        }
         let p = unsafe {
             libc::mmap(
                 null_mut(),
@@ -68,20 +86,265 @@ impl Memory {
             )
         };
         if p == libc::MAP_FAILED {
-            return Err(std::io::Error::last_os_error());
+            return Err(MemoryError::MmapFailed(std::io::Error::last_os_error()));
         }
-        unsafe { libc::memset(p, 0x00, size) };
         Ok(Memory::new(p as *mut u8, size))
     }
 
+    /// Locks the block in RAM until it is deallocated.
+    ///
+    /// Use [`Memory::lock_guard`] to unlock the block automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::MlockFailed`] if mlock fails.
+    pub fn lock(&self) -> std::result::Result<(), MemoryError> {
+        let ret = unsafe { libc::mlock(self.ptr as *const libc::c_void, self.len) };
+        if ret != 0 {
+            return Err(MemoryError::MlockFailed(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Faults in all pages of the block for writing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError::NotPageAligned`] if the block does not start at a page
+    /// boundary, and [`MemoryError::MadviseFailed`] if madvise fails.
+    pub fn prefault(&self) -> std::result::Result<(), MemoryError> {
+        if !(self.ptr as usize).is_multiple_of(PAGE_SIZE) {
+            return Err(MemoryError::NotPageAligned {
+                addr: self.ptr as usize,
+                alignment: PAGE_SIZE,
+            });
+        }
+        let ret = unsafe {
+            libc::madvise(
+                self.ptr as *mut libc::c_void,
+                self.len,
+                libc::MADV_POPULATE_WRITE,
+            )
+        };
+        if ret != 0 {
+            return Err(MemoryError::MadviseFailed(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
     /// Deallocates the memory block.
     ///
-    /// Unmaps the memory region using munmap. Consumes self.
+    /// Unmaps the memory region using munmap. Consumes self. `munmap` also unlocks the block,
+    /// so [`MlockGuard`]s covering it do not unlock the range again when dropped.
     pub fn dealloc(self) {
+        let unmapped = self.ptr as usize..self.ptr as usize + self.len;
+        let mut ranges = locked_ranges();
+        *ranges = ranges
+            .drain(..)
+            .flat_map(|(id, range)| {
+                subtract_ranges(range, [unmapped.clone()])
+                    .into_iter()
+                    .map(move |part| (id, part))
+            })
+            .collect();
         unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
     }
 }
 
+impl Memory {
+    /// Locks the block in RAM until the returned guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if `mlock` fails, e.g., when exceeding `RLIMIT_MEMLOCK`.
+    pub fn lock_guard(&self) -> std::result::Result<MlockGuard, std::io::Error> {
+        MlockGuard::new(self)
+    }
+}
+
+/// Address ranges locked by live [`MlockGuard`]s, tagged with the ID of their guard.
+///
+/// `mlock` does not nest, so a guard only unlocks the parts of its range that no other guard
+/// covers. [`Memory::dealloc`] removes the unmapped parts, which `munmap` already unlocked.
+static LOCKED_RANGES: Mutex<Vec<(u64, Range<usize>)>> = Mutex::new(vec![]);
+static NEXT_GUARD_ID: AtomicU64 = AtomicU64::new(0);
+
+fn locked_ranges() -> MutexGuard<'static, Vec<(u64, Range<usize>)>> {
+    LOCKED_RANGES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the parts of `range` not covered by any of `others`.
+fn subtract_ranges(
+    range: Range<usize>,
+    others: impl IntoIterator<Item = Range<usize>>,
+) -> Vec<Range<usize>> {
+    let mut parts = vec![range];
+    for other in others {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| {
+                [
+                    part.start..part.end.min(other.start),
+                    part.start.max(other.end)..part.end,
+                ]
+            })
+            .filter(|part| !part.is_empty())
+            .collect();
+    }
+    parts
+}
+
+/// RAII guard keeping a [`Memory`] block locked in RAM.
+///
+/// The block is locked with `mlock` on creation and unlocked with `munlock` on drop, except
+/// for the parts still covered by another guard or already deallocated with
+/// [`Memory::dealloc`]. The guard does not borrow the block, so it can be kept alongside it.
+#[derive(Debug)]
+pub struct MlockGuard {
+    id: u64,
+}
+
+impl MlockGuard {
+    /// Locks `memory` in RAM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if `mlock` fails.
+    pub fn new(memory: &Memory) -> std::result::Result<Self, std::io::Error> {
+        let range = memory.ptr as usize..memory.ptr as usize + memory.len;
+        let mut ranges = locked_ranges();
+        mlock(&range)?;
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        ranges.push((id, range));
+        Ok(MlockGuard { id })
+    }
+
+    /// Returns the ranges currently locked by this guard.
+    fn ranges(&self) -> Vec<Range<usize>> {
+        locked_ranges()
+            .iter()
+            .filter(|(id, _)| *id == self.id)
+            .map(|(_, range)| range.clone())
+            .collect()
+    }
+
+    /// Locks the block again.
+    ///
+    /// This faults in pages that were dropped in the meantime, e.g., after `madvise(MADV_FREE)`.
+    /// Locking already locked pages is allowed and has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if `mlock` fails.
+    pub fn extend(&self) -> std::result::Result<(), std::io::Error> {
+        self.ranges().iter().try_for_each(mlock)
+    }
+
+    /// Returns whether the process has at least as much memory locked as the length of this block.
+    ///
+    /// This reads `VmLck` from `/proc/self/status`, which covers all locked memory of the
+    /// process and is therefore only an approximation.
+    pub fn is_locked(&self) -> bool {
+        locked_bytes().is_some_and(|locked| locked >= self.len())
+    }
+
+    /// Returns the number of bytes locked by this guard.
+    ///
+    /// This drops to zero once the block is deallocated with [`Memory::dealloc`].
+    pub fn len(&self) -> usize {
+        self.ranges().iter().map(ExactSizeIterator::len).sum()
+    }
+
+    /// Returns whether the locked block is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for MlockGuard {
+    fn drop(&mut self) {
+        let mut ranges = locked_ranges();
+        let (own, others): (Vec<_>, Vec<_>) = ranges.drain(..).partition(|(id, _)| *id == self.id);
+        *ranges = others;
+        for (_, range) in own {
+            for part in subtract_ranges(range, ranges.iter().map(|(_, range)| range.clone())) {
+                let ret = unsafe { libc::munlock(part.start as *const libc::c_void, part.len()) };
+                if ret != 0 {
+                    warn!(
+                        "munlock({:#x}) failed: {}",
+                        part.start,
+                        std::io::Error::last_os_error()
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn mlock(range: &Range<usize>) -> std::result::Result<(), std::io::Error> {
+    let ret = unsafe { libc::mlock(range.start as *const libc::c_void, range.len()) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns the amount of locked memory of this process in bytes, as reported by `VmLck`.
+fn locked_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmLck:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
+
+impl Memory {
+    /// Returns the start address of `row` in this block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is out of bounds.
+    pub fn addr_at_row(&self, row: RowOffset) -> *mut u8 {
+        self.addr(row.as_bytes())
+    }
+
+    /// Returns the number of cache lines in this block.
+    pub fn cache_line_count(&self) -> usize {
+        self.len / CL_SIZE
+    }
+
+    /// Calls `f(offset, ptr)` for each cache line and collects the results.
+    ///
+    /// `offset` is the byte offset of the cache line, `ptr` points to its first byte.
+    pub fn for_each_cache_line<T>(&self, mut f: impl FnMut(usize, *mut u8) -> T) -> Vec<T> {
+        (0..self.cache_line_count())
+            .map(|cl| cl * CL_SIZE)
+            .map(|offset| f(offset, self.addr(offset)))
+            .collect()
+    }
+
+    /// Writes `f(offset)` to each cache line using volatile writes.
+    pub fn fill_cache_lines(&self, f: impl Fn(usize) -> [u8; CL_SIZE]) {
+        self.for_each_cache_line(|offset, ptr| unsafe {
+            std::ptr::write_volatile(ptr as *mut [u8; CL_SIZE], f(offset))
+        });
+    }
+
+    /// Reads each cache line from DRAM and calls `f(offset, data)`.
+    ///
+    /// Each line is flushed from the cache before reading, so `data` reflects
+    /// the memory contents rather than a cached copy.
+    pub fn read_cache_lines(&self, mut f: impl FnMut(usize, [u8; CL_SIZE])) {
+        self.for_each_cache_line(|offset, ptr| {
+            let data = unsafe {
+                _mm_clflush(ptr);
+                _mm_mfence();
+                std::ptr::read_volatile(ptr as *const [u8; CL_SIZE])
+            };
+            f(offset, data)
+        });
+    }
+}
+
 impl BytePointer for Memory {
     fn addr(&self, offset: usize) -> *mut u8 {
         assert!(
@@ -100,6 +363,13 @@ impl BytePointer for Memory {
     }
 }
 
+impl MemoryRegion for Memory {
+    fn offset_of(&self, addr: *const u8) -> Option<usize> {
+        let offset = (addr as usize).checked_sub(self.ptr as usize)?;
+        (offset < self.len).then_some(offset)
+    }
+}
+
 impl CachedPfnOffset for Memory {
     fn cached_offset(&self) -> &PfnOffset {
         &self.pfn_offset
@@ -154,28 +424,33 @@ impl GetConsecPfns for Memory {
 impl<T> GetConsecPfns for (*mut T, usize) {
     fn consec_pfns(&self) -> Result<ConsecPfns> {
         trace!("Get consecutive PFNs for vaddr 0x{:x}", self.0 as u64);
-        let mut consecs = vec![];
         // optimization: get PFN range
         let mut resolver = LinuxPageMap::new()?;
         let pfns = resolver.get_phys_range(VirtualMemoryArea::from((self.0 as u64, unsafe {
             self.0.byte_add(self.1) as u64
         })))?;
-        if pfns.is_empty() {
-            return Err(Error::EmptyPfnRange);
-        }
-        let mut phys_prev = pfns[0];
-        let mut range_start = phys_prev;
-        for phys in pfns.into_iter().skip(1) {
-            if phys != phys_prev + PAGE_SIZE {
-                consecs.push(range_start..phys_prev + PAGE_SIZE);
-                range_start = phys;
-            }
-            phys_prev = phys;
+        consec_ranges(pfns)
+    }
+}
+
+/// Merges page-wise physical addresses into ranges of consecutive pages.
+pub(crate) fn consec_ranges(pfns: Vec<PhysAddr>) -> Result<ConsecPfns> {
+    if pfns.is_empty() {
+        return Err(Error::EmptyPfnRange);
+    }
+    let mut consecs = vec![];
+    let mut phys_prev = pfns[0];
+    let mut range_start = phys_prev;
+    for phys in pfns.into_iter().skip(1) {
+        if phys != phys_prev + PAGE_SIZE {
+            consecs.push(range_start..phys_prev + PAGE_SIZE);
+            range_start = phys;
         }
-        consecs.push(range_start..phys_prev + PAGE_SIZE);
-        trace!("PFN check done");
-        Ok(consecs)
+        phys_prev = phys;
     }
+    consecs.push(range_start..phys_prev + PAGE_SIZE);
+    trace!("PFN check done");
+    Ok(ConsecPfns(consecs))
 }
 
 /// Formats physical frame number ranges for display.
@@ -184,8 +459,93 @@ pub trait FormatPfns {
     fn format_pfns(&self) -> String;
 }
 
-/// Type alias for consecutive physical frame number ranges.
-type ConsecPfns = Vec<Range<PhysAddr>>;
+/// Ranges of consecutive physical pages, in the virtual address order of a memory region.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsecPfns(pub Vec<Range<PhysAddr>>);
+
+impl ConsecPfns {
+    /// Returns the total size of all ranges in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.iter().map(range_bytes).sum()
+    }
+
+    /// Returns the largest range, or None if there are no ranges.
+    ///
+    /// If several ranges have the largest size, the first one is returned.
+    pub fn largest_range(&self) -> Option<&Range<PhysAddr>> {
+        self.iter().rev().max_by_key(|range| range_bytes(range))
+    }
+
+    /// Returns the smallest range, or None if there are no ranges.
+    ///
+    /// If several ranges have the smallest size, the first one is returned.
+    pub fn smallest_range(&self) -> Option<&Range<PhysAddr>> {
+        self.iter().min_by_key(|range| range_bytes(range))
+    }
+
+    /// Returns true if the memory region is physically contiguous.
+    pub fn is_single_range(&self) -> bool {
+        self.len() == 1
+    }
+
+    /// Returns the physical address of the first page.
+    pub fn first_pfn(&self) -> Option<PhysAddr> {
+        self.first().map(|range| range.start)
+    }
+
+    /// Returns the physical address of the last page.
+    pub fn last_pfn(&self) -> Option<PhysAddr> {
+        self.last().map(|range| range.end - PAGE_SIZE)
+    }
+
+    /// Merges subsequent ranges where one range ends at the start of the next.
+    ///
+    /// The order of the ranges is kept, i.e., adjacent ranges that are not subsequent in
+    /// virtual address order are not merged.
+    pub fn merge_adjacent(self) -> ConsecPfns {
+        let mut merged: Vec<Range<PhysAddr>> = Vec::with_capacity(self.len());
+        for range in self {
+            match merged.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => merged.push(range),
+            }
+        }
+        ConsecPfns(merged)
+    }
+}
+
+fn range_bytes(range: &Range<PhysAddr>) -> usize {
+    (range.end - range.start).as_usize()
+}
+
+impl Deref for ConsecPfns {
+    type Target = [Range<PhysAddr>];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for ConsecPfns {
+    type Item = Range<PhysAddr>;
+    type IntoIter = std::vec::IntoIter<Range<PhysAddr>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ConsecPfns {
+    type Item = &'a Range<PhysAddr>;
+    type IntoIter = std::slice::Iter<'a, Range<PhysAddr>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<Range<PhysAddr>> for ConsecPfns {
+    fn from_iter<I: IntoIterator<Item = Range<PhysAddr>>>(iter: I) -> Self {
+        ConsecPfns(iter.into_iter().collect())
+    }
+}
 
 impl FormatPfns for ConsecPfns {
     fn format_pfns(&self) -> String {
@@ -194,7 +554,7 @@ impl FormatPfns for ConsecPfns {
             pfns += &format!(
                 "{:p}..[{:04} KB]..{:p}\n",
                 range.start,
-                (range.end - range.start).as_usize() / 1024,
+                range_bytes(range) / 1024,
                 range.end
             );
         }
@@ -202,6 +562,12 @@ impl FormatPfns for ConsecPfns {
     }
 }
 
+impl fmt::Display for ConsecPfns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format_pfns())
+    }
+}
+
 // TODO: we can move this alongside consec_alloc/mmap.rs, but we'll need some more refactoring before (self.pfn_offset is private).
 impl Memory {
     #[cfg(false)]
@@ -236,3 +602,192 @@ impl Memory {
         Ok(blocks)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::ROW_SIZE;
+
+    #[test]
+    fn test_cache_lines() {
+        let block = Memory::mmap(PAGE_SIZE).expect("mmap failed");
+        assert_eq!(block.cache_line_count(), PAGE_SIZE / CL_SIZE);
+        block.fill_cache_lines(|offset| [(offset / CL_SIZE) as u8; CL_SIZE]);
+        let mut lines = 0;
+        block.read_cache_lines(|offset, data| {
+            assert_eq!(data, [(offset / CL_SIZE) as u8; CL_SIZE]);
+            lines += 1;
+        });
+        assert_eq!(lines, block.cache_line_count());
+        let offsets = block.for_each_cache_line(|offset, _| offset);
+        assert_eq!(offsets.last(), Some(&(PAGE_SIZE - CL_SIZE)));
+        block.dealloc();
+    }
+
+    #[test]
+    fn test_addr_at_row() {
+        let block = Memory::mmap(2 * ROW_SIZE).expect("mmap failed");
+        assert_eq!(block.addr_at_row(RowOffset(0)), block.ptr());
+        assert_eq!(block.addr_at_row(RowOffset(1)), block.addr(ROW_SIZE));
+        block.dealloc();
+    }
+
+    /// Serializes tests observing the process-wide `VmLck`
+    static VMLCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn test_lock_guard_drop() {
+        let _vmlck = VMLCK.lock().unwrap();
+        let block = Memory::mmap(64 * PAGE_SIZE).expect("mmap failed");
+        let before = locked_bytes().expect("VmLck");
+        {
+            let guard = block.lock_guard().expect("mlock failed");
+            assert_eq!(guard.len(), block.len);
+            assert!(guard.is_locked());
+            assert_eq!(locked_bytes(), Some(before + block.len));
+        }
+        assert_eq!(locked_bytes(), Some(before));
+        block.dealloc();
+    }
+
+    #[test]
+    fn test_lock_guard_double_lock() {
+        let _vmlck = VMLCK.lock().unwrap();
+        let block = Memory::mmap(16 * PAGE_SIZE).expect("mmap failed");
+        let before = locked_bytes().expect("VmLck");
+        let guard = block.lock_guard().expect("mlock failed");
+        let second = block.lock_guard().expect("second mlock failed");
+        guard.extend().expect("extend failed");
+        assert!(guard.is_locked() && second.is_locked());
+        // the block stays locked while the first guard is alive
+        drop(second);
+        assert_eq!(locked_bytes(), Some(before + block.len));
+        drop(guard);
+        assert_eq!(locked_bytes(), Some(before));
+        block.dealloc();
+    }
+
+    #[test]
+    fn test_lock_guard_overlap() {
+        let _vmlck = VMLCK.lock().unwrap();
+        let block = Memory::mmap(24 * PAGE_SIZE).expect("mmap failed");
+        let before = locked_bytes().expect("VmLck");
+        let first = Memory::new(block.ptr, 16 * PAGE_SIZE)
+            .lock_guard()
+            .expect("mlock failed");
+        let second = Memory::new(block.addr(8 * PAGE_SIZE), 16 * PAGE_SIZE)
+            .lock_guard()
+            .expect("mlock failed");
+        assert_eq!(locked_bytes(), Some(before + block.len));
+        drop(first);
+        assert_eq!(locked_bytes(), Some(before + 16 * PAGE_SIZE));
+        drop(second);
+        assert_eq!(locked_bytes(), Some(before));
+        block.dealloc();
+    }
+
+    #[test]
+    fn test_lock_guard_dealloc() {
+        let _vmlck = VMLCK.lock().unwrap();
+        let block = Memory::mmap(16 * PAGE_SIZE).expect("mmap failed");
+        let before = locked_bytes().expect("VmLck");
+        let guard = block.lock_guard().expect("mlock failed");
+        block.clone().dealloc();
+        assert_eq!(locked_bytes(), Some(before));
+        // the unmapped block is not unlocked again
+        assert!(guard.is_empty());
+        drop(guard);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_subtract_ranges() {
+        assert_eq!(subtract_ranges(0..10, []), [0..10]);
+        assert_eq!(subtract_ranges(0..10, [3..5]), [0..3, 5..10]);
+        assert_eq!(subtract_ranges(0..10, [5..20, 20..30]), [0..5]);
+        assert_eq!(subtract_ranges(5..10, [0..2, 0..20]), []);
+    }
+
+    #[test]
+    fn test_mmap_invalid_size() {
+        assert!(matches!(Memory::mmap(0), Err(MemoryError::ZeroSizeLayout)));
+        assert!(matches!(
+            Memory::mmap(usize::MAX),
+            Err(MemoryError::SizeTooLarge {
+                requested: usize::MAX,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_lock_prefault() {
+        let _vmlck = VMLCK.lock().unwrap();
+        let block = Memory::mmap(16 * PAGE_SIZE).expect("mmap failed");
+        let before = locked_bytes().expect("VmLck");
+        block.prefault().expect("prefault failed");
+        block.lock().expect("mlock failed");
+        assert_eq!(locked_bytes(), Some(before + block.len));
+        let misaligned = Memory::new(block.addr(1), PAGE_SIZE);
+        assert!(matches!(
+            misaligned.prefault(),
+            Err(MemoryError::NotPageAligned {
+                alignment: PAGE_SIZE,
+                ..
+            })
+        ));
+        block.dealloc();
+        assert_eq!(locked_bytes(), Some(before));
+    }
+
+    fn pfns(ranges: &[(usize, usize)]) -> ConsecPfns {
+        ranges
+            .iter()
+            .map(|&(start, pages)| {
+                PhysAddr::new(start * PAGE_SIZE)..PhysAddr::new((start + pages) * PAGE_SIZE)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_consec_pfns_ranges() {
+        let pfns = pfns(&[(0x100, 2), (0x200, 4), (0x300, 1), (0x400, 4)]);
+        assert_eq!(pfns.len(), 4);
+        assert_eq!(pfns.total_bytes(), 11 * PAGE_SIZE);
+        assert_eq!(pfns.largest_range(), Some(&pfns[1]));
+        assert_eq!(pfns.smallest_range(), Some(&pfns[2]));
+        assert!(!pfns.is_single_range());
+        assert_eq!(pfns.first_pfn(), Some(PhysAddr::new(0x100 * PAGE_SIZE)));
+        assert_eq!(pfns.last_pfn(), Some(PhysAddr::new(0x403 * PAGE_SIZE)));
+        assert_eq!((&pfns).into_iter().count(), 4);
+        assert_eq!(pfns.to_string(), pfns.format_pfns());
+
+        let empty = ConsecPfns::default();
+        assert_eq!(empty.total_bytes(), 0);
+        assert_eq!(empty.largest_range(), None);
+        assert_eq!(empty.first_pfn(), None);
+        assert_eq!(empty.last_pfn(), None);
+        assert!(!empty.is_single_range());
+    }
+
+    #[test]
+    fn test_consec_pfns_merge_adjacent() {
+        let merged = pfns(&[(0x100, 2), (0x102, 1), (0x200, 1), (0x103, 1), (0x201, 3)]);
+        assert_eq!(
+            merged.merge_adjacent(),
+            pfns(&[(0x100, 3), (0x200, 1), (0x103, 1), (0x201, 3)])
+        );
+        let single = pfns(&[(0x100, 1), (0x101, 1), (0x102, 2)]).merge_adjacent();
+        assert!(single.is_single_range());
+        assert_eq!(single.total_bytes(), 4 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_consec_ranges() {
+        let pages = [0x10, 0x11, 0x12, 0x20, 0x21, 0x13]
+            .map(|pfn| PhysAddr::new(pfn * PAGE_SIZE))
+            .to_vec();
+        let ranges = consec_ranges(pages).expect("consec_ranges failed");
+        assert_eq!(ranges, pfns(&[(0x10, 3), (0x20, 2), (0x13, 1)]));
+    }
+}
@@ -3,6 +3,7 @@
 //! The `memory` module provides the following abstractions:
 //! - `Memory`: A managed memory region that is allocated using HugepageAllocator.
 //! - `VictimMemory`: A trait that combines the `BytePointer`, `Initializable`, and `Checkable` traits.
+//! - `MemoryRegion`: A trait that unifies `Memory` and `ConsecBlocks` for page-, row-, and cache-line-wise access.
 //! - `BytePointer`: A trait for accessing memory as a byte pointer.
 //! - `Initializable`: A trait for initializing memory with (random) values.
 //! - `Checkable`: A trait for checking memory for bitflips.
@@ -12,48 +13,101 @@
 //!
 //! The `memory` module also provides the following helper structs:
 //! - `ConsecBlocks`: A struct that represents a collection of consecutive memory blocks.
+//! - `ArcConsecBlocks`: A reference-counted `ConsecBlocks` that can be shared across threads.
+//! - `MemRegionAnnotation`: A `ConsecBlocks` labeled with metadata for logging and result analysis.
 //! - `MemBlock`: A struct that represents a memory block.
+//! - `MlockGuard`: A RAII guard that keeps a `Memory` block locked in RAM.
+//! - `DRAMGeometry`: A struct that describes the bank, row, and column geometry of a DRAM module.
+//! - `PageTableMonitor`: A struct that detects page table corruption by comparing address translations.
 //! - `PfnOffset`: A struct that represents a physical frame number (PFN) offset.
+//! - `LayoutMap`: A struct that maps the physical DRAM layout of an allocation.
+//! - `PhysicalPageFlags`: A struct that holds the kernel flags of a physical page.
+//! - `RowConflictDetector`: A struct that groups addresses into DRAM banks using access timings.
+//! - `RowChecker`: A struct that checks selected DRAM rows for bit flips.
+//! - `SparseMemory`: A struct that represents a collection of physically non-adjacent memory regions.
 //! - `PfnOffsetResolver`: A struct that resolves the physical frame number (PFN) offset of a provided virtual address.
 //! - `Timer`: A struct that provides a timer for measuring memory access times.
 //!
 //! The `memory` module also provides the following helper functions:
 //! - `construct_memory_tuple_timer`: A function that constructs a memory tuple timer.
+//! - `measure_access_latency`, `classify_latency`: Functions for determining the cache level serving an access.
+//! - `flush_tlb_single`, `flush_tlb_range`, `flush_tlb_all`: Functions for flushing TLB entries.
+//! - `check_byte_value`, `check_data_pattern`, `random_hamming_weight_check`: Functions for checking memory health before an experiment.
+//! - `defragment`, `fragmentation_index`: Functions for reducing and measuring physical memory fragmentation.
+#[cfg(target_arch = "x86_64")]
+mod cache_hierarchy;
 mod consec_blocks;
+mod defrag;
 mod dram_addr;
+mod dram_geometry;
 mod flippy_page;
 mod keyed_cache;
 mod mem_configuration;
+mod mem_region_annotation;
 mod memblock;
+mod page_table_check;
 mod pagemap_info;
 mod pfn_offset;
 mod pfn_offset_resolver;
 mod pfn_resolver;
+mod physical_layout;
+mod physical_page_flags;
+mod row_conflict_detector;
 mod timer;
+#[cfg(target_arch = "x86_64")]
+mod tlb_flush;
 mod virt_to_phys;
 
-pub use self::consec_blocks::ConsecBlocks;
+#[cfg(target_arch = "x86_64")]
+pub use self::cache_hierarchy::{
+    CacheHierarchyConfig, CacheLevel, classify_latency, evict_from_cache, measure_access_latency,
+    warmup_cache,
+};
+pub use self::consec_blocks::{ArcConsecBlocks, ConsecBlocks};
+pub use self::defrag::{
+    DefragStrategy, defragment, fragmentation_index, wait_for_fragmentation_below,
+};
 pub use self::dram_addr::DRAMAddr;
+pub use self::dram_geometry::{DRAMGeometry, DRAMStandard};
 pub use self::flippy_page::{FlippyPage, find_flippy_page};
 pub use self::mem_configuration::{MTX_SIZE, MemConfiguration};
-pub use self::memblock::{Error as ConsecPfnsError, FormatPfns, GetConsecPfns, Memory};
+pub use self::mem_region_annotation::MemRegionAnnotation;
+pub use self::memblock::{
+    ConsecPfns, Error as ConsecPfnsError, FormatPfns, GetConsecPfns, Memory, MlockGuard,
+};
+pub use self::page_table_check::{
+    PageTableMonitor, PteFlip, pte_flip_direction, spawn_pte_monitor,
+};
 pub use self::pfn_offset::PfnOffset;
 pub use self::pfn_offset_resolver::PfnOffsetResolver;
 pub use self::pfn_resolver::PfnResolver;
-pub use self::timer::{MemoryTupleTimer, TimerError, construct_memory_tuple_timer};
+pub use self::physical_layout::{LayoutMap, PhysicalBlock, render_physical_layout};
+pub use self::physical_page_flags::PhysicalPageFlags;
+pub use self::row_conflict_detector::{BankMap, ConflictResult, RowConflictDetector};
+pub use self::timer::{
+    MemoryTupleTimer, MemoryTupleTimerStats, TimerError, construct_memory_tuple_timer,
+};
+#[cfg(target_arch = "x86_64")]
+pub use self::tlb_flush::{
+    TlbFlushStrategy, flush_tlb_all, flush_tlb_range, flush_tlb_single,
+    needs_tlb_flush_after_hammer,
+};
 pub use self::virt_to_phys::PhysAddr;
 pub use self::virt_to_phys::{LinuxPageMap, LinuxPageMapError, VirtToPhysResolver};
 use rand::Rng as _;
 use serde::Serialize;
-use std::arch::x86_64::_mm_clflush;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::io::BufWriter;
+use std::sync::Arc;
 
-use crate::util::{CL_SIZE, PAGE_MASK, PAGE_SIZE, ROW_MASK, ROW_SIZE, Rng};
+use crate::victim::{HammerVictimError, VictimOrchestrator, VictimResult};
+use std::arch::x86_64::{_mm_clflush, _mm_mfence};
 
-use libc::{c_void, memcmp};
-use log::{debug, info, trace};
-use std::{arch::x86_64::_mm_mfence, fmt};
+use crate::util::{CL_SIZE, PAGE_MASK, PAGE_SIZE, ROW_MASK, ROW_SIZE, Rng, RowOffset};
+
+use log::{debug, info, trace, warn};
+use std::fmt;
 
 /// Pointer type for aggressor row addresses.
 ///
@@ -68,15 +122,111 @@ pub enum MemoryError {
     AllocFailed,
     /// Attempted to create a zero-size memory layout
     ZeroSizeLayout,
+    /// The requested size exceeds the maximum supported size
+    SizeTooLarge {
+        /// Requested size in bytes
+        requested: usize,
+        /// Maximum supported size in bytes
+        max: usize,
+    },
+    /// An address is not aligned as required by the operation
+    NotPageAligned {
+        /// The misaligned address
+        addr: usize,
+        /// Required alignment in bytes
+        alignment: usize,
+    },
+    /// `mmap` failed
+    MmapFailed(std::io::Error),
+    /// `mlock` failed
+    MlockFailed(std::io::Error),
+    /// `madvise` failed
+    MadviseFailed(std::io::Error),
+    /// Resolving a physical address failed
+    PhysAddrResolutionFailed(LinuxPageMapError),
 }
 
-impl std::error::Error for MemoryError {}
+impl MemoryError {
+    /// Returns true if the error is caused by missing privileges, e.g., insufficient
+    /// `RLIMIT_MEMLOCK` or missing `CAP_IPC_LOCK` for `mlock`.
+    pub fn is_privilege_error(&self) -> bool {
+        matches!(self, MemoryError::MlockFailed(e) if e.raw_os_error() == Some(libc::EPERM))
+    }
+
+    /// Returns a description of the error with a suggestion on how to resolve it.
+    pub fn to_user_message(&self) -> String {
+        let hint = match self {
+            MemoryError::AllocFailed | MemoryError::MmapFailed(_) => {
+                "Check available memory (free -h) and the virtual memory limit (ulimit -v)."
+            }
+            MemoryError::ZeroSizeLayout => "Request a size of at least one page.",
+            MemoryError::SizeTooLarge { .. } => "Request a smaller size.",
+            MemoryError::NotPageAligned { .. } => "Use a page-aligned address.",
+            MemoryError::MlockFailed(_) if self.is_privilege_error() => {
+                "Run as root or grant CAP_IPC_LOCK."
+            }
+            MemoryError::MlockFailed(_) => {
+                "Raise the locked memory limit (ulimit -l) or run as root."
+            }
+            MemoryError::MadviseFailed(_) => "Check that the kernel supports the madvise advice.",
+            MemoryError::PhysAddrResolutionFailed(_) => {
+                "Run as root to read physical addresses from /proc/self/pagemap."
+            }
+        };
+        format!("{}. {}", self, hint)
+    }
+}
+
+impl std::error::Error for MemoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemoryError::MmapFailed(e)
+            | MemoryError::MlockFailed(e)
+            | MemoryError::MadviseFailed(e) => Some(e),
+            MemoryError::PhysAddrResolutionFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for MemoryError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             MemoryError::AllocFailed => write!(f, "Allocation failed"),
             MemoryError::ZeroSizeLayout => write!(f, "Zero size layout"),
+            MemoryError::SizeTooLarge { requested, max } => write!(
+                f,
+                "Requested size 0x{:x} exceeds maximum size 0x{:x}",
+                requested, max
+            ),
+            MemoryError::NotPageAligned { addr, alignment } => write!(
+                f,
+                "Address 0x{:x} is not aligned to 0x{:x} bytes",
+                addr, alignment
+            ),
+            MemoryError::MmapFailed(e) => write!(f, "mmap failed: {}", e),
+            MemoryError::MlockFailed(e) => write!(f, "mlock failed: {}", e),
+            MemoryError::MadviseFailed(e) => write!(f, "madvise failed: {}", e),
+            MemoryError::PhysAddrResolutionFailed(e) => {
+                write!(f, "Physical address resolution failed: {}", e)
+            }
+        }
+    }
+}
+
+impl From<LinuxPageMapError> for MemoryError {
+    fn from(e: LinuxPageMapError) -> Self {
+        MemoryError::PhysAddrResolutionFailed(e)
+    }
+}
+
+impl From<MemoryError> for std::io::Error {
+    fn from(e: MemoryError) -> Self {
+        match e {
+            MemoryError::MmapFailed(e)
+            | MemoryError::MlockFailed(e)
+            | MemoryError::MadviseFailed(e) => e,
+            e => std::io::Error::other(e),
         }
     }
 }
@@ -87,6 +237,49 @@ impl fmt::Display for MemoryError {
 /// a complete interface for managing victim memory in Rowhammer attacks.
 pub trait VictimMemory: BytePointer + Initializable + Checkable {}
 
+/// Common interface for memory regions such as [`Memory`] and [`ConsecBlocks`].
+///
+/// Combines [`BytePointer`], [`GetConsecPfns`] and [`PfnResolver`] and provides
+/// helpers for iterating the region in units of pages, rows and cache lines.
+/// Regions are expected to start at a page-aligned address.
+pub trait MemoryRegion: BytePointer + GetConsecPfns + PfnResolver {
+    /// Returns the number of pages in this region.
+    fn page_count(&self) -> usize {
+        self.len() / PAGE_SIZE
+    }
+
+    /// Returns the number of rows in this region.
+    fn row_count(&self) -> usize {
+        self.len() / ROW_SIZE
+    }
+
+    /// Returns the number of cache lines in this region.
+    fn cache_line_count(&self) -> usize {
+        self.len() / CL_SIZE
+    }
+
+    /// Returns the byte offset of `addr` in this region, or None if `addr` is out of bounds.
+    fn offset_of(&self, addr: *const u8) -> Option<usize>;
+
+    /// Iterates over the start addresses of all pages in this region.
+    fn iter_pages(&self) -> impl Iterator<Item = *mut u8>
+    where
+        Self: Sized,
+    {
+        (0..self.page_count()).map(|page| self.addr(page * PAGE_SIZE))
+    }
+
+    /// Iterates over the offsets and start addresses of all rows in this region.
+    fn iter_rows(&self) -> impl Iterator<Item = (RowOffset, *mut u8)>
+    where
+        Self: Sized,
+    {
+        (0..self.row_count())
+            .map(RowOffset)
+            .map(|row| (row, self.addr(row.as_bytes())))
+    }
+}
+
 /// Trait for accessing memory as a byte pointer.
 ///
 /// Provides low-level access to memory regions with byte-level addressing.
@@ -136,7 +329,10 @@ pub trait BytePointer {
 /// Different patterns can be used to maximize the probability of inducing bit flips.
 /// Stripe patterns alternate between aggressor rows (ones/zeros) and victim rows
 /// (opposite values) to create charge transfer between adjacent DRAM rows.
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+///
+/// Patterns compare by value: random patterns are equal if they use the same seed, stripe
+/// patterns if they have the same set of aggressor rows.
+#[derive(Clone, Debug, Serialize)]
 pub enum DataPattern {
     /// Random data pattern using a seeded RNG
     Random(Box<Rng>),
@@ -156,9 +352,151 @@ pub enum DataPattern {
     },
     /// All ones (0xFF)
     One,
+    /// Alternates between `base` and `base ^ 0xFF` every `xor_period` bytes
+    XOR {
+        /// The value of the even periods
+        base: u8,
+        /// The period length in bytes, must be a non-zero multiple of `PAGE_SIZE`
+        xor_period: usize,
+    },
+    /// Fills every `pitch`-th row with `aggressor_value`, other rows with `victim_value`
+    RowStripe {
+        /// Distance between two aggressor rows, must be non-zero
+        pitch: usize,
+        /// The value of the aggressor rows
+        aggressor_value: u8,
+        /// The value of all other rows
+        victim_value: u8,
+    },
+    /// Alternates between 0x55 and 0xAA every cache line
+    Checkerboard {
+        /// Whether even cache lines contain 0xAA instead of 0x55
+        invert: bool,
+    },
+    /// Repeats custom bytes, e.g., loaded with [`DataPattern::from_file`], within each page
+    ///
+    /// Byte `i` of every page is byte `i % len` of the custom bytes. The bytes must not be
+    /// empty.
+    Custom(#[serde(skip_serializing)] Arc<Vec<u8>>),
+}
+
+/// Identifies a [`DataPattern`] for comparison and hashing.
+#[derive(PartialEq, Eq, Hash)]
+enum DataPatternKey {
+    Random(u64),
+    StripeZero(BTreeSet<usize>),
+    Zero,
+    StripeOne(BTreeSet<usize>),
+    One,
+    Xor(u8, usize),
+    RowStripe(usize, u8, u8),
+    Checkerboard(bool),
+    Custom(Arc<Vec<u8>>),
+}
+
+impl DataPattern {
+    fn key(&self) -> DataPatternKey {
+        let rows = |rows: &[AggressorPtr]| rows.iter().map(|&row| row as usize).collect();
+        match self {
+            DataPattern::Random(rng) => DataPatternKey::Random(rng.seed()),
+            DataPattern::StripeZero { zeroes } => DataPatternKey::StripeZero(rows(zeroes)),
+            DataPattern::Zero => DataPatternKey::Zero,
+            DataPattern::StripeOne { ones } => DataPatternKey::StripeOne(rows(ones)),
+            DataPattern::One => DataPatternKey::One,
+            DataPattern::XOR { base, xor_period } => DataPatternKey::Xor(*base, *xor_period),
+            DataPattern::RowStripe {
+                pitch,
+                aggressor_value,
+                victim_value,
+            } => DataPatternKey::RowStripe(*pitch, *aggressor_value, *victim_value),
+            DataPattern::Checkerboard { invert } => DataPatternKey::Checkerboard(*invert),
+            DataPattern::Custom(bytes) => DataPatternKey::Custom(bytes.clone()),
+        }
+    }
+}
+
+impl PartialEq for DataPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for DataPattern {}
+
+impl std::hash::Hash for DataPattern {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
 
 impl DataPattern {
+    /// Returns the bitwise complement of this pattern.
+    ///
+    /// Stripe patterns keep their aggressor rows and swap the aggressor and victim values.
+    /// Random patterns have no complement representation and are returned unchanged.
+    pub fn complement(&self) -> DataPattern {
+        match self {
+            DataPattern::Random(rng) => DataPattern::Random(rng.clone()),
+            DataPattern::StripeZero { zeroes } => DataPattern::StripeOne {
+                ones: zeroes.clone(),
+            },
+            DataPattern::Zero => DataPattern::One,
+            DataPattern::StripeOne { ones } => DataPattern::StripeZero {
+                zeroes: ones.clone(),
+            },
+            DataPattern::One => DataPattern::Zero,
+            DataPattern::XOR { base, xor_period } => DataPattern::XOR {
+                base: base ^ 0xFF,
+                xor_period: *xor_period,
+            },
+            DataPattern::RowStripe {
+                pitch,
+                aggressor_value,
+                victim_value,
+            } => DataPattern::RowStripe {
+                pitch: *pitch,
+                aggressor_value: aggressor_value ^ 0xFF,
+                victim_value: victim_value ^ 0xFF,
+            },
+            DataPattern::Checkerboard { invert } => DataPattern::Checkerboard { invert: !invert },
+            DataPattern::Custom(bytes) => {
+                DataPattern::Custom(Arc::new(bytes.iter().map(|b| b ^ 0xFF).collect()))
+            }
+        }
+    }
+
+    /// Loads a [`DataPattern::Custom`] pattern from the binary file at `path`.
+    ///
+    /// Only the first `PAGE_SIZE` bytes are used, shorter files are repeated within each page.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is empty.
+    pub fn from_file(path: &str) -> Result<DataPattern, std::io::Error> {
+        let bytes = std::fs::read(path)?;
+        if bytes.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("data pattern file {} is empty", path),
+            ));
+        }
+        Ok(DataPattern::Custom(Arc::new(bytes)))
+    }
+
+    /// Saves the page this pattern writes to the first page of the address space to `path`.
+    ///
+    /// Loading the file with [`DataPattern::from_file`] yields a pattern writing the same
+    /// content to every page. For patterns that do not depend on the address, e.g.,
+    /// [`DataPattern::Custom`], this is the same pattern. Random patterns write the first page
+    /// of their seeded sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_to_file(&self, path: &str) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.clone().get(std::ptr::null()))
+    }
+
     fn get(&mut self, addr: *const u8) -> [u8; PAGE_SIZE] {
         match self {
             DataPattern::Random(rng) => {
@@ -188,6 +526,40 @@ impl DataPattern {
                 [0x00; PAGE_SIZE]
             }
             DataPattern::One => [0xFF; PAGE_SIZE],
+            DataPattern::XOR { base, xor_period } => {
+                if (addr as usize / *xor_period).is_multiple_of(2) {
+                    [*base; PAGE_SIZE]
+                } else {
+                    [*base ^ 0xFF; PAGE_SIZE]
+                }
+            }
+            DataPattern::RowStripe {
+                pitch,
+                aggressor_value,
+                victim_value,
+            } => {
+                if (addr as usize / ROW_SIZE).is_multiple_of(*pitch) {
+                    [*aggressor_value; PAGE_SIZE]
+                } else {
+                    [*victim_value; PAGE_SIZE]
+                }
+            }
+            DataPattern::Checkerboard { invert } => {
+                let mut arr = [0u8; PAGE_SIZE];
+                let first_line = addr as usize / CL_SIZE;
+                for (i, line) in arr.chunks_exact_mut(CL_SIZE).enumerate() {
+                    let odd = !(first_line + i).is_multiple_of(2);
+                    line.fill(if odd != *invert { 0xAA } else { 0x55 });
+                }
+                arr
+            }
+            DataPattern::Custom(bytes) => {
+                let mut arr = [0u8; PAGE_SIZE];
+                for (byte, &value) in arr.iter_mut().zip(bytes.iter().cycle()) {
+                    *byte = value;
+                }
+                arr
+            }
         }
     }
 }
@@ -207,6 +579,80 @@ pub trait Initializable {
     ///
     /// The callback receives an offset and returns optional page data.
     fn initialize_cb(&self, f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>);
+
+    /// Initializes memory excluding specific pages, reading back each page right after
+    /// writing it.
+    ///
+    /// Returns the pages that did not read back as written, e.g., due to ECC scrubbing or
+    /// copy-on-write by the kernel.
+    #[cfg(feature = "verify-writes")]
+    fn initialize_excluding_verified(
+        &self,
+        pattern: DataPattern,
+        pages: &[*const u8],
+    ) -> Vec<*const u8>;
+
+    /// Initializes a double-sided aggressor/victim row triple in a single pass.
+    ///
+    /// The rows containing `aggressor1` and `aggressor2` are filled with `aggressor_value`,
+    /// the row containing `victim` with `victim_value`, and all other rows with `other_value`.
+    fn initialize_row_pair(
+        &self,
+        aggressor1: *const u8,
+        victim: *const u8,
+        aggressor2: *const u8,
+        aggressor_value: u8,
+        victim_value: u8,
+        other_value: u8,
+    ) where
+        Self: BytePointer,
+    {
+        let aggressors = [
+            aggressor1 as usize & !ROW_MASK,
+            aggressor2 as usize & !ROW_MASK,
+        ];
+        let victim = victim as usize & !ROW_MASK;
+        self.initialize_cb(&mut |offset: usize| {
+            let row = self.addr(offset) as usize & !ROW_MASK;
+            if aggressors.contains(&row) {
+                Some([aggressor_value; PAGE_SIZE])
+            } else if row == victim {
+                Some([victim_value; PAGE_SIZE])
+            } else {
+                Some([other_value; PAGE_SIZE])
+            }
+        });
+    }
+
+    /// Initializes a stripe pattern around the given aggressor rows.
+    ///
+    /// Aggressor rows are filled with `aggressor_value`, their neighbouring rows
+    /// (`aggressor ± ROW_SIZE`) with `victim_value`. If a row is both an aggressor and a
+    /// victim, the aggressor value takes precedence. All other rows are left untouched.
+    fn initialize_stripe(&self, aggressors: &[*const u8], aggressor_value: u8, victim_value: u8)
+    where
+        Self: BytePointer,
+    {
+        let aggressors = aggressors
+            .iter()
+            .map(|&aggr| aggr as usize & !ROW_MASK)
+            .collect::<HashSet<_>>();
+        let victims = aggressors
+            .iter()
+            .flat_map(|&aggr| [aggr.wrapping_sub(ROW_SIZE), aggr.wrapping_add(ROW_SIZE)])
+            .filter(|victim| !aggressors.contains(victim))
+            .collect::<HashSet<_>>();
+        self.initialize_cb(&mut |offset: usize| {
+            let row = self.addr(offset) as usize & !ROW_MASK;
+            if aggressors.contains(&row) {
+                Some([aggressor_value; PAGE_SIZE])
+            } else if victims.contains(&row) {
+                Some([victim_value; PAGE_SIZE])
+            } else {
+                None
+            }
+        });
+    }
 }
 
 /// Represents a bit flip detected in memory.
@@ -259,6 +705,106 @@ impl BitFlip {
             data,
         }
     }
+
+    /// Returns the key identifying this flip independent of the expected data value.
+    pub fn as_key(&self) -> BitFlipKey {
+        BitFlipKey {
+            addr: self.addr,
+            bitmask: self.bitmask,
+        }
+    }
+
+    /// Returns true if both flips occurred at the same address.
+    pub fn address_matches(&self, other: &BitFlip) -> bool {
+        self.addr == other.addr
+    }
+
+    /// Returns true if both flips affect the same bits at the same address, regardless of the
+    /// expected data value.
+    pub fn logically_equivalent(&self, other: &BitFlip) -> bool {
+        self.as_key() == other.as_key()
+    }
+}
+
+impl BitFlip {
+    /// Resolves the physical and DRAM address of this flip using the pagemap of the current
+    /// process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pagemap cannot be read.
+    pub fn resolve(
+        self,
+        mem_config: &MemConfiguration,
+    ) -> Result<ResolvedBitFlip, LinuxPageMapError> {
+        self.resolve_with(&mut LinuxPageMap::new()?, mem_config)
+    }
+
+    /// Resolves the physical and DRAM address of this flip using `resolver`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical address cannot be resolved.
+    pub fn resolve_with<R: VirtToPhysResolver>(
+        self,
+        resolver: &mut R,
+        mem_config: &MemConfiguration,
+    ) -> Result<ResolvedBitFlip, R::Error> {
+        let phys = resolver.get_phys(self.addr as u64)?;
+        Ok(ResolvedBitFlip {
+            flip: self,
+            phys,
+            dram: DRAMAddr::from_phys(phys, mem_config),
+        })
+    }
+}
+
+/// A [`BitFlip`] together with its physical and DRAM address.
+///
+/// Unlike the virtual address of a [`BitFlip`], these addresses can still be analyzed after
+/// the experiment has finished.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ResolvedBitFlip {
+    /// The observed bit flip
+    pub flip: BitFlip,
+    /// Physical address of the bit flip
+    pub phys: PhysAddr,
+    /// DRAM address of the bit flip
+    pub dram: DRAMAddr,
+}
+
+/// Identifies a [`BitFlip`] by address and bitmask only.
+///
+/// Unlike [`BitFlip`], two keys are equal even if the flips were observed with different
+/// expected data values, e.g., when the data pattern changes between rounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BitFlipKey {
+    /// Virtual address where the bit flip occurred
+    pub addr: usize,
+    /// Bitmask indicating which bits flipped (1 = bit flipped)
+    pub bitmask: u8,
+}
+
+impl BitFlipKey {
+    /// Returns true if `flip` occurred at this address with this bitmask.
+    pub fn matches(&self, flip: &BitFlip) -> bool {
+        *self == flip.as_key()
+    }
+}
+
+/// Counts observations of bit flips, grouped by [`BitFlipKey`].
+///
+/// Stores the observation count along with the first observed [`BitFlip`] for each key.
+#[derive(Clone, Debug, Default)]
+pub struct FlipMap(pub HashMap<BitFlipKey, (u64, BitFlip)>);
+
+impl FlipMap {
+    /// Records the given flips, incrementing the count of their respective keys.
+    pub fn observe_keyed(&mut self, flips: impl IntoIterator<Item = BitFlip>) {
+        for flip in flips {
+            self.0.entry(flip.as_key()).or_insert((0, flip)).0 += 1;
+        }
+    }
 }
 
 impl BitFlip {
@@ -292,6 +838,53 @@ impl BitFlip {
     }
 }
 
+/// Summary of a collection of bit flips.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BitFlipStats {
+    /// Number of bit flips
+    pub total: usize,
+    /// Number of flipped bits that changed from 0 to 1
+    pub zero_to_one: usize,
+    /// Number of flipped bits that changed from 1 to 0
+    pub one_to_zero: usize,
+    /// Number of distinct pages containing a bit flip
+    pub unique_pages: usize,
+    /// Number of bit flips per bitmask
+    pub bitmask_histogram: HashMap<u8, usize>,
+}
+
+impl BitFlipStats {
+    /// Summarizes the given bit flips.
+    ///
+    /// Flips of multiple bits count towards the direction of each flipped bit.
+    pub fn from_flips(flips: &[BitFlip]) -> Self {
+        let mut stats = BitFlipStats {
+            total: flips.len(),
+            ..Default::default()
+        };
+        let mut pages = HashSet::new();
+        for flip in flips {
+            let ones = (flip.bitmask & flip.data).count_ones() as usize;
+            stats.one_to_zero += ones;
+            stats.zero_to_one += flip.bitmask.count_ones() as usize - ones;
+            pages.insert(flip.addr & !PAGE_MASK);
+            *stats.bitmask_histogram.entry(flip.bitmask).or_insert(0) += 1;
+        }
+        stats.unique_pages = pages.len();
+        stats
+    }
+
+    /// Returns the most common bitmask, or None if there are no flips.
+    ///
+    /// Ties are broken in favor of the smaller bitmask.
+    pub fn most_common_bitmask(&self) -> Option<u8> {
+        self.bitmask_histogram
+            .iter()
+            .max_by_key(|&(&bitmask, &count)| (count, std::cmp::Reverse(bitmask)))
+            .map(|(&bitmask, _)| bitmask)
+    }
+}
+
 /// Trait for checking memory regions for bit flips.
 ///
 /// Implementors provide methods to compare memory contents against expected patterns
@@ -303,8 +896,37 @@ pub trait Checkable {
     /// Checks memory excluding specific pages.
     fn check_excluding(&self, pattern: DataPattern, pages: &[*const u8]) -> Vec<BitFlip>;
 
+    /// Checks only the given pages of memory.
+    ///
+    /// Pages outside of the memory are ignored. This is considerably faster than
+    /// [`Checkable::check`] for large allocations when the pages that may contain bit flips
+    /// are known, e.g., the pages next to the aggressor rows.
+    fn check_sparse(&self, pattern: DataPattern, pages: &[*const u8]) -> Vec<BitFlip>;
+
     /// Checks memory using a callback function to generate expected values.
     fn check_cb(&self, f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>) -> Vec<BitFlip>;
+
+    /// Checks memory against a pattern and resolves the physical and DRAM address of each
+    /// detected bit flip.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pagemap cannot be read.
+    fn check_resolved(
+        &self,
+        pattern: DataPattern,
+        mem_config: &MemConfiguration,
+    ) -> Result<Vec<ResolvedBitFlip>, LinuxPageMapError> {
+        let flips = self.check(pattern);
+        if flips.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut pagemap = LinuxPageMap::new()?;
+        flips
+            .into_iter()
+            .map(|flip| flip.resolve_with(&mut pagemap, mem_config))
+            .collect()
+    }
 }
 
 /// Blanket implementations for Initializable trait for VictimMemory
@@ -316,60 +938,123 @@ where
         self.initialize_excluding(pattern, &[]);
     }
 
-    fn initialize_excluding(&self, mut pattern: DataPattern, pages: &[*const u8]) {
+    fn initialize_excluding(&self, pattern: DataPattern, pages: &[*const u8]) {
+        info!("initialize buffer with pattern {}", pattern_name(&pattern));
+        self.initialize_cb(&mut pattern_excluding(self, pattern, pages));
+    }
+
+    fn initialize_cb(&self, f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>) {
+        write_pages(self, f, false);
+    }
+
+    #[cfg(feature = "verify-writes")]
+    fn initialize_excluding_verified(
+        &self,
+        pattern: DataPattern,
+        pages: &[*const u8],
+    ) -> Vec<*const u8> {
         info!(
-            "initialize buffer with pattern {}",
-            match &pattern {
-                DataPattern::Random(rng) => format!("random ({:?})", rng),
-                DataPattern::StripeZero { .. } => "stripe zero".into(),
-                DataPattern::Zero => "zero".into(),
-                DataPattern::StripeOne { .. } => "stripe one".into(),
-                DataPattern::One => "one".into(),
-            }
+            "initialize buffer with pattern {} (verifying writes)",
+            pattern_name(&pattern)
         );
-        self.initialize_cb(&mut |offset: usize| {
-            let addr = self.addr(offset);
-            let val = pattern.get(addr); // we must call "get" on addr, even if we don't use it, because pattern RNG is stateful
-            if pages
-                .iter()
-                .any(|&page| page as usize & !PAGE_MASK == addr as usize & !PAGE_MASK)
-            {
-                return None;
-            }
-            Some(val)
-        });
+        write_pages(self, &mut pattern_excluding(self, pattern, pages), true)
     }
+}
 
-    fn initialize_cb(&self, f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>) {
-        let len = self.len();
-        if !len.is_multiple_of(8) {
-            panic!("memory len must be divisible by 8");
+/// Returns a human-readable name of `pattern` for logging.
+fn pattern_name(pattern: &DataPattern) -> String {
+    match pattern {
+        DataPattern::Random(rng) => format!("random ({:?})", rng),
+        DataPattern::StripeZero { .. } => "stripe zero".into(),
+        DataPattern::Zero => "zero".into(),
+        DataPattern::StripeOne { .. } => "stripe one".into(),
+        DataPattern::One => "one".into(),
+        DataPattern::XOR { base, xor_period } => {
+            format!("xor (base {:#x}, period {})", base, xor_period)
         }
-        if !len.is_multiple_of(PAGE_SIZE) {
-            panic!(
-                "memory len ({}) must be divisible by PAGE_SIZE ({})",
-                len, PAGE_SIZE
-            );
+        DataPattern::RowStripe {
+            pitch,
+            aggressor_value,
+            victim_value,
+        } => format!(
+            "row stripe (pitch {}, aggressor {:#x}, victim {:#x})",
+            pitch, aggressor_value, victim_value
+        ),
+        DataPattern::Checkerboard { invert } => format!("checkerboard (invert {})", invert),
+        DataPattern::Custom(bytes) => format!("custom ({} bytes)", bytes.len()),
+    }
+}
+
+/// Returns a callback yielding the content of each page of `memory` for `pattern`, or
+/// `None` for `pages`.
+fn pattern_excluding<'a, T: BytePointer + ?Sized>(
+    memory: &'a T,
+    mut pattern: DataPattern,
+    pages: &'a [*const u8],
+) -> impl FnMut(usize) -> Option<[u8; PAGE_SIZE]> + 'a {
+    move |offset: usize| {
+        let addr = memory.addr(offset);
+        let val = pattern.get(addr); // we must call "get" on addr, even if we don't use it, because pattern RNG is stateful
+        if pages
+            .iter()
+            .any(|&page| page as usize & !PAGE_MASK == addr as usize & !PAGE_MASK)
+        {
+            return None;
         }
+        Some(val)
+    }
+}
 
-        debug!("initialize {} bytes", len);
+/// Writes the pages returned by `f` to `memory`.
+///
+/// If `verify` is set, each page is read back from DRAM right after writing it. Returns the
+/// pages that did not read back as written.
+fn write_pages<T: BytePointer + ?Sized>(
+    memory: &T,
+    f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>,
+    verify: bool,
+) -> Vec<*const u8> {
+    let len = memory.len();
+    if !len.is_multiple_of(8) {
+        panic!("memory len must be divisible by 8");
+    }
+    if !len.is_multiple_of(PAGE_SIZE) {
+        panic!(
+            "memory len ({}) must be divisible by PAGE_SIZE ({})",
+            len, PAGE_SIZE
+        );
+    }
 
-        for offset in (0..len).step_by(PAGE_SIZE) {
-            if let Some(value) = f(offset) {
-                unsafe {
-                    std::ptr::write_volatile(self.addr(offset) as *mut [u8; PAGE_SIZE], value);
+    debug!("initialize {} bytes", len);
+
+    let mut mismatches = vec![];
+    for offset in (0..len).step_by(PAGE_SIZE) {
+        if let Some(value) = f(offset) {
+            let addr = memory.addr(offset);
+            unsafe {
+                std::ptr::write_volatile(addr as *mut [u8; PAGE_SIZE], value);
+            }
+            if verify {
+                let mut matches = true;
+                Memory::new(addr, PAGE_SIZE).read_cache_lines(|cl_offset, data| {
+                    matches &= data == value[cl_offset..cl_offset + CL_SIZE];
+                });
+                if !matches {
+                    warn!("page {:p} did not read back as written", addr);
+                    mismatches.push(addr as *const u8);
                 }
             }
         }
-        debug!("memory init done");
     }
+    debug!("memory init done");
+    mismatches
 }
 
 /// Blanket implementation for PfnResolver trait for BytePointer
 impl<T: BytePointer> PfnResolver for T {
-    fn pfn(&self) -> Result<PhysAddr, LinuxPageMapError> {
+    fn pfn(&self) -> Result<Option<PhysAddr>, LinuxPageMapError> {
         let mut resolver = LinuxPageMap::new()?;
-        resolver.get_phys(self.ptr() as u64)
+        resolver.try_get_phys(self.ptr() as u64)
     }
 }
 
@@ -396,6 +1081,25 @@ where
         })
     }
 
+    fn check_sparse(&self, mut pattern: DataPattern, pages: &[*const u8]) -> Vec<BitFlip> {
+        let pages = pages
+            .iter()
+            .map(|&page| page as usize & !PAGE_MASK)
+            .collect::<HashSet<_>>();
+        // the random pattern is stateful and must be advanced for skipped pages as well
+        let stateful = matches!(pattern, DataPattern::Random(_));
+        self.check_cb(&mut |offset: usize| {
+            let addr = self.addr(offset);
+            if !pages.contains(&(addr as usize & !PAGE_MASK)) {
+                if stateful {
+                    pattern.get(addr);
+                }
+                return None;
+            }
+            Some(pattern.get(addr))
+        })
+    }
+
     fn check_cb(&self, f: &mut dyn FnMut(usize) -> Option<[u8; PAGE_SIZE]>) -> Vec<BitFlip> {
         let len = self.len();
         if !len.is_multiple_of(PAGE_SIZE) {
@@ -407,41 +1111,397 @@ where
 
         let mut ret = vec![];
         for offset in (0..len).step_by(PAGE_SIZE) {
-            if let Some(expected) = f(offset) {
-                unsafe {
-                    for byte_offset in (0..PAGE_SIZE).step_by(CL_SIZE) {
-                        _mm_clflush(self.addr(offset + byte_offset));
-                    }
-                    _mm_mfence();
-                    let cmp = memcmp(
-                        self.addr(offset) as *const c_void,
-                        expected.as_ptr() as *const c_void,
-                        PAGE_SIZE,
-                    );
-                    if cmp == 0 {
-                        continue;
+            let Some(expected) = f(offset) else {
+                debug!("skipping page {} due to exclusion", offset);
+                continue;
+            };
+            let page = Memory::new(self.addr(offset), PAGE_SIZE);
+            page.read_cache_lines(|cl_offset, data| {
+                let expected = &expected[cl_offset..cl_offset + CL_SIZE];
+                if data == expected {
+                    return;
+                }
+                debug!(
+                    "Found bitflip in page {}. Determining exact flip position",
+                    offset
+                );
+                for (i, (&actual, &expected)) in data.iter().zip(expected).enumerate() {
+                    if actual != expected {
+                        let addr = page.addr(cl_offset + i);
+                        ret.push(BitFlip::new(addr, actual ^ expected, expected));
                     }
-                    debug!(
-                        "Found bitflip in page {}. Determining exact flip position",
-                        offset
-                    );
-                    for (i, &expected) in expected.iter().enumerate() {
-                        let addr = self.addr(offset + i);
-                        _mm_clflush(addr);
-                        _mm_mfence();
-                        if *addr != expected {
-                            ret.push(BitFlip::new(addr, *addr ^ expected, expected));
-                        }
+                }
+            });
+        }
+        ret
+    }
+}
+
+/// Result of a memory health check, see [`check_byte_value`] and [`check_data_pattern`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct HammingCheckResult {
+    /// Number of checked bytes
+    pub total_bytes: usize,
+    /// Number of bytes matching the expectation
+    pub matching_bytes: usize,
+    /// Fraction of matching bytes, in `0.0..=1.0`
+    pub fraction: f64,
+    /// Whether the check passed, i.e., `fraction >= 1.0 - tolerance`
+    pub passed: bool,
+}
+
+impl HammingCheckResult {
+    fn new(total_bytes: usize, matching_bytes: usize, fraction: f64, tolerance: f64) -> Self {
+        HammingCheckResult {
+            total_bytes,
+            matching_bytes,
+            fraction,
+            passed: fraction >= 1.0 - tolerance,
+        }
+    }
+}
+
+/// Returns the fraction of set bits in `value`.
+pub fn expected_hamming_weight_fraction(value: u8) -> f64 {
+    value.count_ones() as f64 / 8.0
+}
+
+/// Calls `f` with the contents of each page of `region`.
+fn for_each_page(region: &impl BytePointer, mut f: impl FnMut(&[u8])) {
+    for offset in (0..region.len()).step_by(PAGE_SIZE) {
+        let len = PAGE_SIZE.min(region.len() - offset);
+        f(unsafe { std::slice::from_raw_parts(region.addr(offset), len) });
+    }
+}
+
+/// Checks that the bytes of `region` equal `expected_value`.
+///
+/// Freshly allocated memory has a known value, so a significant number of mismatching
+/// bytes indicates prior bit flips or memory that is used elsewhere. The check passes if
+/// at most a fraction of `tolerance` bytes differs.
+pub fn check_byte_value(
+    region: &impl BytePointer,
+    expected_value: u8,
+    tolerance: f64,
+) -> HammingCheckResult {
+    let mut matching_bytes = 0;
+    for_each_page(region, |page| {
+        matching_bytes += page.iter().filter(|&&b| b == expected_value).count();
+    });
+    let total_bytes = region.len();
+    let fraction = if total_bytes == 0 {
+        1.0
+    } else {
+        matching_bytes as f64 / total_bytes as f64
+    };
+    HammingCheckResult::new(total_bytes, matching_bytes, fraction, tolerance)
+}
+
+/// Checks that the bytes of `region` equal `pattern`, except for the `excluding` pages.
+///
+/// Like [`check_byte_value`], but for memory initialized with an arbitrary data pattern.
+/// Bytes of excluded pages count as matching. The check passes if at most a fraction of
+/// `tolerance` bytes differs.
+pub fn check_data_pattern(
+    region: &impl VictimMemory,
+    pattern: DataPattern,
+    excluding: &[*const u8],
+    tolerance: f64,
+) -> HammingCheckResult {
+    let total_bytes = region.len();
+    let mismatching_bytes = region.check_excluding(pattern, excluding).len();
+    let matching_bytes = total_bytes - mismatching_bytes;
+    let fraction = if total_bytes == 0 {
+        1.0
+    } else {
+        matching_bytes as f64 / total_bytes as f64
+    };
+    HammingCheckResult::new(total_bytes, matching_bytes, fraction, tolerance)
+}
+
+/// Checks that the bits of `region` are balanced, as expected for random data.
+///
+/// `fraction` is `1.0` if exactly half of the bits are set and decreases linearly to `0.0`
+/// for all bits set or cleared. `matching_bytes` is `fraction` scaled to the number of bytes.
+pub fn random_hamming_weight_check(
+    region: &impl BytePointer,
+    tolerance: f64,
+) -> HammingCheckResult {
+    let mut ones = 0;
+    for_each_page(region, |page| {
+        ones += page.iter().map(|b| b.count_ones() as usize).sum::<usize>();
+    });
+    let total_bytes = region.len();
+    let fraction = if total_bytes == 0 {
+        1.0
+    } else {
+        let weight = ones as f64 / (8 * total_bytes) as f64;
+        1.0 - 2.0 * (weight - 0.5).abs()
+    };
+    let matching_bytes = (fraction * total_bytes as f64) as usize;
+    HammingCheckResult::new(total_bytes, matching_bytes, fraction, tolerance)
+}
+
+/// Checks selected DRAM rows of a memory region for bit flips.
+///
+/// In contrast to [`Checkable::check`], only the given rows are read back, which is faster
+/// for targeted verification of few victim rows.
+pub struct RowChecker<'a> {
+    memory: &'a ConsecBlocks,
+    pattern: DataPattern,
+    rows: Vec<*const u8>,
+}
+
+impl<'a> RowChecker<'a> {
+    /// Creates a new row checker.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory` - The memory region containing the rows
+    /// * `pattern` - The pattern `memory` is initialized with
+    /// * `rows` - The rows checked by [`VictimOrchestrator::check`]
+    pub fn new(memory: &'a ConsecBlocks, pattern: DataPattern, rows: Vec<*const u8>) -> Self {
+        RowChecker {
+            memory,
+            pattern,
+            rows,
+        }
+    }
+
+    /// Checks the row containing `row_ptr` for bit flips.
+    pub fn check_row(&self, row_ptr: *const u8) -> Vec<BitFlip> {
+        self.check_rows(&[row_ptr])
+    }
+
+    /// Checks the rows containing the given addresses for bit flips.
+    ///
+    /// Rows outside of the memory region are ignored.
+    pub fn check_rows(&self, rows: &[*const u8]) -> Vec<BitFlip> {
+        let rows = rows
+            .iter()
+            .filter_map(|&row| self.memory.offset_of(row))
+            .map(|offset| offset & !ROW_MASK)
+            .collect::<HashSet<_>>();
+        match self.pattern {
+            DataPattern::Random(_) => {
+                // the random pattern is stateful, so all preceding pages must be generated
+                let mut pattern = self.pattern.clone();
+                let mut flips = vec![];
+                for offset in (0..self.memory.len()).step_by(PAGE_SIZE) {
+                    let expected = pattern.get(self.memory.addr(offset));
+                    if rows.contains(&(offset & !ROW_MASK)) {
+                        flips.extend(self.check_page(offset, &expected));
                     }
                 }
-            } else {
-                debug!("skipping page {} due to exclusion", offset);
+                flips
+            }
+            _ => {
+                let mut rows = rows.into_iter().collect::<Vec<_>>();
+                rows.sort_unstable();
+                let mut pattern = self.pattern.clone();
+                rows.into_iter()
+                    .flat_map(|row| (row..row + ROW_SIZE).step_by(PAGE_SIZE))
+                    .filter(|&offset| offset < self.memory.len())
+                    .flat_map(|offset| {
+                        let expected = pattern.get(self.memory.addr(offset));
+                        self.check_page(offset, &expected)
+                    })
+                    .collect()
             }
         }
-        ret
+    }
+
+    /// Checks all rows within `distance` rows of the row containing `victim`.
+    ///
+    /// Distances are measured in rows of the memory region, not in physical rows.
+    pub fn check_victim_neighborhood(&self, victim: *const u8, distance: usize) -> Vec<BitFlip> {
+        let Some(offset) = self.memory.offset_of(victim) else {
+            return vec![];
+        };
+        let row = offset / ROW_SIZE;
+        let rows = (row.saturating_sub(distance)..=row + distance)
+            .filter(|&row| row < self.memory.row_count())
+            .map(|row| self.memory.addr(row * ROW_SIZE) as *const u8)
+            .collect::<Vec<_>>();
+        self.check_rows(&rows)
+    }
+
+    /// Flushes the page at `offset` from the cache and compares it byte-wise to `expected`.
+    fn check_page(&self, offset: usize, expected: &[u8; PAGE_SIZE]) -> Vec<BitFlip> {
+        let page = self.memory.addr(offset);
+        unsafe {
+            for cl in (0..PAGE_SIZE).step_by(CL_SIZE) {
+                _mm_clflush(page.byte_add(cl));
+            }
+            _mm_mfence();
+        }
+        let data = unsafe { std::ptr::read_volatile(page as *const [u8; PAGE_SIZE]) };
+        data.iter()
+            .zip(expected)
+            .enumerate()
+            .filter(|(_, (actual, expected))| actual != expected)
+            .map(|(i, (&actual, &expected))| {
+                BitFlip::new(unsafe { page.byte_add(i) }, actual ^ expected, expected)
+            })
+            .collect()
+    }
+}
+
+impl VictimOrchestrator for RowChecker<'_> {
+    fn start(&mut self) -> Result<(), HammerVictimError> {
+        Ok(())
+    }
+
+    fn init(&mut self) {
+        self.memory.initialize(self.pattern.clone());
+    }
+
+    fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
+        let flips = self.check_rows(&self.rows);
+        if flips.is_empty() {
+            Err(HammerVictimError::NoFlips)
+        } else {
+            Ok(VictimResult::BitFlips(flips))
+        }
+    }
+
+    fn stop(&mut self) {}
+}
+
+/// A memory region composed of arbitrary, possibly non-adjacent `(ptr, len)` pairs.
+///
+/// Offsets are counted across all pairs in order, similar to [`ConsecBlocks`]. Unlike
+/// [`ConsecBlocks`], the pairs are not required to be physically contiguous, which makes
+/// `SparseMemory` suitable for page sets that are scattered in physical memory.
+#[derive(Clone, Debug, Default)]
+pub struct SparseMemory {
+    /// The `(ptr, len)` pairs making up this region
+    pub regions: Vec<(*mut u8, usize)>,
+}
+
+unsafe impl Send for SparseMemory {}
+
+impl SparseMemory {
+    /// Creates a new sparse region from `(ptr, len)` pairs.
+    pub fn new(regions: Vec<(*mut u8, usize)>) -> Self {
+        SparseMemory { regions }
+    }
+
+    /// Creates a new sparse region treating each pointer in `pages` as one page.
+    pub fn from_pages(pages: Vec<*mut u8>) -> Self {
+        SparseMemory {
+            regions: pages.into_iter().map(|page| (page, PAGE_SIZE)).collect(),
+        }
+    }
+
+    /// Returns the physical address of each page in this region.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical addresses cannot be resolved, e.g., when not running
+    /// as root.
+    pub fn physical_pages(&self) -> Result<Vec<PhysAddr>, LinuxPageMapError> {
+        let virts = (0..self.len())
+            .step_by(PAGE_SIZE)
+            .map(|offset| self.addr(offset) as u64)
+            .collect::<Vec<_>>();
+        LinuxPageMap::new()?.batch_get_phys(&virts)
     }
 }
 
+impl BytePointer for SparseMemory {
+    fn addr(&self, offset: usize) -> *mut u8 {
+        assert!(offset < self.len(), "Offset {} >= {}", offset, self.len());
+        let mut offset = offset;
+        for &(ptr, len) in &self.regions {
+            if offset < len {
+                return unsafe { ptr.byte_add(offset) };
+            }
+            offset -= len;
+        }
+        unreachable!("region not found for offset 0x{:x}", offset);
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        self.regions.first().unwrap().0
+    }
+
+    fn len(&self) -> usize {
+        self.regions.iter().map(|(_, len)| len).sum()
+    }
+}
+
+impl GetConsecPfns for SparseMemory {
+    fn consec_pfns(&self) -> Result<ConsecPfns, ConsecPfnsError> {
+        memblock::consec_ranges(self.physical_pages()?)
+    }
+}
+
+impl VictimMemory for SparseMemory {}
+
+impl Memory {
+    /// Splits this block into its individual pages.
+    ///
+    /// The pages are not copied. Clone the block beforehand to deallocate it later; the
+    /// returned region must not be used after that.
+    pub fn into_pages(self) -> SparseMemory {
+        SparseMemory::from_pages(
+            (0..self.len)
+                .step_by(PAGE_SIZE)
+                .map(|offset| self.addr(offset))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn test_memory_region() -> anyhow::Result<()> {
+    let block = Memory::mmap(2 * ROW_SIZE)?;
+    assert_eq!(block.page_count(), 2 * ROW_SIZE / PAGE_SIZE);
+    assert_eq!(block.row_count(), 2);
+    assert_eq!(
+        MemoryRegion::cache_line_count(&block),
+        2 * ROW_SIZE / CL_SIZE
+    );
+    let rows = block.iter_rows().collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            (RowOffset(0), block.ptr()),
+            (RowOffset(1), block.addr(ROW_SIZE))
+        ]
+    );
+    let pages = block.iter_pages().collect::<Vec<_>>();
+    assert_eq!(pages.len(), block.page_count());
+    assert!(pages.iter().all(|&p| p as usize & PAGE_MASK == 0));
+    assert_eq!(block.offset_of(block.addr(42)), Some(42));
+    assert_eq!(block.offset_of(block.ptr().wrapping_add(block.len)), None);
+    block.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_memory_region_consec_blocks() -> anyhow::Result<()> {
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(ROW_SIZE)?, Memory::mmap(ROW_SIZE)?]);
+    assert_eq!(blocks.page_count(), 2 * ROW_SIZE / PAGE_SIZE);
+    assert_eq!(blocks.row_count(), 2);
+    assert_eq!(blocks.cache_line_count(), 2 * ROW_SIZE / CL_SIZE);
+    let rows = blocks.iter_rows().collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            (RowOffset(0), blocks.blocks[0].ptr()),
+            (RowOffset(1), blocks.blocks[1].ptr())
+        ]
+    );
+    assert_eq!(blocks.iter_pages().count(), blocks.page_count());
+    let second = blocks.blocks[1].addr(42);
+    assert_eq!(blocks.offset_of(second), Some(ROW_SIZE + 42));
+    assert_eq!(blocks.offset_of(std::ptr::null()), None);
+    blocks.dealloc();
+    Ok(())
+}
+
 #[test]
 fn test_pattern_random_clone() {
     let pattern = DataPattern::Random(Box::new(Rng::from_seed(rand::random())));
@@ -450,6 +1510,204 @@ fn test_pattern_random_clone() {
     assert_eq!(a, b);
 }
 
+#[test]
+fn test_pattern_eq_hash() {
+    let mut random = DataPattern::Random(Box::new(Rng::from_seed(42)));
+    let fresh = random.clone();
+    random.get(std::ptr::null());
+    assert_eq!(random, fresh);
+    assert_ne!(random, DataPattern::Random(Box::new(Rng::from_seed(43))));
+    let rows = [ROW_SIZE as AggressorPtr, (3 * ROW_SIZE) as AggressorPtr];
+    let stripe = DataPattern::StripeZero {
+        zeroes: rows.to_vec(),
+    };
+    let reversed = DataPattern::StripeZero {
+        zeroes: vec![rows[1], rows[0], rows[1]],
+    };
+    assert_eq!(stripe, reversed);
+    assert_ne!(stripe, stripe.complement());
+    assert_ne!(DataPattern::Zero, DataPattern::One);
+
+    let mut counts = HashMap::new();
+    for pattern in [random, fresh, stripe, reversed, DataPattern::One] {
+        *counts.entry(pattern).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 3);
+    assert_eq!(
+        counts[&DataPattern::Random(Box::new(Rng::from_seed(42)))],
+        2
+    );
+    assert_eq!(
+        counts[&DataPattern::StripeZero {
+            zeroes: rows.to_vec()
+        }],
+        2
+    );
+}
+
+#[test]
+fn test_pattern_xor() -> anyhow::Result<()> {
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    let pattern = DataPattern::XOR {
+        base: 0x55,
+        xor_period: PAGE_SIZE,
+    };
+    blocks.initialize(pattern.clone());
+    assert_eq!(blocks.check(pattern.clone()), vec![]);
+    for page in 0..4 {
+        let addr = blocks.addr(page * PAGE_SIZE);
+        let expected = if (addr as usize / PAGE_SIZE).is_multiple_of(2) {
+            0x55
+        } else {
+            0xAA
+        };
+        assert_eq!(unsafe { *addr }, expected);
+    }
+    assert_eq!(blocks.check(pattern.complement()).len(), 4 * PAGE_SIZE);
+    blocks.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_pattern_complement() {
+    let xor = DataPattern::XOR {
+        base: 0x0F,
+        xor_period: ROW_SIZE,
+    };
+    assert_eq!(
+        xor.complement(),
+        DataPattern::XOR {
+            base: 0xF0,
+            xor_period: ROW_SIZE
+        }
+    );
+    assert_eq!(xor.complement().complement(), xor);
+    assert_eq!(DataPattern::Zero.complement(), DataPattern::One);
+    assert_eq!(DataPattern::One.complement(), DataPattern::Zero);
+    let rows = vec![ROW_SIZE as AggressorPtr];
+    let mut stripe = DataPattern::StripeZero {
+        zeroes: rows.clone(),
+    }
+    .complement();
+    assert_eq!(stripe, DataPattern::StripeOne { ones: rows });
+    assert_eq!(stripe.get(ROW_SIZE as *const u8), [0xFF; PAGE_SIZE]);
+    assert_eq!(stripe.get(std::ptr::null()), [0x00; PAGE_SIZE]);
+    let checkerboard = DataPattern::Checkerboard { invert: false };
+    assert_eq!(
+        checkerboard.complement(),
+        DataPattern::Checkerboard { invert: true }
+    );
+}
+
+#[test]
+fn test_pattern_row_stripe() -> anyhow::Result<()> {
+    let mut pattern = DataPattern::RowStripe {
+        pitch: 3,
+        aggressor_value: 0x00,
+        victim_value: 0xFF,
+    };
+    assert_eq!(pattern.get(std::ptr::null()), [0x00; PAGE_SIZE]);
+    assert_eq!(pattern.get((3 * ROW_SIZE) as *const u8), [0x00; PAGE_SIZE]);
+    assert_eq!(pattern.get(ROW_SIZE as *const u8), [0xFF; PAGE_SIZE]);
+    assert_eq!(
+        pattern.get((3 * ROW_SIZE + PAGE_SIZE) as *const u8),
+        [0x00; PAGE_SIZE]
+    );
+    assert_eq!(
+        pattern.complement().get((2 * ROW_SIZE) as *const u8),
+        [0x00; PAGE_SIZE]
+    );
+
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    blocks.initialize(pattern.clone());
+    assert_eq!(blocks.check(pattern.clone()), vec![]);
+    blocks.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_pattern_custom_file() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("swage_pattern_{}.bin", std::process::id()));
+    let path = path.to_str().expect("utf-8 path");
+    std::fs::write(path, [0x12, 0x34, 0x56])?;
+    let mut pattern = DataPattern::from_file(path)?;
+    assert_eq!(
+        pattern,
+        DataPattern::Custom(Arc::new(vec![0x12, 0x34, 0x56]))
+    );
+    let page = pattern.get(std::ptr::null());
+    assert_eq!(&page[..4], &[0x12, 0x34, 0x56, 0x12]);
+    assert_eq!(page[PAGE_SIZE - 1], [0x12, 0x34, 0x56][(PAGE_SIZE - 1) % 3]);
+    assert_eq!(pattern.get(PAGE_SIZE as *const u8), page);
+    assert_eq!(pattern.complement().get(std::ptr::null())[0], 0xED);
+
+    // saving and loading a random pattern reproduces its first page
+    let mut random = DataPattern::Random(Box::new(Rng::from_seed(42)));
+    random.save_to_file(path)?;
+    let mut loaded = DataPattern::from_file(path)?;
+    assert_eq!(loaded.get(std::ptr::null()), random.get(std::ptr::null()));
+
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(2 * PAGE_SIZE)?]);
+    blocks.initialize(loaded.clone());
+    assert_eq!(blocks.check(loaded.clone()), vec![]);
+    assert!(!blocks.check(DataPattern::Zero).is_empty());
+    blocks.dealloc();
+
+    std::fs::write(path, [])?;
+    assert_eq!(
+        DataPattern::from_file(path).map_err(|e| e.kind()),
+        Err(std::io::ErrorKind::InvalidData)
+    );
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_pattern_checkerboard() -> anyhow::Result<()> {
+    let mut pattern = DataPattern::Checkerboard { invert: false };
+    let page = pattern.get(std::ptr::null());
+    assert_eq!(page[0], 0x55);
+    assert_eq!(page[CL_SIZE - 1], 0x55);
+    assert_eq!(page[CL_SIZE], 0xAA);
+    assert_eq!(page[PAGE_SIZE - 1], 0xAA);
+    let inverted = pattern.complement().get(std::ptr::null());
+    assert_eq!(inverted[0], 0xAA);
+    assert_eq!(inverted[CL_SIZE], 0x55);
+
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(2 * PAGE_SIZE)?]);
+    blocks.initialize(pattern.clone());
+    assert_eq!(blocks.check(pattern.clone()), vec![]);
+    assert_eq!(unsafe { *blocks.addr(PAGE_SIZE + CL_SIZE) }, 0xAA);
+    assert_eq!(blocks.check(pattern.complement()).len(), 2 * PAGE_SIZE);
+    blocks.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_bitflip_key() {
+    let flip = BitFlip::new(0x1000 as *const u8, 0x01, 0xFF);
+    let other_data = BitFlip::new(0x1000 as *const u8, 0x01, 0x00);
+    let other_mask = BitFlip::new(0x1000 as *const u8, 0x02, 0xFF);
+    assert_ne!(flip, other_data);
+    assert_eq!(flip.as_key(), other_data.as_key());
+    assert!(flip.as_key().matches(&other_data));
+    assert!(!flip.as_key().matches(&other_mask));
+    assert!(flip.logically_equivalent(&other_data));
+    assert!(!flip.logically_equivalent(&other_mask));
+    assert!(flip.address_matches(&other_mask));
+    assert!(!flip.address_matches(&BitFlip::new(0x1001 as *const u8, 0x01, 0xFF)));
+}
+
+#[test]
+fn test_flip_map_observe_keyed() {
+    let flip = BitFlip::new(0x1000 as *const u8, 0x01, 0xFF);
+    let mut map = FlipMap::default();
+    map.observe_keyed([flip, BitFlip::new(0x1000 as *const u8, 0x01, 0x00)]);
+    map.observe_keyed([BitFlip::new(0x2000 as *const u8, 0x01, 0xFF)]);
+    assert_eq!(map.0.len(), 2);
+    assert_eq!(map.0[&flip.as_key()], (2, flip));
+}
+
 #[test]
 fn test_bitflip_direction() {
     let flip = BitFlip::new(std::ptr::null(), 0b0000_0000, 0xFF);
@@ -478,3 +1736,396 @@ fn test_bitflip_direction() {
         FlipDirection::Multiple(vec![FlipDirection::OneToZero, FlipDirection::OneToZero])
     );
 }
+
+/// Maps virtual to physical addresses by adding a fixed offset.
+#[cfg(test)]
+struct TestPageMap(usize);
+
+#[cfg(test)]
+impl VirtToPhysResolver for TestPageMap {
+    type Error = std::convert::Infallible;
+
+    fn get_phys(&mut self, virt: u64) -> Result<PhysAddr, Self::Error> {
+        Ok(PhysAddr::new(virt as usize + self.0))
+    }
+
+    fn get_phys_range(
+        &mut self,
+        region: pagemap2::VirtualMemoryArea,
+    ) -> Result<Vec<PhysAddr>, Self::Error> {
+        (region.start_address()..region.last_address())
+            .step_by(PAGE_SIZE)
+            .map(|virt| self.get_phys(virt))
+            .collect()
+    }
+}
+
+/// One bank bit at row bit 0, twelve row bits above it
+#[cfg(test)]
+fn test_mem_config() -> MemConfiguration {
+    use crate::util::ROW_SHIFT;
+    let mut dram_mtx = [0; MTX_SIZE];
+    dram_mtx[0] = 1 << ROW_SHIFT;
+    for i in 1..=12 {
+        dram_mtx[MTX_SIZE - i] = 1 << (ROW_SHIFT + i);
+    }
+    MemConfiguration {
+        bk_shift: MTX_SIZE - 1,
+        bk_mask: 0b1,
+        row_mask: 0xFFF,
+        dram_mtx,
+        max_bank_bit: ROW_SHIFT as u64,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_bitflip_resolve_with() {
+    let mem_config = test_mem_config();
+    let mut pagemap = TestPageMap(0x4000_0000);
+    let flip = BitFlip::new((3 * ROW_SIZE + 42) as *const u8, 0x01, 0xFF);
+    let resolved = flip
+        .resolve_with(&mut pagemap, &mem_config)
+        .expect("infallible");
+    assert_eq!(resolved.flip, flip);
+    assert_eq!(
+        resolved.phys,
+        PhysAddr::new(0x4000_0000 + 3 * ROW_SIZE + 42)
+    );
+    assert_eq!(resolved.dram, DRAMAddr::new(1, 1, 0));
+    let json = serde_json::to_value(&resolved).unwrap();
+    assert_eq!(json["dram"]["row"], 1);
+    assert_eq!(json["flip"]["bitmask"], 1);
+    assert_eq!(
+        json["phys"],
+        format!("0x{:016x}", 0x4000_0000 + 3 * ROW_SIZE + 42)
+    );
+}
+
+#[test]
+fn test_check_resolved() -> anyhow::Result<()> {
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(2 * PAGE_SIZE)?]);
+    blocks.initialize(DataPattern::Zero);
+    let mem_config = test_mem_config();
+    assert_eq!(
+        blocks.check_resolved(DataPattern::Zero, &mem_config)?,
+        vec![]
+    );
+    unsafe { *blocks.addr(PAGE_SIZE + 1) = 0x10 };
+    let resolved = blocks.check_resolved(DataPattern::Zero, &mem_config)?;
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(
+        resolved[0].flip,
+        BitFlip::new(blocks.addr(PAGE_SIZE + 1), 0x10, 0x00)
+    );
+    assert_eq!(
+        resolved[0].dram,
+        DRAMAddr::from_phys(resolved[0].phys, &mem_config)
+    );
+    blocks.dealloc();
+    Ok(())
+}
+
+#[cfg(feature = "verify-writes")]
+#[test]
+fn test_initialize_excluding_verified() -> anyhow::Result<()> {
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    let pattern = DataPattern::Random(Box::new(Rng::from_seed(42)));
+    let mismatches = blocks.initialize_excluding_verified(pattern.clone(), &[blocks.addr(0)]);
+    assert_eq!(mismatches, vec![]);
+    assert_eq!(blocks.check_excluding(pattern, &[blocks.addr(0)]), vec![]);
+    blocks.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_check_sparse() -> anyhow::Result<()> {
+    let blocks = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    let pattern = DataPattern::Random(Box::new(Rng::from_seed(42)));
+    blocks.initialize(pattern.clone());
+    unsafe {
+        *blocks.addr(1) ^= 0x01;
+        *blocks.addr(2 * PAGE_SIZE + 3) ^= 0x02;
+    }
+    let flips = blocks.check_sparse(pattern.clone(), &[blocks.addr(2 * PAGE_SIZE + 100)]);
+    assert_eq!(flips.len(), 1);
+    assert_eq!(flips[0].addr, blocks.addr(2 * PAGE_SIZE + 3) as usize);
+    assert_eq!(flips[0].bitmask, 0x02);
+    assert_eq!(blocks.check_sparse(pattern.clone(), &[]), vec![]);
+    assert_eq!(blocks.check(pattern).len(), 2);
+    blocks.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_bitflip_stats() {
+    // the flips from test_bitflip_direction, spread over two pages
+    let flips = [
+        BitFlip::new(std::ptr::null(), 0b0000_0000, 0xFF),
+        BitFlip::new(std::ptr::null(), 0b0000_0001, 0b0000_0001),
+        BitFlip::new(std::ptr::null(), 0b0000_0001, 0b1111_1110),
+        BitFlip::new(PAGE_SIZE as *const u8, 0b0000_0011, 0b0000_0010),
+        BitFlip::new(PAGE_SIZE as *const u8, 0b0000_0011, 0b0000_0000),
+        BitFlip::new((PAGE_SIZE + 1) as *const u8, 0b0000_0011, 0b0000_0011),
+    ];
+    let stats = BitFlipStats::from_flips(&flips);
+    assert_eq!(stats.total, 6);
+    assert_eq!(stats.zero_to_one, 4);
+    assert_eq!(stats.one_to_zero, 4);
+    assert_eq!(stats.unique_pages, 2);
+    assert_eq!(
+        stats.bitmask_histogram,
+        HashMap::from([(0b00, 1), (0b01, 2), (0b11, 3)])
+    );
+    assert_eq!(stats.most_common_bitmask(), Some(0b11));
+    assert_eq!(BitFlipStats::from_flips(&[]), BitFlipStats::default());
+    assert_eq!(BitFlipStats::default().most_common_bitmask(), None);
+}
+
+#[cfg(test)]
+fn check_row(row: *mut u8, value: u8) -> Vec<BitFlip> {
+    let row = ConsecBlocks::new(vec![Memory::new(row, ROW_SIZE)]);
+    row.check_cb(&mut |_| Some([value; PAGE_SIZE]))
+}
+
+/// mmaps `rows + 1` rows and returns the allocation along with a row-aligned view of `rows` rows
+#[cfg(test)]
+fn mmap_row_aligned(rows: usize) -> std::io::Result<(Memory, ConsecBlocks)> {
+    let mem = Memory::mmap((rows + 1) * ROW_SIZE)?;
+    let base = mem.addr(ROW_SIZE - (mem.ptr() as usize & ROW_MASK));
+    Ok((
+        mem,
+        ConsecBlocks::new(vec![Memory::new(base, rows * ROW_SIZE)]),
+    ))
+}
+
+#[test]
+fn test_initialize_row_pair() -> anyhow::Result<()> {
+    let (mem, blocks) = mmap_row_aligned(6)?;
+    let rows = blocks.iter_rows().map(|(_, row)| row).collect::<Vec<_>>();
+    blocks.initialize_row_pair(rows[1], rows[2], rows[3], 0x00, 0xFF, 0xAA);
+    for (i, &row) in rows.iter().enumerate() {
+        let expected = match i {
+            1 | 3 => 0x00,
+            2 => 0xFF,
+            _ => 0xAA,
+        };
+        assert_eq!(check_row(row, expected), vec![], "row {}", i);
+    }
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_initialize_stripe() -> anyhow::Result<()> {
+    let (mem, blocks) = mmap_row_aligned(6)?;
+    let rows = blocks.iter_rows().map(|(_, row)| row).collect::<Vec<_>>();
+    blocks.initialize(DataPattern::Zero);
+    blocks.initialize_stripe(&[rows[2], rows[3].wrapping_add(42)], 0x11, 0x22);
+    for (i, &row) in rows.iter().enumerate() {
+        let expected = match i {
+            2 | 3 => 0x11,
+            1 | 4 => 0x22,
+            _ => 0x00,
+        };
+        assert_eq!(check_row(row, expected), vec![], "row {}", i);
+    }
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_check_byte_value() -> anyhow::Result<()> {
+    let mem = Memory::mmap(4 * PAGE_SIZE)?;
+    let result = check_byte_value(&mem, 0x00, 0.0);
+    assert_eq!(result.matching_bytes, mem.len);
+    assert!(result.passed);
+    // corrupt 1% of the bytes
+    let corrupted = mem.len / 100;
+    for i in 0..corrupted {
+        unsafe { std::ptr::write_volatile(mem.addr(i * 97), 0x01) };
+    }
+    let result = check_byte_value(&mem, 0x00, 0.005);
+    assert_eq!(result.total_bytes, mem.len);
+    assert_eq!(result.matching_bytes, mem.len - corrupted);
+    assert!(!result.passed);
+    assert!(check_byte_value(&mem, 0x00, 0.02).passed);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_check_data_pattern() -> anyhow::Result<()> {
+    let mem = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    let pattern = DataPattern::Random(Box::new(Rng::from_seed(rand::random())));
+    mem.initialize(pattern.clone());
+    let result = check_data_pattern(&mem, pattern.clone(), &[], 0.0);
+    assert_eq!(result.matching_bytes, mem.len());
+    assert!(result.passed);
+    // zeroed memory does not match a random pattern
+    assert!(!check_data_pattern(&mem, DataPattern::Zero, &[], 0.5).passed);
+    // corrupt one page, which is only counted if it is not excluded
+    let page = mem.addr(PAGE_SIZE) as *const u8;
+    unsafe { std::ptr::write_bytes(mem.addr(PAGE_SIZE), 0x00, PAGE_SIZE) };
+    let result = check_data_pattern(&mem, pattern.clone(), &[], 0.1);
+    assert!(!result.passed, "{:?}", result);
+    assert!(check_data_pattern(&mem, pattern, &[page], 0.0).passed);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_random_hamming_weight_check() -> anyhow::Result<()> {
+    let mem = ConsecBlocks::new(vec![Memory::mmap(4 * PAGE_SIZE)?]);
+    mem.initialize(DataPattern::Random(Box::new(
+        Rng::from_seed(rand::random()),
+    )));
+    assert!(random_hamming_weight_check(&mem, 0.05).passed);
+    assert_eq!(expected_hamming_weight_fraction(0xFF), 1.0);
+    assert_eq!(expected_hamming_weight_fraction(0x0F), 0.5);
+    // corrupt one page to all ones
+    unsafe { std::ptr::write_bytes(mem.addr(0), 0xFF, PAGE_SIZE) };
+    let result = random_hamming_weight_check(&mem, 0.05);
+    assert!(!result.passed, "{:?}", result);
+    assert!((result.fraction - 0.75).abs() < 0.01);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_row_checker() -> anyhow::Result<()> {
+    let (mem, blocks) = mmap_row_aligned(8)?;
+    let rows = blocks.iter_rows().map(|(_, row)| row).collect::<Vec<_>>();
+    let pattern = DataPattern::Random(Box::new(Rng::from_seed(rand::random())));
+    let mut checker = RowChecker::new(&blocks, pattern, vec![rows[2], rows[5]]);
+    checker.init();
+    assert!(matches!(checker.check(), Err(HammerVictimError::NoFlips)));
+    // corrupt a byte in rows 1 and 5
+    for (row, offset) in [(1, 42), (5, ROW_SIZE - 1)] {
+        unsafe { *rows[row].byte_add(offset) ^= 0x04 };
+    }
+    let flips = checker.check_row(rows[5]);
+    assert_eq!(flips.len(), 1);
+    assert_eq!(flips[0].addr, rows[5] as usize + ROW_SIZE - 1);
+    assert_eq!(flips[0].bitmask, 0x04);
+    assert_eq!(checker.check_rows(&[rows[0], rows[2]]), vec![]);
+    match checker.check() {
+        Ok(VictimResult::BitFlips(flips)) => assert_eq!(flips.len(), 1),
+        _ => panic!("expected bit flips"),
+    }
+    let flips = checker.check_victim_neighborhood(rows[3], 2);
+    assert_eq!(flips.len(), 2);
+    assert_eq!(checker.check_victim_neighborhood(rows[3], 1), vec![]);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_row_checker_stripe() -> anyhow::Result<()> {
+    let (mem, blocks) = mmap_row_aligned(4)?;
+    let rows = blocks.iter_rows().map(|(_, row)| row).collect::<Vec<_>>();
+    let pattern = DataPattern::StripeOne {
+        ones: vec![rows[1]],
+    };
+    let checker = RowChecker::new(&blocks, pattern, vec![]);
+    checker.memory.initialize(checker.pattern.clone());
+    unsafe { *rows[1].byte_add(7) = 0xFE };
+    let flips = checker.check_victim_neighborhood(rows[0], 1);
+    assert_eq!(
+        flips,
+        vec![BitFlip::new(rows[1].wrapping_byte_add(7), 0x01, 0xFF)]
+    );
+    assert_eq!(checker.check_row(std::ptr::null()), vec![]);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_sparse_memory_addr() {
+    let a = 0x1000_0000 as *mut u8;
+    let b = 0x3000_0000 as *mut u8;
+    let sparse = SparseMemory::new(vec![(a, 2 * PAGE_SIZE), (b, PAGE_SIZE)]);
+    assert_eq!(sparse.len(), 3 * PAGE_SIZE);
+    assert_eq!(sparse.ptr(), a);
+    assert_eq!(
+        sparse.addr(PAGE_SIZE + 8),
+        a.wrapping_byte_add(PAGE_SIZE + 8)
+    );
+    assert_eq!(sparse.addr(2 * PAGE_SIZE), b);
+    assert_eq!(
+        sparse.addr(3 * PAGE_SIZE - 1),
+        b.wrapping_byte_add(PAGE_SIZE - 1)
+    );
+}
+
+#[test]
+fn test_sparse_memory_from_pages() -> anyhow::Result<()> {
+    let mem = Memory::mmap(4 * PAGE_SIZE)?;
+    let pages = mem.clone().into_pages();
+    assert_eq!(pages.regions.len(), 4);
+    assert_eq!(pages.len(), mem.len());
+    // reversed page order: offsets cross into non-adjacent pages
+    let reversed = SparseMemory::from_pages(pages.regions.iter().rev().map(|(p, _)| *p).collect());
+    assert_eq!(reversed.addr(0), mem.addr(3 * PAGE_SIZE));
+    assert_eq!(reversed.addr(PAGE_SIZE + 1), mem.addr(2 * PAGE_SIZE + 1));
+    assert_eq!(reversed.addr(4 * PAGE_SIZE - 1), mem.addr(PAGE_SIZE - 1));
+    reversed.initialize(DataPattern::Zero);
+    assert_eq!(reversed.check(DataPattern::Zero), vec![]);
+    assert_eq!(reversed.physical_pages()?.len(), 4);
+    mem.dealloc();
+    Ok(())
+}
+
+#[test]
+fn test_memory_error_source() {
+    use std::error::Error as _;
+    let io = || std::io::Error::from_raw_os_error(libc::EPERM);
+    for e in [
+        MemoryError::MmapFailed(io()),
+        MemoryError::MlockFailed(io()),
+        MemoryError::MadviseFailed(io()),
+    ] {
+        let source = e.source().expect("no source");
+        assert_eq!(source.to_string(), io().to_string());
+        assert!(e.to_string().ends_with(&source.to_string()));
+        assert_eq!(std::io::Error::from(e).raw_os_error(), Some(libc::EPERM));
+    }
+    for e in [
+        MemoryError::AllocFailed,
+        MemoryError::ZeroSizeLayout,
+        MemoryError::SizeTooLarge {
+            requested: 0x2000,
+            max: 0x1000,
+        },
+        MemoryError::NotPageAligned {
+            addr: 0x1001,
+            alignment: PAGE_SIZE,
+        },
+    ] {
+        assert!(e.source().is_none());
+        assert!(!e.is_privilege_error());
+        assert!(e.to_user_message().starts_with(&e.to_string()));
+    }
+    assert_eq!(
+        MemoryError::NotPageAligned {
+            addr: 0x1001,
+            alignment: 0x1000
+        }
+        .to_string(),
+        "Address 0x1001 is not aligned to 0x1000 bytes"
+    );
+}
+
+#[test]
+fn test_memory_error_privilege() {
+    let eperm = MemoryError::MlockFailed(std::io::Error::from_raw_os_error(libc::EPERM));
+    assert!(eperm.is_privilege_error());
+    assert!(eperm.to_user_message().contains("CAP_IPC_LOCK"));
+    let enomem = MemoryError::MlockFailed(std::io::Error::from_raw_os_error(libc::ENOMEM));
+    assert!(!enomem.is_privilege_error());
+    assert!(enomem.to_user_message().contains("ulimit -l"));
+    assert!(
+        !MemoryError::MmapFailed(std::io::Error::from_raw_os_error(libc::EPERM))
+            .is_privilege_error()
+    );
+}
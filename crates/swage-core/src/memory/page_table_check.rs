@@ -0,0 +1,181 @@
+use crate::memory::{FlipDirection, LinuxPageMap, LinuxPageMapError, PhysAddr};
+use crate::util::{CancelableJoinHandle, spawn_cancelable};
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// A change of the physical address a virtual address maps to.
+///
+/// Indicates a corrupted page table entry, e.g., caused by a Rowhammer bit flip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PteFlip {
+    /// Virtual address whose translation changed
+    pub virtual_addr: u64,
+    /// Physical address at snapshot time
+    pub before: PhysAddr,
+    /// Physical address at check time
+    pub after: PhysAddr,
+}
+
+/// Detects page table corruption by comparing virtual-to-physical translations over time.
+///
+/// Take a snapshot of the translations with [`PageTableMonitor::snapshot_ptes`] before
+/// hammering and compare against it with [`PageTableMonitor::check_ptes`] afterwards.
+#[derive(Debug, Default)]
+pub struct PageTableMonitor {
+    pid: u32,
+    snapshot: Vec<(u64, PhysAddr)>,
+}
+
+impl PageTableMonitor {
+    /// Creates a monitor without snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the physical addresses of `addrs` in process `pid`.
+    ///
+    /// Replaces any previous snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the pagemap of `pid` fails.
+    pub fn snapshot_ptes(&mut self, addrs: &[u64], pid: u32) -> Result<(), LinuxPageMapError> {
+        let phys = LinuxPageMap::for_process(pid)?.batch_get_phys(addrs)?;
+        self.pid = pid;
+        self.snapshot = addrs.iter().copied().zip(phys).collect();
+        Ok(())
+    }
+
+    /// Re-reads the physical addresses of the snapshot and returns all changed translations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the pagemap fails.
+    pub fn check_ptes(&mut self) -> Result<Vec<PteFlip>, LinuxPageMapError> {
+        if self.snapshot.is_empty() {
+            return Ok(vec![]);
+        }
+        let addrs = self
+            .snapshot
+            .iter()
+            .map(|(virt, _)| *virt)
+            .collect::<Vec<_>>();
+        let phys = LinuxPageMap::for_process(self.pid)?.batch_get_phys(&addrs)?;
+        Ok(self
+            .snapshot
+            .iter()
+            .zip(phys)
+            .filter(|((_, before), after)| before != after)
+            .map(|(&(virtual_addr, before), after)| PteFlip {
+                virtual_addr,
+                before,
+                after,
+            })
+            .collect())
+    }
+}
+
+/// Returns the flipped bits between two physical addresses.
+///
+/// Each entry contains the bit index and whether it flipped from zero to one or vice versa.
+pub fn pte_flip_direction(before: PhysAddr, after: PhysAddr) -> Vec<(u8, FlipDirection)> {
+    let before = before.as_usize();
+    let after = after.as_usize();
+    let diff = before ^ after;
+    (0..usize::BITS as u8)
+        .filter(|bit| diff & (1 << bit) != 0)
+        .map(|bit| {
+            let direction = if after & (1 << bit) != 0 {
+                FlipDirection::ZeroToOne
+            } else {
+                FlipDirection::OneToZero
+            };
+            (bit, direction)
+        })
+        .collect()
+}
+
+/// Spawns a thread checking the translations of `addrs` in process `pid` every `interval`.
+///
+/// The snapshot is taken when the thread starts. Joining the returned handle stops the
+/// thread and returns all detected flips.
+pub fn spawn_pte_monitor(
+    addrs: Vec<u64>,
+    pid: u32,
+    interval: Duration,
+) -> CancelableJoinHandle<Vec<PteFlip>> {
+    spawn_cancelable(move |running| {
+        let mut monitor = PageTableMonitor::new();
+        let mut flips = vec![];
+        if let Err(e) = monitor.snapshot_ptes(&addrs, pid) {
+            warn!("Failed to snapshot PTEs: {}", e);
+            return flips;
+        }
+        while running.load(Ordering::Relaxed) {
+            match monitor.check_ptes() {
+                Ok(new_flips) => {
+                    for flip in &new_flips {
+                        info!("Detected PTE flip: {:?}", flip);
+                    }
+                    flips.extend(new_flips);
+                }
+                Err(e) => {
+                    warn!("Failed to check PTEs: {}", e);
+                    break;
+                }
+            }
+            sleep(interval);
+        }
+        flips
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{BytePointer, Memory};
+    use crate::util::PAGE_SIZE;
+
+    #[test]
+    fn test_pte_flip_direction() {
+        let flips = pte_flip_direction(PhysAddr::new(0b1010_0000), PhysAddr::new(0b1001_0000));
+        assert_eq!(
+            flips,
+            vec![(4, FlipDirection::ZeroToOne), (5, FlipDirection::OneToZero)]
+        );
+        assert!(pte_flip_direction(PhysAddr::new(42), PhysAddr::new(42)).is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_check_ptes() -> anyhow::Result<()> {
+        let block = Memory::mmap(4 * PAGE_SIZE)?;
+        let addrs = (0..4)
+            .map(|page| block.addr(page * PAGE_SIZE) as u64)
+            .collect::<Vec<_>>();
+        let mut monitor = PageTableMonitor::new();
+        monitor.snapshot_ptes(&addrs, std::process::id())?;
+        assert!(monitor.check_ptes()?.is_empty());
+        block.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_spawn_pte_monitor() -> anyhow::Result<()> {
+        let block = Memory::mmap(PAGE_SIZE)?;
+        let handle = spawn_pte_monitor(
+            vec![block.ptr() as u64],
+            std::process::id(),
+            Duration::from_millis(1),
+        );
+        sleep(Duration::from_millis(10));
+        let flips = handle.join().expect("monitor thread panicked");
+        assert!(flips.is_empty());
+        block.dealloc();
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 use crate::memory::keyed_cache::KeyedCache;
 use crate::memory::mem_configuration::MemConfiguration;
+use crate::memory::{
+    DRAMAddr, Memory, MemoryTupleTimer, PfnOffsetResolver, TimerError, construct_memory_tuple_timer,
+};
+use crate::util::ROW_SIZE;
 use std::cell::RefCell;
 
 type CacheKey = (MemConfiguration, u64);
@@ -19,6 +23,85 @@ pub enum PfnOffset {
     Dynamic(Box<RefCell<Option<(CacheValue, CacheKey)>>>),
 }
 
+impl PfnOffset {
+    /// Determines the PFN offset of `block` by timing row pairs with `timer`.
+    ///
+    /// This is a shorthand for [`PfnOffsetResolver::pfn_offset`] without progress
+    /// reporting.
+    ///
+    /// # Returns
+    ///
+    /// A fixed offset in rows, or None if detection fails
+    pub fn from_timing(
+        block: &Memory,
+        config: &MemConfiguration,
+        threshold: u64,
+        timer: &dyn MemoryTupleTimer,
+    ) -> Option<PfnOffset> {
+        block
+            .pfn_offset(config, threshold, timer, None)
+            .map(|offset| PfnOffset::Fixed(offset.0))
+    }
+
+    /// Determines the PFN offset of `block` using the default timer for this architecture.
+    ///
+    /// In contrast to [`PfnOffset::from_timing`], the result is returned as a
+    /// dynamic offset keyed by `config` and `threshold`. A failed detection is
+    /// recorded as well, i.e., [`PfnOffset::as_bytes`] returns None in that case.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no timer is available for the current architecture.
+    pub fn auto_detect(
+        block: &Memory,
+        config: &MemConfiguration,
+        threshold: u64,
+    ) -> Result<PfnOffset, TimerError> {
+        let timer = construct_memory_tuple_timer()?;
+        let offset = block.pfn_offset(config, threshold, &*timer, None);
+        Ok(PfnOffset::Dynamic(Box::new(RefCell::new(Some((
+            offset.map(|offset| offset.0),
+            (*config, threshold),
+        ))))))
+    }
+
+    /// Returns true if the offset is known to be zero rows.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            PfnOffset::Fixed(offset) => *offset == 0,
+            PfnOffset::Dynamic(cell) => matches!(*cell.borrow(), Some((Some(0), _))),
+        }
+    }
+
+    /// Returns the offset in rows if it is known for `config`.
+    ///
+    /// Dynamic offsets are only valid for the memory configuration they were determined with.
+    fn rows(&self, config: &MemConfiguration) -> Option<usize> {
+        match self {
+            PfnOffset::Fixed(offset) => Some(*offset),
+            PfnOffset::Dynamic(cell) => match *cell.borrow() {
+                Some((offset, (cfg, _))) if cfg == *config => offset,
+                _ => None,
+            },
+        }
+    }
+
+    /// Converts the offset to bytes.
+    ///
+    /// Returns None if the offset is unknown for `config`.
+    pub fn as_bytes(&self, config: &MemConfiguration) -> Option<usize> {
+        self.rows(config).map(|rows| rows * ROW_SIZE)
+    }
+
+    /// Decodes `base` into a DRAM address, taking the offset into account.
+    ///
+    /// Returns None if the offset is unknown for `config`.
+    pub fn as_dram_addr(&self, config: &MemConfiguration, base: *const u8) -> Option<DRAMAddr> {
+        let offset = self.as_bytes(config)?;
+        Some(DRAMAddr::from_virt(base.wrapping_byte_add(offset), config))
+    }
+}
+
 /// Trait for types that provide cached PFN offset access.
 pub trait CachedPfnOffset {
     /// Returns a reference to the PFN offset.
@@ -54,3 +137,130 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MTX_SIZE;
+    use crate::util::{ROW_SHIFT, Size::MB};
+
+    const THRESHOLD: u64 = 300;
+    const ADDR: *mut u8 = 0x200000000 as *mut u8;
+
+    struct TestTimer<'a> {
+        callback: &'a dyn Fn((*const u8, *const u8)) -> u64,
+    }
+
+    impl MemoryTupleTimer for TestTimer<'_> {
+        unsafe fn time_subsequent_access_from_ram(
+            &self,
+            a: *const u8,
+            b: *const u8,
+            _rounds: usize,
+        ) -> u64 {
+            (self.callback)((a, b))
+        }
+    }
+
+    /// Two bank bits: (b13 ^ b16, b14 ^ b17)
+    fn mem_config() -> MemConfiguration {
+        let mut dram_mtx = [0; MTX_SIZE];
+        dram_mtx[MTX_SIZE - 1] = (1 << ROW_SHIFT) | (1 << (ROW_SHIFT + 3));
+        dram_mtx[MTX_SIZE - 2] = (1 << (ROW_SHIFT + 1)) | (1 << (ROW_SHIFT + 4));
+        MemConfiguration {
+            bk_mask: 0b11,
+            dram_mtx,
+            max_bank_bit: ROW_SHIFT as u64 + 4,
+            ..Default::default()
+        }
+    }
+
+    fn timer_for(
+        base_addr: usize,
+        mem_config: &MemConfiguration,
+    ) -> impl Fn((*const u8, *const u8)) -> u64 {
+        move |(a, b)| {
+            let a = base_addr + (a as usize - ADDR as usize);
+            let b = base_addr + (b as usize - ADDR as usize);
+            let a = DRAMAddr::from_virt(a as *const u8, mem_config);
+            let b = DRAMAddr::from_virt(b as *const u8, mem_config);
+            if a.bank == b.bank {
+                THRESHOLD + 100
+            } else {
+                THRESHOLD - 100
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_timing() {
+        let mem_config = mem_config();
+        let row_offsets = mem_config.bank_function_period() as usize / 2;
+        for row_offset in 0..row_offsets {
+            let callback = timer_for(ADDR as usize + row_offset * ROW_SIZE, &mem_config);
+            let timer = TestTimer {
+                callback: &callback,
+            };
+            let block = Memory::new(ADDR, MB(4).bytes());
+            let offset = PfnOffset::from_timing(&block, &mem_config, THRESHOLD, &timer)
+                .expect("offset not found");
+            assert_eq!(offset.is_zero(), row_offset == 0);
+            assert_eq!(offset.as_bytes(&mem_config), Some(row_offset * ROW_SIZE));
+        }
+    }
+
+    #[test]
+    fn test_from_timing_not_consecutive() {
+        let mem_config = mem_config();
+        let timer = TestTimer {
+            callback: &|_| THRESHOLD + 100,
+        };
+        let block = Memory::new(ADDR, MB(4).bytes());
+        assert!(PfnOffset::from_timing(&block, &mem_config, THRESHOLD, &timer).is_none());
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_auto_detect() -> anyhow::Result<()> {
+        let mem_config = mem_config();
+        // fixed offsets are reused without timing
+        let block = Memory::new_with_parts(ADDR, MB(4).bytes(), PfnOffset::Fixed(3));
+        let offset = PfnOffset::auto_detect(&block, &mem_config, THRESHOLD)?;
+        assert!(!offset.is_zero());
+        assert_eq!(offset.as_bytes(&mem_config), Some(3 * ROW_SIZE));
+        // dynamic offsets are only valid for the configuration they were determined with
+        assert_eq!(offset.as_bytes(&MemConfiguration::default()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_zero() {
+        let key = (mem_config(), THRESHOLD);
+        assert!(PfnOffset::Fixed(0).is_zero());
+        assert!(!PfnOffset::Fixed(1).is_zero());
+        assert!(PfnOffset::Dynamic(Box::new(RefCell::new(Some((Some(0), key))))).is_zero());
+        assert!(!PfnOffset::Dynamic(Box::new(RefCell::new(Some((None, key))))).is_zero());
+        assert!(!PfnOffset::Dynamic(Box::new(RefCell::new(None))).is_zero());
+    }
+
+    #[test]
+    fn test_as_dram_addr() {
+        let mem_config = mem_config();
+        let base = ADDR as *const u8;
+        assert_eq!(
+            PfnOffset::Fixed(0).as_dram_addr(&mem_config, base),
+            Some(DRAMAddr::from_virt(base, &mem_config))
+        );
+        assert_eq!(
+            PfnOffset::Fixed(1).as_dram_addr(&mem_config, base),
+            Some(DRAMAddr::from_virt(
+                base.wrapping_add(ROW_SIZE),
+                &mem_config
+            ))
+        );
+        assert_eq!(
+            PfnOffset::Dynamic(Box::new(RefCell::new(None))).as_dram_addr(&mem_config, base),
+            None
+        );
+    }
+}
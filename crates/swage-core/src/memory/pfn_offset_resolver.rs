@@ -5,7 +5,7 @@ use super::DRAMAddr;
 use super::{BytePointer, MemoryTupleTimer, pfn_offset::CachedPfnOffset};
 use crate::memory::mem_configuration::MemConfiguration;
 use crate::util::NamedProgress;
-use crate::util::ROW_SIZE;
+use crate::util::{ROW_SIZE, RowOffset};
 use indicatif::MultiProgress;
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
@@ -34,7 +34,7 @@ pub trait PfnOffsetResolver {
         conflict_threshold: u64,
         timer: &dyn MemoryTupleTimer,
         progress: Option<&MultiProgress>,
-    ) -> Option<usize>;
+    ) -> Option<RowOffset>;
 }
 
 impl<T> PfnOffsetResolver for T
@@ -54,10 +54,10 @@ where
         conflict_threshold: u64,
         timer: &dyn MemoryTupleTimer,
         progress: Option<&MultiProgress>,
-    ) -> Option<usize> {
+    ) -> Option<RowOffset> {
         // reuse cached value if possible
         if let Some(offset) = self.get_cached((*mem_config, conflict_threshold)) {
-            return Some(offset);
+            return Some(RowOffset(offset));
         }
         // find PFN offset
         let num_rows = self.len() / ROW_SIZE;
@@ -82,7 +82,9 @@ where
             let time = unsafe { timer.time_subsequent_access_from_ram(addr1, addr2, 1000) };
             if time > conflict_threshold {
                 info!("Pre-check failed. Block is not consecutive");
-                return self.put(None, (*mem_config, conflict_threshold));
+                return self
+                    .put(None, (*mem_config, conflict_threshold))
+                    .map(RowOffset);
             }
         } else {
             debug!("Skip pre-check, block is too small");
@@ -136,8 +138,11 @@ where
                     continue 'next_offset;
                 }
             }
-            return self.put(Some(row_offset), (*mem_config, conflict_threshold));
+            return self
+                .put(Some(row_offset), (*mem_config, conflict_threshold))
+                .map(RowOffset);
         }
         self.put(None, (*mem_config, conflict_threshold))
+            .map(RowOffset)
     }
 }
@@ -1,4 +1,4 @@
-use crate::memory::{LinuxPageMap, VirtToPhysResolver};
+use crate::memory::LinuxPageMap;
 
 use super::virt_to_phys::{LinuxPageMapError, PhysAddr};
 
@@ -9,24 +9,27 @@ pub type Result<T> = std::result::Result<T, LinuxPageMapError>;
 pub trait PfnResolver {
     /// Returns the physical frame number for this address.
     ///
+    /// Returns `None` if the page is not present or the PFN is hidden, e.g., when not running
+    /// as root. See [`LinuxPageMap::try_get_phys`].
+    ///
     /// # Errors
     ///
     /// Returns error if physical address cannot be resolved
-    fn pfn(&self) -> Result<PhysAddr>;
+    fn pfn(&self) -> Result<Option<PhysAddr>>;
 }
 
 /// implementation for PfnResolver trait for raw pointers
 impl<T> PfnResolver for *mut T {
-    fn pfn(&self) -> Result<PhysAddr> {
+    fn pfn(&self) -> Result<Option<PhysAddr>> {
         let mut resolver = LinuxPageMap::new()?;
-        resolver.get_phys(*self as u64)
+        resolver.try_get_phys(*self as u64)
     }
 }
 
 /// implementation for PfnResolver trait for raw pointers
 impl<T> PfnResolver for *const T {
-    fn pfn(&self) -> Result<PhysAddr> {
+    fn pfn(&self) -> Result<Option<PhysAddr>> {
         let mut resolver = LinuxPageMap::new()?;
-        resolver.get_phys(*self as u64)
+        resolver.try_get_phys(*self as u64)
     }
 }
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Write as _};
+
+use crate::memory::{
+    BytePointer, ConsecBlocks, ConsecPfnsError, DRAMAddr, LinuxPageMap, MemConfiguration, PhysAddr,
+};
+use crate::util::ROW_SIZE;
+
+/// A single row-sized chunk of a [`ConsecBlocks`] allocation and its DRAM location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhysicalBlock {
+    /// Virtual address of the chunk
+    pub virtual_addr: *const u8,
+    /// Physical address of the chunk
+    pub physical_addr: PhysAddr,
+    /// DRAM bank of the chunk
+    pub bank: usize,
+    /// DRAM row of the chunk
+    pub row: usize,
+    /// Index of the block in [`ConsecBlocks::blocks`] containing the chunk
+    pub block_idx: usize,
+}
+
+/// Physical memory layout of an allocation, keyed by `(bank, row)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutMap(pub BTreeMap<(usize, usize), PhysicalBlock>);
+
+/// Resolves the DRAM location of every row-sized chunk in `blocks`.
+///
+/// If several chunks map to the same `(bank, row)`, the first one is kept.
+///
+/// # Errors
+///
+/// Returns an error if the physical addresses cannot be resolved.
+pub fn render_physical_layout(
+    blocks: &ConsecBlocks,
+    config: &MemConfiguration,
+) -> Result<LayoutMap, ConsecPfnsError> {
+    let chunks = blocks
+        .blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, block)| {
+            (0..block.len())
+                .step_by(ROW_SIZE)
+                .map(move |offset| (idx, block.addr(offset) as *const u8))
+        })
+        .collect::<Vec<_>>();
+    let virts = chunks.iter().map(|&(_, v)| v as u64).collect::<Vec<_>>();
+    let phys = LinuxPageMap::new()?.batch_get_phys(&virts)?;
+    let mut layout = LayoutMap::default();
+    for (&(block_idx, virtual_addr), physical_addr) in chunks.iter().zip(phys) {
+        let dram = DRAMAddr::from_phys(physical_addr, config);
+        layout
+            .0
+            .entry((dram.bank, dram.row))
+            .or_insert(PhysicalBlock {
+                virtual_addr,
+                physical_addr,
+                bank: dram.bank,
+                row: dram.row,
+                block_idx,
+            });
+    }
+    Ok(layout)
+}
+
+impl LayoutMap {
+    /// Renders the layout as a grid with one column per bank and one line per row.
+    ///
+    /// Only rows occupied in at least one bank are shown, and at most `max_rows` of them.
+    /// Each cell contains the block index of the chunk at that location, or `.` if the
+    /// location is not part of the allocation.
+    pub fn to_ascii_art(&self, max_rows: usize) -> String {
+        let banks = self
+            .0
+            .keys()
+            .map(|&(bank, _)| bank)
+            .collect::<BTreeSet<_>>();
+        let rows = self.0.keys().map(|&(_, row)| row).collect::<BTreeSet<_>>();
+        let mut out = String::new();
+        let _ = write!(out, "{:>8} |", "row");
+        for bank in &banks {
+            let _ = write!(out, " {:>4}", format!("b{}", bank));
+        }
+        out.push('\n');
+        for &row in rows.iter().take(max_rows) {
+            let _ = write!(out, "{:>8} |", row);
+            for &bank in &banks {
+                match self.0.get(&(bank, row)) {
+                    Some(block) => {
+                        let _ = write!(out, " {:>4}", block.block_idx);
+                    }
+                    None => {
+                        let _ = write!(out, " {:>4}", ".");
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        if rows.len() > max_rows {
+            let _ = writeln!(out, "... ({} more rows)", rows.len() - max_rows);
+        }
+        out
+    }
+
+    /// Returns `(bank, start_row, gap_size)` for every run of missing rows between
+    /// occupied rows of a bank.
+    pub fn bank_gaps(&self) -> Vec<(usize, usize, usize)> {
+        let mut gaps = vec![];
+        let mut prev: Option<(usize, usize)> = None;
+        for &(bank, row) in self.0.keys() {
+            if let Some((prev_bank, prev_row)) = prev
+                && prev_bank == bank
+                && row > prev_row + 1
+            {
+                gaps.push((bank, prev_row + 1, row - prev_row - 1));
+            }
+            prev = Some((bank, row));
+        }
+        gaps
+    }
+}
+
+impl Display for LayoutMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ascii_art(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(entries: &[(usize, usize, usize)]) -> LayoutMap {
+        LayoutMap(
+            entries
+                .iter()
+                .map(|&(bank, row, block_idx)| {
+                    (
+                        (bank, row),
+                        PhysicalBlock {
+                            virtual_addr: std::ptr::null(),
+                            physical_addr: PhysAddr::new(0),
+                            bank,
+                            row,
+                            block_idx,
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_bank_gaps() {
+        let layout = layout(&[
+            (0, 0, 0),
+            (0, 1, 0),
+            (0, 5, 1),
+            (1, 2, 0),
+            (1, 3, 0),
+            (2, 4, 1),
+        ]);
+        assert_eq!(layout.bank_gaps(), vec![(0, 2, 3)]);
+        assert_eq!(LayoutMap::default().bank_gaps(), vec![]);
+    }
+
+    #[test]
+    fn test_to_ascii_art() {
+        let layout = layout(&[(0, 0, 0), (1, 0, 0), (1, 7, 1)]);
+        let expected = "     row |   b0   b1\n       0 |    0    0\n       7 |    .    1\n";
+        assert_eq!(layout.to_ascii_art(10), expected);
+        assert_eq!(layout.to_string(), expected);
+        let truncated = layout.to_ascii_art(1);
+        assert!(truncated.ends_with("... (1 more rows)\n"));
+        assert!(!truncated.contains("   7 |"));
+    }
+}
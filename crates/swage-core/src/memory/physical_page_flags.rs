@@ -0,0 +1,199 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::memory::{BytePointer, LinuxPageMap, Memory};
+use crate::util::{PAGE_SHIFT, PAGE_SIZE};
+
+/// Path of the kernel interface exposing the flags of each physical page
+const KPAGEFLAGS: &str = "/proc/kpageflags";
+
+/// Flags of a physical page as reported by `/proc/kpageflags`.
+///
+/// See the Linux documentation on `/proc/kpageflags` for details on the individual flags.
+/// Reading the flags requires root privileges.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PhysicalPageFlags(pub u64);
+
+impl PhysicalPageFlags {
+    /// Page is locked for exclusive access
+    pub const LOCKED: u64 = 1 << 0;
+    /// IO error occurred
+    pub const ERROR: u64 = 1 << 1;
+    /// Page has been referenced since last LRU list enqueue/requeue
+    pub const REFERENCED: u64 = 1 << 2;
+    /// Page has up-to-date data
+    pub const UPTODATE: u64 = 1 << 3;
+    /// Page has been written to, hence contains new data
+    pub const DIRTY: u64 = 1 << 4;
+    /// Page is in one of the LRU lists
+    pub const LRU: u64 = 1 << 5;
+    /// Page is in the active LRU list
+    pub const ACTIVE: u64 = 1 << 6;
+    /// Page is managed by the SLAB/SLUB allocator
+    pub const SLAB: u64 = 1 << 7;
+    /// Page is being synced to disk
+    pub const WRITEBACK: u64 = 1 << 8;
+    /// Page will be reclaimed soon after its pageout IO completed
+    pub const RECLAIM: u64 = 1 << 9;
+    /// Free memory block managed by the buddy allocator
+    pub const BUDDY: u64 = 1 << 10;
+    /// Memory mapped page
+    pub const MMAP: u64 = 1 << 11;
+    /// Memory mapped page that is not part of a file
+    pub const ANON: u64 = 1 << 12;
+    /// Page is mapped to swap space
+    pub const SWAPCACHE: u64 = 1 << 13;
+    /// Page is backed by swap/RAM
+    pub const SWAPBACKED: u64 = 1 << 14;
+    /// First page of a compound page
+    pub const COMPOUND_HEAD: u64 = 1 << 15;
+    /// Continuation page of a compound page
+    pub const COMPOUND_TAIL: u64 = 1 << 16;
+    /// Page is part of a huge page
+    pub const HUGE: u64 = 1 << 17;
+    /// Page is in the unevictable LRU list
+    pub const UNEVICTABLE: u64 = 1 << 18;
+    /// Hardware detected memory corruption on this page
+    pub const HWPOISON: u64 = 1 << 19;
+
+    const NAMES: [(u64, &'static str); 20] = [
+        (Self::LOCKED, "LOCKED"),
+        (Self::ERROR, "ERROR"),
+        (Self::REFERENCED, "REFERENCED"),
+        (Self::UPTODATE, "UPTODATE"),
+        (Self::DIRTY, "DIRTY"),
+        (Self::LRU, "LRU"),
+        (Self::ACTIVE, "ACTIVE"),
+        (Self::SLAB, "SLAB"),
+        (Self::WRITEBACK, "WRITEBACK"),
+        (Self::RECLAIM, "RECLAIM"),
+        (Self::BUDDY, "BUDDY"),
+        (Self::MMAP, "MMAP"),
+        (Self::ANON, "ANON"),
+        (Self::SWAPCACHE, "SWAPCACHE"),
+        (Self::SWAPBACKED, "SWAPBACKED"),
+        (Self::COMPOUND_HEAD, "COMPOUND_HEAD"),
+        (Self::COMPOUND_TAIL, "COMPOUND_TAIL"),
+        (Self::HUGE, "HUGE"),
+        (Self::UNEVICTABLE, "UNEVICTABLE"),
+        (Self::HWPOISON, "HWPOISON"),
+    ];
+
+    /// Reads the flags of the physical page `pfn`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `/proc/kpageflags` cannot be read, e.g., when not running as root.
+    pub fn read(pfn: u64) -> std::io::Result<Self> {
+        Ok(Self::read_range(pfn, 1)?[0])
+    }
+
+    /// Reads the flags of the `count` physical pages starting at `pfn_start`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `/proc/kpageflags` cannot be read, e.g., when not running as root.
+    pub fn read_range(pfn_start: u64, count: usize) -> std::io::Result<Vec<Self>> {
+        let mut file = File::open(KPAGEFLAGS)?;
+        file.seek(SeekFrom::Start(pfn_start * 8))?;
+        let mut buf = vec![0u8; count * 8];
+        file.read_exact(&mut buf)?;
+        Ok(Self::parse(&buf))
+    }
+
+    /// Parses native-endian 64-bit flag entries as read from `/proc/kpageflags`.
+    fn parse(buf: &[u8]) -> Vec<Self> {
+        buf.chunks_exact(8)
+            .map(|entry| PhysicalPageFlags(u64::from_ne_bytes(entry.try_into().unwrap())))
+            .collect()
+    }
+
+    /// Returns true if all bits of `flag` are set.
+    pub fn contains(&self, flag: u64) -> bool {
+        self.0 & flag == flag
+    }
+
+    /// Returns true if the page is part of a huge page.
+    pub fn is_huge(&self) -> bool {
+        self.contains(Self::HUGE)
+    }
+
+    /// Returns true if the page is free and managed by the buddy allocator.
+    pub fn is_buddy(&self) -> bool {
+        self.contains(Self::BUDDY)
+    }
+
+    /// Returns true if the hardware detected memory corruption on the page.
+    pub fn is_hwpoison(&self) -> bool {
+        self.contains(Self::HWPOISON)
+    }
+}
+
+impl fmt::Debug for PhysicalPageFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "PhysicalPageFlags({:#x}: {})", self.0, names.join(" | "))
+    }
+}
+
+impl Memory {
+    /// Returns the flags of each physical page backing this block.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the physical addresses or page flags cannot be read, e.g., when
+    /// not running as root.
+    pub fn page_flags(&self) -> std::io::Result<Vec<PhysicalPageFlags>> {
+        let virts = (0..self.len())
+            .step_by(PAGE_SIZE)
+            .map(|offset| self.addr(offset) as u64)
+            .collect::<Vec<_>>();
+        let phys = LinuxPageMap::new()
+            .and_then(|mut pagemap| pagemap.batch_get_phys(&virts))
+            .map_err(std::io::Error::other)?;
+        phys.into_iter()
+            .map(|phys| PhysicalPageFlags::read((phys.as_usize() >> PAGE_SHIFT) as u64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags() {
+        // UPTODATE | LRU | ACTIVE | MMAP | ANON | SWAPBACKED, and COMPOUND_HEAD | HUGE | BUDDY | HWPOISON
+        let entries = [0x5868u64, 0xa8400];
+        let buf = entries
+            .iter()
+            .flat_map(|e| e.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let flags = PhysicalPageFlags::parse(&buf);
+        assert_eq!(flags.len(), 2);
+        assert!(flags[0].contains(PhysicalPageFlags::ANON | PhysicalPageFlags::MMAP));
+        assert!(!flags[0].is_huge() && !flags[0].is_buddy() && !flags[0].is_hwpoison());
+        assert!(flags[1].contains(PhysicalPageFlags::COMPOUND_HEAD));
+        assert!(flags[1].is_huge() && flags[1].is_buddy() && flags[1].is_hwpoison());
+        assert_eq!(
+            format!("{:?}", PhysicalPageFlags(0x5868)),
+            "PhysicalPageFlags(0x5868: UPTODATE | LRU | ACTIVE | MMAP | ANON | SWAPBACKED)"
+        );
+    }
+
+    #[test]
+    fn test_page_flags() -> anyhow::Result<()> {
+        let mem = Memory::mmap(4 * PAGE_SIZE)?;
+        let flags = mem.page_flags()?;
+        assert_eq!(flags.len(), 4);
+        assert!(flags.iter().all(|f| f.contains(PhysicalPageFlags::MMAP)));
+        assert!(flags.iter().all(|f| !f.is_buddy()));
+        mem.dealloc();
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use crate::memory::{BytePointer, ConsecBlocks, MemoryTupleTimer};
+use crate::util::ROW_SIZE;
+use log::debug;
+
+/// Result of timing a pair of addresses in [`RowConflictDetector::test_conflict`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConflictResult {
+    /// Measured access time of the address pair
+    pub timing: u64,
+    /// Whether the timing indicates a row buffer conflict, i.e., both addresses map to
+    /// different rows in the same bank
+    pub same_bank: bool,
+}
+
+/// Addresses grouped by DRAM bank, as discovered by [`RowConflictDetector::build_bank_map`].
+///
+/// Bank ids are assigned in order of discovery and are not related to the bank numbering
+/// of the memory controller.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BankMap(pub HashMap<usize, Vec<*const u8>>);
+
+impl BankMap {
+    /// Returns two addresses in `bank`, or `None` if fewer than two addresses are known.
+    pub fn any_conflict_pair(&self, bank: usize) -> Option<(*const u8, *const u8)> {
+        match self.0.get(&bank)?.as_slice() {
+            [a, b, ..] => Some((*a, *b)),
+            _ => None,
+        }
+    }
+}
+
+/// Discovers DRAM bank conflicts from access timings.
+///
+/// Unlike [`crate::memory::DRAMAddr`], this does not require physical addresses, and can
+/// therefore be used when PFNs cannot be resolved (e.g., when not running as root).
+pub struct RowConflictDetector {
+    timer: Box<dyn MemoryTupleTimer>,
+    conflict_threshold: u64,
+}
+
+impl RowConflictDetector {
+    /// Creates a new detector.
+    ///
+    /// # Arguments
+    ///
+    /// * `timer` - The timer used to measure access times of address pairs
+    /// * `conflict_threshold` - Access times above this threshold are considered a row
+    ///   buffer conflict
+    pub fn new(timer: Box<dyn MemoryTupleTimer>, conflict_threshold: u64) -> Self {
+        RowConflictDetector {
+            timer,
+            conflict_threshold,
+        }
+    }
+
+    /// Times accessing `a` and `b` back to back and checks for a row buffer conflict.
+    ///
+    /// Both addresses must be valid for reads.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn test_conflict(&self, a: *const u8, b: *const u8, rounds: usize) -> ConflictResult {
+        let timing = unsafe { self.timer.time_subsequent_access_from_ram(a, b, rounds) };
+        ConflictResult {
+            timing,
+            same_bank: timing > self.conflict_threshold,
+        }
+    }
+
+    /// Returns all same-bank pairs among the addresses `row_stride` rows apart in `region`.
+    ///
+    /// This measures every pair of sampled addresses, i.e., quadratically many.
+    pub fn find_bank_conflicts_in_region(
+        &self,
+        region: &ConsecBlocks,
+        row_stride: usize,
+        rounds: usize,
+    ) -> Vec<(*const u8, *const u8)> {
+        let addrs = sample_rows(region, row_stride);
+        let mut conflicts = vec![];
+        for (i, &a) in addrs.iter().enumerate() {
+            for &b in &addrs[i + 1..] {
+                if self.test_conflict(a, b, rounds).same_bank {
+                    conflicts.push((a, b));
+                }
+            }
+        }
+        debug!(
+            "Found {} conflicts among {} addresses",
+            conflicts.len(),
+            addrs.len()
+        );
+        conflicts
+    }
+
+    /// Groups the rows of `region` into banks.
+    ///
+    /// Each row is measured against one representative row of every bank found so far and
+    /// added to the first bank it conflicts with, or starts a new bank. This takes
+    /// `O(rows * banks)` measurements.
+    pub fn build_bank_map(&self, region: &ConsecBlocks, rounds: usize) -> BankMap {
+        let addrs = sample_rows(region, 1);
+        let mut banks = BankMap::default();
+        for addr in addrs.iter().copied() {
+            let bank = (0..banks.0.len())
+                .find(|bank| self.test_conflict(banks.0[bank][0], addr, rounds).same_bank)
+                .unwrap_or(banks.0.len());
+            banks.0.entry(bank).or_insert_with(Vec::new).push(addr);
+        }
+        debug!("Found {} banks among {} rows", banks.0.len(), addrs.len());
+        banks
+    }
+}
+
+/// Returns the start address of every `row_stride`-th row in `region`.
+fn sample_rows(region: &ConsecBlocks, row_stride: usize) -> Vec<*const u8> {
+    (0..region.len())
+        .step_by(row_stride.max(1) * ROW_SIZE)
+        .map(|offset| region.addr(offset) as *const u8)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use crate::util::ROW_SHIFT;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const THRESHOLD: u64 = 300;
+
+    /// Maps addresses to four banks by row bits 0 and 1.
+    struct TestTimer;
+
+    impl MemoryTupleTimer for TestTimer {
+        unsafe fn time_subsequent_access_from_ram(
+            &self,
+            a: *const u8,
+            b: *const u8,
+            _rounds: usize,
+        ) -> u64 {
+            let bank = |addr: *const u8| (addr as usize >> ROW_SHIFT) & 0b11;
+            if a != b && bank(a) == bank(b) {
+                400
+            } else {
+                200
+            }
+        }
+    }
+
+    fn region(rows: usize) -> ConsecBlocks {
+        let base = 0x2000000000 as *mut u8;
+        let half = rows / 2 * ROW_SIZE;
+        ConsecBlocks::new(vec![
+            Memory::new(base, half),
+            Memory::new(unsafe { base.byte_add(2 * half) }, half),
+        ])
+    }
+
+    fn detector() -> RowConflictDetector {
+        RowConflictDetector::new(Box::new(TestTimer), THRESHOLD)
+    }
+
+    #[test]
+    fn test_conflict() {
+        let detector = detector();
+        let a = 0x2000000000 as *const u8;
+        let result = detector.test_conflict(a, a.wrapping_byte_add(4 * ROW_SIZE), 1);
+        assert_eq!(
+            result,
+            ConflictResult {
+                timing: 400,
+                same_bank: true
+            }
+        );
+        assert!(
+            !detector
+                .test_conflict(a, a.wrapping_byte_add(ROW_SIZE), 1)
+                .same_bank
+        );
+    }
+
+    #[test]
+    fn test_find_bank_conflicts_in_region() {
+        let detector = detector();
+        let region = region(8);
+        // rows 0, 2 of both blocks: all pairs conflict in banks 0 and 2
+        let conflicts = detector.find_bank_conflicts_in_region(&region, 2, 1);
+        assert_eq!(
+            conflicts,
+            vec![
+                (
+                    region.addr(0) as *const u8,
+                    region.addr(4 * ROW_SIZE) as *const u8
+                ),
+                (
+                    region.addr(2 * ROW_SIZE) as *const u8,
+                    region.addr(6 * ROW_SIZE) as *const u8
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_bank_map() {
+        let detector = detector();
+        let region = region(16);
+        let banks = detector.build_bank_map(&region, 1);
+        assert_eq!(banks.0.len(), 4);
+        for (bank, addrs) in &banks.0 {
+            assert_eq!(addrs.len(), 4);
+            let (a, b) = banks.any_conflict_pair(*bank).expect("no pair");
+            assert!(detector.test_conflict(a, b, 1).same_bank);
+        }
+        assert_eq!(banks.any_conflict_pair(4), None);
+    }
+
+    /// Counts the measurements of [`TestTimer`].
+    struct CountingTimer(Arc<AtomicUsize>);
+
+    impl MemoryTupleTimer for CountingTimer {
+        unsafe fn time_subsequent_access_from_ram(
+            &self,
+            a: *const u8,
+            b: *const u8,
+            rounds: usize,
+        ) -> u64 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            unsafe { TestTimer.time_subsequent_access_from_ram(a, b, rounds) }
+        }
+    }
+
+    #[test]
+    fn test_build_bank_map_measurements() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let detector = RowConflictDetector::new(Box::new(CountingTimer(count.clone())), THRESHOLD);
+        let banks = detector.build_bank_map(&region(64), 1);
+        assert_eq!(banks.0.len(), 4);
+        // each row is compared against at most one row of each of the 4 banks
+        assert!(count.load(Ordering::Relaxed) <= 64 * 4);
+    }
+
+    #[test]
+    fn test_any_conflict_pair_single_address() {
+        let banks = BankMap(HashMap::from([(0, vec![0x1000 as *const u8])]));
+        assert_eq!(banks.any_conflict_pair(0), None);
+    }
+}
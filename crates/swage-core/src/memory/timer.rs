@@ -3,7 +3,18 @@ use {core::arch::x86_64, core::ptr};
 
 #[cfg(target_arch = "aarch64")]
 use anyhow::bail;
-use log::trace;
+use itertools::Itertools;
+use log::{debug, trace};
+use serde::Serialize;
+
+use crate::memory::{BytePointer, DRAMAddr, LinuxPageMap, MemConfiguration, Memory, MemoryError};
+use crate::util::PAGE_SIZE;
+use crate::util::Size::MB;
+
+/// Size of the buffer searched for calibration pairs
+const CALIBRATION_BUFFER_SIZE: usize = MB(64).bytes();
+/// Number of measurements taken per calibration pair
+const CALIBRATION_SAMPLES: usize = 10_000;
 
 /// Measures memory access timing between address pairs.
 ///
@@ -20,6 +31,199 @@ pub trait MemoryTupleTimer {
         b: *const u8,
         rounds: usize,
     ) -> u64;
+
+    /// Measures the access time of `a` and `b` `rounds` times and returns all measurements.
+    ///
+    /// The default implementation takes each sample with a single-round
+    /// [`MemoryTupleTimer::time_subsequent_access_from_ram`].
+    ///
+    /// # Safety
+    /// * `a` and `b` must be valid pointers to memory locations
+    unsafe fn time_samples(&self, a: *const u8, b: *const u8, rounds: usize) -> Vec<u64> {
+        (0..rounds)
+            .map(|_| unsafe { self.time_subsequent_access_from_ram(a, b, 1) })
+            .collect()
+    }
+
+    /// Measures the access time of `a` and `b` `rounds` times and returns a histogram.
+    ///
+    /// The range between the fastest and the slowest measurement is split into `buckets`
+    /// bins of equal width. Each entry holds the number of measurements in that bin.
+    ///
+    /// # Safety
+    /// * `a` and `b` must be valid pointers to memory locations
+    unsafe fn time_histogram(
+        &self,
+        a: *const u8,
+        b: *const u8,
+        rounds: usize,
+        buckets: usize,
+    ) -> Vec<u64> {
+        histogram(&unsafe { self.time_samples(a, b, rounds) }, buckets)
+    }
+
+    /// Measures the access time of `a` and `b` `rounds` times and summarizes the distribution.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rounds` is 0.
+    ///
+    /// # Safety
+    /// * `a` and `b` must be valid pointers to memory locations
+    unsafe fn stats(&self, a: *const u8, b: *const u8, rounds: usize) -> MemoryTupleTimerStats {
+        assert!(rounds > 0, "Cannot compute stats of zero rounds");
+        MemoryTupleTimerStats::from_samples(unsafe { self.time_samples(a, b, rounds) })
+    }
+
+    /// Measures a conflict threshold for this machine.
+    ///
+    /// Times two page pairs in the same bank and two page pairs in different banks, as
+    /// resolved with `mem_config`, and returns the midpoint between the median timings of
+    /// both groups. This requires resolving physical addresses, i.e., running as root.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimerError::CalibrationError`] if no suitable pairs are found or the timings
+    /// of both groups are not clearly separated.
+    fn calibrate(&self, mem_config: &MemConfiguration) -> Result<u64, TimerError> {
+        let buffer = Memory::mmap(CALIBRATION_BUFFER_SIZE)?;
+        let result = calibration_pairs(&buffer, mem_config).and_then(|(conflict, no_conflict)| {
+            let measure = |pairs: &[PagePair]| {
+                pairs
+                    .iter()
+                    .flat_map(|&(a, b)| unsafe { self.time_samples(a, b, CALIBRATION_SAMPLES) })
+                    .collect_vec()
+            };
+            threshold_from_samples(measure(&conflict), measure(&no_conflict))
+        });
+        buffer.dealloc();
+        result
+    }
+}
+
+/// Pair of page addresses timed during calibration
+type PagePair = (*const u8, *const u8);
+
+/// Finds two same-bank and two different-bank page pairs in `buffer`.
+fn calibration_pairs(
+    buffer: &Memory,
+    mem_config: &MemConfiguration,
+) -> Result<(Vec<PagePair>, Vec<PagePair>), TimerError> {
+    const PAIRS: usize = 2;
+    let pages = (0..buffer.len())
+        .step_by(PAGE_SIZE)
+        .map(|offset| buffer.addr(offset) as *const u8)
+        .collect_vec();
+    let phys = LinuxPageMap::new()
+        .and_then(|mut pagemap| {
+            pagemap.batch_get_phys(&pages.iter().map(|&p| p as u64).collect_vec())
+        })
+        .map_err(MemoryError::from)?;
+    let dram = phys
+        .into_iter()
+        .map(|phys| DRAMAddr::from_phys(phys, mem_config))
+        .collect_vec();
+    let (mut conflict, mut no_conflict) = (vec![], vec![]);
+    for base in 0..PAIRS {
+        let same_bank = (base + 1..pages.len())
+            .find(|&i| dram[i].bank == dram[base].bank && dram[i].row != dram[base].row);
+        let other_bank = (base + 1..pages.len()).find(|&i| dram[i].bank != dram[base].bank);
+        conflict.extend(same_bank.map(|i| (pages[base], pages[i])));
+        no_conflict.extend(other_bank.map(|i| (pages[base], pages[i])));
+    }
+    if conflict.len() < PAIRS || no_conflict.len() < PAIRS {
+        return Err(TimerError::CalibrationError(format!(
+            "found {} same-bank and {} different-bank pairs, expected {} each",
+            conflict.len(),
+            no_conflict.len(),
+            PAIRS
+        )));
+    }
+    Ok((conflict, no_conflict))
+}
+
+/// Returns the midpoint between the median `conflict` and `no_conflict` timings.
+///
+/// The 10th percentile of the conflict timings must lie above the 90th percentile of the
+/// non-conflict timings.
+fn threshold_from_samples(
+    mut conflict: Vec<u64>,
+    mut no_conflict: Vec<u64>,
+) -> Result<u64, TimerError> {
+    if conflict.is_empty() || no_conflict.is_empty() {
+        return Err(TimerError::CalibrationError("no samples".into()));
+    }
+    conflict.sort_unstable();
+    no_conflict.sort_unstable();
+    let (conflict_low, no_conflict_high) =
+        (percentile(&conflict, 10), percentile(&no_conflict, 90));
+    if conflict_low <= no_conflict_high {
+        return Err(TimerError::CalibrationError(format!(
+            "conflict timings (p10 {}) overlap with non-conflict timings (p90 {})",
+            conflict_low, no_conflict_high
+        )));
+    }
+    let (conflict, no_conflict) = (percentile(&conflict, 50), percentile(&no_conflict, 50));
+    debug!(
+        "Calibration medians: conflict {}, no conflict {}",
+        conflict, no_conflict
+    );
+    Ok((conflict + no_conflict) / 2)
+}
+
+/// Returns the `p`-th percentile of the ascending `sorted` list, using the nearest rank below.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    sorted[(sorted.len() - 1) * p / 100]
+}
+
+/// Counts `samples` in `buckets` bins of equal width between the smallest and the largest
+/// sample.
+fn histogram(samples: &[u64], buckets: usize) -> Vec<u64> {
+    let mut hist = vec![0; buckets];
+    let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) else {
+        return hist;
+    };
+    if buckets == 0 {
+        return hist;
+    }
+    let range = (max - min) as u128 + 1;
+    for &sample in samples {
+        let bucket = ((sample - min) as u128 * buckets as u128 / range) as usize;
+        hist[bucket] += 1;
+    }
+    hist
+}
+
+/// Summary of access time measurements, as returned by [`MemoryTupleTimer::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct MemoryTupleTimerStats {
+    /// Fastest measurement
+    pub min: u64,
+    /// Slowest measurement
+    pub max: u64,
+    /// Mean of all measurements
+    pub mean: f64,
+    /// Median measurement
+    pub p50: u64,
+    /// 95th percentile
+    pub p95: u64,
+    /// 99th percentile
+    pub p99: u64,
+}
+
+impl MemoryTupleTimerStats {
+    /// Summarizes `samples`, which must not be empty.
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        samples.sort_unstable();
+        MemoryTupleTimerStats {
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            mean: samples.iter().sum::<u64>() as f64 / samples.len() as f64,
+            p50: percentile(&samples, 50),
+            p95: percentile(&samples, 95),
+            p99: percentile(&samples, 99),
+        }
+    }
 }
 
 /// Errors that can occur when creating memory timers.
@@ -28,6 +232,12 @@ pub enum TimerError {
     /// Current CPU architecture is not supported for timing
     #[error("Architecture not supported")]
     ArchitectureNotSupported,
+    /// Calibrating the conflict threshold failed
+    #[error("Calibration failed: {0}")]
+    CalibrationError(String),
+    /// Allocating or resolving calibration memory failed
+    #[error(transparent)]
+    MemoryError(#[from] MemoryError),
 }
 
 /// Creates a memory timer for the current architecture.
@@ -59,6 +269,12 @@ impl MemoryTupleTimer for DefaultMemoryTupleTimer {
         b: *const u8,
         rounds: usize,
     ) -> u64 {
+        let measurements = unsafe { self.time_samples(a, b, rounds) };
+        trace!("Measurements: {:?}", measurements);
+        median(measurements)
+    }
+
+    unsafe fn time_samples(&self, a: *const u8, b: *const u8, rounds: usize) -> Vec<u64> {
         unsafe {
             let mut measurements = Vec::with_capacity(rounds);
             //flush data from cache
@@ -66,7 +282,6 @@ impl MemoryTupleTimer for DefaultMemoryTupleTimer {
             x86_64::_mm_clflush(b);
             let mut aux = 0;
             let mut run_idx = 0;
-            let mut sum = 0;
             while run_idx < rounds {
                 x86_64::_mm_mfence(); //ensures clean slate memory access time wise
                 let before = x86_64::__rdtscp(&mut aux); // read timestamp counter
@@ -77,15 +292,12 @@ impl MemoryTupleTimer for DefaultMemoryTupleTimer {
                 x86_64::_mm_mfence(); //ensure rdtsc is done
                 let time = after - before;
                 measurements.push(time);
-                sum += time;
                 run_idx += 1;
                 //flush data from cache
                 x86_64::_mm_clflush(a);
                 x86_64::_mm_clflush(b);
             }
-            trace!("Measurements: {:?}", measurements);
-            let _mean = sum / rounds as u64;
-            median(measurements)
+            measurements
         }
     }
 }
@@ -95,3 +307,77 @@ fn median(mut list: Vec<u64>) -> u64 {
     let mid = list.len() / 2;
     (list[mid] + list[mid + 1]) / 2
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ConstTimer;
+
+    impl MemoryTupleTimer for ConstTimer {
+        unsafe fn time_subsequent_access_from_ram(
+            &self,
+            _a: *const u8,
+            _b: *const u8,
+            _rounds: usize,
+        ) -> u64 {
+            200
+        }
+    }
+
+    #[test]
+    fn test_threshold_from_samples() {
+        let conflict = (380..420).collect_vec();
+        let no_conflict = (190..210).collect_vec();
+        assert_eq!(threshold_from_samples(conflict, no_conflict).unwrap(), 299);
+    }
+
+    #[test]
+    fn test_threshold_from_samples_overlap() {
+        let conflict = (250..400).collect_vec();
+        let no_conflict = (200..300).collect_vec();
+        assert!(matches!(
+            threshold_from_samples(conflict, no_conflict),
+            Err(TimerError::CalibrationError(_))
+        ));
+        assert!(matches!(
+            threshold_from_samples(vec![], vec![200]),
+            Err(TimerError::CalibrationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(histogram(&[100, 101, 150, 199, 200], 2), vec![3, 2]);
+        assert_eq!(histogram(&[100, 100, 100], 4), vec![3, 0, 0, 0]);
+        assert_eq!(histogram(&[], 3), vec![0, 0, 0]);
+        assert!(histogram(&[100], 0).is_empty());
+        let hist = unsafe { ConstTimer.time_histogram(std::ptr::null(), std::ptr::null(), 10, 5) };
+        assert_eq!(hist, vec![10, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = MemoryTupleTimerStats::from_samples((1..=100).rev().collect());
+        assert_eq!(
+            stats,
+            MemoryTupleTimerStats {
+                min: 1,
+                max: 100,
+                mean: 50.5,
+                p50: 50,
+                p95: 95,
+                p99: 99,
+            }
+        );
+        let stats = unsafe { ConstTimer.stats(std::ptr::null(), std::ptr::null(), 3) };
+        assert_eq!((stats.min, stats.max, stats.p99), (200, 200, 200));
+    }
+
+    #[test]
+    fn test_calibrate_single_bank() {
+        // every page maps to bank 0, so there are no different-bank pairs
+        let result = ConstTimer.calibrate(&MemConfiguration::default());
+        assert!(matches!(result, Err(TimerError::CalibrationError(_))));
+    }
+}
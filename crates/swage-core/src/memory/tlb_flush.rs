@@ -0,0 +1,177 @@
+//! Utilities for flushing TLB entries.
+//!
+//! Stale TLB entries can hide bit flips in page table entries, since the CPU keeps using the
+//! cached translation instead of walking the (corrupted) page table again.
+//!
+//! The `INVLPG`, `INVPCID` and `mov cr3` instructions are privileged. When running in user
+//! mode, single pages are flushed by toggling their protection with `mprotect`, which makes the
+//! kernel invalidate the respective TLB entries. Flushing the whole TLB from user mode requires
+//! a kernel helper running with root privileges and is not supported.
+use crate::util::{PAGE_MASK, PAGE_SIZE};
+use log::warn;
+use serde::Serialize;
+
+/// Strategy for flushing TLB entries before reading back victim memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum TlbFlushStrategy {
+    /// Do not flush the TLB
+    #[default]
+    None,
+    /// Flush the TLB entry of a single virtual address
+    Single(u64),
+    /// Flush the TLB entries of all pages in `start..end`
+    Range(u64, u64),
+    /// Flush the entire TLB
+    All,
+}
+
+impl TlbFlushStrategy {
+    /// Flushes the TLB according to this strategy.
+    pub fn flush(&self) {
+        match *self {
+            TlbFlushStrategy::None => {}
+            TlbFlushStrategy::Single(virt) => flush_tlb_single(virt),
+            TlbFlushStrategy::Range(start, end) => flush_tlb_range(start, end),
+            TlbFlushStrategy::All => {
+                if let Err(e) = flush_tlb_all() {
+                    warn!("Failed to flush TLB: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the current privilege level (CPL), i.e., 0 for kernel mode and 3 for user mode.
+fn privilege_level() -> u16 {
+    let cs: u16;
+    unsafe {
+        std::arch::asm!("mov {0:x}, cs", out(reg) cs, options(nomem, nostack, preserves_flags))
+    };
+    cs & 3
+}
+
+/// Flushes the TLB entry of the page containing `virt`.
+///
+/// Uses `INVLPG` in kernel mode. In user mode, the page protection is toggled instead. The
+/// page must be mapped readable and writable by the current process, since its protection is
+/// reset to `PROT_READ | PROT_WRITE`.
+pub fn flush_tlb_single(virt: u64) {
+    if privilege_level() == 0 {
+        // SAFETY: INVLPG only invalidates a TLB entry and is permitted at CPL 0
+        unsafe { std::arch::asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags)) };
+    } else {
+        reprotect(virt & !(PAGE_MASK as u64), PAGE_SIZE);
+    }
+}
+
+/// Flushes the TLB entries of all pages overlapping `start..end`.
+///
+/// See [`flush_tlb_single`] for requirements on the pages.
+pub fn flush_tlb_range(start: u64, end: u64) {
+    let start = start & !(PAGE_MASK as u64);
+    for virt in (start..end).step_by(PAGE_SIZE) {
+        flush_tlb_single(virt);
+    }
+}
+
+/// Flushes the entire TLB, including global entries.
+///
+/// Uses `INVPCID` if supported by the CPU and falls back to reloading `CR3` otherwise.
+///
+/// # Errors
+///
+/// Both instructions are privileged. From user mode, this requires a kernel helper running
+/// with root privileges, so an error is returned instead.
+pub fn flush_tlb_all() -> std::io::Result<()> {
+    if privilege_level() != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "flushing the entire TLB requires kernel mode",
+        ));
+    }
+    let has_invpcid = std::arch::x86_64::__cpuid_count(7, 0).ebx & (1 << 10) != 0;
+    if has_invpcid {
+        // INVPCID type 2: invalidate all mappings, including global ones
+        let descriptor = [0u64; 2];
+        // SAFETY: running at CPL 0 and INVPCID is supported
+        unsafe {
+            std::arch::asm!(
+                "invpcid {}, [{}]",
+                in(reg) 2u64,
+                in(reg) descriptor.as_ptr(),
+                options(nostack, preserves_flags)
+            )
+        };
+    } else {
+        // SAFETY: running at CPL 0, writing back the current CR3 value keeps the address space
+        unsafe {
+            std::arch::asm!(
+                "mov {0}, cr3",
+                "mov cr3, {0}",
+                out(reg) _,
+                options(nostack, preserves_flags)
+            )
+        };
+    }
+    Ok(())
+}
+
+/// Returns whether the TLB must be flushed after hammering to observe page table corruption.
+pub fn needs_tlb_flush_after_hammer() -> bool {
+    true
+}
+
+/// Toggles the protection of `len` bytes at the page-aligned `addr`, making the kernel flush
+/// the respective TLB entries.
+fn reprotect(addr: u64, len: usize) {
+    let addr = addr as *mut libc::c_void;
+    let ret = unsafe { libc::mprotect(addr, len, libc::PROT_READ) };
+    if ret != 0 {
+        warn!(
+            "mprotect({:p}) failed: {}",
+            addr,
+            std::io::Error::last_os_error()
+        );
+        return;
+    }
+    let ret = unsafe { libc::mprotect(addr, len, libc::PROT_READ | libc::PROT_WRITE) };
+    if ret != 0 {
+        warn!(
+            "mprotect({:p}) failed: {}",
+            addr,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{BytePointer, Memory};
+
+    #[test]
+    fn test_privilege_level() {
+        assert_eq!(privilege_level(), 3);
+        assert!(needs_tlb_flush_after_hammer());
+    }
+
+    #[test]
+    fn test_flush_tlb_range() -> anyhow::Result<()> {
+        let mem = Memory::mmap(4 * PAGE_SIZE)?;
+        unsafe { std::ptr::write_bytes(mem.ptr(), 0x42, mem.len) };
+        let start = mem.addr(42) as u64;
+        TlbFlushStrategy::Range(start, start + 2 * PAGE_SIZE as u64).flush();
+        TlbFlushStrategy::Single(mem.addr(3 * PAGE_SIZE) as u64).flush();
+        let data = unsafe { std::slice::from_raw_parts(mem.ptr(), mem.len) };
+        assert!(data.iter().all(|&b| b == 0x42));
+        // pages must still be writable after the flush
+        unsafe { std::ptr::write_volatile(mem.addr(PAGE_SIZE), 0x00) };
+        mem.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_tlb_all_user_mode() {
+        assert!(flush_tlb_all().is_err());
+    }
+}
@@ -1,18 +1,19 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
 use std::ops::{Add, Sub};
 
-use crate::util::PAGE_SHIFT;
+use crate::util::{PAGE_SHIFT, PAGE_SIZE};
 use itertools::Itertools;
 use log::warn;
 use pagemap2::{MapsEntry, PageMapEntry, PageMapError, VirtualMemoryArea};
-use serde::Serialize;
 use thiserror::Error;
 
 #[repr(transparent)]
-#[derive(Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 /// Physical memory address.
 ///
-/// A newtype wrapper around a physical address value.
+/// A newtype wrapper around a physical address value. Serializes as a hex string, e.g.,
+/// `"0x000000007f000000"`.
 pub struct PhysAddr(usize);
 
 impl Debug for PhysAddr {
@@ -27,10 +28,40 @@ impl PhysAddr {
         PhysAddr(addr)
     }
 
+    /// Creates a new physical address from a `u64`, e.g., as read from pagemap.
+    pub fn from_u64(addr: u64) -> Self {
+        PhysAddr(addr as usize)
+    }
+
     /// Returns the address as a usize.
     pub fn as_usize(&self) -> usize {
         self.0
     }
+
+    /// Adds `rhs` to the address, returning `None` on overflow.
+    pub fn checked_add(self, rhs: usize) -> Option<PhysAddr> {
+        self.0.checked_add(rhs).map(PhysAddr)
+    }
+
+    /// Adds `rhs` to the address, saturating at `usize::MAX`.
+    pub fn saturating_add(self, rhs: usize) -> PhysAddr {
+        PhysAddr(self.0.saturating_add(rhs))
+    }
+
+    /// Rounds the address down to a multiple of `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn align_down(self, align: usize) -> PhysAddr {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        PhysAddr(self.0 & !(align - 1))
+    }
+
+    /// Returns the offset of the address within its page.
+    pub fn page_offset(&self) -> usize {
+        self.0 & (PAGE_SIZE - 1)
+    }
 }
 
 /// Trait for resolving virtual addresses to physical addresses.
@@ -66,6 +97,59 @@ pub struct LinuxPageMapError(#[from] PageMapError);
 /// Requires root privileges to access pagemap.
 pub struct LinuxPageMap {
     pagemap_wrapper: pagemap2::PageMap,
+    cache: Option<PageCache>,
+}
+
+/// Physical page addresses indexed by virtual page address, evicting the least recently used
+/// page when full.
+struct PageCache {
+    capacity: usize,
+    /// virtual page -> (physical page, last use)
+    pages: HashMap<u64, (PhysAddr, u64)>,
+    /// last use -> virtual page
+    uses: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl PageCache {
+    fn new(capacity: usize) -> Self {
+        PageCache {
+            capacity,
+            pages: HashMap::with_capacity(capacity),
+            uses: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, page: u64) -> Option<PhysAddr> {
+        let (phys, last_use) = self.pages.get_mut(&page)?;
+        self.uses.remove(last_use);
+        self.clock += 1;
+        *last_use = self.clock;
+        self.uses.insert(self.clock, page);
+        Some(*phys)
+    }
+
+    fn insert(&mut self, page: u64, phys: PhysAddr) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(page);
+        if self.pages.len() >= self.capacity
+            && let Some((_, lru)) = self.uses.pop_first()
+        {
+            self.pages.remove(&lru);
+        }
+        self.clock += 1;
+        self.pages.insert(page, (phys, self.clock));
+        self.uses.insert(self.clock, page);
+    }
+
+    fn remove(&mut self, page: u64) {
+        if let Some((_, last_use)) = self.pages.remove(&page) {
+            self.uses.remove(&last_use);
+        }
+    }
 }
 
 impl LinuxPageMap {
@@ -90,9 +174,46 @@ impl LinuxPageMap {
     pub fn for_process(pid: u32) -> Result<LinuxPageMap, LinuxPageMapError> {
         let res = LinuxPageMap {
             pagemap_wrapper: pagemap2::PageMap::new(pid as u64)?,
+            cache: None,
         };
         Ok(res)
     }
+
+    /// Creates a new pagemap for the current process that caches up to `capacity` resolved pages.
+    ///
+    /// Cached pages are not looked up in `/proc/self/pagemap` again, so call
+    /// [`LinuxPageMap::invalidate_page`] when a page is unmapped or migrated. When the cache is
+    /// full, the least recently used page is evicted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if opening `/proc/self/pagemap` fails.
+    pub fn with_cache(capacity: usize) -> Result<LinuxPageMap, LinuxPageMapError> {
+        let mut res = Self::new()?;
+        res.cache = Some(PageCache::new(capacity));
+        Ok(res)
+    }
+
+    /// Removes the page containing `virt` from the cache.
+    pub fn invalidate_page(&mut self, virt: u64) {
+        if let Some(cache) = &mut self.cache {
+            cache.remove(virt & !0xFFF);
+        }
+    }
+
+    /// Returns the cached physical page for the page containing `virt`.
+    fn cached_page(&mut self, virt: u64) -> Option<PhysAddr> {
+        self.cache.as_mut()?.get(virt & !0xFFF)
+    }
+
+    /// Caches the physical page of `virt`. Invalid PFNs are not cached.
+    fn cache_page(&mut self, virt: u64, phys: PhysAddr) {
+        if let Some(cache) = &mut self.cache
+            && phys.0 >> PAGE_SHIFT != 0
+        {
+            cache.insert(virt & !0xFFF, phys.align_down(PAGE_SIZE));
+        }
+    }
 }
 
 pub struct PageMap(pub Vec<(MapsEntry, Vec<PageMapEntry>)>);
@@ -107,9 +228,129 @@ impl LinuxPageMap {
     }
 }
 
+impl LinuxPageMap {
+    /// Translates multiple virtual addresses to physical addresses.
+    ///
+    /// Addresses are grouped by the VMA containing them, and each group is resolved
+    /// with a single pagemap query spanning from its lowest to its highest page.
+    /// This is considerably faster than calling [`VirtToPhysResolver::get_phys`] for
+    /// each address.
+    ///
+    /// # Returns
+    ///
+    /// The physical addresses in the order of `virts`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the process maps or pagemap fails.
+    pub fn batch_get_phys(&mut self, virts: &[u64]) -> Result<Vec<PhysAddr>, LinuxPageMapError> {
+        let vmas = pagemap2::maps(self.pagemap_wrapper.pid())?
+            .iter()
+            .map(|entry| entry.vma())
+            .collect_vec();
+
+        // group address indices by VMA. Addresses outside of any VMA form their own group.
+        let mut groups: BTreeMap<(u64, u64), Vec<usize>> = BTreeMap::new();
+        for (idx, &virt) in virts.iter().enumerate() {
+            let key = vmas
+                .iter()
+                .find(|vma| vma.contains(virt))
+                .map(|vma| (vma.start_address(), vma.last_address() + 1))
+                .unwrap_or((virt & !0xFFF, (virt & !0xFFF) + PAGE_SIZE as u64));
+            groups.entry(key).or_default().push(idx);
+        }
+
+        let mut phys = vec![PhysAddr::default(); virts.len()];
+        for indices in groups.values_mut() {
+            indices.retain(|&idx| match self.cached_page(virts[idx]) {
+                Some(page) => {
+                    phys[idx] = page + (virts[idx] & 0xFFF) as usize;
+                    false
+                }
+                None => true,
+            });
+            if indices.is_empty() {
+                continue;
+            }
+            let first_page = indices.iter().map(|&i| virts[i] & !0xFFF).min().unwrap();
+            let last_page = indices.iter().map(|&i| virts[i] & !0xFFF).max().unwrap();
+            let region = VirtualMemoryArea::from((first_page, last_page + PAGE_SIZE as u64));
+            let entries = self.pagemap_wrapper.pagemap_vma(&region)?;
+            for &idx in indices.iter() {
+                let virt = virts[idx];
+                let page = ((virt & !0xFFF) - first_page) as usize >> PAGE_SHIFT;
+                let pfn = entries[page].pfn()?;
+                if pfn == 0 {
+                    warn!(
+                        "Got invalid PFN 0 for virtual address 0x{:x}. Are we root?",
+                        virt
+                    );
+                }
+                phys[idx] = PhysAddr(((pfn << PAGE_SHIFT) | (virt & 0xFFF)) as usize);
+                self.cache_page(virt, phys[idx]);
+            }
+        }
+        Ok(phys)
+    }
+
+    /// Translates multiple virtual addresses of the current process to physical addresses.
+    ///
+    /// # Returns
+    ///
+    /// `(virt, phys)` pairs sorted by virtual address
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if opening or reading the pagemap fails.
+    pub fn batch_get_phys_sorted(
+        mut virts: Vec<u64>,
+    ) -> Result<Vec<(u64, PhysAddr)>, LinuxPageMapError> {
+        virts.sort_unstable();
+        let phys = LinuxPageMap::new()?.batch_get_phys(&virts)?;
+        Ok(virts.into_iter().zip(phys).collect())
+    }
+}
+
+impl LinuxPageMap {
+    /// Translates a virtual address to a physical address, if the mapping is known to be valid.
+    ///
+    /// Unlike [`VirtToPhysResolver::get_phys`], this does not return a bogus address when the
+    /// kernel hides the PFN.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the page is not present, or if the PFN reads as zero, which happens when not
+    /// running as root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the pagemap fails.
+    pub fn try_get_phys(&mut self, virt: u64) -> Result<Option<PhysAddr>, LinuxPageMapError> {
+        if let Some(page) = self.cached_page(virt) {
+            return Ok(Some(page + (virt & 0xFFF) as usize));
+        }
+        let vaddr_start_page = virt & !0xFFF;
+        let memory_region = VirtualMemoryArea::from((vaddr_start_page, vaddr_start_page + 4095));
+        let entry = self.pagemap_wrapper.pagemap_vma(&memory_region)?;
+        let Some(entry) = entry.first().filter(|entry| entry.present()) else {
+            return Ok(None);
+        };
+        let pfn = entry.pfn()?;
+        if pfn == 0 {
+            return Ok(None);
+        }
+        let phys_addr = PhysAddr(((pfn << PAGE_SHIFT) | (virt & 0xFFF)) as usize);
+        self.cache_page(virt, phys_addr);
+        Ok(Some(phys_addr))
+    }
+}
+
 impl VirtToPhysResolver for LinuxPageMap {
     type Error = LinuxPageMapError;
     fn get_phys(&mut self, virt: u64) -> Result<PhysAddr, Self::Error> {
+        if let Some(page) = self.cached_page(virt) {
+            return Ok(page + (virt & 0xFFF) as usize);
+        }
         //calc virtual address of page containing ptr_to_start
         let vaddr_start_page = virt & !0xFFF;
         let vaddr_end_page = vaddr_start_page + 4095;
@@ -132,9 +373,10 @@ impl VirtToPhysResolver for LinuxPageMap {
             );
         }
 
-        let phys_addr = ((pfn << PAGE_SHIFT) | (virt & 0xFFF)) as usize;
+        let phys_addr = PhysAddr(((pfn << PAGE_SHIFT) | (virt & 0xFFF)) as usize);
+        self.cache_page(virt, phys_addr);
 
-        Ok(PhysAddr(phys_addr))
+        Ok(phys_addr)
     }
     fn get_phys_range(
         &mut self,
@@ -151,6 +393,51 @@ impl VirtToPhysResolver for LinuxPageMap {
     }
 }
 
+impl From<u64> for PhysAddr {
+    fn from(addr: u64) -> Self {
+        PhysAddr::from_u64(addr)
+    }
+}
+
+/// Serializes a physical address as a zero-padded hex string, e.g., `"0x000000007f000000"`.
+impl serde::Serialize for PhysAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("0x{:016x}", self.0))
+    }
+}
+
+/// Representations accepted when deserializing a [`PhysAddr`].
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum PhysAddrRepr {
+    /// A hex string such as `"0x7f000000"`
+    Hex(String),
+    /// A decimal integer, as serialized by earlier versions
+    Decimal(usize),
+}
+
+/// Deserializes a physical address from a hex string such as `"0x7f000000"` or a decimal
+/// integer.
+impl<'de> serde::Deserialize<'de> for PhysAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        match PhysAddrRepr::deserialize(deserializer).map_err(|_| {
+            D::Error::custom("expected a physical address such as \"0x7f000000\" or an integer")
+        })? {
+            PhysAddrRepr::Hex(s) => {
+                let digits = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .ok_or_else(|| D::Error::custom(format!("missing 0x prefix in {:?}", s)))?;
+                usize::from_str_radix(digits, 16)
+                    .map(PhysAddr)
+                    .map_err(|e| D::Error::custom(format!("invalid hex address {:?}: {}", s, e)))
+            }
+            PhysAddrRepr::Decimal(addr) => Ok(PhysAddr(addr)),
+        }
+    }
+}
+
 impl From<PhysAddr> for usize {
     fn from(addr: PhysAddr) -> usize {
         addr.0
@@ -190,7 +477,7 @@ impl Add<usize> for PhysAddr {
     type Output = PhysAddr;
 
     fn add(self, rhs: usize) -> Self::Output {
-        PhysAddr(self.0 + rhs)
+        self.checked_add(rhs).expect("physical address overflow")
     }
 }
 
@@ -202,3 +489,145 @@ impl Sub<usize> for PhysAddr {
         PhysAddr(self.0 - rhs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{BytePointer, Memory};
+
+    #[test]
+    fn test_batch_get_phys() -> anyhow::Result<()> {
+        let block = Memory::mmap(16 * PAGE_SIZE)?;
+        let stack_var = 0u64;
+        let mut virts = (0..16)
+            .rev()
+            .map(|page| block.addr(page * PAGE_SIZE + page * 8) as u64)
+            .collect_vec();
+        virts.push(block.ptr() as u64);
+        virts.push(&stack_var as *const u64 as u64);
+
+        let mut pagemap = LinuxPageMap::new()?;
+        let single = virts
+            .iter()
+            .map(|&virt| pagemap.get_phys(virt))
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = pagemap.batch_get_phys(&virts)?;
+        assert_eq!(single, batch);
+
+        let sorted = LinuxPageMap::batch_get_phys_sorted(virts.clone())?;
+        assert!(sorted.is_sorted_by_key(|(virt, _)| *virt));
+        for (virt, phys) in sorted {
+            let idx = virts.iter().position(|&v| v == virt).unwrap();
+            assert_eq!(phys, single[idx]);
+        }
+        block.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_phys_addr_arithmetic() {
+        let addr = PhysAddr::new(0x1234_5678);
+        assert_eq!(addr.checked_add(8), Some(PhysAddr(0x1234_5680)));
+        assert_eq!(PhysAddr(usize::MAX - 1).checked_add(2), None);
+        assert_eq!(
+            PhysAddr(usize::MAX - 1).saturating_add(2),
+            PhysAddr(usize::MAX)
+        );
+        assert_eq!(addr.align_down(PAGE_SIZE), PhysAddr(0x1234_5000));
+        assert_eq!(addr.align_down(1 << 20), PhysAddr(0x1230_0000));
+        assert_eq!(addr.page_offset(), 0x678);
+        assert_eq!(addr.align_down(PAGE_SIZE) + addr.page_offset(), addr);
+    }
+
+    #[test]
+    fn test_phys_addr_serde() {
+        let addr = PhysAddr::from(0x7f00_0000u64);
+        assert_eq!(addr, PhysAddr::from_u64(0x7f00_0000));
+        assert_eq!(
+            serde_json::to_string(&addr).unwrap(),
+            "\"0x000000007f000000\""
+        );
+        let parse = |json: &str| serde_json::from_str::<PhysAddr>(json);
+        assert_eq!(parse("\"0x000000007f000000\"").unwrap(), addr);
+        assert_eq!(parse("\"0x7F000000\"").unwrap(), addr);
+        assert_eq!(parse("2130706432").unwrap(), addr);
+        assert!(parse("\"7f000000\"").is_err());
+        assert!(parse("\"0xzz\"").is_err());
+        assert!(parse("-1").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "physical address overflow")]
+    fn test_phys_addr_add_overflow() {
+        let _ = PhysAddr(usize::MAX) + 1;
+    }
+
+    #[test]
+    fn test_page_cache_lru() {
+        let mut cache = PageCache::new(2);
+        cache.insert(0x1000, PhysAddr(0xA000));
+        cache.insert(0x2000, PhysAddr(0xB000));
+        assert_eq!(cache.get(0x1000), Some(PhysAddr(0xA000)));
+        // 0x2000 is the least recently used page
+        cache.insert(0x3000, PhysAddr(0xC000));
+        assert_eq!(cache.get(0x2000), None);
+        assert_eq!(cache.get(0x1000), Some(PhysAddr(0xA000)));
+        assert_eq!(cache.get(0x3000), Some(PhysAddr(0xC000)));
+        cache.remove(0x3000);
+        assert_eq!(cache.get(0x3000), None);
+        assert_eq!(cache.pages.len(), cache.uses.len());
+        let mut disabled = PageCache::new(0);
+        disabled.insert(0x1000, PhysAddr(0xA000));
+        assert_eq!(disabled.get(0x1000), None);
+    }
+
+    #[test]
+    fn test_with_cache() -> anyhow::Result<()> {
+        let block = Memory::mmap(4 * PAGE_SIZE)?;
+        let virts = (0..4)
+            .map(|page| block.addr(page * PAGE_SIZE + 42) as u64)
+            .collect_vec();
+        let expected = LinuxPageMap::new()?.batch_get_phys(&virts)?;
+        let mut pagemap = LinuxPageMap::with_cache(16)?;
+        assert_eq!(pagemap.batch_get_phys(&virts)?, expected);
+        assert_eq!(pagemap.get_phys(virts[1])?, expected[1]);
+        // cached pages are served without querying the pagemap
+        if let Some(cache) = &mut pagemap.cache {
+            cache.insert(virts[2] & !0xFFF, PhysAddr(0x1234_5000));
+        }
+        assert_eq!(pagemap.get_phys(virts[2])?, PhysAddr(0x1234_5000 + 42));
+        pagemap.invalidate_page(virts[2]);
+        assert_eq!(pagemap.get_phys(virts[2])?, expected[2]);
+        block.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_get_phys() -> anyhow::Result<()> {
+        let block = Memory::mmap(PAGE_SIZE)?;
+        let mut pagemap = LinuxPageMap::new()?;
+        let virt = block.addr(42) as u64;
+        match pagemap.try_get_phys(virt)? {
+            Some(phys) => assert_eq!(phys, pagemap.get_phys(virt)?),
+            // not running as root
+            None => assert_eq!(pagemap.get_phys(virt)?.as_usize(), 42),
+        }
+        block.dealloc();
+
+        // reserved, but never touched
+        let untouched = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                PAGE_SIZE,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(untouched, libc::MAP_FAILED);
+        assert_eq!(pagemap.try_get_phys(untouched as u64)?, None);
+        unsafe { libc::munmap(untouched, PAGE_SIZE) };
+        Ok(())
+    }
+}
@@ -76,9 +76,9 @@ impl PageInjector<Child> for BuddyPageInjector {
     fn inject(&mut self) -> Result<Child, Self::Error> {
         let target_page = (self.injection_config.target_addr & !PAGE_MASK) as *mut libc::c_void;
         debug!(
-            "Injecting target page {:p}, phys {:p}, into victim process {}",
+            "Injecting target page {:p}, phys {:?}, into victim process {}",
             target_page,
-            target_page.pfn().unwrap_or_default(),
+            target_page.pfn().ok().flatten(),
             self.cmd.as_ref().unwrap().get_program().to_str().unwrap()
         );
         let bait: *mut libc::c_void = if self.injection_config.bait_count_before
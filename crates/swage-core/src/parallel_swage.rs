@@ -0,0 +1,134 @@
+use crate::Swage;
+use crate::allocator::{AllocatorPool, ConsecAllocator};
+use crate::hammerer::Hammering;
+use log::info;
+use std::sync::Arc;
+
+/// Runs several [`Swage`] experiments concurrently, sharing one [`AllocatorPool`].
+///
+/// Each worker thread constructs its own [`Swage`] instance with the `factory`, passing a
+/// handle to the shared pool to be used as allocator, and runs it to completion.
+pub struct ParallelSwage<A: ConsecAllocator + Send, F> {
+    pool: Arc<AllocatorPool<A>>,
+    workers: usize,
+    factory: F,
+}
+
+impl<A: ConsecAllocator + Send + 'static, F> ParallelSwage<A, F> {
+    /// Creates a new parallel experiment with `workers` threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The allocators shared by all workers
+    /// * `workers` - The number of concurrently running [`Swage`] instances
+    /// * `factory` - Constructs the [`Swage`] instance of a worker from the pool handle
+    pub fn new(pool: AllocatorPool<A>, workers: usize, factory: F) -> Self {
+        ParallelSwage {
+            pool: Arc::new(pool),
+            workers,
+            factory,
+        }
+    }
+
+    /// Runs all workers and waits for their completion.
+    ///
+    /// Returns the serialized experiments of each worker, in worker order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a worker thread panics.
+    pub fn run<PH, H, VE>(self) -> Vec<serde_json::Value>
+    where
+        F: Fn(Arc<AllocatorPool<A>>) -> Swage<PH, H, A::Error, VE> + Sync,
+        PH: Hammering,
+        H: Hammering,
+        VE: std::error::Error,
+    {
+        std::thread::scope(|s| {
+            let handles = (0..self.workers)
+                .map(|worker| {
+                    let pool = self.pool.clone();
+                    let factory = &self.factory;
+                    s.spawn(move || {
+                        info!("Starting worker {}", worker);
+                        let experiments = factory(pool).run();
+                        info!("Worker {} finished", worker);
+                        serde_json::to_value(&experiments).expect("serialize experiments")
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("worker panicked"))
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{ConsecBlocks, Memory};
+    use crate::util::{PAGE_SIZE, Size};
+    use crate::{MemCheck, SwageConfig};
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct MmapAllocator(Arc<AtomicU64>);
+
+    impl ConsecAllocator for MmapAllocator {
+        type Error = std::io::Error;
+        fn block_size(&self) -> Size {
+            Size::B(PAGE_SIZE)
+        }
+        fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(ConsecBlocks::new(vec![Memory::mmap(size.bytes())?]))
+        }
+    }
+
+    struct NopHammerer;
+
+    impl Hammering for NopHammerer {
+        type Error = Infallible;
+        fn hammer(&self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parallel_swage() {
+        let counters = (0..2)
+            .map(|_| Arc::new(AtomicU64::new(0)))
+            .collect::<Vec<_>>();
+        let pool = AllocatorPool::new(counters.iter().map(|c| MmapAllocator(c.clone())).collect());
+        let parallel = ParallelSwage::new(pool, 4, |pool| {
+            Swage::<_, _, std::io::Error, std::io::Error>::builder()
+                .allocator(pool)
+                .profile_hammerer_factory(|_| NopHammerer)
+                .victim_factory(|memory, profile| {
+                    Ok(Box::new(MemCheck::new(
+                        ConsecBlocks::clone(&memory),
+                        profile.pattern,
+                        vec![].into(),
+                    )))
+                })
+                .pattern_size(PAGE_SIZE)
+                .config(SwageConfig {
+                    profiling_rounds: 1,
+                    repetitions: Some(3),
+                    ..Default::default()
+                })
+                .build()
+                .expect("invalid config")
+        });
+        let results = parallel.run();
+        assert_eq!(results.len(), 4);
+        assert!(
+            results
+                .iter()
+                .all(|experiments| experiments.as_array().unwrap().len() == 3)
+        );
+        assert!(counters.iter().all(|c| c.load(Ordering::Relaxed) == 6));
+    }
+}
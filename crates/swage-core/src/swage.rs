@@ -1,20 +1,30 @@
 use crate::MemCheck;
 use crate::allocator::{ConsecAllocator, alloc_memory};
 use crate::hammerer::Hammering;
-use crate::memory::{BitFlip, BytePointer, ConsecBlocks, DataPattern, Initializable};
-use crate::util::{NamedProgress, PAGE_MASK, Rng, Size};
+use crate::memory::{
+    ArcConsecBlocks, BitFlip, BitFlipStats, BytePointer, ConsecBlocks, DataPattern, FlipDirection,
+    FlipMap, Initializable, MemRegionAnnotation,
+};
+use crate::util::{ExperimentTimer, NamedProgress, PAGE_MASK, Rng, Size};
 use crate::victim::{HammerVictimError, VictimOrchestrator, VictimResult};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::{debug, info, warn};
-use serde::{Serialize, Serializer};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_with::{DurationSeconds, serde_as};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub type ProfileHammererFactory<H> = Box<dyn Fn(ConsecBlocks) -> H>;
 pub type HammererFactory<H1, H2> = Box<dyn Fn(H1, ConsecBlocks, RoundProfile) -> H2>;
 pub type VictimFactory<E> =
-    Box<dyn Fn(ConsecBlocks, RoundProfile) -> Result<Box<dyn VictimOrchestrator>, E>>;
+    Box<dyn Fn(ArcConsecBlocks, RoundProfile) -> Result<Box<dyn VictimOrchestrator>, E>>;
+pub type RoundCallback<AE, HE, VE> = Box<dyn Fn(&RoundResult<AE, HE, VE>)>;
+/// Results of a single round of [`Swage::run`].
+pub type RoundResult<AE, HE, VE> = ExperimentData<VictimResult, HammerError<AE, HE, VE>>;
 
 /// Main orchestrator for conducting end-to-end Rowhammer experiments.
 ///
@@ -40,12 +50,12 @@ pub type VictimFactory<E> =
 pub struct Swage<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error> {
     allocator: Box<dyn ConsecAllocator<Error = AE>>,
     profile_hammerer_factory: ProfileHammererFactory<PH>,
-    profile_data_pattern: DataPatternKind,
     hammerer_factory: HammererFactory<PH, H>,
     victim_factory: VictimFactory<VE>,
     pattern_size: usize,
     progress: Option<MultiProgress>,
     config: SwageConfig,
+    on_round_complete: Option<RoundCallback<AE, H::Error, VE>>,
 }
 
 /// Profiling results from a series of hammering rounds.
@@ -60,21 +70,122 @@ pub struct RoundProfile {
     pub pattern: DataPattern,
 }
 
+impl RoundProfile {
+    /// Returns a copy of this profile keeping only the bit flips in direction `dir`.
+    ///
+    /// Flips of multiple bits match a direction if all bits flipped in that direction.
+    /// [`FlipDirection::Any`] keeps all bit flips.
+    pub fn filter_by_direction(&self, dir: &FlipDirection) -> RoundProfile {
+        RoundProfile {
+            bit_flips: self
+                .bit_flips
+                .iter()
+                .filter(|flip| has_direction(flip, dir))
+                .copied()
+                .collect(),
+            pattern: self.pattern.clone(),
+        }
+    }
+
+    /// Splits this profile into the bit flips from 0 to 1 and those from 1 to 0.
+    ///
+    /// Flips of multiple bits in different directions are in neither profile.
+    pub fn partition_by_direction(&self) -> (RoundProfile, RoundProfile) {
+        (
+            self.filter_by_direction(&FlipDirection::ZeroToOne),
+            self.filter_by_direction(&FlipDirection::OneToZero),
+        )
+    }
+}
+
+/// Returns true if `flip` flipped in direction `dir`, see [`RoundProfile::filter_by_direction`].
+fn has_direction(flip: &BitFlip, dir: &FlipDirection) -> bool {
+    match (flip.flip_direction(), dir) {
+        (_, FlipDirection::Any) => true,
+        (FlipDirection::Multiple(dirs), FlipDirection::ZeroToOne | FlipDirection::OneToZero) => {
+            dirs.iter().all(|d| d == dir)
+        }
+        (actual, dir) => actual == *dir,
+    }
+}
+
 /// Configuration parameters for Swage experiments.
 ///
 /// Controls profiling behavior, reproducibility requirements, and execution timeouts.
+///
+/// The configuration can be loaded from a TOML file using [`SwageConfig::from_toml`].
+/// Timeouts are given in seconds and missing keys take their default value:
+///
+/// ```toml
+/// profiling_rounds = 10
+/// reproducibility_threshold = 0.8
+/// profile_data_patterns = ["Random", { XOR = { period = 8192 } }]
+/// hammering_timeout_secs = 300
+/// repetitions = 1
+/// ```
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct SwageConfig {
     /// Number of profiling rounds to identify vulnerable bit flips
     pub profiling_rounds: u64,
     /// Minimum fraction of rounds a bit flip must appear during profiling to be considered reproducible (0.0-1.0)
     pub reproducibility_threshold: f64,
+    /// Data patterns to profile with, each for `profiling_rounds` rounds. The pattern
+    /// yielding the most bit flips is used for the attack.
+    pub profile_data_patterns: Vec<DataPatternKind>,
 
     /// Timeout for total hammering operation (None = unlimited)
+    #[serde(
+        rename = "hammering_timeout_secs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
     pub hammering_timeout: Option<Duration>,
     /// Number of times to repeat the attack (None = unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub repetitions: Option<u64>,
     /// Overall experiment timeout (None = no timeout)
+    #[serde(rename = "timeout_secs", skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
     pub timeout: Option<Duration>,
+    /// Flag to stop the experiment gracefully, e.g., from [`setup_signal_handler`](crate::util::setup_signal_handler)
+    #[serde(skip)]
+    pub stop_flag: Option<Arc<AtomicBool>>,
+}
+
+/// Errors that can occur when loading a [`SwageConfig`].
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The configuration file is not a valid TOML configuration.
+    #[error(transparent)]
+    Parse(#[from] toml::de::Error),
+}
+
+impl SwageConfig {
+    /// Loads the configuration from the TOML file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] if the file cannot be read or parsed.
+    pub fn from_toml(path: &str) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Serializes the configuration to TOML, e.g., for saving it alongside the results.
+    ///
+    /// The stop flag is not serialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`toml::ser::Error`] if the configuration cannot be represented in TOML.
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
 }
 
 impl Default for SwageConfig {
@@ -82,9 +193,11 @@ impl Default for SwageConfig {
         Self {
             profiling_rounds: 10,
             reproducibility_threshold: 0.8,
+            profile_data_patterns: vec![DataPatternKind::Random],
             hammering_timeout: None,
             repetitions: Some(1),
             timeout: None,
+            stop_flag: None,
         }
     }
 }
@@ -105,8 +218,13 @@ pub struct ExperimentData<T, E> {
     results: Vec<std::result::Result<T, E>>,
     /// Profiling data from the experiment
     profiling: RoundProfile,
-    /// Additional JSON metadata (implementation-specific)
+    /// Additional JSON metadata (victim-specific data and phase timings)
     data: Option<serde_json::Value>,
+    /// Wall time of the experiment
+    duration: Duration,
+    /// Serialized [`MemRegionAnnotation`]s added with [`ExperimentData::annotate`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<serde_json::Value>,
 }
 
 impl<T, E> ExperimentData<T, E> {
@@ -114,13 +232,235 @@ impl<T, E> ExperimentData<T, E> {
         results: Vec<std::result::Result<T, E>>,
         profiling: RoundProfile,
         data: Option<serde_json::Value>,
+        duration: Duration,
     ) -> Self {
         Self {
             date: chrono::Local::now().to_rfc3339(),
             results,
             profiling,
             data,
+            duration,
+            annotations: vec![],
+        }
+    }
+
+    /// Returns the wall time of the experiment.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Adds `annotation` to the serialized experiment data.
+    ///
+    /// The annotation is serialized immediately, so later changes to it are not recorded.
+    pub fn annotate(&mut self, annotation: &MemRegionAnnotation) {
+        self.annotations
+            .push(serde_json::to_value(annotation).expect("serialize annotation"));
+    }
+}
+
+impl<E: Display> ExperimentData<VictimResult, E> {
+    /// Returns the number of rounds that completed without error.
+    pub fn successful_rounds(&self) -> usize {
+        self.results.iter().filter(|result| result.is_ok()).count()
+    }
+
+    /// Returns the number of rounds that failed with an error.
+    pub fn failed_rounds(&self) -> usize {
+        self.results.len() - self.successful_rounds()
+    }
+
+    /// Returns the fraction of successful rounds, or 0.0 if there are no rounds.
+    pub fn success_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        self.successful_rounds() as f64 / self.results.len() as f64
+    }
+
+    /// Returns the bit flips observed in all rounds.
+    pub fn all_bit_flips(&self) -> Vec<&BitFlip> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                Ok(VictimResult::BitFlips(flips)) => Some(flips),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Summarizes the bit flips observed in all rounds.
+    pub fn flip_stats(&self) -> BitFlipStats {
+        let flips = self
+            .all_bit_flips()
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        BitFlipStats::from_flips(&flips)
+    }
+
+    /// Returns the addresses of all observed bit flips.
+    pub fn unique_flip_addresses(&self) -> HashSet<usize> {
+        self.all_bit_flips()
+            .into_iter()
+            .map(|flip| flip.addr)
+            .collect()
+    }
+
+    /// Returns the error messages of all failed rounds.
+    pub fn error_messages(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    /// Returns true if at least one round observed bit flips.
+    pub fn is_successful(&self) -> bool {
+        self.results
+            .iter()
+            .any(|result| matches!(result, Ok(VictimResult::BitFlips(flips)) if !flips.is_empty()))
+    }
+
+    /// Returns the results of all successful rounds.
+    pub fn filter_successful_results(&self) -> Vec<&VictimResult> {
+        self.results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .collect()
+    }
+}
+
+impl<E: Display> Display for ExperimentData<VictimResult, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} rounds successful ({:.1}%), {} bit flips at {} addresses",
+            self.date,
+            self.successful_rounds(),
+            self.results.len(),
+            100.0 * self.success_rate(),
+            self.all_bit_flips().len(),
+            self.unique_flip_addresses().len()
+        )
+    }
+}
+
+/// Summary statistics over the experiments returned by [`Swage::run`].
+///
+/// A round is a single attack result of an [`ExperimentData`], i.e., one hammering and
+/// checking iteration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SwageStatistics {
+    /// Number of rounds in all experiments
+    pub total_rounds: u32,
+    /// Number of rounds that completed without error
+    pub successful_rounds: u32,
+    /// Number of rounds that failed with an error other than [`HammerVictimError::NoFlips`]
+    pub failed_rounds: u32,
+    /// Number of rounds that failed with [`HammerVictimError::NoFlips`]
+    pub no_flip_rounds: u32,
+    /// Number of bit flips observed in all rounds
+    pub total_bit_flips: usize,
+    /// Number of distinct addresses of the observed bit flips
+    pub unique_addresses: usize,
+    /// Average number of bit flips per round
+    pub flip_rate_per_round: f64,
+    /// Number of reproducible bit flips found during profiling
+    pub profiling_bit_flip_count: usize,
+    /// Sum of the wall times of all experiments
+    pub total_wall_time: Duration,
+}
+
+impl SwageStatistics {
+    /// Computes the statistics of `experiments`.
+    pub fn from_experiments<AE: std::error::Error, HE: std::error::Error, VE: std::error::Error>(
+        experiments: &[RoundResult<AE, HE, VE>],
+    ) -> SwageStatistics {
+        let mut stats = SwageStatistics::default();
+        let mut addresses = HashSet::new();
+        for experiment in experiments {
+            for result in &experiment.results {
+                stats.total_rounds += 1;
+                match result {
+                    Ok(_) => stats.successful_rounds += 1,
+                    Err(HammerError::VictimError(HammerVictimError::NoFlips)) => {
+                        stats.no_flip_rounds += 1
+                    }
+                    Err(_) => stats.failed_rounds += 1,
+                }
+            }
+            stats.total_bit_flips += experiment.all_bit_flips().len();
+            addresses.extend(experiment.unique_flip_addresses());
+            stats.profiling_bit_flip_count += experiment.profiling.bit_flips.len();
+            stats.total_wall_time += experiment.duration;
+        }
+        stats.unique_addresses = addresses.len();
+        stats.flip_rate_per_round = stats.flip_rate();
+        stats
+    }
+
+    /// Aggregates the statistics of several runs, e.g., on different machines.
+    ///
+    /// Flip addresses are not comparable across machines, so `unique_addresses` is the sum
+    /// of the individual counts.
+    pub fn combined(stats: &[SwageStatistics]) -> SwageStatistics {
+        let mut combined =
+            stats
+                .iter()
+                .fold(SwageStatistics::default(), |acc, stats| SwageStatistics {
+                    total_rounds: acc.total_rounds + stats.total_rounds,
+                    successful_rounds: acc.successful_rounds + stats.successful_rounds,
+                    failed_rounds: acc.failed_rounds + stats.failed_rounds,
+                    no_flip_rounds: acc.no_flip_rounds + stats.no_flip_rounds,
+                    total_bit_flips: acc.total_bit_flips + stats.total_bit_flips,
+                    unique_addresses: acc.unique_addresses + stats.unique_addresses,
+                    flip_rate_per_round: 0.0,
+                    profiling_bit_flip_count: acc.profiling_bit_flip_count
+                        + stats.profiling_bit_flip_count,
+                    total_wall_time: acc.total_wall_time + stats.total_wall_time,
+                });
+        combined.flip_rate_per_round = combined.flip_rate();
+        combined
+    }
+
+    /// Returns true if at least one bit flip was observed.
+    pub fn is_attack_successful(&self) -> bool {
+        self.total_bit_flips > 0
+    }
+
+    fn flip_rate(&self) -> f64 {
+        if self.total_rounds == 0 {
+            return 0.0;
         }
+        self.total_bit_flips as f64 / self.total_rounds as f64
+    }
+}
+
+impl Display for SwageStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows: [(&str, String); 9] = [
+            ("Total rounds", self.total_rounds.to_string()),
+            ("Successful rounds", self.successful_rounds.to_string()),
+            ("Failed rounds", self.failed_rounds.to_string()),
+            ("Rounds without flips", self.no_flip_rounds.to_string()),
+            ("Bit flips", self.total_bit_flips.to_string()),
+            ("Unique flip addresses", self.unique_addresses.to_string()),
+            (
+                "Flips per round",
+                format!("{:.2}", self.flip_rate_per_round),
+            ),
+            (
+                "Profiling bit flips",
+                self.profiling_bit_flip_count.to_string(),
+            ),
+            ("Wall time", format!("{:.1?}", self.total_wall_time)),
+        ];
+        for (name, value) in rows {
+            writeln!(f, "{:<24}{:>12}", name, value)?;
+        }
+        Ok(())
     }
 }
 
@@ -167,11 +507,15 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         &mut self,
         start: Instant,
         hammering_time: &mut Duration,
-    ) -> ExperimentData<VictimResult, HammerError<AE, H::Error, VE>> {
+    ) -> RoundResult<AE, H::Error, VE> {
+        let mut timer = ExperimentTimer::new();
         info!("Starting bait allocation");
         //unsafe { shm_unlink(CString::new("HAMMER_SHM").unwrap().as_ptr()) };
-        let memory = match alloc_memory(self.allocator.as_mut(), Size::B(self.pattern_size)) {
-            Ok(memory) => memory,
+        let phase = timer.start_phase("allocation");
+        let memory = alloc_memory(self.allocator.as_mut(), Size::B(self.pattern_size));
+        drop(phase);
+        let memory = match memory {
+            Ok(memory) => memory.into_arc(),
             Err(e) => {
                 warn!("Failed to allocate memory: {}", e);
                 return ExperimentData::new(
@@ -180,7 +524,8 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
                         bit_flips: vec![],
                         pattern: DataPattern::Random(Box::new(Rng::from_seed(rand::random()))),
                     },
-                    None,
+                    round_data(None, &timer),
+                    timer.elapsed(),
                 );
             }
         };
@@ -188,31 +533,35 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
 
         info!("Profiling memory for vulnerable addresses");
 
-        let hammerer = (self.profile_hammerer_factory)(memory.clone());
+        let hammerer = (self.profile_hammerer_factory)(ConsecBlocks::clone(&memory));
 
+        let phase = timer.start_phase("profiling");
         let profiling = hammer_profile(
             &hammerer,
-            memory.clone(),
-            self.profile_data_pattern,
+            ConsecBlocks::clone(&memory),
+            &self.config.profile_data_patterns,
             self.config.profiling_rounds,
             self.config.reproducibility_threshold,
             self.progress.clone(),
         );
+        drop(phase);
         debug!("Profiling results: {:?}", profiling);
         if profiling.bit_flips.is_empty() {
             warn!("No vulnerable addresses found");
-            memory.dealloc();
+            release_memory(memory);
             return ExperimentData::new(
                 vec![Err(HammerError::NoVulnerableCells)],
                 profiling.clone(),
-                None,
+                round_data(None, &timer),
+                timer.elapsed(),
             );
         }
 
         let flips = profiling.bit_flips.clone();
         let dpattern = profiling.pattern.clone();
 
-        let hammerer = (self.hammerer_factory)(hammerer, memory.clone(), profiling.clone());
+        let hammerer =
+            (self.hammerer_factory)(hammerer, ConsecBlocks::clone(&memory), profiling.clone());
 
         let mut victim = match (self.victim_factory)(memory.clone(), profiling.clone()) {
             Ok(v) => v,
@@ -220,21 +569,28 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
                 return ExperimentData::new(
                     vec![Err(HammerError::VictimFailed(e))],
                     profiling,
-                    None,
+                    round_data(None, &timer),
+                    timer.elapsed(),
                 );
             }
         };
 
-        match victim.as_mut().start() {
+        let phase = timer.start_phase("victim_start");
+        let started = victim.as_mut().start();
+        drop(phase);
+        match started {
             Ok(_) => {}
             Err(e) => {
                 warn!("Failed to start victim: {:?}", e);
                 victim.stop();
-                memory.dealloc();
+                let data = round_data(victim.serialize(), &timer);
+                drop(victim);
+                release_memory(memory);
                 return ExperimentData::new(
                     vec![Err(HammerError::VictimError(e))],
                     profiling.clone(),
-                    victim.serialize(),
+                    data,
+                    timer.elapsed(),
                 );
             }
         }
@@ -254,7 +610,11 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         };
 
         let mut results: Vec<Result<VictimResult, HammerError<AE, H::Error, VE>>> = vec![];
-        loop {
+        for iteration in 0.. {
+            if self.is_stopping() {
+                info!("Stop requested. Stopping.");
+                break;
+            }
             if check_timeout(self.config.timeout, Instant::now() - start) {
                 info!("Timeout reached. Stopping.");
                 break;
@@ -266,16 +626,30 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
             if let Some(hammer_progress) = &hammer_progress {
                 hammer_progress.set_position(hammering_time.as_secs());
             }
+            if iteration > 0 {
+                let phase = timer.start_phase("victim_reset");
+                let reset = victim.reset();
+                drop(phase);
+                if let Err(e) = reset {
+                    warn!("Failed to reset victim: {:?}", e);
+                    results.push(Err(HammerError::VictimError(e)));
+                    break;
+                }
+            }
             memory.initialize_excluding(dpattern.clone(), &flip_pages); // TODO maybe remove this?
             victim.init();
             let hammer_start = Instant::now();
+            let phase = timer.start_phase("hammering");
             let result = hammerer.hammer();
+            drop(phase);
             *hammering_time += Instant::now().duration_since(hammer_start);
             match result {
                 Ok(_) => {}
                 Err(err) => results.push(Err(HammerError::HammeringFailed(err))),
             };
+            let phase = timer.start_phase("check");
             let result = victim.check();
+            drop(phase);
             match result {
                 Ok(result) => {
                     info!("Hammering successful: {:?}", result);
@@ -297,14 +671,16 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
             }
         }
         victim.stop();
-        memory.dealloc();
-        ExperimentData::new(results, profiling.clone(), victim.serialize())
+        let data = round_data(victim.serialize(), &timer);
+        drop(victim);
+        release_memory(memory);
+        ExperimentData::new(results, profiling.clone(), data, timer.elapsed())
     }
 
     /// Start the attack.
     ///
     /// Returns a vector of ExperimentData with VictimResults and possible Error observed.
-    pub fn run(mut self) -> Vec<ExperimentData<VictimResult, HammerError<AE, H::Error, VE>>> {
+    pub fn run(mut self) -> Vec<RoundResult<AE, H::Error, VE>> {
         let mut experiments = vec![];
 
         let repetitions = self.config.repetitions;
@@ -339,6 +715,10 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
             if let Some(timeout_progress) = &timeout_progress {
                 timeout_progress.set_position((Instant::now() - start).as_secs());
             }
+            if self.is_stopping() {
+                info!("Stop requested. Stopping.");
+                break;
+            }
             if rep > 0 && check_timeout(timeout, Instant::now() - start) {
                 info!("Timeout reached. Stopping.");
                 break;
@@ -353,25 +733,136 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
                     (hammering_timeout - hammering_time).as_secs() / 60,
                 );
             }
-            experiments.push(self.round(start, &mut hammering_time));
+            let experiment = self.round(start, &mut hammering_time);
+            if let Some(on_round_complete) = &self.on_round_complete {
+                on_round_complete(&experiment);
+            }
+            experiments.push(experiment);
         }
         experiments
     }
+
+    /// Summarizes the experiments returned by [`Swage::run`].
+    ///
+    /// See [`SwageStatistics::from_experiments`].
+    pub fn statistics(results: &[RoundResult<AE, H::Error, VE>]) -> SwageStatistics {
+        SwageStatistics::from_experiments(results)
+    }
+
+    /// Returns true if a stop was requested via [`SwageConfig::stop_flag`].
+    pub fn is_stopping(&self) -> bool {
+        self.config
+            .stop_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
+
+impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error> Drop
+    for Swage<PH, H, AE, VE>
+{
+    fn drop(&mut self) {
+        if self.is_stopping() {
+            info!("Experiment stopped by signal");
+        }
+    }
+}
+
+/// Deallocates the round memory if no victim holds a reference anymore.
+///
+/// Memory that is still referenced is leaked instead of unmapped under a live reference.
+fn release_memory(memory: ArcConsecBlocks) {
+    match memory.try_unwrap() {
+        Ok(memory) => memory.dealloc(),
+        Err(_) => warn!("Memory is still referenced after the round, not deallocating"),
+    }
+}
+
+/// Combines victim data and phase timings of a round into the [`ExperimentData::data`] value.
+fn round_data(
+    victim: Option<serde_json::Value>,
+    timer: &ExperimentTimer,
+) -> Option<serde_json::Value> {
+    Some(serde_json::json!({
+        "victim": victim,
+        "timing": timer.summary(),
+    }))
 }
 
 fn check_timeout(timeout: Option<Duration>, duration: Duration) -> bool {
     timeout.is_some_and(|timeout| duration > timeout)
 }
 
-/// Hammer a given `memory` region `num_rounds` times to profile for vulnerable addresses.
+/// Hammer a given `memory` region `num_rounds` times for each of `patterns` to profile for
+/// vulnerable addresses.
+///
+/// Returns the profile of the pattern yielding the most bit flips, preferring earlier
+/// patterns on ties. If no pattern yields reproducible bit flips, the candidates observed
+/// with any pattern before applying `reproducibility_threshold` are merged into a profile
+/// using the first pattern.
 fn hammer_profile<E: std::error::Error>(
     hammerer: &dyn Hammering<Error = E>,
     memory: ConsecBlocks,
-    pattern: DataPatternKind,
+    patterns: &[DataPatternKind],
     num_rounds: u64,
     reproducibility_threshold: f64,
     progress: Option<MultiProgress>,
 ) -> RoundProfile {
+    let mut best: Option<RoundProfile> = None;
+    let mut observed = FlipMap::default();
+    let mut first_pattern = None;
+    for &pattern in patterns {
+        let (profile, candidates) = hammer_profile_pattern(
+            hammerer,
+            memory.clone(),
+            pattern,
+            num_rounds,
+            reproducibility_threshold,
+            progress.clone(),
+        );
+        info!(
+            "Profiling with pattern {:?} found {} bit flips",
+            pattern,
+            profile.bit_flips.len()
+        );
+        first_pattern.get_or_insert_with(|| profile.pattern.clone());
+        for (key, (count, flip)) in candidates.0 {
+            observed.0.entry(key).or_insert((0, flip)).0 += count;
+        }
+        if best
+            .as_ref()
+            .is_none_or(|best| profile.bit_flips.len() > best.bit_flips.len())
+        {
+            best = Some(profile);
+        }
+    }
+    let best = best.expect("no profile data patterns");
+    if !best.bit_flips.is_empty() {
+        return best;
+    }
+    info!(
+        "No pattern found reproducible bit flips, merging {} candidates",
+        observed.0.len()
+    );
+    RoundProfile {
+        bit_flips: observed.0.into_values().map(|(_, flip)| flip).collect(),
+        pattern: first_pattern.expect("no profile data patterns"),
+    }
+}
+
+/// Hammer a given `memory` region `num_rounds` times with `pattern` to profile for
+/// vulnerable addresses.
+///
+/// Returns the profile of bit flips meeting `reproducibility_threshold` along with all
+/// candidates observed before applying the threshold.
+fn hammer_profile_pattern<E: std::error::Error>(
+    hammerer: &dyn Hammering<Error = E>,
+    memory: ConsecBlocks,
+    pattern: DataPatternKind,
+    num_rounds: u64,
+    reproducibility_threshold: f64,
+    progress: Option<MultiProgress>,
+) -> (RoundProfile, FlipMap) {
     let p = progress.as_ref().map(|p| {
         let p = p.add(ProgressBar::new(num_rounds));
         p.set_style(ProgressStyle::named_bar("Profiling round"));
@@ -380,18 +871,29 @@ fn hammer_profile<E: std::error::Error>(
     });
 
     const _SHM_SEED: u64 = 9804201662804659191;
-    let mut candidates = HashMap::new();
+    let mut candidates = FlipMap::default();
+    let mut observed = FlipMap::default();
     let min_repro_count = (reproducibility_threshold * num_rounds as f64) as u64;
     let pattern = match pattern {
         DataPatternKind::Random => DataPattern::Random(Box::new(Rng::from_seed(rand::random()))),
         DataPatternKind::One => DataPattern::One,
         DataPatternKind::Zero => DataPattern::Zero,
+        DataPatternKind::XOR { period } => DataPattern::XOR {
+            base: 0x00,
+            xor_period: period,
+        },
+        DataPatternKind::RowStripe(pitch) => DataPattern::RowStripe {
+            pitch,
+            aggressor_value: 0x00,
+            victim_value: 0xFF,
+        },
+        DataPatternKind::Checkerboard => DataPattern::Checkerboard { invert: false },
     };
     for r in 1..=num_rounds {
         if let Some(p) = p.as_ref() {
             p.set_position(r);
         }
-        if candidates.is_empty() && r > num_rounds - min_repro_count {
+        if candidates.0.is_empty() && r > num_rounds - min_repro_count {
             warn!(
                 "No candidates and only {} round(s) left. Stopping profiling, continuing with next pattern",
                 num_rounds - r
@@ -414,29 +916,30 @@ fn hammer_profile<E: std::error::Error>(
                         vec![]
                     }
                 };
-                for flip in bit_flips {
-                    let entry = candidates.entry(flip).or_insert(0);
-                    *entry += 1;
-                }
+                observed.observe_keyed(bit_flips.iter().copied());
+                candidates.observe_keyed(bit_flips);
             }
             Err(e) => {
                 warn!("Profiling hammering round not successful: {:?}", e);
             }
         }
         let remaining_rounds = num_rounds - r;
-        candidates.retain(|_, v| *v + remaining_rounds >= min_repro_count);
+        candidates
+            .0
+            .retain(|_, (count, _)| *count + remaining_rounds >= min_repro_count);
         info!("Profiling round {} candidates: {:?}", r, candidates);
     }
-    RoundProfile {
-        bit_flips: candidates.keys().cloned().collect(),
+    let profile = RoundProfile {
+        bit_flips: candidates.0.values().map(|&(_, flip)| flip).collect(),
         pattern,
-    }
+    };
+    (profile, observed)
 }
 
 /// Data pattern selection for configuration.
 ///
 /// Used to specify which type of data pattern to use in the aggressors.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DataPatternKind {
     /// Random data pattern
     Random,
@@ -444,17 +947,27 @@ pub enum DataPatternKind {
     Zero,
     /// All ones (0xFF)
     One,
+    /// Alternating 0x00 and 0xFF every `period` bytes, see [`DataPattern::XOR`]
+    XOR {
+        /// The period length in bytes
+        period: usize,
+    },
+    /// 0x00 every `pitch`-th row and 0xFF elsewhere, see [`DataPattern::RowStripe`]
+    RowStripe(usize),
+    /// Alternating 0x55 and 0xAA every cache line, see [`DataPattern::Checkerboard`]
+    Checkerboard,
 }
 
 pub struct SwageBuilder<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error> {
     allocator: Option<Box<dyn ConsecAllocator<Error = AE>>>,
     profile_hammerer_factory: Option<ProfileHammererFactory<PH>>,
-    profile_data_pattern: DataPatternKind,
     hammerer_factory: HammererFactory<PH, H>,
     victim_factory: Option<VictimFactory<VE>>,
     pattern_size: Option<usize>,
     progress: Option<MultiProgress>,
     config: SwageConfig,
+    profile_data_patterns: Option<Vec<DataPatternKind>>,
+    on_round_complete: Option<RoundCallback<AE, H::Error, VE>>,
 }
 
 impl<H: Hammering, AE: std::error::Error, VE: std::error::Error> Default
@@ -464,12 +977,13 @@ impl<H: Hammering, AE: std::error::Error, VE: std::error::Error> Default
         SwageBuilder {
             allocator: None,
             profile_hammerer_factory: None,
-            profile_data_pattern: DataPatternKind::Random,
             hammerer_factory: Box::new(|h, _, _| h),
             victim_factory: None,
             pattern_size: None,
             progress: None,
             config: SwageConfig::default(),
+            profile_data_patterns: None,
+            on_round_complete: None,
         }
     }
 }
@@ -477,6 +991,10 @@ impl<H: Hammering, AE: std::error::Error, VE: std::error::Error> Default
 impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
     SwageBuilder<PH, H, AE, VE>
 {
+    /// Sets the allocator.
+    ///
+    /// This changes the error type of the results, so a callback set using
+    /// [`SwageBuilder::on_round_complete`] is discarded.
     pub fn allocator<A: ConsecAllocator + 'static>(
         self,
         allocator: A,
@@ -484,12 +1002,13 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         SwageBuilder {
             allocator: Some(Box::new(allocator)),
             profile_hammerer_factory: self.profile_hammerer_factory,
-            profile_data_pattern: self.profile_data_pattern,
             hammerer_factory: self.hammerer_factory,
             victim_factory: self.victim_factory,
             pattern_size: self.pattern_size,
             progress: self.progress,
             config: self.config,
+            profile_data_patterns: self.profile_data_patterns,
+            on_round_complete: None,
         }
     }
 
@@ -501,11 +1020,28 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         self
     }
 
-    pub fn profile_data_pattern(mut self, profile_data_pattern: DataPatternKind) -> Self {
-        self.profile_data_pattern = profile_data_pattern;
+    /// Profiles using a single data pattern.
+    ///
+    /// This overrides [`SwageConfig::profile_data_patterns`], regardless of whether
+    /// [`SwageBuilder::config`] is called before or after.
+    pub fn profile_data_pattern(self, profile_data_pattern: DataPatternKind) -> Self {
+        self.profile_data_patterns(vec![profile_data_pattern])
+    }
+
+    /// Profiles using each of the given data patterns, keeping the one yielding the most
+    /// bit flips.
+    ///
+    /// This overrides [`SwageConfig::profile_data_patterns`], regardless of whether
+    /// [`SwageBuilder::config`] is called before or after.
+    pub fn profile_data_patterns(mut self, profile_data_patterns: Vec<DataPatternKind>) -> Self {
+        self.profile_data_patterns = Some(profile_data_patterns);
         self
     }
 
+    /// Sets the factory creating the attack hammerer from the profiling hammerer.
+    ///
+    /// This changes the error type of the results, so a callback set using
+    /// [`SwageBuilder::on_round_complete`] is discarded.
     pub fn hammerer_factory<H1: Hammering>(
         self,
         hammerer_factory: impl Fn(PH, ConsecBlocks, RoundProfile) -> H1 + 'static,
@@ -513,18 +1049,22 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         SwageBuilder {
             allocator: self.allocator,
             profile_hammerer_factory: self.profile_hammerer_factory,
-            profile_data_pattern: self.profile_data_pattern,
             hammerer_factory: Box::new(hammerer_factory),
             victim_factory: self.victim_factory,
             pattern_size: self.pattern_size,
             progress: self.progress,
             config: self.config,
+            profile_data_patterns: self.profile_data_patterns,
+            on_round_complete: None,
         }
     }
 
     pub fn victim_factory(
         mut self,
-        victim_factory: impl Fn(ConsecBlocks, RoundProfile) -> Result<Box<dyn VictimOrchestrator>, VE>
+        victim_factory: impl Fn(
+            ArcConsecBlocks,
+            RoundProfile,
+        ) -> Result<Box<dyn VictimOrchestrator>, VE>
         + 'static,
     ) -> Self {
         self.victim_factory = Some(Box::new(victim_factory));
@@ -546,30 +1086,79 @@ impl<PH: Hammering, H: Hammering, AE: std::error::Error, VE: std::error::Error>
         self
     }
 
+    /// Sets a callback invoked with the results of each round as soon as it completes.
+    ///
+    /// This allows observing progress and streaming results, e.g., to a file, during long
+    /// experiments instead of waiting for [`Swage::run`] to return. Must be set after
+    /// [`SwageBuilder::allocator`] and [`SwageBuilder::hammerer_factory`].
+    pub fn on_round_complete(
+        mut self,
+        on_round_complete: impl Fn(&RoundResult<AE, H::Error, VE>) + 'static,
+    ) -> Self {
+        self.on_round_complete = Some(Box::new(on_round_complete));
+        self
+    }
+
     pub fn build(self) -> Result<Swage<PH, H, AE, VE>, Error> {
-        if !(self.config.timeout.is_some()
-            || self.config.repetitions.is_some()
-            || self.config.hammering_timeout.is_some())
+        let mut config = self.config;
+        if let Some(profile_data_patterns) = self.profile_data_patterns {
+            config.profile_data_patterns = profile_data_patterns;
+        }
+        if !(config.timeout.is_some()
+            || config.repetitions.is_some()
+            || config.hammering_timeout.is_some())
         {
             return Err(Error::InvalidConfig(
                 "At least one of timeout, repetitions or hammering_timeout must be set".into(),
             ));
         }
+        if config.profile_data_patterns.is_empty() {
+            return Err(Error::InvalidConfig(
+                "profile_data_patterns must not be empty".into(),
+            ));
+        }
+        if config
+            .profile_data_patterns
+            .contains(&DataPatternKind::XOR { period: 0 })
+        {
+            return Err(Error::InvalidConfig(
+                "XOR period in profile_data_patterns must be greater than 0".into(),
+            ));
+        }
+        let allocator = self.allocator.ok_or(Error::Allocator)?;
+        let pattern_size = self.pattern_size.ok_or(Error::PatternSize)?;
+        validate_pattern_size(pattern_size, allocator.block_size())?;
         Ok(Swage {
-            allocator: self.allocator.ok_or(Error::Allocator)?,
+            allocator,
             profile_hammerer_factory: self
                 .profile_hammerer_factory
                 .ok_or(Error::ProfileHammerer)?,
-            profile_data_pattern: self.profile_data_pattern,
             hammerer_factory: self.hammerer_factory,
             victim_factory: self.victim_factory.ok_or(Error::Victim)?,
             progress: self.progress,
-            pattern_size: self.pattern_size.ok_or(Error::PatternSize)?,
-            config: self.config,
+            pattern_size,
+            config,
+            on_round_complete: self.on_round_complete,
         })
     }
 }
 
+/// Checks that `pattern_size` can be allocated in blocks of `block_size`.
+fn validate_pattern_size(pattern_size: usize, block_size: Size) -> Result<(), Error> {
+    if pattern_size == 0 {
+        return Err(Error::InvalidConfig(
+            "pattern_size must be greater than 0".into(),
+        ));
+    }
+    if !pattern_size.is_multiple_of(block_size.bytes()) {
+        return Err(Error::InvalidConfig(format!(
+            "pattern_size must be a multiple of allocator block_size ({})",
+            block_size
+        )));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("No allocator specified")]
@@ -583,3 +1172,500 @@ pub enum Error {
     #[error("Invalid config: {0}")]
     InvalidConfig(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use crate::util::PAGE_SIZE;
+    use std::convert::Infallible;
+
+    struct MmapAllocator;
+
+    impl ConsecAllocator for MmapAllocator {
+        type Error = std::io::Error;
+        fn block_size(&self) -> Size {
+            Size::B(PAGE_SIZE)
+        }
+        fn alloc_consec_blocks(&mut self, size: Size) -> Result<ConsecBlocks, Self::Error> {
+            Ok(ConsecBlocks::new(vec![Memory::mmap(size.bytes())?]))
+        }
+    }
+
+    /// Hammerer that raises the stop flag instead of hammering.
+    struct StopHammerer(Arc<AtomicBool>);
+
+    impl Hammering for StopHammerer {
+        type Error = Infallible;
+        fn hammer(&self) -> Result<(), Self::Error> {
+            self.0.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn swage(
+        flag: Arc<AtomicBool>,
+    ) -> Swage<StopHammerer, StopHammerer, std::io::Error, Infallible> {
+        builder(flag).build().expect("invalid config")
+    }
+
+    fn builder(
+        flag: Arc<AtomicBool>,
+    ) -> SwageBuilder<StopHammerer, StopHammerer, std::io::Error, Infallible> {
+        let hammerer_flag = flag.clone();
+        Swage::<_, _, std::io::Error, _>::builder()
+            .allocator(MmapAllocator)
+            .profile_hammerer_factory(move |_| StopHammerer(hammerer_flag.clone()))
+            .victim_factory(|memory, profile| {
+                Ok(Box::new(MemCheck::new(
+                    ConsecBlocks::clone(&memory),
+                    profile.pattern,
+                    vec![].into(),
+                )))
+            })
+            .pattern_size(PAGE_SIZE)
+            .config(SwageConfig {
+                profiling_rounds: 1,
+                repetitions: None,
+                timeout: Some(Duration::from_secs(3600)),
+                stop_flag: Some(flag),
+                ..Default::default()
+            })
+    }
+
+    #[test]
+    fn test_build_validates_pattern_size() {
+        let flag = Arc::new(AtomicBool::new(false));
+        assert!(builder(flag.clone()).build().is_ok());
+        assert!(matches!(
+            builder(flag.clone()).pattern_size(0).build(),
+            Err(Error::InvalidConfig(msg)) if msg.contains("greater than 0")
+        ));
+        assert!(matches!(
+            builder(flag.clone()).pattern_size(PAGE_SIZE + 1).build(),
+            Err(Error::InvalidConfig(msg)) if msg.contains("multiple of allocator block_size")
+        ));
+        assert!(
+            builder(flag.clone())
+                .pattern_size(3 * PAGE_SIZE)
+                .build()
+                .is_ok()
+        );
+        let missing = Swage::<StopHammerer, _, std::io::Error, Infallible>::builder()
+            .allocator(MmapAllocator)
+            .config(SwageConfig::default());
+        assert!(matches!(missing.build(), Err(Error::PatternSize)));
+    }
+
+    #[test]
+    fn test_build_rejects_xor_period_zero() {
+        let flag = Arc::new(AtomicBool::new(false));
+        assert!(
+            builder(flag.clone())
+                .profile_data_patterns(vec![DataPatternKind::XOR { period: 8192 }])
+                .build()
+                .is_ok()
+        );
+        assert!(matches!(
+            builder(flag)
+                .profile_data_patterns(vec![DataPatternKind::Random, DataPatternKind::XOR { period: 0 }])
+                .build(),
+            Err(Error::InvalidConfig(msg)) if msg.contains("XOR period")
+        ));
+    }
+
+    #[test]
+    fn test_profile_data_patterns_before_config() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let swage = builder(flag.clone())
+            .profile_data_pattern(DataPatternKind::One)
+            .config(SwageConfig {
+                repetitions: Some(1),
+                ..Default::default()
+            })
+            .build()
+            .expect("invalid config");
+        assert_eq!(
+            swage.config.profile_data_patterns,
+            vec![DataPatternKind::One]
+        );
+        let swage = builder(flag).build().expect("invalid config");
+        assert_eq!(
+            swage.config.profile_data_patterns,
+            vec![DataPatternKind::Random]
+        );
+    }
+
+    #[test]
+    fn test_stop_flag_before_run() {
+        let flag = Arc::new(AtomicBool::new(true));
+        let swage = swage(flag);
+        assert!(swage.is_stopping());
+        assert!(swage.run().is_empty());
+    }
+
+    #[test]
+    fn test_stop_flag_during_run() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let swage = swage(flag.clone());
+        assert!(!swage.is_stopping());
+        let experiments = swage.run();
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(experiments.len(), 1);
+    }
+
+    #[test]
+    fn test_on_round_complete() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let rounds = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = rounds.clone();
+        let swage = builder(flag)
+            .on_round_complete(move |experiment| {
+                assert_eq!(experiment.failed_rounds(), 1);
+                counter.set(counter.get() + 1);
+            })
+            .build()
+            .expect("invalid config");
+        let experiments = swage.run();
+        assert_eq!(experiments.len(), 1);
+        assert_eq!(rounds.get(), 1);
+    }
+
+    #[test]
+    fn test_round_profile_xor_serialization() {
+        let profile = RoundProfile {
+            bit_flips: vec![],
+            pattern: DataPattern::XOR {
+                base: 0x00,
+                xor_period: 8192,
+            },
+        };
+        let json = serde_json::to_value(&profile).unwrap();
+        assert_eq!(json["pattern"]["XOR"]["xor_period"], 8192);
+        assert_eq!(json["pattern"]["XOR"]["base"], 0);
+    }
+
+    #[test]
+    fn test_config_toml() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("swage_config_{}.toml", std::process::id()));
+        let path = path.to_str().expect("non-UTF-8 temp dir");
+        std::fs::write(
+            path,
+            "profiling_rounds = 5\nreproducibility_threshold = 0.5\nhammering_timeout_secs = 300\n",
+        )?;
+        let config = SwageConfig::from_toml(path)?;
+        std::fs::remove_file(path)?;
+        assert_eq!(config.profiling_rounds, 5);
+        assert_eq!(config.reproducibility_threshold, 0.5);
+        assert_eq!(config.hammering_timeout, Some(Duration::from_secs(300)));
+        // missing keys take their default value
+        assert_eq!(config.profile_data_patterns, vec![DataPatternKind::Random]);
+        assert_eq!(config.repetitions, Some(1));
+        assert_eq!(config.timeout, None);
+
+        let toml = config.to_toml_string()?;
+        assert_eq!(
+            toml,
+            "profiling_rounds = 5\nreproducibility_threshold = 0.5\nprofile_data_patterns = [\"Random\"]\nhammering_timeout_secs = 300\nrepetitions = 1\n"
+        );
+        assert!(matches!(
+            SwageConfig::from_toml("/nonexistent/swage.toml"),
+            Err(ConfigError::Io(_))
+        ));
+        assert!(toml::from_str::<SwageConfig>("profiling_rounds = \"ten\"").is_err());
+        let config: SwageConfig =
+            toml::from_str("profile_data_patterns = [\"Zero\", { XOR = { period = 8192 } }]")?;
+        assert_eq!(
+            config.profile_data_patterns,
+            vec![DataPatternKind::Zero, DataPatternKind::XOR { period: 8192 }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_profile_filter_by_direction() {
+        let profile = RoundProfile {
+            bit_flips: vec![
+                BitFlip::new(0x1000 as *const u8, 0x01, 0x00),
+                BitFlip::new(0x2000 as *const u8, 0x01, 0x01),
+                BitFlip::new(0x3000 as *const u8, 0x03, 0x00),
+                BitFlip::new(0x4000 as *const u8, 0x03, 0x01),
+            ],
+            pattern: DataPattern::Zero,
+        };
+        let addrs = |profile: &RoundProfile| {
+            profile
+                .bit_flips
+                .iter()
+                .map(|flip| flip.addr)
+                .collect::<Vec<_>>()
+        };
+        let (zero_to_one, one_to_zero) = profile.partition_by_direction();
+        assert_eq!(addrs(&zero_to_one), vec![0x1000, 0x3000]);
+        assert_eq!(addrs(&one_to_zero), vec![0x2000]);
+        assert_eq!(
+            addrs(&profile.filter_by_direction(&FlipDirection::Any)),
+            vec![0x1000, 0x2000, 0x3000, 0x4000]
+        );
+        assert_eq!(
+            addrs(&profile.filter_by_direction(&FlipDirection::Multiple(vec![
+                FlipDirection::OneToZero,
+                FlipDirection::ZeroToOne
+            ]))),
+            vec![0x4000]
+        );
+        assert_eq!(zero_to_one.pattern, DataPattern::Zero);
+    }
+
+    /// Hammerer clearing a byte, i.e., flipping it unless the pattern wrote 0x00.
+    struct ClearHammerer(*mut u8);
+
+    impl Hammering for ClearHammerer {
+        type Error = Infallible;
+        fn hammer(&self) -> Result<(), Self::Error> {
+            unsafe { std::ptr::write_volatile(self.0, 0x00) };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hammer_profile_patterns() -> anyhow::Result<()> {
+        let memory = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]);
+        let hammerer = ClearHammerer(memory.addr(42));
+        let profile = |patterns: &[DataPatternKind]| {
+            hammer_profile(&hammerer, memory.clone(), patterns, 2, 0.5, None)
+        };
+        let best = profile(&[DataPatternKind::Zero, DataPatternKind::One]);
+        assert_eq!(best.pattern, DataPattern::One);
+        assert_eq!(best.bit_flips.len(), 1);
+        assert_eq!(best.bit_flips[0].addr, memory.addr(42) as usize);
+        let none = profile(&[DataPatternKind::Zero, DataPatternKind::Zero]);
+        assert_eq!(none.pattern, DataPattern::Zero);
+        assert!(none.bit_flips.is_empty());
+        memory.dealloc();
+        Ok(())
+    }
+
+    /// Hammerer clearing a different byte on each call, so no flip reproduces.
+    struct WanderingHammerer {
+        memory: ConsecBlocks,
+        calls: std::cell::Cell<usize>,
+    }
+
+    impl Hammering for WanderingHammerer {
+        type Error = Infallible;
+        fn hammer(&self) -> Result<(), Self::Error> {
+            self.calls.set(self.calls.get() + 1);
+            unsafe { std::ptr::write_volatile(self.memory.addr(self.calls.get()), 0x00) };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hammer_profile_merges_candidates() -> anyhow::Result<()> {
+        let memory = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]);
+        let hammerer = WanderingHammerer {
+            memory: memory.clone(),
+            calls: std::cell::Cell::new(0),
+        };
+        let patterns = [DataPatternKind::One, DataPatternKind::One];
+        let profile = hammer_profile(&hammerer, memory.clone(), &patterns, 4, 0.5, None);
+        assert_eq!(profile.pattern, DataPattern::One);
+        let mut addrs = profile
+            .bit_flips
+            .iter()
+            .map(|flip| flip.addr)
+            .collect::<Vec<_>>();
+        addrs.sort();
+        let expected = (1..=8).map(|i| memory.addr(i) as usize).collect::<Vec<_>>();
+        assert_eq!(addrs, expected);
+        memory.dealloc();
+        Ok(())
+    }
+
+    fn experiment(
+        results: Vec<Result<VictimResult, HammerVictimError>>,
+    ) -> ExperimentData<VictimResult, HammerVictimError> {
+        let profile = RoundProfile {
+            bit_flips: vec![],
+            pattern: DataPattern::Zero,
+        };
+        ExperimentData::new(results, profile, None, Duration::from_secs(1))
+    }
+
+    fn flip(addr: usize) -> BitFlip {
+        BitFlip::new(addr as *const u8, 0x01, 0x00)
+    }
+
+    #[test]
+    fn test_experiment_data_annotate() -> anyhow::Result<()> {
+        let profile = RoundProfile {
+            bit_flips: vec![],
+            pattern: DataPattern::Zero,
+        };
+        let mut data =
+            ExperimentData::<VictimResult, String>::new(vec![], profile, None, Duration::ZERO);
+        assert!(serde_json::to_value(&data)?.get("annotations").is_none());
+        let blocks = ConsecBlocks::new(vec![Memory::mmap(PAGE_SIZE)?]);
+        let annotation =
+            MemRegionAnnotation::new(blocks, "aggressor_bank_0").with_metadata("bank", 0);
+        data.annotate(&annotation);
+        let json = serde_json::to_value(&data)?;
+        assert_eq!(json["annotations"][0]["label"], "aggressor_bank_0");
+        assert_eq!(json["annotations"][0]["metadata"]["bank"], 0);
+        annotation.into_inner().dealloc();
+        Ok(())
+    }
+
+    #[test]
+    fn test_experiment_data_empty() {
+        let data = experiment(vec![]);
+        assert_eq!(data.successful_rounds(), 0);
+        assert_eq!(data.failed_rounds(), 0);
+        assert_eq!(data.success_rate(), 0.0);
+        assert!(!data.is_successful());
+        assert!(data.all_bit_flips().is_empty());
+        assert!(
+            data.to_string()
+                .ends_with(": 0/0 rounds successful (0.0%), 0 bit flips at 0 addresses")
+        );
+    }
+
+    #[test]
+    fn test_experiment_data_mixed() {
+        let data = experiment(vec![
+            Ok(VictimResult::BitFlips(vec![flip(0x1000), flip(0x2000)])),
+            Err(HammerVictimError::NoFlips),
+            Ok(VictimResult::BitFlips(vec![flip(0x1000)])),
+            Ok(VictimResult::String("done".into())),
+        ]);
+        assert_eq!(data.successful_rounds(), 3);
+        assert_eq!(data.failed_rounds(), 1);
+        assert_eq!(data.success_rate(), 0.75);
+        assert!(data.is_successful());
+        assert_eq!(data.all_bit_flips().len(), 3);
+        assert_eq!(
+            data.unique_flip_addresses(),
+            HashSet::from([0x1000, 0x2000])
+        );
+        assert_eq!(data.error_messages(), vec!["No flips detected".to_string()]);
+        assert_eq!(data.filter_successful_results().len(), 3);
+        assert!(
+            data.to_string()
+                .ends_with(": 3/4 rounds successful (75.0%), 3 bit flips at 2 addresses")
+        );
+    }
+
+    #[test]
+    fn test_experiment_data_without_flips() {
+        let data = experiment(vec![
+            Ok(VictimResult::BitFlips(vec![])),
+            Ok(VictimResult::Nothing),
+            Err(HammerVictimError::NotRunning),
+        ]);
+        assert_eq!(data.successful_rounds(), 2);
+        assert!(!data.is_successful());
+        assert!(data.all_bit_flips().is_empty());
+        assert!(data.unique_flip_addresses().is_empty());
+        assert_eq!(
+            data.error_messages(),
+            vec!["Victim is not running".to_string()]
+        );
+
+        let failed = experiment(vec![Err(HammerVictimError::NoFlips)]);
+        assert_eq!(failed.success_rate(), 0.0);
+        assert!(failed.filter_successful_results().is_empty());
+    }
+
+    type TestHammerError = HammerError<std::io::Error, Infallible, std::io::Error>;
+
+    fn attack(
+        results: Vec<Result<VictimResult, TestHammerError>>,
+        profiling_flips: usize,
+    ) -> ExperimentData<VictimResult, TestHammerError> {
+        let profile = RoundProfile {
+            bit_flips: (0..profiling_flips).map(|i| flip(i * PAGE_SIZE)).collect(),
+            pattern: DataPattern::Zero,
+        };
+        ExperimentData::new(results, profile, None, Duration::from_secs(1))
+    }
+
+    #[test]
+    fn test_statistics() {
+        let experiments = vec![
+            attack(
+                vec![
+                    Ok(VictimResult::BitFlips(vec![flip(0x1000), flip(0x2000)])),
+                    Err(HammerError::VictimError(HammerVictimError::NoFlips)),
+                ],
+                2,
+            ),
+            attack(
+                vec![
+                    Ok(VictimResult::BitFlips(vec![flip(0x1000)])),
+                    Err(HammerError::NoVulnerableCells),
+                ],
+                1,
+            ),
+        ];
+        let stats = SwageStatistics::from_experiments(&experiments);
+        assert_eq!(
+            stats,
+            SwageStatistics {
+                total_rounds: 4,
+                successful_rounds: 2,
+                failed_rounds: 1,
+                no_flip_rounds: 1,
+                total_bit_flips: 3,
+                unique_addresses: 2,
+                flip_rate_per_round: 0.75,
+                profiling_bit_flip_count: 3,
+                total_wall_time: Duration::from_secs(2),
+            }
+        );
+        assert!(stats.is_attack_successful());
+        let flip_stats = experiments[0].flip_stats();
+        assert_eq!(flip_stats.total, 2);
+        assert_eq!(flip_stats.unique_pages, 2);
+        let table = stats.to_string();
+        assert_eq!(table.lines().count(), 9);
+        assert!(table.contains("Flips per round"));
+        assert!(table.lines().any(|line| line.ends_with("0.75")));
+        assert_eq!(serde_json::to_value(&stats).unwrap()["unique_addresses"], 2);
+    }
+
+    #[test]
+    fn test_statistics_empty() {
+        let stats =
+            SwageStatistics::from_experiments::<std::io::Error, Infallible, std::io::Error>(&[]);
+        assert_eq!(stats, SwageStatistics::default());
+        assert!(!stats.is_attack_successful());
+        assert_eq!(stats.flip_rate_per_round, 0.0);
+    }
+
+    #[test]
+    fn test_statistics_combined() {
+        let a = SwageStatistics::from_experiments(&[attack(
+            vec![Ok(VictimResult::BitFlips(vec![flip(0x1000)]))],
+            1,
+        )]);
+        let b = SwageStatistics::from_experiments(&[attack(
+            vec![
+                Err(HammerError::VictimError(HammerVictimError::NoFlips)),
+                Err(HammerError::VictimError(HammerVictimError::NoFlips)),
+                Ok(VictimResult::BitFlips(vec![flip(0x1000)])),
+            ],
+            0,
+        )]);
+        let combined = SwageStatistics::combined(&[a, b]);
+        assert_eq!(combined.total_rounds, 4);
+        assert_eq!(combined.successful_rounds, 2);
+        assert_eq!(combined.no_flip_rounds, 2);
+        assert_eq!(combined.total_bit_flips, 2);
+        assert_eq!(combined.unique_addresses, 2);
+        assert_eq!(combined.flip_rate_per_round, 0.5);
+        assert_eq!(combined.profiling_bit_flip_count, 1);
+        assert_eq!(combined.total_wall_time, Duration::from_secs(2));
+        assert_eq!(SwageStatistics::combined(&[]), SwageStatistics::default());
+    }
+}
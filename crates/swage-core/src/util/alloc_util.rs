@@ -1,10 +1,10 @@
-use crate::memory::{BytePointer, Memory};
-use crate::util::ROW_SIZE;
+use crate::memory::{BytePointer, Memory, MlockGuard};
+use crate::util::{CancelableTask, PAGE_SHIFT, ROW_SIZE};
 use libc::{
     MAP_POPULATE, MAP_SHARED, O_CREAT, O_RDWR, PROT_READ, PROT_WRITE, S_IRUSR, S_IWUSR, close,
     shm_open,
 };
-use log::{info, trace};
+use log::{info, trace, warn};
 use std::{
     cmp::min,
     ffi::CString,
@@ -13,7 +13,7 @@ use std::{
         Arc, Mutex,
         atomic::{AtomicBool, Ordering},
     },
-    thread::{JoinHandle, sleep, spawn},
+    thread::sleep,
     time::Duration,
 };
 
@@ -108,6 +108,54 @@ pub fn mmap<P>(addr: *mut libc::c_void, len: usize) -> *mut P {
     v as *mut P
 }
 
+/// Maps anonymous memory exactly at `hint`.
+///
+/// Unlike [`mmap`], the mapping is discarded if the kernel places it at a different address.
+/// Returns `None` if the mapping fails or does not start at `hint`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn mmap_at<P>(hint: *mut libc::c_void, len: usize) -> Option<*mut P> {
+    use libc::{MAP_ANONYMOUS, MAP_POPULATE, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+
+    let v = unsafe {
+        libc::mmap(
+            hint,
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS | MAP_POPULATE,
+            -1,
+            0,
+        )
+    };
+    if v == libc::MAP_FAILED {
+        return None;
+    }
+    if v != hint {
+        trace!("mmap placed {:p} instead of {:p}", v, hint);
+        unsafe { munmap(v, len) };
+        return None;
+    }
+    unsafe { libc::memset(v, 0x11, len) };
+    Some(v as *mut P)
+}
+
+/// Maps anonymous memory at `hint`, retrying at `hint + i * len` for up to `retries` further attempts.
+///
+/// Returns `None` if no attempt succeeds. See [`mmap_at`].
+pub fn mmap_retry_at<P>(hint: *mut libc::c_void, len: usize, retries: u32) -> Option<*mut P> {
+    (0..=retries as usize).find_map(|i| mmap_at(hint.wrapping_byte_add(i * len), len))
+}
+
+/// Base address for hints returned by [`page_frame_aligned_hint`].
+const PFN_HINT_BASE: usize = 0x2000000000;
+
+/// Returns a virtual address hint whose low bits match the physical address of `pfn`.
+///
+/// The hint lies in a fixed region starting at `0x2000000000`. All address bits below
+/// bit 37 equal the respective bits of the physical address `pfn << PAGE_SHIFT`.
+pub fn page_frame_aligned_hint(pfn: usize) -> *mut libc::c_void {
+    (PFN_HINT_BASE | ((pfn << PAGE_SHIFT) & (PFN_HINT_BASE - 1))) as *mut libc::c_void
+}
+
 /// Unmap memory
 ///
 /// # Safety
@@ -125,29 +173,177 @@ pub unsafe fn munmap<P>(addr: *mut P, len: usize) {
     );
 }
 
+/// Synchronization of the page-locking thread with other threads writing the memory.
+#[derive(Clone, Debug)]
+pub enum LockType {
+    /// Writes hold the mutex
+    Mutex(Arc<Mutex<()>>),
+    /// Writes spin until they can set the flag from `false` to `true`, and clear it afterwards
+    Atomic(Arc<AtomicBool>),
+}
+
+impl LockType {
+    /// Runs `f` while holding the lock.
+    pub fn with_lock<R>(&self, f: impl FnOnce() -> R) -> R {
+        match self {
+            LockType::Mutex(mutex) => {
+                let _guard = mutex.lock().unwrap();
+                f()
+            }
+            LockType::Atomic(flag) => {
+                while flag
+                    .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .is_err()
+                {
+                    std::hint::spin_loop();
+                }
+                let result = f();
+                flag.store(false, Ordering::Release);
+                result
+            }
+        }
+    }
+}
+
+/// Configuration of [`spawn_page_locking_thread`].
+#[derive(Clone, Debug)]
+pub struct PageLockerConfig {
+    /// Pause between two passes over the memory in milliseconds
+    pub write_interval_ms: u64,
+    /// Lock held while writing a row
+    pub lock_type: LockType,
+}
+
+impl Default for PageLockerConfig {
+    fn default() -> Self {
+        PageLockerConfig {
+            write_interval_ms: 100,
+            lock_type: LockType::Mutex(Arc::default()),
+        }
+    }
+}
+
 /// Spawn a thread that periodically writes 0s to the allocated memory blocks.
 /// This is used to lock the memory in RAM, preventing it from being swapped out.
+///
+/// In addition, the blocks are `mlock`ed while the thread is running. Each block is locked
+/// once, on the first pass it is seen in `blocks`, and unlocked when it is removed from
+/// `blocks` or the thread stops. The thread runs until the returned task is cancelled.
+///
+/// Each row is written while holding `config.lock_type`. [`LockType::Atomic`] avoids the
+/// mutex overhead for threads contending on the memory, e.g., the hammering thread, at the
+/// cost of spinning while the lock is held.
 pub fn spawn_page_locking_thread(
     blocks: Arc<Mutex<Vec<Memory>>>,
-    mem_lock: Arc<Mutex<()>>,
-    stop: Arc<AtomicBool>,
-) -> JoinHandle<()> {
-    spawn(move || {
+    config: PageLockerConfig,
+) -> CancelableTask<()> {
+    let (task, _) = CancelableTask::spawn(move |token| {
         info!(target: "loader", "Loader thread started");
-        while !stop.load(Ordering::Relaxed) {
+        // Guards of all blocks seen so far, keyed by block address, or `None` if mlock failed.
+        // Guards of deallocated blocks are empty and replaced if the address is reused.
+        let mut guards: Vec<(*mut u8, Option<MlockGuard>)> = vec![];
+        while !token.is_cancelled() {
             let blocks = blocks.lock().unwrap().clone();
-            for block in blocks {
+            guards.retain(|(ptr, guard)| {
+                blocks.iter().any(|block| block.ptr == *ptr)
+                    && guard.as_ref().is_none_or(|guard| !guard.is_empty())
+            });
+            for block in &blocks {
+                if guards.iter().any(|(ptr, _)| *ptr == block.ptr) {
+                    continue;
+                }
+                let guard = block
+                    .lock_guard()
+                    .inspect_err(|e| warn!(target: "loader", "mlock failed: {}", e))
+                    .ok();
+                guards.push((block.ptr, guard));
+            }
+            for block in &blocks {
                 for offset in (0..block.len).step_by(ROW_SIZE) {
                     let addr = block.addr(offset);
                     let count = min(ROW_SIZE, block.len - offset);
                     trace!(target: "loader", "Waiting for memory lock");
-                    let mem_lock = mem_lock.lock().unwrap();
-                    unsafe { std::ptr::write_bytes(addr, 0, count) };
-                    drop(mem_lock);
+                    config
+                        .lock_type
+                        .with_lock(|| unsafe { std::ptr::write_bytes(addr, 0, count) });
                 }
             }
-            sleep(Duration::from_millis(100));
+            sleep(Duration::from_millis(config.write_interval_ms));
         }
         info!(target: "loader", "Stopping loader thread");
-    })
+    });
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PAGE_SIZE;
+
+    /// Returns an address range of `len` bytes that is currently unmapped
+    fn free_hint(len: usize) -> *mut libc::c_void {
+        let p: *mut libc::c_void = mmap(std::ptr::null_mut(), len);
+        unsafe { munmap(p, len) };
+        p
+    }
+
+    #[test]
+    fn test_mmap_at() {
+        let hint = free_hint(PAGE_SIZE);
+        let p: *mut libc::c_void = mmap_at(hint, PAGE_SIZE).expect("mmap_at");
+        assert_eq!(p, hint);
+        // the hint is occupied now
+        assert_eq!(mmap_at::<libc::c_void>(hint, PAGE_SIZE), None);
+        unsafe { munmap(p, PAGE_SIZE) };
+    }
+
+    #[test]
+    fn test_mmap_retry_at() {
+        let hint = free_hint(2 * PAGE_SIZE);
+        let first: *mut libc::c_void = mmap_at(hint, PAGE_SIZE).expect("mmap_at");
+        assert_eq!(mmap_retry_at::<libc::c_void>(hint, PAGE_SIZE, 0), None);
+        let second: *mut libc::c_void = mmap_retry_at(hint, PAGE_SIZE, 1).expect("mmap_retry_at");
+        assert_eq!(second, hint.wrapping_byte_add(PAGE_SIZE));
+        unsafe {
+            munmap(first, PAGE_SIZE);
+            munmap(second, PAGE_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_lock_type_atomic() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let lock = LockType::Atomic(flag.clone());
+        assert!(lock.with_lock(|| flag.load(Ordering::Relaxed)));
+        assert!(!flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_spawn_page_locking_thread() {
+        let block = Memory::mmap(2 * ROW_SIZE).expect("mmap failed");
+        unsafe { std::ptr::write_bytes(block.ptr(), 0xFF, block.len) };
+        let flag = Arc::new(AtomicBool::new(false));
+        let config = PageLockerConfig {
+            write_interval_ms: 1,
+            lock_type: LockType::Atomic(flag.clone()),
+        };
+        let task = spawn_page_locking_thread(Arc::new(Mutex::new(vec![block.clone()])), config);
+        // the loader only writes while it holds the lock
+        let written = || (0..block.len).all(|offset| unsafe { *block.addr(offset) } == 0);
+        while !LockType::Atomic(flag.clone()).with_lock(written) {
+            sleep(Duration::from_millis(1));
+        }
+        task.cancel_and_join().expect("loader panicked");
+        assert!(!flag.load(Ordering::Relaxed));
+        block.dealloc();
+    }
+
+    #[test]
+    fn test_page_frame_aligned_hint() {
+        let pfn = 0x12345;
+        let hint = page_frame_aligned_hint(pfn) as usize;
+        assert_eq!(hint & (PFN_HINT_BASE - 1), pfn << PAGE_SHIFT);
+        assert_eq!(hint & !(PFN_HINT_BASE - 1), PFN_HINT_BASE);
+        assert_eq!(hint & (PAGE_SIZE - 1), 0);
+    }
 }
@@ -46,3 +46,108 @@ impl<T> CancelableJoinHandle<T> {
         self.handle.join()
     }
 }
+
+/// Token telling a [`CancelableTask`] to stop.
+///
+/// Clones share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the task to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the task was requested to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A thread that can be requested to stop using a [`CancellationToken`].
+pub struct CancelableTask<T> {
+    handle: thread::JoinHandle<T>,
+    token: CancellationToken,
+}
+
+impl<T: Send + 'static> CancelableTask<T> {
+    /// Spawns `func` on a new thread.
+    ///
+    /// `func` is passed a [`CancellationToken`] it should check regularly to determine
+    /// when to exit. A clone of the token is returned, e.g., to cancel the task from
+    /// another thread.
+    pub fn spawn(
+        func: impl FnOnce(CancellationToken) -> T + Send + 'static,
+    ) -> (CancelableTask<T>, CancellationToken) {
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let handle = thread::spawn(move || func(t));
+        (
+            CancelableTask {
+                handle,
+                token: token.clone(),
+            },
+            token,
+        )
+    }
+}
+
+impl<T> CancelableTask<T> {
+    /// Returns `true` if the task's thread has finished running.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Cancels the task and waits for it to finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the task panicked
+    pub fn cancel_and_join(self) -> thread::Result<T> {
+        self.token.cancel();
+        self.handle.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cancelable_task() {
+        let (task, token) = CancelableTask::spawn(|token| {
+            let mut iterations = 0;
+            while !token.is_cancelled() {
+                iterations += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+            iterations
+        });
+        assert!(!token.is_cancelled());
+        thread::sleep(Duration::from_millis(10));
+        assert!(!task.is_finished());
+        assert!(task.cancel_and_join().expect("task panicked") > 0);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_from_token() {
+        let (task, token) = CancelableTask::spawn(|token| {
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        token.cancel();
+        while !task.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        task.cancel_and_join().expect("task panicked");
+    }
+}
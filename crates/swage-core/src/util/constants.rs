@@ -20,3 +20,63 @@ pub const TIMER_ROUNDS: usize = 100_000;
 
 /// Base address for hugepage memory allocations
 pub const BASE_MSB: *mut libc::c_void = 0x2000000000 as *mut libc::c_void;
+
+/// Offset of a DRAM row, counted in rows.
+///
+/// Distinguishes row offsets from byte offsets. Use [`RowOffset::as_bytes`] or
+/// `usize::from` to convert to a byte offset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RowOffset(pub usize);
+
+impl RowOffset {
+    /// Returns the offset in bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> usize {
+        self.0 * ROW_SIZE
+    }
+
+    /// Converts a byte offset to a row offset, or returns None if `bytes` is not row-aligned.
+    pub fn from_bytes(bytes: usize) -> Option<RowOffset> {
+        (bytes & ROW_MASK == 0).then_some(RowOffset(bytes >> ROW_SHIFT))
+    }
+}
+
+impl From<RowOffset> for usize {
+    fn from(offset: RowOffset) -> Self {
+        offset.as_bytes()
+    }
+}
+
+impl std::ops::Add<usize> for RowOffset {
+    type Output = RowOffset;
+    fn add(self, rows: usize) -> RowOffset {
+        RowOffset(self.0 + rows)
+    }
+}
+
+impl std::ops::Sub<usize> for RowOffset {
+    type Output = RowOffset;
+    fn sub(self, rows: usize) -> RowOffset {
+        RowOffset(self.0 - rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_offset_conversion() {
+        assert_eq!(RowOffset(3).as_bytes(), 3 * ROW_SIZE);
+        assert_eq!(usize::from(RowOffset(0)), 0);
+        assert_eq!(RowOffset::from_bytes(5 * ROW_SIZE), Some(RowOffset(5)));
+        assert_eq!(RowOffset(2) + 3, RowOffset(5));
+        assert_eq!(RowOffset(5) - 3, RowOffset(2));
+    }
+
+    #[test]
+    fn test_row_offset_from_bytes_unaligned() {
+        assert_eq!(RowOffset::from_bytes(ROW_SIZE + 1), None);
+        assert_eq!(RowOffset::from_bytes(PAGE_SIZE), None);
+    }
+}
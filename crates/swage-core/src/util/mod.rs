@@ -2,11 +2,14 @@
 //!
 //! This module provides various helper types and traits including:
 //! - [`Size`] - Memory size representation
+//! - [`RowOffset`] - Row offsets distinguished from byte offsets
+//! - [`BackoffStrategy`] - Delay policies for retrying failed operations
 //! - Constants for memory operations ([`PAGE_SIZE`], [`ROW_SIZE`], etc.)
-//! - [`GroupBy`] trait for collection grouping operations
+//! - [`GroupBy`] and [`GroupBySorted`] traits for collection grouping operations
 //! - [`ReadLine`] trait for reading lines from child process stdout
 //! - Progress reporting utilities ([`NamedProgress`])
 //! - Random number generation ([`Rng`])
+//! - Experiment phase timing ([`ExperimentTimer`])
 
 mod alloc_util;
 mod cancelable_thread;
@@ -14,16 +17,21 @@ mod constants;
 mod named_progress;
 mod rng;
 mod size;
+mod timer;
 
 pub use self::alloc_util::*;
 pub use self::cancelable_thread::*;
 pub use self::constants::*;
 pub use self::named_progress::NamedProgress;
 pub use self::rng::Rng;
-pub use self::size::Size;
+pub use self::size::{ParseSizeError, Size};
+pub use self::timer::{ExperimentTimer, PhaseGuard, PhaseSummary};
 
-use std::collections::HashMap;
+use rand::Rng as _;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::time::{Duration, Instant};
 
 /// Trait for grouping collection elements by a key function.
@@ -61,6 +69,51 @@ impl<T> GroupBy<T> for Vec<T> {
     }
 }
 
+impl<K2, V> GroupBy<V> for HashMap<K2, V> {
+    fn group_by<K: std::hash::Hash + std::cmp::Eq, F: Fn(&V) -> K>(
+        self,
+        f: F,
+    ) -> HashMap<K, Vec<V>> {
+        self.into_values().collect::<Vec<_>>().group_by(f)
+    }
+}
+
+/// Trait for grouping collection elements by an ordered key function.
+///
+/// Like [`GroupBy`], but returns the groups in a `BTreeMap`, so iterating over the
+/// groups visits the keys in ascending order. Use this when the group order must be
+/// reproducible across runs.
+pub trait GroupBySorted<V> {
+    /// Groups elements by the result of applying a function to each element.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function that extracts a grouping key from each element
+    ///
+    /// # Returns
+    ///
+    /// Returns a `BTreeMap` where keys are the grouping keys and values are
+    /// vectors of elements that share that key.
+    fn group_by_sorted<K: Ord, F: Fn(&V) -> K>(self, f: F) -> BTreeMap<K, Vec<V>>;
+}
+
+impl<T> GroupBySorted<T> for Vec<T> {
+    fn group_by_sorted<K: Ord, F: Fn(&T) -> K>(self, f: F) -> BTreeMap<K, Vec<T>> {
+        let mut out = BTreeMap::new();
+        for elem in self {
+            let k = f(&elem);
+            out.entry(k).or_insert(vec![]).push(elem);
+        }
+        out
+    }
+}
+
+impl<K2, V> GroupBySorted<V> for HashMap<K2, V> {
+    fn group_by_sorted<K: Ord, F: Fn(&V) -> K>(self, f: F) -> BTreeMap<K, Vec<V>> {
+        self.into_values().collect::<Vec<_>>().group_by_sorted(f)
+    }
+}
+
 /// Creates a vector by applying a function to each index.
 ///
 /// # Arguments
@@ -85,11 +138,47 @@ pub fn make_vec<T>(n: usize, f: impl Fn(usize) -> T) -> Vec<T> {
     v
 }
 
+/// Creates a vector by applying a fallible function to each index.
+///
+/// Stops at the first error and returns it.
+///
+/// # Arguments
+///
+/// * `n` - Number of elements to create
+/// * `f` - Function that takes an index and returns a value or an error
+///
+/// # Errors
+///
+/// Returns the first error returned by `f`.
+///
+/// # Examples
+///
+/// ```
+/// use swage_core::util::try_make_vec;
+///
+/// let squares = try_make_vec(5, |i| Ok::<_, ()>(i * i));
+/// assert_eq!(squares, Ok(vec![0, 1, 4, 9, 16]));
+/// ```
+pub fn try_make_vec<T, E>(n: usize, mut f: impl FnMut(usize) -> Result<T, E>) -> Result<Vec<T>, E> {
+    let mut v = Vec::with_capacity(n);
+    for i in 0..n {
+        v.push(f(i)?);
+    }
+    Ok(v)
+}
+
 /// Macro for retrying operations until they succeed.
 ///
 /// This macro continuously executes a closure until it returns `Ok`, logging errors
 /// for each failed attempt. **Warning**: This creates an infinite loop if the operation
 /// never succeeds.
+///
+/// An optional [`BackoffStrategy`] determines the delay between attempts, e.g.,
+/// `retry!(f, BackoffStrategy::default_allocation())`. Without it, attempts are retried
+/// immediately.
+///
+/// With a maximum number of retries, e.g., `retry!(f, backoff, 3)`, the macro evaluates to a
+/// `Result` holding the last error once all retries failed, and retries are logged as warnings.
 #[macro_export]
 macro_rules! retry {
     ($f:expr) => {{
@@ -103,6 +192,126 @@ macro_rules! retry {
             }
         }
     }};
+    ($f:expr, $backoff:expr) => {{
+        let f = $f;
+        let backoff: &$crate::util::BackoffStrategy = &$backoff;
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Ok(x) => break x,
+                Err(e) => {
+                    log::error!("retry! block failed: {}", e);
+                    std::thread::sleep(backoff.delay_for_attempt(attempt));
+                    attempt += 1;
+                }
+            }
+        }
+    }};
+    ($f:expr, $backoff:expr, $max_retries:expr) => {{
+        let f = $f;
+        let backoff: &$crate::util::BackoffStrategy = &$backoff;
+        let max_retries: u32 = $max_retries;
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Ok(x) => break Ok(x),
+                Err(e) if attempt < max_retries => {
+                    let delay = backoff.delay_for_attempt(attempt);
+                    attempt += 1;
+                    log::warn!(
+                        "retry! block failed: {}. Retry {}/{} in {:?}",
+                        e,
+                        attempt,
+                        max_retries,
+                        delay
+                    );
+                    std::thread::sleep(delay);
+                }
+                Err(e) => break Err(e),
+            }
+        }
+    }};
+}
+
+/// Policy for the delay between retries of a failed operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackoffStrategy {
+    /// Waits the same duration before each attempt
+    Fixed(Duration),
+    /// Multiplies the delay by `factor` after each attempt, up to `max`
+    Exponential {
+        /// Delay before the first retry
+        base: Duration,
+        /// Maximum delay
+        max: Duration,
+        /// Growth factor per attempt
+        factor: f64,
+    },
+    /// Increases the delay by `step` after each attempt, up to `max`
+    Linear {
+        /// Delay before the first retry
+        base: Duration,
+        /// Increase per attempt
+        step: Duration,
+        /// Maximum delay
+        max: Duration,
+    },
+    /// Waits a uniformly random duration between `base` and `max`
+    Jitter {
+        /// Minimum delay
+        base: Duration,
+        /// Maximum delay
+        max: Duration,
+    },
+}
+
+impl BackoffStrategy {
+    /// Returns the default strategy for retrying allocations: exponential backoff starting
+    /// at 100 ms, doubling up to 5 s.
+    pub fn default_allocation() -> Self {
+        BackoffStrategy::Exponential {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(5),
+            factor: 2.0,
+        }
+    }
+
+    /// Returns the delay before retry number `attempt`, starting at 0.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        match *self {
+            BackoffStrategy::Fixed(delay) => delay,
+            BackoffStrategy::Exponential { base, max, factor } => {
+                let secs = base.as_secs_f64() * factor.powi(attempt.min(i32::MAX as u32) as i32);
+                if secs.is_finite() && secs < max.as_secs_f64() {
+                    Duration::from_secs_f64(secs.max(0.0))
+                } else {
+                    max
+                }
+            }
+            BackoffStrategy::Linear { base, step, max } => step
+                .checked_mul(attempt)
+                .and_then(|inc| base.checked_add(inc))
+                .map_or(max, |delay| delay.min(max)),
+            BackoffStrategy::Jitter { base, max } => {
+                let min = base.min(max).as_millis() as u64;
+                let max = max.as_millis() as u64;
+                Duration::from_millis(rand::rng().random_range(min..=max))
+            }
+        }
+    }
+}
+
+/// Registers a SIGINT handler for graceful shutdown.
+///
+/// Returns a flag that is set to `true` once SIGINT is received. Pass it to
+/// [`SwageConfig::stop_flag`](crate::SwageConfig::stop_flag) to stop running
+/// experiments after the current hammering round.
+pub fn setup_signal_handler() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&flag)) {
+        log::warn!("Failed to register SIGINT handler: {}", e);
+    }
+    flag
 }
 
 /// Trait for reading lines of strings from a stream with timeout support.
@@ -149,7 +358,9 @@ impl ReadLine for std::process::ChildStdout {
 
 #[cfg(test)]
 mod tests {
-    use super::GroupBy;
+    use super::{BackoffStrategy, GroupBy, GroupBySorted, try_make_vec};
+    use std::collections::HashMap;
+    use std::time::Duration;
 
     #[test]
     fn test_group_mod2() {
@@ -178,4 +389,133 @@ mod tests {
         assert_eq!(groups["a"], vec!["apple", "apricot"]);
         assert_eq!(groups["b"], vec!["banana", "blueberry"]);
     }
+
+    #[test]
+    fn test_try_make_vec_error() {
+        let mut calls = 0;
+        let result = try_make_vec(5, |i| {
+            calls += 1;
+            if i == 2 { Err(i) } else { Ok(i) }
+        });
+        assert_eq!(result, Err(2));
+        assert_eq!(calls, 3);
+        assert_eq!(try_make_vec(0, |_| Err::<usize, _>(())), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_group_hashmap() {
+        let map: HashMap<&str, usize> = [("a", 1), ("b", 2), ("c", 3), ("d", 4)].into();
+        let mut groups = map.group_by(|x| x % 2);
+        assert_eq!(groups.len(), 2);
+        groups.values_mut().for_each(|group| group.sort());
+        assert_eq!(groups[&0], vec![2, 4]);
+        assert_eq!(groups[&1], vec![1, 3]);
+    }
+
+    #[test]
+    fn test_group_sorted() {
+        let addrs = vec![5, 3, 8, 1, 6];
+        let groups = addrs.group_by_sorted(|x| x % 3);
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(groups[&0], vec![3, 6]);
+        assert_eq!(groups[&1], vec![1]);
+        assert_eq!(groups[&2], vec![5, 8]);
+        let map: HashMap<usize, usize> = (0..10).map(|i| (i, 9 - i)).collect();
+        let groups = map.group_by_sorted(|x| x / 5);
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    /// Returns the delays of the first `n` attempts
+    fn delays(strategy: BackoffStrategy, n: u32) -> Vec<Duration> {
+        (0..n).map(|i| strategy.delay_for_attempt(i)).collect()
+    }
+
+    #[test]
+    fn test_backoff_fixed() {
+        let delay = Duration::from_millis(42);
+        assert!(
+            delays(BackoffStrategy::Fixed(delay), 10)
+                .iter()
+                .all(|&d| d == delay)
+        );
+    }
+
+    #[test]
+    fn test_backoff_exponential() {
+        let strategy = BackoffStrategy::default_allocation();
+        let delays = delays(strategy, 100);
+        assert_eq!(delays[0], Duration::from_millis(100));
+        assert_eq!(delays[3], Duration::from_millis(800));
+        assert!(delays.windows(2).all(|w| w[0] <= w[1]));
+        assert!(delays.iter().all(|&d| d <= Duration::from_secs(5)));
+        assert_eq!(delays[99], Duration::from_secs(5));
+        assert_eq!(strategy.delay_for_attempt(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_linear() {
+        let strategy = BackoffStrategy::Linear {
+            base: Duration::from_millis(10),
+            step: Duration::from_millis(20),
+            max: Duration::from_millis(100),
+        };
+        let delays = delays(strategy, 10);
+        assert_eq!(delays[..3], [10, 30, 50].map(Duration::from_millis));
+        assert!(delays.windows(2).all(|w| w[0] <= w[1]));
+        assert!(delays.iter().all(|&d| d <= Duration::from_millis(100)));
+        assert_eq!(
+            strategy.delay_for_attempt(u32::MAX),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let strategy = BackoffStrategy::Jitter {
+            base: Duration::from_millis(5),
+            max: Duration::from_millis(50),
+        };
+        assert!(
+            delays(strategy, 100)
+                .iter()
+                .all(|&d| d >= Duration::from_millis(5) && d <= Duration::from_millis(50))
+        );
+    }
+
+    #[test]
+    fn test_retry_with_backoff() {
+        let attempts = std::cell::Cell::new(0);
+        let value = crate::retry!(
+            || {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err("failed")
+                } else {
+                    Ok(42)
+                }
+            },
+            BackoffStrategy::Fixed(Duration::ZERO)
+        );
+        assert_eq!(value, 42);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_max_retries() {
+        let attempts = std::cell::Cell::new(0);
+        let count = || {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(attempts.get())
+            } else {
+                Ok(attempts.get())
+            }
+        };
+        let backoff = BackoffStrategy::Fixed(Duration::ZERO);
+        assert_eq!(crate::retry!(&count, backoff, 3), Ok(3));
+        attempts.set(0);
+        // the only retry fails as well, so its error is returned
+        assert_eq!(crate::retry!(&count, backoff, 1), Err(2));
+        assert_eq!(attempts.get(), 2);
+    }
 }
@@ -1,10 +1,14 @@
 use rand::{RngCore, SeedableRng, rngs::StdRng};
 use serde::Serialize;
+use std::hash::{Hash, Hasher};
 
 /// Seedable random number generator.
 ///
 /// Wraps StdRng to provide deterministic randomness from a seed value.
-#[derive(Debug, Serialize, PartialEq, Eq)]
+///
+/// Two generators are equal if they were created from the same seed, regardless of how
+/// many values they produced since.
+#[derive(Debug, Serialize)]
 pub struct Rng {
     seed: u64,
     #[serde(skip_serializing)]
@@ -23,6 +27,25 @@ impl Rng {
             rng: StdRng::seed_from_u64(seed),
         }
     }
+
+    /// Returns the seed this RNG was created from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl PartialEq for Rng {
+    fn eq(&self, other: &Self) -> bool {
+        self.seed == other.seed
+    }
+}
+
+impl Eq for Rng {}
+
+impl Hash for Rng {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.seed.hash(state);
+    }
 }
 
 impl RngCore for Rng {
@@ -58,4 +81,13 @@ mod tests {
         let b = cloned_rng.next_u64();
         assert_eq!(a, b, "Cloned Rng should start with the same seed");
     }
+
+    #[test]
+    fn test_rng_eq() {
+        let mut rng = Rng::from_seed(0x42);
+        rng.next_u64();
+        assert_eq!(rng, Rng::from_seed(0x42));
+        assert_eq!(rng.seed(), 0x42);
+        assert_ne!(rng, Rng::from_seed(0x43));
+    }
 }
@@ -17,6 +17,15 @@
 /// let large = Size::GB(2);
 /// assert_eq!(large.bytes(), 2 * (1 << 30));
 /// ```
+///
+/// Sizes compare by their value in bytes, independent of the unit:
+///
+/// ```
+/// use swage_core::util::Size;
+///
+/// assert_eq!(Size::KB(1024), Size::MB(1));
+/// assert!(Size::GB(1) > Size::MB(1023));
+/// ```
 #[derive(Clone, Copy, Debug)]
 pub enum Size {
     /// Size in bytes
@@ -50,6 +59,96 @@ impl Size {
             Size::GB(gb) => *gb * (1 << 30),
         }
     }
+
+    /// Converts this size to bytes, assuming the conversion does not overflow.
+    ///
+    /// Behaves like [`Size::bytes`], but allows the optimizer to omit overflow handling.
+    ///
+    /// # Safety
+    ///
+    /// The size in bytes must fit into a `usize`.
+    #[inline(always)]
+    pub const unsafe fn bytes_unchecked(&self) -> usize {
+        let (value, shift) = match self {
+            Size::B(bytes) => (*bytes, 0),
+            Size::KB(kb) => (*kb, 10),
+            Size::MB(mb) => (*mb, 20),
+            Size::GB(gb) => (*gb, 30),
+        };
+        unsafe { core::hint::assert_unchecked(value <= usize::MAX >> shift) };
+        value << shift
+    }
+
+    /// Creates a size using the largest unit that represents `n` bytes exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use swage_core::util::Size;
+    ///
+    /// assert!(matches!(Size::from_bytes(2 << 30), Size::GB(2)));
+    /// assert!(matches!(Size::from_bytes(1536), Size::B(1536)));
+    /// ```
+    pub const fn from_bytes(n: usize) -> Size {
+        if n == 0 {
+            Size::B(0)
+        } else if n.is_multiple_of(1 << 30) {
+            Size::GB(n >> 30)
+        } else if n.is_multiple_of(1 << 20) {
+            Size::MB(n >> 20)
+        } else if n.is_multiple_of(1 << 10) {
+            Size::KB(n >> 10)
+        } else {
+            Size::B(n)
+        }
+    }
+
+    /// Parses a size such as `"4 MB"`, `"4MB"` or `"4096"`.
+    ///
+    /// Units are case-insensitive. Sizes without unit are interpreted as bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number or unit is invalid, or if the size in bytes does not
+    /// fit into a `usize`.
+    pub fn parse(s: &str) -> Result<Size, ParseSizeError> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let value = value.parse::<usize>()?;
+        let (size, shift) = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => (Size::B(value), 0),
+            "KB" => (Size::KB(value), 10),
+            "MB" => (Size::MB(value), 20),
+            "GB" => (Size::GB(value), 30),
+            unit => return Err(ParseSizeError::UnknownUnit(unit.into())),
+        };
+        if value > usize::MAX >> shift {
+            return Err(ParseSizeError::TooLarge(s.into()));
+        }
+        Ok(size)
+    }
+}
+
+/// Errors that can occur when parsing a [`Size`].
+#[derive(Debug, thiserror::Error)]
+pub enum ParseSizeError {
+    /// The numeric part is not a valid number
+    #[error(transparent)]
+    InvalidNumber(#[from] std::num::ParseIntError),
+    /// The unit is not one of B, KB, MB or GB
+    #[error("Unknown size unit: {0}")]
+    UnknownUnit(String),
+    /// The size in bytes does not fit into a `usize`
+    #[error("Size too large: {0}")]
+    TooLarge(String),
+}
+
+impl std::str::FromStr for Size {
+    type Err = ParseSizeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Size::parse(s)
+    }
 }
 
 impl std::fmt::Display for Size {
@@ -63,9 +162,140 @@ impl std::fmt::Display for Size {
     }
 }
 
+/// Serializes a size in its human-readable form, e.g., `"4 MB"`.
+impl serde::Serialize for Size {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Representations accepted when deserializing a [`Size`].
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SizeRepr {
+    /// A string such as `"4 MB"` or `"4MB"`
+    Text(String),
+    /// A plain number of bytes
+    Bytes(usize),
+    /// The structured form, e.g., `{"MB": 4}`
+    Unit(SizeUnit),
+}
+
+#[derive(serde::Deserialize)]
+enum SizeUnit {
+    B(usize),
+    KB(usize),
+    MB(usize),
+    GB(usize),
+}
+
+/// Deserializes a size from a string such as `"4 MB"`, a number of bytes, or the
+/// structured form `{"MB": 4}`.
+impl<'de> serde::Deserialize<'de> for Size {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        match SizeRepr::deserialize(deserializer).map_err(|_| {
+            D::Error::custom("expected a size such as \"4 MB\", a number of bytes or {\"MB\": 4}")
+        })? {
+            SizeRepr::Text(s) => Size::parse(&s).map_err(D::Error::custom),
+            SizeRepr::Bytes(bytes) => Ok(Size::B(bytes)),
+            SizeRepr::Unit(SizeUnit::B(bytes)) => Ok(Size::B(bytes)),
+            SizeRepr::Unit(SizeUnit::KB(kb)) => Ok(Size::KB(kb)),
+            SizeRepr::Unit(SizeUnit::MB(mb)) => Ok(Size::MB(mb)),
+            SizeRepr::Unit(SizeUnit::GB(gb)) => Ok(Size::GB(gb)),
+        }
+    }
+}
+
+impl PartialEq for Size {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes() == other.bytes()
+    }
+}
+
+impl Eq for Size {}
+
+impl PartialOrd for Size {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Size {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.bytes().cmp(&other.bytes())
+    }
+}
+
+impl std::hash::Hash for Size {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bytes().hash(state);
+    }
+}
+
+/// Adds two sizes. The result is in bytes, so no precision is lost for mixed units.
+impl std::ops::Add for Size {
+    type Output = Size;
+    fn add(self, rhs: Size) -> Size {
+        Size::B(self.bytes() + rhs.bytes())
+    }
+}
+
+/// Subtracts two sizes. The result is in bytes, so no precision is lost for mixed units.
+impl std::ops::Sub for Size {
+    type Output = Size;
+    fn sub(self, rhs: Size) -> Size {
+        Size::B(self.bytes() - rhs.bytes())
+    }
+}
+
+/// Multiplies a size by a factor, keeping its unit.
+impl std::ops::Mul<usize> for Size {
+    type Output = Size;
+    fn mul(self, rhs: usize) -> Size {
+        match self {
+            Size::B(bytes) => Size::B(bytes * rhs),
+            Size::KB(kb) => Size::KB(kb * rhs),
+            Size::MB(mb) => Size::MB(mb * rhs),
+            Size::GB(gb) => Size::GB(gb * rhs),
+        }
+    }
+}
+
+/// Divides a size by a divisor. The result is in bytes, rounded down.
+///
+/// # Panics
+///
+/// Panics if `rhs` is 0.
+impl std::ops::Div<usize> for Size {
+    type Output = Size;
+    fn div(self, rhs: usize) -> Size {
+        assert!(rhs != 0, "Cannot divide size {} by zero", self);
+        Size::B(self.bytes() / rhs)
+    }
+}
+
+/// Returns the remainder of dividing a size by a divisor, in bytes.
+///
+/// # Panics
+///
+/// Panics if `rhs` is 0.
+impl std::ops::Rem<usize> for Size {
+    type Output = Size;
+    fn rem(self, rhs: usize) -> Size {
+        assert!(
+            rhs != 0,
+            "Cannot compute remainder of size {} modulo zero",
+            self
+        );
+        Size::B(self.bytes() % rhs)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::util::Size;
+    use proptest::prelude::*;
 
     #[test]
     fn size_conversions() {
@@ -75,5 +305,155 @@ mod tests {
         assert_eq!(mb.bytes(), 12 * (1 << 20));
         let gb = Size::GB(12);
         assert_eq!(gb.bytes(), 12 * (1 << 30));
+        assert_eq!(unsafe { gb.bytes_unchecked() }, gb.bytes());
+    }
+
+    #[test]
+    fn size_from_bytes() {
+        assert!(matches!(Size::from_bytes(0), Size::B(0)));
+        assert!(matches!(Size::from_bytes(1023), Size::B(1023)));
+        assert!(matches!(Size::from_bytes(1024), Size::KB(1)));
+        assert!(matches!(Size::from_bytes((1 << 20) - 1024), Size::KB(1023)));
+        assert!(matches!(Size::from_bytes(1 << 20), Size::MB(1)));
+        assert!(matches!(
+            Size::from_bytes((1 << 30) + (1 << 20)),
+            Size::MB(1025)
+        ));
+        assert!(matches!(Size::from_bytes(1 << 30), Size::GB(1)));
+        assert!(matches!(Size::from_bytes((1 << 30) + 1), Size::B(_)));
+    }
+
+    #[test]
+    fn size_parse() {
+        assert_eq!("4 MB".parse::<Size>().unwrap().bytes(), 4 << 20);
+        assert_eq!("4mb".parse::<Size>().unwrap().bytes(), 4 << 20);
+        assert_eq!("1GB".parse::<Size>().unwrap().bytes(), 1 << 30);
+        assert_eq!("8 KB".parse::<Size>().unwrap().bytes(), 8192);
+        assert_eq!("4096".parse::<Size>().unwrap().bytes(), 4096);
+        assert_eq!(
+            Size::parse(&Size::MB(12).to_string()).unwrap().bytes(),
+            12 << 20
+        );
+        assert!("4 TB".parse::<Size>().is_err());
+        assert!("MB".parse::<Size>().is_err());
+        assert!(matches!(
+            format!("{} GB", usize::MAX >> 29).parse::<Size>(),
+            Err(crate::util::ParseSizeError::TooLarge(_))
+        ));
+        assert!(format!("{} GB", usize::MAX >> 30).parse::<Size>().is_ok());
+    }
+
+    #[test]
+    fn size_arithmetic() {
+        let sum = Size::MB(4) * 3 + Size::KB(512);
+        assert!(matches!(sum, Size::B(_)));
+        assert_eq!(sum.bytes(), 12 * (1 << 20) + 512 * (1 << 10));
+        assert!(matches!(Size::MB(4) * 3, Size::MB(12)));
+        assert_eq!((Size::GB(1) - Size::MB(1)).bytes(), 1023 << 20);
+        assert_eq!((Size::MB(3) / 2).bytes(), 3 << 19);
+        assert_eq!((Size::KB(3) / 2048).bytes(), 1);
+        assert_eq!((Size::KB(3) % 2048).bytes(), 1024);
+        assert_eq!((Size::MB(1) % 4096).bytes(), 0);
+        for (a, b) in [
+            (Size::B(1), Size::GB(1)),
+            (Size::KB(7), Size::MB(3)),
+            (Size::GB(2), Size::B(0)),
+        ] {
+            assert_eq!((a + b).bytes(), a.bytes() + b.bytes());
+            assert_eq!((b + a - a).bytes(), b.bytes());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "divide size 4 MB by zero")]
+    fn size_div_zero() {
+        let _ = Size::MB(4) / 0;
+    }
+
+    #[test]
+    #[should_panic(expected = "modulo zero")]
+    fn size_rem_zero() {
+        let _ = Size::MB(4) % 0;
+    }
+
+    #[test]
+    fn size_equality() {
+        assert_eq!(Size::KB(1024), Size::MB(1));
+        assert_eq!(Size::B(1 << 30), Size::GB(1));
+        assert_eq!(Size::B(0), Size::GB(0));
+        assert_ne!(Size::KB(1023), Size::MB(1));
+        assert_ne!(Size::B((1 << 20) + 1), Size::MB(1));
+        assert_eq!(Size::MB(4) * 3 + Size::KB(512), Size::KB(12 * 1024 + 512));
+        let set = std::collections::HashSet::from([Size::KB(2048), Size::MB(2)]);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn size_ordering() {
+        assert!(Size::MB(2) >= Size::KB(2048));
+        assert!(Size::MB(2) > Size::KB(2047));
+        assert!(Size::B(1025) > Size::KB(1));
+        assert!(Size::GB(1) > Size::MB(1023));
+        assert!(Size::GB(1) < Size::MB(1025));
+        let mut sizes = [
+            Size::GB(1),
+            Size::B(12),
+            Size::MB(3),
+            Size::KB(4),
+            Size::B(4096),
+        ];
+        sizes.sort();
+        assert_eq!(
+            sizes.iter().map(Size::bytes).collect::<Vec<_>>(),
+            vec![12, 4096, 4096, 3 << 20, 1 << 30]
+        );
+        assert_eq!(Size::KB(1).max(Size::B(1000)), Size::KB(1));
+    }
+
+    #[test]
+    fn size_serde() {
+        assert_eq!(serde_json::to_string(&Size::MB(4)).unwrap(), "\"4 MB\"");
+        assert_eq!(serde_json::to_string(&Size::GB(1)).unwrap(), "\"1 GB\"");
+        let parse = |json: &str| serde_json::from_str::<Size>(json);
+        assert!(matches!(parse("\"4MB\"").unwrap(), Size::MB(4)));
+        assert!(matches!(parse("\"4 kb\"").unwrap(), Size::KB(4)));
+        assert!(matches!(parse("{\"MB\": 4}").unwrap(), Size::MB(4)));
+        assert!(matches!(parse("{\"B\": 12}").unwrap(), Size::B(12)));
+        assert!(matches!(parse("4096").unwrap(), Size::B(4096)));
+        assert!(parse("\"4 TB\"").is_err());
+        assert!(parse("{\"TB\": 4}").is_err());
+        assert!(parse("-1").is_err());
+    }
+
+    fn any_size() -> impl Strategy<Value = Size> {
+        prop_oneof![
+            any::<usize>().prop_map(Size::B),
+            (0..usize::MAX >> 10).prop_map(Size::KB),
+            (0..usize::MAX >> 20).prop_map(Size::MB),
+            (0..usize::MAX >> 30).prop_map(Size::GB),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn size_serde_roundtrip(size in any_size()) {
+            let json = serde_json::to_string(&size).unwrap();
+            let parsed: Size = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(parsed, size);
+            prop_assert_eq!(parsed.to_string(), size.to_string());
+        }
+
+        #[test]
+        fn size_display_roundtrip(s in "[0-9]{1,9} ?([bB]|[kKmMgG][bB])?") {
+            let size = s.parse::<Size>().unwrap();
+            prop_assert_eq!(size.to_string().parse::<Size>().unwrap(), size);
+        }
+
+        #[test]
+        fn size_parse_arbitrary(s in "\\PC*") {
+            if let Ok(size) = s.parse::<Size>() {
+                prop_assert_eq!(size.to_string().parse::<Size>().unwrap(), size);
+            }
+        }
     }
 }
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Records durations of named experiment phases.
+///
+/// Phases are measured with [`PhaseGuard`]s obtained from
+/// [`ExperimentTimer::start_phase`]. A phase may be recorded multiple times,
+/// e.g., once per hammering round.
+pub struct ExperimentTimer {
+    start: Instant,
+    phases: HashMap<String, Vec<Duration>>,
+}
+
+/// Measures a single phase and records it in the [`ExperimentTimer`] when dropped.
+pub struct PhaseGuard<'a> {
+    timer: &'a mut ExperimentTimer,
+    name: String,
+    start: Instant,
+}
+
+/// Aggregated durations of a phase.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct PhaseSummary {
+    /// Number of recorded durations
+    pub count: usize,
+    /// Sum of all durations
+    pub total: Duration,
+    /// Shortest duration
+    pub min: Duration,
+    /// Longest duration
+    pub max: Duration,
+    /// Average duration
+    pub avg: Duration,
+}
+
+impl Default for ExperimentTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExperimentTimer {
+    /// Creates a new timer starting now.
+    pub fn new() -> Self {
+        ExperimentTimer {
+            start: Instant::now(),
+            phases: HashMap::new(),
+        }
+    }
+
+    /// Returns the time elapsed since the timer was created.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Starts measuring the phase `name`.
+    ///
+    /// The duration is recorded when the returned guard is dropped.
+    pub fn start_phase(&mut self, name: &str) -> PhaseGuard<'_> {
+        PhaseGuard {
+            timer: self,
+            name: name.to_string(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Records a duration for the phase `name`.
+    pub fn record(&mut self, name: &str, elapsed: Duration) {
+        self.phases
+            .entry(name.to_string())
+            .or_default()
+            .push(elapsed);
+    }
+
+    /// Summarizes the recorded durations per phase.
+    pub fn summary(&self) -> HashMap<String, PhaseSummary> {
+        self.phases
+            .iter()
+            .filter(|(_, durations)| !durations.is_empty())
+            .map(|(name, durations)| {
+                let total: Duration = durations.iter().sum();
+                let summary = PhaseSummary {
+                    count: durations.len(),
+                    total,
+                    min: *durations.iter().min().unwrap(),
+                    max: *durations.iter().max().unwrap(),
+                    avg: total / durations.len() as u32,
+                };
+                (name.clone(), summary)
+            })
+            .collect()
+    }
+}
+
+impl Drop for PhaseGuard<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        self.timer.record(&self.name, elapsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_guard() {
+        let mut timer = ExperimentTimer::new();
+        for _ in 0..2 {
+            let _phase = timer.start_phase("sleep");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        timer.record("manual", Duration::from_millis(5));
+
+        let summary = timer.summary();
+        let sleep = &summary["sleep"];
+        assert_eq!(sleep.count, 2);
+        assert!(sleep.min >= Duration::from_millis(10));
+        assert!(sleep.total >= Duration::from_millis(20));
+        assert!(sleep.min <= sleep.avg && sleep.avg <= sleep.max);
+        assert_eq!(
+            summary["manual"],
+            PhaseSummary {
+                count: 1,
+                total: Duration::from_millis(5),
+                min: Duration::from_millis(5),
+                max: Duration::from_millis(5),
+                avg: Duration::from_millis(5),
+            }
+        );
+        assert!(timer.elapsed() >= sleep.total);
+    }
+}
@@ -10,9 +10,12 @@
 
 use crate::memory::BitFlip;
 use crate::memory::FlippyPage;
+use crate::memory::HammingCheckResult;
 use crate::memory::LinuxPageMapError;
 use core::panic;
+use log::warn;
 use serde::Serialize;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Errors that can occur during victim operations.
@@ -47,6 +50,12 @@ pub enum HammerVictimError {
     /// A protocol-level error occurred in victim communication.
     #[error("Protocol Error: {0}")]
     ProtocolError(String),
+    /// The victim memory failed the health check before the experiment.
+    #[error("Memory health check failed: {0:?}")]
+    HealthCheckFailed(HammingCheckResult),
+    /// The victim check did not complete within the given duration.
+    #[error("Victim check timed out after {0:?}")]
+    Timeout(Duration),
 }
 
 /// Result type returned by victim check operations.
@@ -77,6 +86,64 @@ impl VictimResult {
             _ => panic!("Invalid variant. Expected BitFlips, got {:?}", self),
         }
     }
+
+    /// Merges two results into one.
+    ///
+    /// [`VictimResult::Nothing`] is neutral. Results of the same kind are concatenated,
+    /// string results are combined into [`VictimResult::Strings`]. Bit flips merged with
+    /// string results are rendered using their `Debug` representation.
+    pub fn merge(self, other: VictimResult) -> VictimResult {
+        match (self, other) {
+            (VictimResult::Nothing, other) => other,
+            (this, VictimResult::Nothing) => this,
+            (VictimResult::BitFlips(mut flips), VictimResult::BitFlips(other)) => {
+                flips.extend(other);
+                VictimResult::BitFlips(flips)
+            }
+            (this, other) => {
+                let mut strings = this.into_strings();
+                strings.extend(other.into_strings());
+                VictimResult::Strings(strings)
+            }
+        }
+    }
+
+    /// Merges all `results` into one using [`VictimResult::merge`].
+    ///
+    /// Returns [`VictimResult::Nothing`] if `results` is empty.
+    pub fn merge_all(results: Vec<VictimResult>) -> VictimResult {
+        results
+            .into_iter()
+            .fold(VictimResult::Nothing, VictimResult::merge)
+    }
+
+    /// Returns true if this result reports any effect of the attack, i.e., at least one
+    /// bit flip or a non-empty message.
+    pub fn is_success(&self) -> bool {
+        match self {
+            VictimResult::BitFlips(flips) => !flips.is_empty(),
+            VictimResult::String(s) => !s.is_empty(),
+            VictimResult::Strings(strings) => !strings.is_empty(),
+            VictimResult::Nothing => false,
+        }
+    }
+
+    /// Returns the number of bit flips in this result, or 0 for non-`BitFlips` results.
+    pub fn flip_count(&self) -> usize {
+        match self {
+            VictimResult::BitFlips(flips) => flips.len(),
+            _ => 0,
+        }
+    }
+
+    fn into_strings(self) -> Vec<String> {
+        match self {
+            VictimResult::BitFlips(flips) => flips.iter().map(|f| format!("{:?}", f)).collect(),
+            VictimResult::String(s) => vec![s],
+            VictimResult::Strings(strings) => strings,
+            VictimResult::Nothing => vec![],
+        }
+    }
 }
 
 /// Trait for orchestrating victim applications or memory regions targeted by Rowhammer attacks.
@@ -95,6 +162,7 @@ impl VictimResult {
 /// 5. [`stop()`](VictimOrchestrator::stop) - Clean up victim resources
 ///
 /// Steps 2-4 may be repeated multiple times between start and stop.
+/// [`reset()`](VictimOrchestrator::reset) re-initializes the victim between repetitions.
 ///
 /// # Examples
 ///
@@ -140,12 +208,52 @@ pub trait VictimOrchestrator {
     /// * The victim is not in a valid state
     fn check(&mut self) -> Result<VictimResult, HammerVictimError>;
 
+    /// Checks if the hammering attack was successful, giving up after `timeout`.
+    ///
+    /// The default implementation runs [`check()`](VictimOrchestrator::check) and only
+    /// logs a warning if it took longer than `timeout`. As the check borrows the victim,
+    /// it cannot be abandoned on another thread, so a hanging check still blocks, and a
+    /// finished check is not worth discarding. Victims that may hang, e.g., processes
+    /// communicating via a pipe, should override this to interrupt the check.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by [`check()`](VictimOrchestrator::check). Overriding
+    /// implementations return [`HammerVictimError::Timeout`] if the check did not complete
+    /// in time.
+    fn check_with_timeout(&mut self, timeout: Duration) -> Result<VictimResult, HammerVictimError> {
+        let start = Instant::now();
+        let result = self.check();
+        let elapsed = start.elapsed();
+        if elapsed > timeout {
+            warn!(
+                "Victim check took {:?}, exceeding timeout {:?}",
+                elapsed, timeout
+            );
+        }
+        result
+    }
+
     /// Stops the victim and releases resources.
     ///
     /// This method is called at the end of an experiment to clean up the victim
     /// environment, stop processes, and release any allocated resources.
     fn stop(&mut self);
 
+    /// Resets the victim between attack iterations.
+    ///
+    /// The default implementation calls [`stop()`](VictimOrchestrator::stop) and then
+    /// [`start()`](VictimOrchestrator::start). Implementors that can reset more cheaply,
+    /// e.g., without relaunching a process, should override this.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HammerVictimError`] if the victim cannot be restarted.
+    fn reset(&mut self) -> Result<(), HammerVictimError> {
+        self.stop();
+        self.start()
+    }
+
     /// Optionally serializes victim-specific data to JSON.
     ///
     /// This method allows victims to provide additional metadata or state
@@ -159,3 +267,278 @@ pub trait VictimOrchestrator {
         None
     }
 }
+
+/// A sequence of victims orchestrated as one.
+///
+/// Allows checking multiple victims in the same experiment, e.g., a
+/// [`MemCheck`](crate::MemCheck) alongside a victim process. Victims are started,
+/// initialized and checked in insertion order and stopped in reverse order.
+#[derive(Default)]
+pub struct OrchestratorChain(Vec<Box<dyn VictimOrchestrator>>);
+
+impl OrchestratorChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a victim to the chain.
+    pub fn push(&mut self, victim: Box<dyn VictimOrchestrator>) {
+        self.0.push(victim);
+    }
+
+    /// Checks each victim using `check` and merges the results.
+    fn check_each(
+        &mut self,
+        mut check: impl FnMut(&mut dyn VictimOrchestrator) -> Result<VictimResult, HammerVictimError>,
+    ) -> Result<VictimResult, HammerVictimError> {
+        let mut result = None;
+        for victim in &mut self.0 {
+            match check(victim.as_mut()) {
+                Ok(r) => {
+                    result = Some(match result {
+                        Some(prev) => VictimResult::merge(prev, r),
+                        None => r,
+                    })
+                }
+                Err(HammerVictimError::NoFlips) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        result.ok_or(HammerVictimError::NoFlips)
+    }
+}
+
+impl VictimOrchestrator for OrchestratorChain {
+    /// Starts all victims in order.
+    ///
+    /// If a victim fails to start, all previously started victims are stopped again.
+    fn start(&mut self) -> Result<(), HammerVictimError> {
+        for idx in 0..self.0.len() {
+            if let Err(e) = self.0[idx].start() {
+                for victim in self.0[..idx].iter_mut().rev() {
+                    victim.stop();
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self) {
+        for victim in &mut self.0 {
+            victim.init();
+        }
+    }
+
+    /// Checks all victims and merges their results using [`VictimResult::merge`].
+    ///
+    /// Victims reporting [`HammerVictimError::NoFlips`] are skipped, any other error is
+    /// returned immediately. Returns [`HammerVictimError::NoFlips`] if no victim
+    /// reported a result.
+    fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
+        self.check_each(|victim| victim.check())
+    }
+
+    /// Checks all victims like [`check()`](VictimOrchestrator::check), passing the time
+    /// remaining of `timeout` to each victim.
+    fn check_with_timeout(&mut self, timeout: Duration) -> Result<VictimResult, HammerVictimError> {
+        let start = Instant::now();
+        self.check_each(|victim| {
+            let remaining = timeout
+                .checked_sub(start.elapsed())
+                .ok_or(HammerVictimError::Timeout(timeout))?;
+            victim.check_with_timeout(remaining)
+        })
+    }
+
+    fn stop(&mut self) {
+        for victim in self.0.iter_mut().rev() {
+            victim.stop();
+        }
+    }
+
+    /// Resets all victims in order, stopping at the first error.
+    fn reset(&mut self) -> Result<(), HammerVictimError> {
+        for victim in &mut self.0 {
+            victim.reset()?;
+        }
+        Ok(())
+    }
+
+    /// Serializes all victims into a JSON array.
+    ///
+    /// Victims without data are represented as `null`.
+    fn serialize(&self) -> Option<serde_json::Value> {
+        Some(serde_json::Value::Array(
+            self.0
+                .iter()
+                .map(|victim| victim.serialize().unwrap_or(serde_json::Value::Null))
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemCheck;
+    use crate::memory::{BytePointer, ConsecBlocks, DataPattern, Memory};
+    use crate::util::PAGE_SIZE;
+
+    fn mem_check() -> (MemCheck, *mut u8) {
+        let memory = Memory::mmap(PAGE_SIZE).expect("mmap failed");
+        let ptr = memory.ptr();
+        let victim = MemCheck::new(
+            ConsecBlocks::new(vec![memory]),
+            DataPattern::Zero,
+            vec![].into(),
+        );
+        (victim, ptr)
+    }
+
+    #[test]
+    fn test_victim_result_merge() {
+        let flip = BitFlip::new(0x1000 as *const u8, 0x1, 0x0);
+        let merged = VictimResult::Nothing.merge(VictimResult::BitFlips(vec![flip]));
+        assert_eq!(merged.bit_flips(), vec![flip]);
+        let merged = VictimResult::BitFlips(vec![flip]).merge(VictimResult::BitFlips(vec![flip]));
+        assert_eq!(merged.bit_flips(), vec![flip, flip]);
+        let merged = VictimResult::String("a".into()).merge(VictimResult::String("b".into()));
+        assert!(matches!(merged, VictimResult::Strings(s) if s == ["a", "b"]));
+    }
+
+    #[test]
+    fn test_victim_result_merge_all() {
+        let flip = BitFlip::new(0x1000 as *const u8, 0x1, 0x0);
+        assert!(matches!(
+            VictimResult::merge_all(vec![]),
+            VictimResult::Nothing
+        ));
+        let merged = VictimResult::merge_all(vec![
+            VictimResult::BitFlips(vec![flip]),
+            VictimResult::Nothing,
+            VictimResult::BitFlips(vec![flip, flip]),
+        ]);
+        assert_eq!(merged.flip_count(), 3);
+        assert!(merged.is_success());
+        let merged = VictimResult::merge_all(vec![
+            VictimResult::String("a".into()),
+            VictimResult::BitFlips(vec![flip]),
+        ]);
+        assert_eq!(merged.flip_count(), 0);
+        assert!(matches!(&merged, VictimResult::Strings(s) if s.len() == 2 && s[0] == "a"));
+
+        assert!(!VictimResult::Nothing.is_success());
+        assert!(!VictimResult::BitFlips(vec![]).is_success());
+        assert!(!VictimResult::String(String::new()).is_success());
+        assert!(VictimResult::Strings(vec!["a".into()]).is_success());
+    }
+
+    #[test]
+    fn test_orchestrator_chain() {
+        let (first, _) = mem_check();
+        let (second, second_ptr) = mem_check();
+        let mut chain = OrchestratorChain::new();
+        chain.push(Box::new(first));
+        chain.push(Box::new(second));
+        chain.start().expect("start failed");
+
+        chain.init();
+        assert!(matches!(chain.check(), Err(HammerVictimError::NoFlips)));
+
+        chain.init();
+        unsafe { *second_ptr.add(42) = 0x10 };
+        let flips = chain.check().expect("check failed").bit_flips();
+        assert_eq!(
+            flips,
+            vec![BitFlip::new(second_ptr.wrapping_add(42), 0x10, 0x0)]
+        );
+
+        let json = chain.serialize().expect("no data");
+        assert_eq!(json.as_array().map(|a| a.len()), Some(2));
+        chain.stop();
+    }
+
+    /// Counts lifecycle calls.
+    #[derive(Default)]
+    struct CountingVictim {
+        starts: usize,
+        stops: usize,
+    }
+
+    impl VictimOrchestrator for CountingVictim {
+        fn start(&mut self) -> Result<(), HammerVictimError> {
+            self.starts += 1;
+            Ok(())
+        }
+
+        fn init(&mut self) {}
+
+        fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
+            Err(HammerVictimError::NoFlips)
+        }
+
+        fn stop(&mut self) {
+            self.stops += 1;
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut victim = CountingVictim::default();
+        victim.start().expect("start failed");
+        victim.reset().expect("reset failed");
+        assert_eq!((victim.starts, victim.stops), (2, 1));
+
+        // MemCheck only runs its health check on start
+        let (mem_check, ptr) = mem_check();
+        let mut chain = OrchestratorChain::new();
+        chain.push(Box::new(mem_check.with_health_check(0.0)));
+        chain.start().expect("start failed");
+        unsafe { *ptr = 0xFF };
+        chain.reset().expect("reset failed");
+        chain.stop();
+    }
+
+    /// Victim whose check takes 20ms.
+    struct SlowVictim;
+
+    impl VictimOrchestrator for SlowVictim {
+        fn start(&mut self) -> Result<(), HammerVictimError> {
+            Ok(())
+        }
+
+        fn init(&mut self) {}
+
+        fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
+            std::thread::sleep(Duration::from_millis(20));
+            Err(HammerVictimError::NoFlips)
+        }
+
+        fn stop(&mut self) {}
+    }
+
+    #[test]
+    fn test_check_with_timeout() {
+        let mut victim = SlowVictim;
+        assert!(matches!(
+            victim.check_with_timeout(Duration::from_secs(1)),
+            Err(HammerVictimError::NoFlips)
+        ));
+        // the default implementation keeps the result of an overrunning check
+        assert!(matches!(
+            victim.check_with_timeout(Duration::from_millis(1)),
+            Err(HammerVictimError::NoFlips)
+        ));
+
+        // the second victim has no time left
+        let mut chain = OrchestratorChain::new();
+        chain.push(Box::new(SlowVictim));
+        chain.push(Box::new(SlowVictim));
+        assert!(matches!(
+            chain.check_with_timeout(Duration::from_millis(10)),
+            Err(HammerVictimError::Timeout(_))
+        ));
+    }
+}
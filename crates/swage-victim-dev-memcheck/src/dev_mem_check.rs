@@ -1,7 +1,7 @@
 use libc::{
     MAP_ANONYMOUS, MAP_FAILED, MAP_POPULATE, MAP_SHARED, PROT_READ, PROT_WRITE, mmap, munmap,
 };
-use log::debug;
+use log::{debug, warn};
 use serde::Serialize;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -42,17 +42,14 @@ impl DevMemCheck {
     ///
     /// Returns error if physical addresses cannot be resolved
     pub fn new(targets: Vec<BitFlip>) -> Result<Self> {
-        Ok(DevMemCheck {
-            targets: targets
-                .into_iter()
-                .map(|target| {
-                    (target.addr as *const u8)
-                        .pfn()
-                        .map(|pfn| (target, pfn))
-                        .map_err(|e| e.into())
-                })
-                .collect::<Result<Vec<_>>>()?,
-        })
+        let mut resolved = Vec::with_capacity(targets.len());
+        for target in targets {
+            match (target.addr as *const u8).pfn()? {
+                Some(pfn) => resolved.push((target, pfn)),
+                None => warn!("Skipping target {:?}: page not present", target),
+            }
+        }
+        Ok(DevMemCheck { targets: resolved })
     }
 }
 
@@ -139,6 +136,12 @@ impl VictimOrchestrator for DevMemCheck {
     }
 
     fn stop(&mut self) {}
+
+    /// Does nothing, as the target pages are unmapped once on start and
+    /// [`VictimOrchestrator::init`] rewrites all targets.
+    fn reset(&mut self) -> std::result::Result<(), HammerVictimError> {
+        Ok(())
+    }
 }
 
 impl From<DevMemCheckError> for HammerVictimError {
@@ -0,0 +1,31 @@
+//! Victim running as a separate process.
+//!
+//! This crate provides a victim implementation that launches an arbitrary victim
+//! binary and communicates with it over a pipe protocol. The victim process is
+//! responsible for setting up its own state and reporting whether hammering induced
+//! bit flips, which allows testing real victim applications without implementing
+//! [`swage_core::victim::VictimOrchestrator`] manually.
+//!
+//! Implements the [`swage_core::victim::VictimOrchestrator`] trait.
+//!
+//! # Protocol
+//!
+//! With [`VictimProtocol::StdioJson`], messages are newline-delimited JSON objects
+//! exchanged over the victim's stdin and stdout:
+//!
+//! | Request           | Response                                                 |
+//! |-------------------|----------------------------------------------------------|
+//! | `{"cmd":"init"}`  | `{"ok":true}`                                            |
+//! | `{"cmd":"check"}` | `{"result":"flip","addr":"0x...","bitmask":"0x..."}`     |
+//! |                   | `{"result":"none"}`                                      |
+//!
+//! # Use Cases
+//!
+//! - Attacking real-world victim applications
+//! - Victims written in other languages than Rust
+
+#![warn(missing_docs)]
+
+mod process_victim;
+
+pub use process_victim::{ProcessVictim, VictimProtocol};
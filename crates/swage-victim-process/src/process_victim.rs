@@ -0,0 +1,333 @@
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use swage_core::memory::BitFlip;
+use swage_core::util::ReadLine;
+use swage_core::victim::{HammerVictimError, VictimOrchestrator, VictimResult};
+
+/// Protocol used to communicate with a [`ProcessVictim`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum VictimProtocol {
+    /// Newline-delimited JSON over the victim's stdin and stdout.
+    #[default]
+    StdioJson,
+}
+
+#[derive(Serialize)]
+struct Request {
+    cmd: &'static str,
+}
+
+#[derive(Deserialize)]
+struct InitResponse {
+    ok: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+enum CheckResponse {
+    Flip {
+        addr: String,
+        bitmask: String,
+        #[serde(default)]
+        data: Option<String>,
+    },
+    None,
+}
+
+/// Victim running as a separate process.
+///
+/// The victim binary is spawned on [`VictimOrchestrator::start`] and killed on
+/// [`VictimOrchestrator::stop`]. Initialization and checking are delegated to the victim
+/// process using the configured [`VictimProtocol`].
+///
+/// Responses are read on a separate thread, so [`VictimOrchestrator::check_with_timeout`]
+/// returns once the timeout expires even if the victim hangs. The victim is stopped in
+/// that case and must be restarted, e.g., using [`VictimOrchestrator::reset`].
+pub struct ProcessVictim {
+    command: Command,
+    protocol: VictimProtocol,
+    running: Option<RunningVictim>,
+}
+
+struct RunningVictim {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines read from the victim's stdout
+    responses: Receiver<std::io::Result<Vec<u8>>>,
+}
+
+impl ProcessVictim {
+    /// Creates a new process victim.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Command launching the victim binary. Stdin and stdout are replaced
+    ///   by pipes when the victim is started.
+    /// * `protocol` - Protocol spoken by the victim binary
+    pub fn new(command: Command, protocol: VictimProtocol) -> Self {
+        ProcessVictim {
+            command,
+            protocol,
+            running: None,
+        }
+    }
+
+    /// Sends `cmd` to the victim and parses its response.
+    ///
+    /// If no response arrives within `timeout`, the victim is stopped, as a late response
+    /// would be mistaken for the response to the next request.
+    fn request<T: DeserializeOwned>(
+        &mut self,
+        cmd: &'static str,
+        timeout: Option<Duration>,
+    ) -> Result<T, HammerVictimError> {
+        let running = self.running.as_mut().ok_or(HammerVictimError::NotRunning)?;
+        match self.protocol {
+            VictimProtocol::StdioJson => {
+                let mut line = serde_json::to_vec(&Request { cmd })
+                    .map_err(|e| HammerVictimError::ProtocolError(e.to_string()))?;
+                line.push(b'\n');
+                running.stdin.write_all(&line)?;
+                running.stdin.flush()?;
+                let response = match timeout {
+                    Some(timeout) => match running.responses.recv_timeout(timeout) {
+                        Ok(response) => Some(response),
+                        Err(RecvTimeoutError::Timeout) => {
+                            warn!("Victim did not respond to {} within {:?}", cmd, timeout);
+                            self.stop();
+                            return Err(HammerVictimError::Timeout(timeout));
+                        }
+                        Err(RecvTimeoutError::Disconnected) => None,
+                    },
+                    None => running.responses.recv().ok(),
+                };
+                let response = match response {
+                    Some(Ok(response)) => response,
+                    Some(Err(e)) if e.kind() != std::io::ErrorKind::WouldBlock => {
+                        return Err(e.into());
+                    }
+                    _ => {
+                        return Err(HammerVictimError::ProtocolError(format!(
+                            "Victim closed stdout before responding to {}",
+                            cmd
+                        )));
+                    }
+                };
+                debug!("Victim response: {}", String::from_utf8_lossy(&response));
+                serde_json::from_slice(&response).map_err(|e| {
+                    HammerVictimError::ProtocolError(format!(
+                        "Invalid response to {}: {:?} ({})",
+                        cmd,
+                        String::from_utf8_lossy(&response),
+                        e
+                    ))
+                })
+            }
+        }
+    }
+
+    /// Sends the check command and converts the response into a [`VictimResult`].
+    fn check_response(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<VictimResult, HammerVictimError> {
+        match self.request::<CheckResponse>("check", timeout)? {
+            CheckResponse::Flip {
+                addr,
+                bitmask,
+                data,
+            } => {
+                let flip = BitFlip::new(
+                    parse_hex(&addr)? as *const u8,
+                    parse_hex_u8(&bitmask)?,
+                    data.as_deref().map(parse_hex_u8).transpose()?.unwrap_or(0),
+                );
+                Ok(VictimResult::BitFlips(vec![flip]))
+            }
+            CheckResponse::None => Err(HammerVictimError::NoFlips),
+        }
+    }
+}
+
+/// Parses a hexadecimal number with optional `0x` prefix.
+fn parse_hex(value: &str) -> Result<usize, HammerVictimError> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    usize::from_str_radix(digits, 16).map_err(|e| {
+        HammerVictimError::ProtocolError(format!("Invalid hex value {}: {}", value, e))
+    })
+}
+
+fn parse_hex_u8(value: &str) -> Result<u8, HammerVictimError> {
+    u8::try_from(parse_hex(value)?)
+        .map_err(|e| HammerVictimError::ProtocolError(format!("Invalid byte {}: {}", value, e)))
+}
+
+impl VictimOrchestrator for ProcessVictim {
+    fn start(&mut self) -> Result<(), HammerVictimError> {
+        if self.running.is_some() {
+            return Ok(());
+        }
+        let mut child = self
+            .command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let (tx, responses) = mpsc::channel();
+        // exits once the victim closes stdout or the victim is stopped
+        std::thread::spawn(move || {
+            loop {
+                let line = stdout.read_line();
+                let closed = line.is_err();
+                if tx.send(line).is_err() || closed {
+                    break;
+                }
+            }
+        });
+        debug!("Started victim process {}", child.id());
+        self.running = Some(RunningVictim {
+            child,
+            stdin,
+            responses,
+        });
+        Ok(())
+    }
+
+    fn init(&mut self) {
+        match self.request::<InitResponse>("init", None) {
+            Ok(InitResponse { ok: true }) => {}
+            Ok(InitResponse { ok: false }) => warn!("Victim process reported failed init"),
+            Err(e) => warn!("Failed to initialize victim process: {}", e),
+        }
+    }
+
+    fn check(&mut self) -> Result<VictimResult, HammerVictimError> {
+        self.check_response(None)
+    }
+
+    fn check_with_timeout(&mut self, timeout: Duration) -> Result<VictimResult, HammerVictimError> {
+        self.check_response(Some(timeout))
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut running) = self.running.take() {
+            // closing stdin lets well-behaved victims exit on their own
+            drop(running.stdin);
+            if let Err(e) = running.child.kill() {
+                warn!("Failed to kill victim process: {}", e);
+            }
+            if let Err(e) = running.child.wait() {
+                warn!("Failed to wait for victim process: {}", e);
+            }
+        }
+    }
+
+    fn serialize(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({
+            "program": self.command.get_program().to_string_lossy(),
+            "args": self
+                .command
+                .get_args()
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>(),
+            "protocol": self.protocol,
+        }))
+    }
+}
+
+impl Drop for ProcessVictim {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawns a shell victim answering `check` with `check_response`.
+    fn victim(check_response: &str) -> ProcessVictim {
+        let script = format!(
+            r#"while read line; do
+                case "$line" in
+                    *init*) echo '{{"ok":true}}' ;;
+                    *check*) echo '{}' ;;
+                esac
+            done"#,
+            check_response
+        );
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        ProcessVictim::new(command, VictimProtocol::StdioJson)
+    }
+
+    #[test]
+    fn test_check_flip() -> Result<(), HammerVictimError> {
+        let mut victim = victim(r#"{"result":"flip","addr":"0x1000","bitmask":"0x4"}"#);
+        victim.start()?;
+        victim.init();
+        match victim.check()? {
+            VictimResult::BitFlips(flips) => {
+                assert_eq!(flips.len(), 1);
+                assert_eq!(flips[0].addr, 0x1000);
+                assert_eq!(flips[0].bitmask, 0x4);
+            }
+            _ => panic!("expected bit flips"),
+        }
+        victim.stop();
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_none() -> Result<(), HammerVictimError> {
+        let mut victim = victim(r#"{"result":"none"}"#);
+        victim.start()?;
+        victim.init();
+        assert!(matches!(victim.check(), Err(HammerVictimError::NoFlips)));
+        victim.stop();
+        assert!(matches!(victim.check(), Err(HammerVictimError::NotRunning)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_invalid_response() -> Result<(), HammerVictimError> {
+        let mut victim = victim(r#"{"result":"maybe"}"#);
+        victim.start()?;
+        assert!(matches!(
+            victim.check(),
+            Err(HammerVictimError::ProtocolError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_with_timeout() -> Result<(), HammerVictimError> {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("read line; sleep 1");
+        let mut victim = ProcessVictim::new(command, VictimProtocol::StdioJson);
+        victim.start()?;
+        assert!(matches!(
+            victim.check_with_timeout(Duration::from_millis(50)),
+            Err(HammerVictimError::Timeout(_))
+        ));
+        assert!(matches!(victim.check(), Err(HammerVictimError::NotRunning)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("0x7f00").unwrap(), 0x7f00);
+        assert_eq!(parse_hex("ff").unwrap(), 0xff);
+        assert!(parse_hex("0xzz").is_err());
+        assert!(parse_hex_u8("0x100").is_err());
+    }
+}
@@ -178,6 +178,7 @@
 //! let swage = Swage::<Blacksmith, _, std::io::Error, std::io::Error>::builder()
 //!     .allocator(allocator)
 //!     .profile_hammerer_factory(|memory| {
+//!         // adapt the hammering timing to the DRAM module with `Blacksmith::with_config`
 //!         todo!("Build blacksmith hammerer");
 //!     })
 //!     .victim_factory(|memory, profiling| {
@@ -228,6 +229,9 @@
 //! - `thp` - Transparent Huge Pages allocator
 //! - `blacksmith` - Blacksmith hammerer
 //! - `dev-mem` - /dev/mem hammerer
+//! - `rowhammer-simple` - Simple double-sided hammerer
+//! - `rowhammer-trr-bypass` - TRR-bypass hammerer
+//! - `victim-process` - Victim running as a separate process
 //!
 //! ## Safety and Ethics
 //!
@@ -259,6 +263,21 @@ pub mod dev_mem {
     pub use swage_dev_mem::*;
 }
 
+#[cfg(feature = "rowhammer-simple")]
+pub mod rowhammer_simple {
+    pub use swage_rowhammer_simple::*;
+}
+
+#[cfg(feature = "rowhammer-trr-bypass")]
+pub mod rowhammer_trr_bypass {
+    pub use swage_rowhammer_trr_bypass::*;
+}
+
+#[cfg(feature = "victim-process")]
+pub mod victim_process {
+    pub use swage_victim_process::*;
+}
+
 /// Prelude module for convenient imports.
 ///
 /// This module re-exports commonly used types and traits, allowing users to import
@@ -29,13 +29,17 @@ fn main() -> anyhow::Result<()> {
     let args = CliArgs::parse();
     info!("CLI args: {:?}", args);
     let bs_config = BlacksmithConfig::from_jsonfile(&args.config)?;
-    let mem_config = MemConfiguration::from_blacksmith(&bs_config);
+    let mem_config = MemConfiguration::from_blacksmith(&bs_config)?;
     //0..64 {
     //let bait_before = args.bait_before;
     let progress = MultiProgress::new();
 
     let mut allocator: Box<dyn ConsecAllocator> = match args.alloc_strategy.as_ref() {
-        "pfn" => Box::new(swage_pfn::Pfn::new(mem_config, None.into())),
+        "pfn" => Box::new(swage_pfn::Pfn::new(
+            mem_config,
+            None.into(),
+            Default::default(),
+        )),
         "spoiler" => Box::new(swage_spoiler::Spoiler::new(
             mem_config,
             bs_config.threshold.into(),
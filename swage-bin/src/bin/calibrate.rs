@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use log::{info, warn};
+use rand::Rng;
+use swage_blacksmith::{BlacksmithConfig, FromBlacksmithConfig};
+use swage_core::allocator::ConsecAllocator;
+use swage_core::memory::{
+    BytePointer, ConsecBlocks, MemConfiguration, Memory, MemoryTupleTimer, RowConflictDetector,
+    construct_memory_tuple_timer,
+};
+use swage_core::util::{CL_SIZE, ROW_SHIFT, ROW_SIZE, Size};
+use swage_hugepage::HugepageAllocator;
+use swage_thp::THP;
+
+/// Lowest address bit considered for bank functions (cache line granularity)
+const MIN_BANK_BIT: u32 = CL_SIZE.trailing_zeros();
+
+/// Maximum number of address bits XORed in a single bank function
+const MAX_FUNCTION_BITS: usize = 4;
+
+/// Number of histogram bins used for threshold detection
+const HISTOGRAM_BINS: usize = 100;
+
+/// CLI arguments for the `calibrate` binary.
+///
+/// Infers the DRAM bank functions and the row conflict threshold of this machine from access
+/// timings and writes them as a `blacksmith` config.
+///
+/// Limitations: Measuring timings requires root privileges (for `rdtscp`-based timers and
+/// pinning) and an idle system. Bank functions are only detected up to the alignment of the
+/// allocated block (21 bits for THP, 30 bits for 1 GB hugepages), and only if they XOR at
+/// most four address bits. The inferred config may therefore be incomplete and should be
+/// checked against known configs of the same CPU and DIMM.
+#[derive(Debug, Parser)]
+struct CliArgs {
+    /// The allocator used to obtain physically contiguous memory.
+    #[clap(long = "allocator", value_enum, default_value = "thp")]
+    allocator: AllocatorKind,
+    /// The path of the `blacksmith` config file to write.
+    #[clap(long = "output", default_value = "config/bs-config.json")]
+    output: String,
+    /// Only print the inferred parameters without writing the config file.
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+    /// The number of random address pairs timed for threshold detection.
+    #[clap(long = "samples", default_value = "5000")]
+    samples: usize,
+    /// The number of rows sampled for bank detection.
+    #[clap(long = "rows", default_value = "256")]
+    rows: usize,
+    /// The number of measurement rounds per address pair.
+    #[clap(long = "rounds", default_value = "1000")]
+    rounds: usize,
+}
+
+/// Allocators providing aligned, physically contiguous memory.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AllocatorKind {
+    /// 2 MB transparent huge pages
+    Thp,
+    /// 1 GB hugepages
+    Hugepage,
+}
+
+impl AllocatorKind {
+    /// Allocates a physically contiguous block and returns it with its alignment in bits.
+    fn alloc(self) -> Result<(ConsecBlocks, u32)> {
+        Ok(match self {
+            // the threshold is only used to check bank conflicts between several blocks
+            AllocatorKind::Thp => (THP::new(0, None).alloc_consec_blocks(Size::MB(2))?, 21),
+            AllocatorKind::Hugepage => (
+                HugepageAllocator::default().alloc_consec_blocks(Size::MB(512))?,
+                30,
+            ),
+        })
+    }
+}
+
+/// Returns the value separating the two most frequent peaks of `samples`.
+///
+/// The samples are sorted into `bins` equally sized bins. The valley is the center of the
+/// least populated bin between the two highest peaks, or None if there are fewer than two
+/// peaks.
+fn bimodal_valley(samples: &[u64], bins: usize) -> Option<u64> {
+    let (&min, &max) = (samples.iter().min()?, samples.iter().max()?);
+    let width = (max - min) / bins as u64 + 1;
+    let mut histogram = vec![0usize; bins];
+    for &sample in samples {
+        histogram[((sample - min) / width) as usize] += 1;
+    }
+    let is_peak = |i: usize| {
+        histogram[i] > 0
+            && (i == 0 || histogram[i - 1] <= histogram[i])
+            && (i + 1 == histogram.len() || histogram[i + 1] < histogram[i])
+    };
+    let peaks = (0..histogram.len())
+        .filter(|&i| is_peak(i))
+        .sorted_by_key(|&i| std::cmp::Reverse(histogram[i]))
+        .take(2)
+        .sorted()
+        .collect_vec();
+    let [low, high] = peaks[..] else {
+        return None;
+    };
+    let valley = (low..=high).min_by_key(|&i| histogram[i])?;
+    Some(min + valley as u64 * width + width / 2)
+}
+
+/// Measures the row conflict threshold from the timings of random address pairs in `block`.
+fn detect_threshold(
+    timer: &dyn MemoryTupleTimer,
+    block: &ConsecBlocks,
+    samples: usize,
+    rounds: usize,
+) -> Option<u64> {
+    let mut rng = rand::rng();
+    let timings = (0..samples)
+        .map(|_| {
+            let a = block.addr(rng.random_range(0..block.len()) & !(CL_SIZE - 1));
+            let b = block.addr(rng.random_range(0..block.len()) & !(CL_SIZE - 1));
+            unsafe { timer.time_subsequent_access_from_ram(a, b, rounds) }
+        })
+        .collect_vec();
+    bimodal_valley(&timings, HISTOGRAM_BINS)
+}
+
+/// Returns `rows` randomly chosen rows of `block` as a region for bank detection.
+fn sample_rows(block: &ConsecBlocks, rows: usize) -> ConsecBlocks {
+    let mut rng = rand::rng();
+    let offsets = (0..rows)
+        .map(|_| rng.random_range(0..block.len() / ROW_SIZE) * ROW_SIZE)
+        .unique()
+        .sorted()
+        .collect_vec();
+    ConsecBlocks::new(
+        offsets
+            .into_iter()
+            .map(|offset| Memory::new(block.addr(offset), ROW_SIZE))
+            .collect(),
+    )
+}
+
+/// Assigns each sampled address of `block` to a bank.
+///
+/// Rows are grouped with [`RowConflictDetector::build_bank_map`]. To detect bank functions
+/// involving column bits, addresses with a single column bit flipped are added and assigned to
+/// the bank whose rows they conflict with.
+fn label_banks(
+    detector: &RowConflictDetector,
+    block: &ConsecBlocks,
+    rows: usize,
+    rounds: usize,
+) -> Vec<(usize, usize)> {
+    let base = block.ptr() as usize;
+    let banks = detector.build_bank_map(&sample_rows(block, rows), rounds);
+    info!("Found {} banks", banks.0.len());
+    let mut labels = banks
+        .0
+        .iter()
+        .flat_map(|(&bank, addrs)| addrs.iter().map(move |&addr| (addr as usize - base, bank)))
+        .collect_vec();
+    let row_labels = labels.clone();
+    for &(offset, _) in row_labels.iter().take(banks.0.len()) {
+        for bit in MIN_BANK_BIT..ROW_SHIFT as u32 {
+            let flipped = offset ^ (1 << bit);
+            let bank = banks.0.iter().find_map(|(&bank, addrs)| {
+                let other = addrs
+                    .iter()
+                    .find(|&&addr| (addr as usize - base) >> ROW_SHIFT != flipped >> ROW_SHIFT)?;
+                let conflict = detector.test_conflict(block.addr(flipped), *other, rounds);
+                conflict.same_bank.then_some(bank)
+            });
+            match bank {
+                Some(bank) => labels.push((flipped, bank)),
+                None => warn!("No bank found for offset 0x{:x}", flipped),
+            }
+        }
+    }
+    labels
+}
+
+/// Infers bank functions from addresses labeled with their bank.
+///
+/// Enumerates all XORs of up to [`MAX_FUNCTION_BITS`] bits below `max_bit` that are constant
+/// within each bank, and returns a linearly independent subset, preferring functions of few
+/// bits. The returned masks are reduced such that the lowest bit of each function does not
+/// occur in any other function.
+fn infer_bank_functions(labels: &[(usize, usize)], max_bit: u32) -> Vec<usize> {
+    let bits = (MIN_BANK_BIT..max_bit).collect_vec();
+    let is_bank_function = |mask: usize| {
+        let mut values = HashMap::new();
+        labels.iter().all(|&(offset, bank)| {
+            let value = (offset & mask).count_ones() & 1;
+            *values.entry(bank).or_insert(value) == value
+        })
+    };
+    let mut basis: Vec<usize> = vec![];
+    for mask in (1..=MAX_FUNCTION_BITS)
+        .flat_map(|n| bits.iter().combinations(n))
+        .map(|bits| bits.into_iter().fold(0, |mask, bit| mask | (1 << bit)))
+    {
+        let functions = basis
+            .iter()
+            .chain(std::iter::once(&mask))
+            .copied()
+            .collect_vec();
+        if reduce(&functions).len() > basis.len() && is_bank_function(mask) {
+            basis = reduce(&functions);
+        }
+    }
+    basis
+}
+
+/// Gaussian elimination over GF(2), dropping linearly dependent masks.
+fn reduce(masks: &[usize]) -> Vec<usize> {
+    let mut reduced: Vec<usize> = vec![];
+    for &mask in masks {
+        let mask = reduced.iter().fold(mask, |mask, &r| {
+            if mask & lowest_bit(r) != 0 {
+                mask ^ r
+            } else {
+                mask
+            }
+        });
+        if mask == 0 {
+            continue;
+        }
+        for r in reduced.iter_mut() {
+            if *r & lowest_bit(mask) != 0 {
+                *r ^= mask;
+            }
+        }
+        reduced.push(mask);
+    }
+    reduced
+}
+
+fn lowest_bit(mask: usize) -> usize {
+    mask & mask.wrapping_neg()
+}
+
+fn bits_of(mask: usize) -> Vec<u32> {
+    (0..usize::BITS)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .collect()
+}
+
+/// Builds a `blacksmith` config from the bank functions.
+///
+/// The lowest bit of each bank function is replaced by the function, all remaining bits are
+/// used as column bits (below the row size) or row bits.
+fn to_config_json(threshold: u64, functions: &[usize]) -> serde_json::Value {
+    let pivots = functions
+        .iter()
+        .map(|&f| lowest_bit(f))
+        .fold(0, |a, b| a | b);
+    let remaining =
+        |bits: std::ops::Range<u32>| bits.filter(|bit| pivots & (1 << bit) == 0).collect_vec();
+    serde_json::json!({
+        "threshold": threshold,
+        "bank_bits": functions.iter().map(|&f| bits_of(f)).collect_vec(),
+        "row_bits": remaining(ROW_SHIFT as u32..30),
+        "col_bits": remaining(0..ROW_SHIFT as u32),
+    })
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let args = CliArgs::parse();
+    info!("CLI args: {:?}", args);
+
+    let (block, max_bit) = args.allocator.alloc()?;
+    let timer = construct_memory_tuple_timer()?;
+
+    let threshold = detect_threshold(timer.as_ref(), &block, args.samples, args.rounds)
+        .context("timings are not bimodal, no row conflicts observed")?;
+    println!("Row conflict threshold: {}", threshold);
+
+    let detector = RowConflictDetector::new(timer, threshold);
+    let labels = label_banks(&detector, &block, args.rows, args.rounds);
+    let bank_count = labels.iter().map(|(_, bank)| bank).unique().count();
+    let functions = infer_bank_functions(&labels, max_bit);
+    if 1 << functions.len() != bank_count {
+        warn!(
+            "Found {} bank functions for {} banks, results are incomplete",
+            functions.len(),
+            bank_count
+        );
+    }
+    for function in &functions {
+        println!("Bank function: {:?}", bits_of(*function));
+    }
+
+    let json = to_config_json(threshold, &functions);
+    let config: BlacksmithConfig = serde_json::from_value(json.clone())?;
+    if let Err(e) = config.validate() {
+        bail!("Inferred config is invalid: {}", e);
+    }
+    let mem_config = MemConfiguration::from_blacksmith(&config)?;
+    println!(
+        "{} banks, bank function period: {} rows",
+        mem_config.get_bank_count(),
+        mem_config.bank_function_period()
+    );
+
+    let json = serde_json::to_string_pretty(&json)?;
+    if args.dry_run {
+        println!("{}", json);
+    } else {
+        std::fs::write(&args.output, json)?;
+        info!("Wrote config to {}", args.output);
+    }
+    block.dealloc();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Labels offsets with the banks of `functions`.
+    fn label(offsets: impl Iterator<Item = usize>, functions: &[usize]) -> Vec<(usize, usize)> {
+        offsets
+            .map(|offset| {
+                let bank = functions.iter().enumerate().fold(0, |bank, (i, &f)| {
+                    bank | ((((offset & f).count_ones() & 1) as usize) << i)
+                });
+                (offset, bank)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bimodal_valley() {
+        let samples = [200u64; 50]
+            .into_iter()
+            .chain([210; 30])
+            .chain([400; 20])
+            .chain([390; 10])
+            .collect_vec();
+        let valley = bimodal_valley(&samples, 20).expect("no valley");
+        assert!((211..390).contains(&valley), "valley {}", valley);
+        assert_eq!(bimodal_valley(&[200; 10], 20), None);
+        assert_eq!(bimodal_valley(&[], 20), None);
+    }
+
+    #[test]
+    fn test_infer_bank_functions() {
+        let functions = [(1 << 6) | (1 << 13), (1 << 14) | (1 << 18), 1 << 15];
+        let mut rng = rand::rng();
+        let offsets = (0..512).map(|_| rng.random_range(0..1 << 21) & !(CL_SIZE - 1));
+        let inferred = infer_bank_functions(&label(offsets, &functions), 21);
+        assert_eq!(inferred.len(), 3);
+        // the inferred functions span the same space
+        assert_eq!(reduce(&[&inferred[..], &functions[..]].concat()).len(), 3);
+    }
+
+    #[test]
+    fn test_to_config_json() {
+        let functions = reduce(&[(1 << 6) | (1 << 13), (1 << 14) | (1 << 18)]);
+        let json = to_config_json(300, &functions);
+        let config: BlacksmithConfig = serde_json::from_value(json).expect("invalid config");
+        let geometry = config.validate().expect("invalid geometry");
+        assert_eq!(geometry.banks, 4);
+        assert_eq!(config.col_bits.len() + config.row_bits.len(), 28);
+    }
+
+    #[test]
+    #[ignore = "requires root and THP support"]
+    fn test_calibrate_thp() -> Result<()> {
+        let (block, max_bit) = AllocatorKind::Thp.alloc()?;
+        let timer = construct_memory_tuple_timer()?;
+        let threshold = detect_threshold(timer.as_ref(), &block, 2000, 1000).expect("no valley");
+        let detector = RowConflictDetector::new(timer, threshold);
+        let labels = label_banks(&detector, &block, 64, 1000);
+        assert!(!infer_bank_functions(&labels, max_bit).is_empty());
+        block.dealloc();
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires root and 1 GB hugepages"]
+    fn test_calibrate_hugepage() -> Result<()> {
+        let (block, _) = AllocatorKind::Hugepage.alloc()?;
+        let timer = construct_memory_tuple_timer()?;
+        assert!(detect_threshold(timer.as_ref(), &block, 2000, 1000).is_some());
+        block.dealloc();
+        Ok(())
+    }
+}
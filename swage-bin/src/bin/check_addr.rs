@@ -19,7 +19,7 @@ fn main() -> Result<()> {
 
     let config = BlacksmithConfig::from_jsonfile(&args.config)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
 
     let memory: Memory = Memory::hugepage(HugepageSize::OneGb)?;
     let base_msb = memory.ptr();
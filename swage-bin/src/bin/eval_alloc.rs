@@ -9,11 +9,10 @@ use clap::Parser;
 use indicatif::MultiProgress;
 use log::{info, warn};
 use serde::Serialize;
-use swage_blacksmith::FromBlacksmithConfig;
-use swage_blacksmith::blacksmith_config::BlacksmithConfig;
-use swage_core::allocator::ConsecAllocator;
-use swage_core::memory::{FormatPfns, GetConsecPfns, MemConfiguration};
-use swage_core::util::MB;
+use swage_blacksmith::{BlacksmithConfig, FromBlacksmithConfig};
+use swage_core::allocator::{ConsecAllocator, PoolAllocator};
+use swage_core::memory::{FormatPfns, GetConsecPfns, MemConfiguration, render_physical_layout};
+use swage_core::util::Size;
 
 /// CLI arguments for the `eval_alloc` binary.
 ///
@@ -30,9 +29,9 @@ struct CliArgs {
     /// The number of allocation attempts to perform.
     #[clap(long = "attempts", default_value = "10")]
     attempts: u32,
-    /// The size to allocate per attempt in MB.
-    #[clap(long = "size", default_value = "4")]
-    size_mb: usize,
+    /// The size to allocate per attempt, e.g., `4MB`.
+    #[clap(long = "size", default_value = "4MB")]
+    size: Size,
     /// Repeat the allocation evaluation this many times.
     #[clap(long = "repeat", default_value = "1")]
     repeat: usize,
@@ -48,6 +47,13 @@ struct CliArgs {
     /// Deallocate memory after each allocation (for testing allocation/deallocation cycles).
     #[clap(long = "deallocate")]
     deallocate: bool,
+    /// Print the physical DRAM layout after each successful allocation.
+    #[clap(long = "layout")]
+    layout: bool,
+    /// Serve allocations from a pool of this many pre-allocated blocks. With `--deallocate`,
+    /// blocks are returned to the pool instead of being unmapped.
+    #[clap(long = "pool")]
+    pool: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -63,6 +69,7 @@ struct AllocationResult {
 #[derive(Debug, Serialize)]
 struct EvaluationResults {
     args: CliArgs,
+    pool_setup_ms: Option<u64>,
     total_attempts: u32,
     successful_attempts: u32,
     failed_attempts: u32,
@@ -76,6 +83,7 @@ impl EvaluationResults {
     fn new(args: CliArgs) -> Self {
         Self {
             args,
+            pool_setup_ms: None,
             total_attempts: 0,
             successful_attempts: 0,
             failed_attempts: 0,
@@ -114,33 +122,65 @@ impl EvaluationResults {
 fn evaluate_allocator(args: &CliArgs) -> Result<EvaluationResults> {
     let progress = MultiProgress::new();
     let bs_config = BlacksmithConfig::from_jsonfile(&args.config)?;
-    let mem_config = MemConfiguration::from_blacksmith(&bs_config);
+    let mem_config = MemConfiguration::from_blacksmith(&bs_config)?;
 
-    let mut allocator: Box<dyn ConsecAllocator> = match args.alloc_strategy.as_ref() {
-        "pfn" => Box::new(swage_pfn::Pfn::new(mem_config, None.into())),
-        "spoiler" => Box::new(swage_spoiler::Spoiler::new(
+    match args.alloc_strategy.as_ref() {
+        "pfn" => evaluate(
+            args,
+            swage_pfn::Pfn::new(mem_config, None.into(), Default::default()),
+            mem_config,
+        ),
+        "spoiler" => evaluate(
+            args,
+            swage_spoiler::Spoiler::new(mem_config, bs_config.threshold.into(), Some(progress)),
             mem_config,
-            bs_config.threshold.into(),
-            Some(progress),
-        )),
+        ),
         _ => panic!("Unknown allocator"),
-    };
+    }
+}
 
+fn evaluate<A: ConsecAllocator>(
+    args: &CliArgs,
+    allocator: A,
+    mem_config: MemConfiguration,
+) -> Result<EvaluationResults> {
     let mut results = EvaluationResults::new(args.clone());
-    let allocation_size = args.size_mb * MB;
+    let allocation_size = args.size;
+
+    let (mut allocator, mut pool) = match args.pool {
+        Some(pool_size) => {
+            let start_time = Instant::now();
+            let pool = PoolAllocator::new(allocator, pool_size)
+                .map_err(|e| anyhow::anyhow!("Failed to fill the pool: {}", e))?;
+            let duration = start_time.elapsed();
+            info!(
+                "Pre-allocated {} blocks in {}ms",
+                pool_size,
+                duration.as_millis()
+            );
+            results.pool_setup_ms = Some(duration.as_millis() as u64);
+            (None, Some(pool))
+        }
+        None => (Some(allocator), None),
+    };
 
     info!(
         "Starting allocation evaluation with {} attempts",
         args.attempts
     );
-    info!("Allocation size: {} MB", args.size_mb);
+    info!("Allocation size: {}", args.size);
     info!("Allocation strategy: {:?}", args.alloc_strategy);
 
     for attempt in 1..=args.attempts {
         info!("Attempt number {}", attempt);
         let start_time = Instant::now();
 
-        let allocation_result = match allocator.alloc_consec_blocks(allocation_size) {
+        let allocation = match (&mut allocator, &mut pool) {
+            (_, Some(pool)) => pool.alloc_consec_blocks(allocation_size),
+            (Some(allocator), None) => allocator.alloc_consec_blocks(allocation_size),
+            (None, None) => unreachable!("either an allocator or a pool is set"),
+        };
+        let allocation_result = match allocation {
             Ok(memory) => {
                 let duration = start_time.elapsed();
                 let (pfn_count, pfns_str) = match memory.consec_pfns() {
@@ -163,6 +203,21 @@ fn evaluate_allocator(args: &CliArgs) -> Result<EvaluationResults> {
                     }
                 }
 
+                if args.layout {
+                    match render_physical_layout(&memory, &mem_config) {
+                        Ok(layout) => {
+                            info!("  Layout:\n{}", layout);
+                            for (bank, start_row, gap_size) in layout.bank_gaps() {
+                                info!(
+                                    "  Bank {}: {} rows missing from row {}",
+                                    bank, gap_size, start_row
+                                );
+                            }
+                        }
+                        Err(e) => warn!("Failed to get layout: {:?}", e),
+                    }
+                }
+
                 let result = AllocationResult {
                     attempt,
                     success: true,
@@ -174,7 +229,10 @@ fn evaluate_allocator(args: &CliArgs) -> Result<EvaluationResults> {
 
                 // Deallocate if requested
                 if args.deallocate {
-                    memory.dealloc();
+                    match &mut pool {
+                        Some(pool) => pool.release(memory),
+                        None => memory.dealloc(),
+                    }
                     if args.verbose {
                         info!("  Memory deallocated");
                     }
@@ -208,6 +266,9 @@ fn evaluate_allocator(args: &CliArgs) -> Result<EvaluationResults> {
 
         results.add_allocation(allocation_result);
     }
+    if let Some(pool) = pool {
+        pool.dealloc();
+    }
 
     Ok(results)
 }
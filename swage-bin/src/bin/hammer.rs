@@ -0,0 +1,415 @@
+use std::{convert::Infallible, fmt::Write as _, fs::File, io::BufReader};
+
+use anyhow::{Context, Result, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use log::info;
+use swage_blacksmith::{
+    Attempts, Blacksmith, BlacksmithConfig, BlockShift, FromBlacksmithConfig, FuzzSummary,
+    HammeringPattern, MAX_JIT_SIZE, PatternAddressMapper,
+};
+use swage_core::allocator::ConsecAllocator;
+use swage_core::memory::{ConsecBlocks, MemConfiguration};
+use swage_core::util::setup_signal_handler;
+use swage_core::{MemCheck, Swage, SwageConfig};
+use swage_hugepage::HugepageAllocator;
+use swage_thp::THP;
+
+/// CLI arguments for the `hammer` binary.
+///
+/// Runs a Swage experiment hammering a Blacksmith pattern, or inspects the patterns of a
+/// fuzz summary with `list-patterns`.
+#[derive(Debug, Parser)]
+struct CliArgs {
+    /// The `blacksmith` config file.
+    #[clap(long = "bs-config", default_value = "config/bs-config.json")]
+    bs_config: String,
+    /// The JSON file containing the Blacksmith fuzz summary.
+    #[clap(
+        long = "fuzz-summary",
+        default_value = "config/fuzz-summary.json",
+        global = true
+    )]
+    fuzz_summary: String,
+    /// The TOML file with the Swage experiment configuration. Defaults to `SwageConfig::default()`.
+    #[clap(long = "config")]
+    config: Option<String>,
+    /// The pattern ID to hammer. Defaults to the pattern with the most bit flips.
+    #[clap(long = "pattern")]
+    pattern: Option<String>,
+    /// The allocator used to obtain physically contiguous memory.
+    #[clap(long = "alloc-strategy", value_enum, default_value = "thp")]
+    alloc_strategy: AllocatorKind,
+    /// The number of hammering attempts per round.
+    #[clap(long = "attempts", default_value = "10")]
+    attempts: u32,
+    /// Select the address mapping by hammering each mapping on the allocated memory instead of
+    /// using the bit flips recorded during fuzzing.
+    #[clap(long = "profile-mapping")]
+    profile_mapping: bool,
+    /// The maximum size of the JIT-compiled hammering program in bytes. Must not exceed
+    /// the default.
+    #[clap(long = "max-jit-size", default_value_t = MAX_JIT_SIZE)]
+    max_jit_size: usize,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the patterns of the fuzz summary as a table.
+    ListPatterns {
+        /// Only list patterns with at least this many bit flips.
+        #[clap(long = "min-flips")]
+        min_flips: Option<usize>,
+        /// The column to sort the patterns by.
+        #[clap(long = "sort-by", value_enum, default_value = "id")]
+        sort_by: SortBy,
+        /// Print the address mappings of a single pattern instead of the table.
+        #[clap(long = "pattern-id")]
+        pattern_id: Option<String>,
+    },
+}
+
+/// Sort orders of `list-patterns`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SortBy {
+    /// Ascending pattern ID
+    Id,
+    /// Descending number of bit flips
+    Flips,
+    /// Descending number of activations
+    Activations,
+}
+
+/// Allocators providing physically contiguous memory.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AllocatorKind {
+    /// 2 MB transparent huge pages
+    Thp,
+    /// 1 GB hugepages
+    Hugepage,
+}
+
+/// Sorts `patterns` in place by `sort_by`.
+fn sort_patterns(patterns: &mut [HammeringPattern], sort_by: SortBy) {
+    match sort_by {
+        SortBy::Id => patterns.sort_by(|a, b| a.id.cmp(&b.id)),
+        SortBy::Flips => patterns.sort_by_key(|p| std::cmp::Reverse(p.count_bitflips())),
+        SortBy::Activations => patterns.sort_by_key(|p| std::cmp::Reverse(p.total_activations())),
+    }
+}
+
+/// Formats `patterns` as an ASCII table with one row per pattern.
+fn pattern_table(patterns: &[HammeringPattern]) -> String {
+    let header = [
+        "ID",
+        "Total Activations",
+        "Num Mappings",
+        "Total Bit Flips",
+        "Most Effective Mapping ID",
+    ];
+    let rows = patterns
+        .iter()
+        .map(|p| {
+            [
+                p.id.clone(),
+                p.total_activations().to_string(),
+                p.address_mappings.len().to_string(),
+                p.count_bitflips().to_string(),
+                p.determine_most_effective_mapping()
+                    .map_or("-".to_string(), |m| m.id),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..header.len())
+        .map(|col| {
+            rows.iter()
+                .map(|row| row[col].len())
+                .chain([header[col].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let separator = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+    let mut table = String::new();
+    for cells in [header.map(String::from).to_vec(), separator]
+        .into_iter()
+        .chain(rows.into_iter().map(Vec::from))
+    {
+        let cells = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>();
+        let _ = writeln!(table, "{}", cells.join(" | ").trim_end());
+    }
+    table
+}
+
+/// Formats the address mappings of `pattern` as JSON, mapping each aggressor to its address.
+fn pattern_details(pattern: &HammeringPattern) -> Result<String> {
+    let mappings = pattern
+        .address_mappings
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "id": m.id,
+                "bit_flips": m.count_bitflips(),
+                "aggressor_to_addr": m.aggressor_to_addr(),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "id": pattern.id,
+        "total_activations": pattern.total_activations(),
+        "address_mappings": mappings,
+    }))?)
+}
+
+fn list_patterns(
+    fuzz_summary: &str,
+    min_flips: Option<usize>,
+    sort_by: SortBy,
+    pattern_id: Option<String>,
+) -> Result<()> {
+    let file = File::open(fuzz_summary)
+        .with_context(|| format!("Failed to open fuzz summary {}", fuzz_summary))?;
+    let mut summary: FuzzSummary = serde_json::from_reader(BufReader::new(file))?;
+    if let Some(id) = pattern_id {
+        let pattern = summary
+            .filter_by_pattern_id(&id)
+            .with_context(|| format!("Pattern {} not found", id))?;
+        println!("{}", pattern_details(pattern)?);
+        return Ok(());
+    }
+    if let Some(min) = min_flips {
+        summary.filter_by_min_flips(min);
+    }
+    sort_patterns(&mut summary.hammering_patterns, sort_by);
+    print!("{}", pattern_table(&summary.hammering_patterns));
+    Ok(())
+}
+
+/// Loads the pattern to hammer and its most effective mapping.
+fn load_pattern(
+    fuzz_summary: &str,
+    pattern_id: Option<&str>,
+) -> Result<(HammeringPattern, PatternAddressMapper)> {
+    let pattern = match pattern_id {
+        Some(id) => HammeringPattern::load_pattern_from_json(fuzz_summary, id)?,
+        None => HammeringPattern::load_patterns(fuzz_summary)?
+            .into_iter()
+            .max_by_key(HammeringPattern::count_bitflips)
+            .context("Fuzz summary contains no patterns")?,
+    };
+    let mapping = pattern
+        .determine_most_effective_mapping()
+        .with_context(|| format!("Pattern {} has no address mappings", pattern.id))?;
+    Ok((pattern, mapping))
+}
+
+fn hammer<A: ConsecAllocator + 'static>(
+    args: &CliArgs,
+    allocator: A,
+    mem_config: MemConfiguration,
+    config: SwageConfig,
+) -> Result<()> {
+    let (pattern, mapping) = load_pattern(&args.fuzz_summary, args.pattern.as_deref())?;
+    let block_size = allocator.block_size().bytes();
+    let block_shift = block_size.ilog2() as usize;
+    // a profiled mapping is only known once memory is allocated, so reserve enough for any
+    let num_sets = if args.profile_mapping {
+        pattern
+            .address_mappings
+            .iter()
+            .map(|m| m.aggressor_sets(mem_config, block_shift).len())
+            .max()
+            .unwrap_or(0)
+    } else {
+        mapping.aggressor_sets(mem_config, block_shift).len()
+    };
+    info!(
+        "Pattern {} needs {} blocks of {} bytes",
+        pattern.id, num_sets, block_size
+    );
+    if num_sets == 0 {
+        bail!("Mapping {} has no aggressors", mapping.id);
+    }
+
+    let attempts = Attempts::from(args.attempts);
+    let profile_mapping = args.profile_mapping;
+    let max_jit_size = args.max_jit_size;
+    let profiling_rounds = u32::try_from(config.profiling_rounds).unwrap_or(u32::MAX);
+    type S<E> = Swage<Blacksmith, Blacksmith, E, Infallible>;
+    let swage = S::<A::Error>::builder()
+        .allocator(allocator)
+        .profile_hammerer_factory(move |memory| {
+            let blacksmith = if profile_mapping {
+                let (blacksmith, mapping) = Blacksmith::profile_pattern(
+                    mem_config,
+                    &pattern,
+                    BlockShift::from(block_shift),
+                    &memory,
+                    attempts,
+                    profiling_rounds,
+                );
+                info!("Profiled mapping {}", mapping.id);
+                blacksmith
+            } else {
+                Blacksmith::new(
+                    mem_config,
+                    &pattern,
+                    &mapping,
+                    BlockShift::from(block_shift),
+                    &memory,
+                    attempts,
+                )
+                .expect("failed to create Blacksmith hammerer")
+            };
+            if let Err(e) = blacksmith.program().validate_with_limit(max_jit_size) {
+                panic!("JIT program exceeds --max-jit-size: {}", e);
+            }
+            blacksmith
+        })
+        .victim_factory(|memory, profile| {
+            Ok(Box::new(MemCheck::new(
+                ConsecBlocks::clone(&memory),
+                profile.pattern,
+                vec![].into(),
+            )))
+        })
+        .pattern_size(num_sets * block_size)
+        .config(config)
+        .build()?;
+
+    let experiments = swage.run();
+    println!("{}", serde_json::to_string_pretty(&experiments)?);
+    println!("{}", S::<A::Error>::statistics(&experiments));
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let args = CliArgs::parse();
+    info!("CLI args: {:?}", args);
+
+    if let Some(Command::ListPatterns {
+        min_flips,
+        sort_by,
+        pattern_id,
+    }) = args.command
+    {
+        return list_patterns(&args.fuzz_summary, min_flips, sort_by, pattern_id);
+    }
+
+    if args.max_jit_size > MAX_JIT_SIZE {
+        bail!(
+            "--max-jit-size {} exceeds the limit of {} bytes",
+            args.max_jit_size,
+            MAX_JIT_SIZE
+        );
+    }
+    let bs_config = BlacksmithConfig::from_jsonfile(&args.bs_config)?;
+    let mem_config = MemConfiguration::from_blacksmith(&bs_config)?;
+    // stop after the current round on SIGINT instead of killing the process mid-hammering
+    let config = match &args.config {
+        Some(path) => SwageConfig::from_toml(path)
+            .with_context(|| format!("Failed to load config {}", path))?,
+        None => SwageConfig::default(),
+    };
+    let config = SwageConfig {
+        stop_flag: Some(setup_signal_handler()),
+        ..config
+    };
+    match args.alloc_strategy {
+        AllocatorKind::Thp => hammer(
+            &args,
+            THP::new(bs_config.threshold, None),
+            mem_config,
+            config,
+        ),
+        AllocatorKind::Hugepage => hammer(&args, HugepageAllocator::default(), mem_config, config),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzz_summary() -> Vec<HammeringPattern> {
+        let mapping = |id: &str, flips: usize| {
+            let flip = r#"{"dram_addr":{"bank":1,"row":2,"col":3},"bitmask":1,"data":0}"#;
+            format!(
+                r#"{{"id":"{}","aggressor_to_addr":[[0,{{"bank":1,"row":1,"col":0}}]],"bit_flips":[[{}]],
+                "code_jitter":{{"fencing_strategy":"LATEST_POSSIBLE","flushing_strategy":"EARLIEST_POSSIBLE",
+                "num_aggs_for_sync":2,"pattern_sync_each_ref":false,"total_activations":5000000}}}}"#,
+                id,
+                vec![flip; flips].join(",")
+            )
+        };
+        let pattern = |id: &str, activations: u32, mappings: &[String]| {
+            format!(
+                r#"{{"id":"{}","total_activations":{},"num_refresh_intervals":16,"access_ids":[0],"address_mappings":[{}]}}"#,
+                id,
+                activations,
+                mappings.join(",")
+            )
+        };
+        let json = format!(
+            r#"{{"hammering_patterns":[{},{},{}]}}"#,
+            pattern("b", 300, &[mapping("m0", 1), mapping("m1", 2)]),
+            pattern("a", 100, &[]),
+            pattern("c", 200, &[mapping("m2", 5)]),
+        );
+        HammeringPattern::load_from_str(&json).expect("invalid fuzz summary")
+    }
+
+    fn ids(patterns: &[HammeringPattern]) -> Vec<&str> {
+        patterns.iter().map(|p| p.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_patterns() {
+        let mut patterns = fuzz_summary();
+        sort_patterns(&mut patterns, SortBy::Id);
+        assert_eq!(ids(&patterns), ["a", "b", "c"]);
+        sort_patterns(&mut patterns, SortBy::Flips);
+        assert_eq!(ids(&patterns), ["c", "b", "a"]);
+        sort_patterns(&mut patterns, SortBy::Activations);
+        assert_eq!(ids(&patterns), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_pattern_table() {
+        let table = pattern_table(&fuzz_summary());
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "ID | Total Activations | Num Mappings | Total Bit Flips | Most Effective Mapping ID"
+        );
+        assert_eq!(
+            lines[2],
+            "b  | 300               | 2            | 3               | m1"
+        );
+        assert_eq!(
+            lines[3],
+            "a  | 100               | 0            | 0               | -"
+        );
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn test_pattern_details() -> Result<()> {
+        let details: serde_json::Value =
+            serde_json::from_str(&pattern_details(&fuzz_summary()[0])?)?;
+        assert_eq!(details["id"], "b");
+        assert_eq!(details["address_mappings"][1]["id"], "m1");
+        assert_eq!(details["address_mappings"][1]["bit_flips"], 2);
+        assert_eq!(
+            details["address_mappings"][0]["aggressor_to_addr"]["0"]["row"],
+            1
+        );
+        Ok(())
+    }
+}
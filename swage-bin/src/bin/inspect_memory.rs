@@ -0,0 +1,189 @@
+use std::{
+    fmt::Write as _, fs::File, os::unix::fs::FileExt, sync::atomic::Ordering, thread::sleep,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use log::info;
+use swage_blacksmith::{BlacksmithConfig, FromBlacksmithConfig};
+use swage_core::memory::{
+    ConsecPfns, DRAMAddr, FormatPfns, LinuxPageMap, MemConfiguration, VirtToPhysResolver,
+};
+use swage_core::util::{PAGE_SIZE, Size, setup_signal_handler};
+
+/// Number of bytes printed per hex dump line.
+const BYTES_PER_LINE: usize = 16;
+
+/// CLI arguments for the `inspect_memory` binary.
+///
+/// Inspects memory at a virtual address of a running process, e.g., an experiment. The memory
+/// is read through `/proc/<pid>/mem`, so reads may be served from the CPU cache instead of
+/// DRAM. Reading another process and resolving physical addresses requires root privileges.
+#[derive(Debug, Parser)]
+struct CliArgs {
+    /// The `blacksmith` config file.
+    #[clap(long = "config", default_value = "config/bs-config.json")]
+    config: String,
+    /// The process whose memory is inspected.
+    #[clap(long = "pid")]
+    pid: u32,
+    /// The virtual address to inspect in process `pid` (hex, with or without `0x` prefix).
+    #[clap(long = "addr", value_parser = parse_hex)]
+    addr: usize,
+    /// The number of bytes to inspect, e.g., `256` or `4 KB`.
+    #[clap(long = "len", default_value = "256")]
+    len: Size,
+    /// Re-read and print the memory every 100ms until SIGINT.
+    #[clap(long = "watch")]
+    watch: bool,
+    /// Print the consecutive PFN ranges of the inspected memory.
+    #[clap(long = "pfns")]
+    pfns: bool,
+    /// Print the DRAM address of each page of the inspected memory.
+    #[clap(long = "dram-layout")]
+    dram_layout: bool,
+}
+
+fn parse_hex(s: &str) -> Result<usize, std::num::ParseIntError> {
+    usize::from_str_radix(s.trim_start_matches("0x"), 16)
+}
+
+/// Formats `data` as a hex dump with offset, hex bytes, and ASCII representation.
+fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let _ = write!(out, "{:08x} ", line * BYTES_PER_LINE);
+        for i in 0..BYTES_PER_LINE {
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(out, " {:02x}", byte);
+                }
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  |");
+        out.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    out
+}
+
+/// Reads `len` bytes at virtual address `addr` from `mem`, the `/proc/<pid>/mem` file of a process.
+fn read_memory(mem: &File, addr: usize, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut data = vec![0; len];
+    mem.read_exact_at(&mut data, addr as u64)?;
+    Ok(data)
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let args = CliArgs::parse();
+    info!("CLI args: {:?}", args);
+
+    let bs_config = BlacksmithConfig::from_jsonfile(&args.config)?;
+    let mem_config = MemConfiguration::from_blacksmith(&bs_config)?;
+    let len = args.len.bytes();
+
+    let mem = File::open(format!("/proc/{}/mem", args.pid))
+        .with_context(|| format!("Failed to open memory of process {}", args.pid))?;
+    let mut pagemap = LinuxPageMap::for_process(args.pid).context("LinuxPageMap requires root")?;
+    let phys = pagemap.get_phys(args.addr as u64)?;
+    let dram = DRAMAddr::from_phys(phys, &mem_config);
+    println!(
+        "virt: {:#x}, phys: {:#x}, DRAM (bank, row, col): {}",
+        args.addr,
+        phys.as_usize(),
+        dram
+    );
+
+    let start = args.addr & !(PAGE_SIZE - 1);
+    let pages = (start..args.addr + len)
+        .step_by(PAGE_SIZE)
+        .map(|virt| virt as u64)
+        .collect::<Vec<_>>();
+    if args.pfns {
+        let pfns = pagemap
+            .batch_get_phys(&pages)?
+            .into_iter()
+            .map(|phys| phys..phys + PAGE_SIZE)
+            .collect::<ConsecPfns>()
+            .merge_adjacent();
+        println!("PFNs:\n{}", pfns.format_pfns());
+    }
+
+    if args.dram_layout {
+        for (virt, phys) in pages.iter().zip(pagemap.batch_get_phys(&pages)?) {
+            let dram = DRAMAddr::from_phys(phys, &mem_config);
+            println!("{:#x} -> {:#x}: {}", virt, phys.as_usize(), dram);
+        }
+    }
+
+    let stop = setup_signal_handler();
+    loop {
+        let data = read_memory(&mem, args.addr, len)
+            .with_context(|| format!("Failed to read {} bytes at {:#x}", len, args.addr))?;
+        print!("{}", hex_dump(&data));
+        if !args.watch || stop.load(Ordering::Relaxed) {
+            break;
+        }
+        sleep(Duration::from_millis(100));
+        println!();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump() {
+        let data = (0..20u8).map(|i| i + b'A' - 2).collect::<Vec<_>>();
+        let dump = hex_dump(&data);
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "00000000  3f 40 41 42 43 44 45 46 47 48 49 4a 4b 4c 4d 4e  |?@ABCDEFGHIJKLMN|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  4f 50 51 52                                      |OPQR|"
+        );
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_hex_dump_non_printable() {
+        assert_eq!(
+            hex_dump(&[0x00, b' ', 0xff]),
+            "00000000  00 20 ff                                         |. .|\n"
+        );
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test]
+    fn test_read_memory() -> Result<()> {
+        let data = (0..=255u8).collect::<Vec<_>>();
+        let mem = File::open(format!("/proc/{}/mem", std::process::id()))?;
+        let read = read_memory(&mem, data.as_ptr() as usize + 16, 32)?;
+        assert_eq!(read, data[16..48]);
+        assert!(read_memory(&mem, 0, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("0x1000"), Ok(0x1000));
+        assert_eq!(parse_hex("dead"), Ok(0xdead));
+        assert!(parse_hex("xyz").is_err());
+    }
+}
@@ -30,7 +30,7 @@ fn main() -> anyhow::Result<()> {
     let timer = construct_memory_tuple_timer()?;
     let config = BlacksmithConfig::from_jsonfile(&args.config).with_context(|| "from_jsonfile")?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
     let mem = if args.use_hugepage {
         alloc_1g_hugepage()?
     } else {
@@ -1,37 +1,40 @@
 use rand::{Rng, rng};
-use swage::allocator::ConsecAllocator;
+use swage::allocator::{ChainAllocator, ConsecAllocator, FallbackAllocator, NumaAllocator};
 use swage::util::PAGE_MASK;
 use swage_blacksmith::BlacksmithConfig;
 use swage_blacksmith::{FromBitDefs, FromBlacksmithConfig};
+use swage_coco::CoCo;
 use swage_core::memory::{
-    DRAMAddr, MemConfiguration, Memory, MemoryTupleTimer, PfnOffset, PfnOffsetResolver,
-    PfnResolver, construct_memory_tuple_timer,
+    BytePointer, DRAMAddr, MemConfiguration, Memory, MemoryTupleTimer, PfnOffset,
+    PfnOffsetResolver, PfnResolver, PhysAddr, construct_memory_tuple_timer,
 };
-use swage_core::util::{ROW_SHIFT, ROW_SIZE, Size::MB};
+use swage_core::util::{ROW_SHIFT, ROW_SIZE, RowOffset, Size::MB};
 use swage_hugepage::HugepageAllocator;
+use swage_pfn::Pfn;
+use swage_thp::THP;
 
 const CONFIG_FILE: &str = "../config/bs-config.json";
 
-#[test]
-fn test_pfn_offset_mock_timer() -> anyhow::Result<()> {
-    struct TestTimer<'a> {
-        callback: &'a dyn Fn((*const u8, *const u8)) -> u64,
-    }
+struct TestTimer<'a> {
+    callback: &'a dyn Fn((*const u8, *const u8)) -> u64,
+}
 
-    impl MemoryTupleTimer for TestTimer<'_> {
-        unsafe fn time_subsequent_access_from_ram(
-            &self,
-            a: *const u8,
-            b: *const u8,
-            _rounds: usize,
-        ) -> u64 {
-            (self.callback)((a, b))
-        }
+impl MemoryTupleTimer for TestTimer<'_> {
+    unsafe fn time_subsequent_access_from_ram(
+        &self,
+        a: *const u8,
+        b: *const u8,
+        _rounds: usize,
+    ) -> u64 {
+        (self.callback)((a, b))
     }
+}
 
+#[test]
+fn test_pfn_offset_mock_timer() -> anyhow::Result<()> {
     let config = BlacksmithConfig::from_jsonfile(CONFIG_FILE)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
     const ADDR: *mut u8 = 0x200000000 as *mut u8;
 
     // it is not possible to determine the highest bank bit by only using one single memblock.
@@ -58,7 +61,7 @@ fn test_pfn_offset_mock_timer() -> anyhow::Result<()> {
         let offset = block.pfn_offset(&mem_config, config.threshold, &timer, None);
 
         assert!(offset.is_some());
-        assert_eq!(offset.unwrap(), row_offset);
+        assert_eq!(offset.unwrap(), RowOffset(row_offset));
     }
 
     Ok(())
@@ -68,7 +71,7 @@ fn test_pfn_offset_mock_timer() -> anyhow::Result<()> {
 fn test_pfn_offset_mmap() -> anyhow::Result<()> {
     let config = BlacksmithConfig::from_jsonfile(CONFIG_FILE)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
     let block = Memory::mmap(MB(4).bytes())?;
     let timer = construct_memory_tuple_timer()?;
     let pfn_offset = block.pfn_offset(&mem_config, config.threshold, &*timer, None);
@@ -83,15 +86,15 @@ fn test_pfn_offset_hugepage() -> anyhow::Result<()> {
     env_logger::init();
     let config = BlacksmithConfig::from_jsonfile(CONFIG_FILE)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
-    let mut allocator = HugepageAllocator {};
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
+    let mut allocator = HugepageAllocator::default();
     let blocks = allocator.alloc_consec_blocks(swage::util::Size::GB(1))?;
     let block = blocks.blocks.first().expect("No blocks");
     let timer = construct_memory_tuple_timer()?;
     let pfn_offset = block.pfn_offset(&mem_config, config.threshold, &*timer, None);
     println!("VA: 0x{:02x}", block.ptr as usize);
-    println!("PFN: 0x{:p}", block.pfn()?);
-    assert_eq!(pfn_offset, Some(0));
+    println!("PFN: {:?}", block.pfn()?);
+    assert_eq!(pfn_offset, Some(RowOffset(0)));
     blocks.dealloc();
     Ok(())
 }
@@ -100,7 +103,7 @@ fn test_pfn_offset_hugepage() -> anyhow::Result<()> {
 fn test_virt_offset() -> anyhow::Result<()> {
     let config = BlacksmithConfig::from_jsonfile(CONFIG_FILE)?;
     let mem_config =
-        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits);
+        MemConfiguration::from_bitdefs(config.bank_bits, config.row_bits, config.col_bits)?;
     let bank_bits_mask = (mem_config.bank_function_period() as usize * ROW_SIZE - 1) as isize;
     //let row_offsets = (1 << (mem_config.max_bank_bit + 1 - ROW_SHIFT as u64)) as u64;
     //let mut rng = thread_rng();
@@ -139,7 +142,7 @@ fn test_virt_offset() -> anyhow::Result<()> {
 #[allow(clippy::never_loop)]
 fn test_virt_zero_gap() -> anyhow::Result<()> {
     let config = BlacksmithConfig::from_jsonfile(CONFIG_FILE)?;
-    let mem_config = MemConfiguration::from_blacksmith(&config);
+    let mem_config = MemConfiguration::from_blacksmith(&config)?;
     let mut rand = rng();
     for _ in 0..1000000 {
         let v = (rand.random::<i64>() as isize) << 12;
@@ -172,3 +175,161 @@ fn test_virt_zero_gap() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// Returns a configuration with four XOR bank functions, repeating every 512 rows.
+fn xor_mem_config() -> MemConfiguration {
+    use swage_blacksmith::BitDef::{Multi, Single};
+    let bank_bits = (13..17).map(|bit| Multi(vec![bit, bit + 4])).collect();
+    let row_bits = (17..30).map(Single).collect();
+    let col_bits = (0..13).map(Single).collect();
+    MemConfiguration::from_bitdefs(bank_bits, row_bits, col_bits).expect("invalid config")
+}
+
+#[test]
+fn test_double_sided_aggressors_mock_timer() -> anyhow::Result<()> {
+    const THRESHOLD: u64 = 300;
+    let mem_config = xor_mem_config();
+    const ADDR: *mut u8 = 0x200000000 as *mut u8;
+
+    let timer = TestTimer {
+        callback: &|(a, b)| {
+            let a = DRAMAddr::from_virt(a, &mem_config);
+            let b = DRAMAddr::from_virt(b, &mem_config);
+            if a.is_same_bank(&b) && a.row != b.row {
+                THRESHOLD + 100
+            } else {
+                THRESHOLD - 100
+            }
+        },
+    };
+
+    let mut rand = rng();
+    for _ in 0..1000 {
+        let victim = DRAMAddr::new(
+            rand.random_range(0..mem_config.get_bank_count()),
+            rand.random_range(1..mem_config.get_row_count() - 1),
+            rand.random_range(0..=mem_config.col_mask),
+        );
+        let [below, above] = victim.double_sided_aggressors(ADDR, &mem_config);
+        for (aggressor, row) in [(below, victim.row - 1), (above, victim.row + 1)] {
+            let dram = DRAMAddr::from_virt(aggressor, &mem_config);
+            assert_eq!(dram, DRAMAddr::new(victim.bank, row, victim.col));
+            let victim = victim.to_virt(ADDR, mem_config);
+            assert!(
+                unsafe { timer.time_subsequent_access_from_ram(aggressor, victim, 1) } > THRESHOLD
+            );
+        }
+        assert!(unsafe { timer.time_subsequent_access_from_ram(below, above, 1) } > THRESHOLD);
+    }
+
+    let first = DRAMAddr::new(0, 0, 0);
+    assert_eq!(
+        first.row_neighbors(-1, &mem_config).row,
+        mem_config.get_row_count() - 1
+    );
+    assert_eq!(first.row_neighbors(5, &mem_config), DRAMAddr::new(0, 5, 0));
+    Ok(())
+}
+
+#[test]
+fn test_row_distance() {
+    let mem_config = xor_mem_config();
+    let dram = |addr: usize| DRAMAddr::from_virt(addr as *const u8, &mem_config);
+    // physical addresses from test_virt_offset
+    let base = dram(0x419df9000);
+    assert_eq!(base, DRAMAddr::new(12, 7910, 1));
+    assert_eq!(dram(0x4a1a0000), DRAMAddr::new(11, 5652, 0));
+    assert_eq!(dram(0x4c111000), DRAMAddr::new(0, 524, 1));
+    assert!(!base.is_same_bank(&dram(0x4a1a0000)));
+    assert_eq!(base.row_distance(&dram(0x4a1a0000)), None);
+    assert_eq!(base.row_distance(&base), Some(0));
+    for distance in [1, 2, 7, 100] {
+        let below = base.row_neighbors(-distance, &mem_config);
+        let above = dram(
+            base.row_neighbors(distance, &mem_config)
+                .to_virt(std::ptr::null(), mem_config) as usize,
+        );
+        assert!(base.is_same_bank(&above));
+        assert_eq!(base.row_distance(&below), Some(distance as usize));
+        assert_eq!(above.row_distance(&base), Some(distance as usize));
+        assert_eq!(below.row_distance(&above), Some(2 * distance as usize));
+    }
+}
+
+#[test]
+fn test_from_phys() {
+    let mem_config = xor_mem_config();
+    // physical addresses from test_virt_offset
+    for p in [0x419df9000, 0x19bd000, 0x4a1a0000, 0x4c111000, 0x2033000] {
+        assert_eq!(
+            DRAMAddr::from_phys(PhysAddr::new(p), &mem_config),
+            DRAMAddr::from_virt(p as *const u8, &mem_config)
+        );
+    }
+    assert_eq!(
+        DRAMAddr::from_phys(PhysAddr::new(0x4c111000), &mem_config),
+        DRAMAddr::new(0, 524, 1)
+    );
+}
+
+#[test]
+fn test_fallback_allocator_block_size() {
+    let allocator = FallbackAllocator::new(
+        THP::new(0, None),
+        Pfn::new(xor_mem_config(), None.into(), Default::default()),
+    );
+    assert_eq!(allocator.block_size(), MB(4));
+    let chain = ChainAllocator::new()
+        .with(Pfn::new(xor_mem_config(), None.into(), Default::default()))
+        .with(THP::new(0, None));
+    assert_eq!(chain.block_size(), MB(4));
+}
+
+#[test]
+#[ignore]
+fn test_fallback_allocator_pfn_thp() {
+    let mut allocator = FallbackAllocator::new(
+        Pfn::new(xor_mem_config(), None.into(), Default::default()),
+        THP::new(0, None),
+    );
+    let blocks = allocator
+        .alloc_consec_blocks(MB(8))
+        .expect("allocation failed");
+    assert_eq!(blocks.len(), MB(8).bytes());
+    blocks.dealloc();
+    if CoCo::available() {
+        let mut allocator = FallbackAllocator::new(CoCo::new().expect("CoCo"), THP::new(0, None));
+        let blocks = allocator
+            .alloc_consec_blocks(MB(8))
+            .expect("allocation failed");
+        assert_eq!(blocks.len(), MB(8).bytes());
+        blocks.dealloc();
+    }
+    let mut chain = ChainAllocator::new();
+    if let Ok(coco) = CoCo::new() {
+        chain = chain.with(coco);
+    }
+    let mut chain = chain
+        .with(Pfn::new(xor_mem_config(), None.into(), Default::default()))
+        .with(THP::new(0, None));
+    let blocks = chain.alloc_consec_blocks(MB(8)).expect("allocation failed");
+    assert_eq!(blocks.len(), MB(8).bytes());
+    blocks.dealloc();
+}
+
+#[test]
+#[ignore]
+fn test_numa_allocator_hugepage() -> anyhow::Result<()> {
+    let nodes = NumaAllocator::detected_nodes();
+    assert!(
+        nodes.len() > 1,
+        "requires a NUMA machine, found nodes {:?}",
+        nodes
+    );
+    for node in nodes {
+        let mut allocator = NumaAllocator::new(HugepageAllocator::default(), node)?;
+        let blocks = allocator.alloc_consec_blocks(MB(512))?;
+        blocks.dealloc();
+    }
+    Ok(())
+}